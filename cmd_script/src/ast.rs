@@ -0,0 +1,110 @@
+/*
+The World Command Scripting Language Syntax Tree
+
+The parsed script; the top-level statements run as the main program, and the functions are called from it.
+ */
+
+use crate::Span;
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Script {
+    pub body: Vec<Stmt>,  // the top-level statements in their order
+    pub functions: Vec<Function>,
+    pub end: Span,  // the end of the script, where the main program returns
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<(String, Span)>,
+    pub body: Vec<Stmt>,
+    pub span: Span,  // the span of the function name
+    pub end: Span,  // the closing brace of the body, where the function returns without a value
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Stmt {
+    Let(String, Expr, Span),  // declares the variable; the span is of the variable name
+    Assign(String, Expr, Span),  // assigns to a declared variable; the span is of the variable name
+    SetNamespace(Vec<String>, Expr, Span),  // writes to the namespace property; the span is of the path
+    If(Expr, Vec<Stmt>, Vec<Stmt>),  // the condition, the `if` block and the `else` block
+    While(Expr, Vec<Stmt>),
+    Break(Span),
+    Continue(Span),
+    Return(Option<Expr>, Span),  // the span is of the `return` keyword
+    Print(Expr),
+    Expr(Expr),  // the resulting value is discarded
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ExprKind {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    List(Vec<Expr>),  // packs the elements into a single list value
+    Var(String),
+    Namespace(Vec<String>),  // reads the namespace property
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),  // calls the script function
+    NamespaceCall(Vec<String>, Vec<Expr>),  // calls the namespace command like `:MTXG-CMD:Teleport`
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "%",
+            BinaryOp::Eq => "==",
+            BinaryOp::NotEq => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::LtEq => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::GtEq => ">=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+        }
+    }
+}
+
+impl UnaryOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "!",
+        }
+    }
+}
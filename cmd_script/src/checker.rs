@@ -0,0 +1,351 @@
+/*
+The World Command Scripting Language Checker
+
+Checks the script before any of it is generated; the variables and functions are resolved, and the
+expressions are typed against the values the bytecode stack can hold. All the problems are collected
+as diagnostics instead of stopping at the first one.
+
+The types follow the interpreter: integers are upgraded to floats when mixed with floats, strings can
+only be added to strings, and lists can only be printed, tested as conditions or discarded since every
+other command takes single values off the stack. The values of namespaces and function parameters are
+only known at run time, so they are accepted anywhere a single value is.
+ */
+
+use crate::ast::{Script, Function, Stmt, Expr, ExprKind, UnaryOp, BinaryOp};
+use crate::library::{self, Param};
+use crate::{CompileError, Span};
+
+use std::collections::HashMap;
+use std::fmt;
+
+
+// The types of the values on the bytecode stack
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Type {
+    Int,
+    Float,
+    Str,
+    List,
+    Any,  // only known at run time; never a list
+}
+
+impl Type {
+    fn is_number(&self) -> bool {
+        matches!(self, Type::Int | Type::Float | Type::Any)
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "Int"),
+            Type::Float => write!(f, "Float"),
+            Type::Str => write!(f, "Str"),
+            Type::List => write!(f, "List"),
+            Type::Any => write!(f, "any value"),
+        }
+    }
+}
+
+struct Checker<'a> {
+    functions: HashMap<&'a str, &'a Function>,
+    returns: HashMap<&'a str, Type>,  // the inferred return type of each function
+    diagnostics: Vec<CompileError>,
+
+    // states of the function (or the main program) being checked
+    in_function: bool,
+    scopes: Vec<HashMap<&'a str, (Type, Span)>>,  // the visible variables of each block with their types and declarations
+    loops: usize,  // the number of enclosing loops
+    returned: Vec<Type>,  // the types of the values returned so far
+}
+
+// checks the whole script; the diagnostics are ordered by where they are in the script
+pub(crate) fn check(script: &Script) -> Vec<CompileError> {
+    let mut checker = Checker {
+        functions: HashMap::new(),
+        returns: HashMap::new(),
+        diagnostics: Vec::new(),
+        in_function: false,
+        scopes: Vec::new(),
+        loops: 0,
+        returned: Vec::new(),
+    };
+
+    for func in &script.functions {
+        match checker.functions.get(func.name.as_str()) {
+            Some(first) => checker.diagnostics.push(CompileError::DuplicateFunction(func.span, func.name.clone(), first.span)),
+            None => { checker.functions.insert(&func.name, func); },
+        }
+    }
+
+    // the return types are inferred first so the calls can be typed regardless of the declaration order
+    // only the diagnostics of the second pass are kept
+    for func in &script.functions {
+        let ret = checker.function(func);
+        checker.returns.insert(&func.name, ret);
+    }
+    checker.diagnostics.retain(|diag| matches!(diag, CompileError::DuplicateFunction(..)));
+
+    checker.in_function = false;
+    checker.scopes = vec![HashMap::new()];
+    checker.loops = 0;
+    checker.block(&script.body);
+    for func in &script.functions {
+        checker.function(func);
+    }
+
+    let mut diagnostics = checker.diagnostics;
+    diagnostics.sort_by_key(|diag| (diag.span().line, diag.span().col_start));
+    diagnostics
+}
+
+// checks if every path through the statements ends with a `return`
+fn always_returns(stmts: &[Stmt]) -> bool {
+    match stmts.last() {
+        Some(Stmt::Return(..)) => true,
+        Some(Stmt::If(_, then, els)) => always_returns(then) && always_returns(els),
+        _ => false,
+    }
+}
+
+impl<'a> Checker<'a> {
+    // checks the function and returns the type of the values it returns
+    fn function(&mut self, func: &'a Function) -> Type {
+        self.in_function = true;
+        self.scopes = vec![HashMap::new()];
+        self.loops = 0;
+        self.returned.clear();
+
+        for (name, span) in &func.params {
+            if self.scopes[0].insert(name, (Type::Any, *span)).is_some() {
+                self.diagnostics.push(CompileError::DuplicateParameter(*span, name.clone()));
+            }
+        }
+        self.block(&func.body);
+        if !always_returns(&func.body) {
+            // falls off the end and returns 0
+            self.returned.push(Type::Int);
+        }
+
+        match self.returned.split_first() {
+            Some((first, rest)) if rest.iter().all(|ty| ty == first) => *first,
+            _ => Type::Any,
+        }
+    }
+
+    fn variable(&self, name: &str) -> Option<(Type, Span)> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).copied()
+    }
+
+    // lists cannot be stored, passed or returned; they are single items made of many values on the stack
+    fn single_value(&mut self, ty: Type, span: Span, usage: &'static str) -> Type {
+        if ty == Type::List {
+            self.diagnostics.push(CompileError::ListValue(span, usage));
+            Type::Any
+        } else {
+            ty
+        }
+    }
+
+    fn block(&mut self, stmts: &'a [Stmt]) {
+        self.scopes.push(HashMap::new());
+        for stmt in stmts {
+            self.statement(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::Let(name, val, span) => {
+                let ty = self.expression(val);
+                let ty = self.single_value(ty, val.span, "stored in a variable");
+                self.scopes.last_mut().unwrap().insert(name, (ty, *span));
+            },
+            Stmt::Assign(name, val, span) => {
+                let ty = self.expression(val);
+                let ty = self.single_value(ty, val.span, "stored in a variable");
+                match self.variable(name) {
+                    Some((var_ty, decl)) => {
+                        // the variables keep the type they were declared with
+                        if var_ty != Type::Any && ty != Type::Any && var_ty != ty {
+                            self.diagnostics.push(CompileError::AssignType(val.span, name.clone(), var_ty, ty, decl));
+                        }
+                    },
+                    None => self.diagnostics.push(CompileError::UnknownVariable(*span, name.clone())),
+                }
+            },
+            Stmt::SetNamespace(_, val, _) => {
+                let ty = self.expression(val);
+                self.single_value(ty, val.span, "written to a namespace");
+            },
+            Stmt::If(cond, then, els) => {
+                self.expression(cond);
+                self.block(then);
+                self.block(els);
+            },
+            Stmt::While(cond, body) => {
+                self.expression(cond);
+                self.loops += 1;
+                self.block(body);
+                self.loops -= 1;
+            },
+            Stmt::Break(span) | Stmt::Continue(span) if self.loops == 0 => {
+                let keyword = if matches!(stmt, Stmt::Break(_)) { "break" } else { "continue" };
+                self.diagnostics.push(CompileError::OutsideOfLoop(*span, keyword));
+            },
+            Stmt::Break(_) | Stmt::Continue(_) => {},
+            Stmt::Return(val, span) => {
+                let ty = match val {
+                    Some(val) => {
+                        let ty = self.expression(val);
+                        self.single_value(ty, val.span, "returned")
+                    },
+                    None => Type::Int,
+                };
+                if self.in_function {
+                    self.returned.push(ty);
+                } else if val.is_some() {
+                    self.diagnostics.push(CompileError::ReturnValueOutsideOfFunction(*span));
+                }
+            },
+            Stmt::Print(val) => { self.expression(val); },
+            Stmt::Expr(Expr { kind: ExprKind::NamespaceCall(path, args), span }) => {
+                self.namespace_call(path, args, *span, true);
+            },
+            Stmt::Expr(val) => { self.expression(val); },
+        }
+    }
+
+    // the type of the value the expression leaves on the stack
+    fn expression(&mut self, expr: &'a Expr) -> Type {
+        match &expr.kind {
+            ExprKind::Int(_) => Type::Int,
+            ExprKind::Float(_) => Type::Float,
+            ExprKind::Str(_) => Type::Str,
+            ExprKind::List(elems) => {
+                for elem in elems {
+                    self.expression(elem);
+                }
+                Type::List
+            },
+            ExprKind::Var(name) => match self.variable(name) {
+                Some((ty, _)) => ty,
+                None => {
+                    self.diagnostics.push(CompileError::UnknownVariable(expr.span, name.clone()));
+                    Type::Any
+                },
+            },
+            ExprKind::Namespace(_) => Type::Any,
+            ExprKind::Unary(op, val) => {
+                let ty = self.expression(val);
+                match (op, ty) {
+                    (UnaryOp::Not, ty) if ty != Type::List => Type::Int,
+                    (UnaryOp::Neg, ty) if ty.is_number() => ty,
+                    _ => {
+                        self.diagnostics.push(CompileError::OperandType(expr.span, op.symbol(), ty));
+                        Type::Any
+                    },
+                }
+            },
+            ExprKind::Binary(op, lhs, rhs) => {
+                let lhs_ty = self.expression(lhs);
+                let rhs_ty = self.expression(rhs);
+                match binary_type(*op, lhs_ty, rhs_ty) {
+                    Some(ty) => ty,
+                    None => {
+                        self.diagnostics.push(CompileError::OperandTypes(expr.span, op.symbol(), (lhs_ty, lhs.span), (rhs_ty, rhs.span)));
+                        Type::Any
+                    },
+                }
+            },
+            ExprKind::Call(name, args) => {
+                for arg in args {
+                    let ty = self.expression(arg);
+                    self.single_value(ty, arg.span, "passed to a function");
+                }
+
+                match self.functions.get(name.as_str()) {
+                    Some(func) if func.params.len() != args.len() => {
+                        self.diagnostics.push(CompileError::ArgumentCount(expr.span, name.clone(), func.params.len(), args.len()));
+                    },
+                    Some(_) => {},
+                    None => self.diagnostics.push(CompileError::UnknownFunction(expr.span, name.clone())),
+                }
+                self.returns.get(name.as_str()).copied().unwrap_or(Type::Any)
+            },
+            ExprKind::NamespaceCall(path, args) => self.namespace_call(path, args, expr.span, false),
+        }
+    }
+
+    // the commands outside of the library are unknown until run time, and assumed to return a single value
+    // within expressions
+    fn namespace_call(&mut self, path: &[String], args: &'a [Expr], span: Span, statement: bool) -> Type {
+        let arg_tys = args.iter().map(|arg| {
+            let ty = self.expression(arg);
+            self.single_value(ty, arg.span, "passed to a command")
+        }).collect::<Vec<_>>();
+
+        if !library::is_library(path) {
+            return Type::Any;
+        }
+        let name = format!(":{}", path.join(":"));
+        let sig = match library::signature(path) {
+            Some(sig) => sig,
+            None => {
+                self.diagnostics.push(CompileError::UnknownCommand(span, name));
+                return Type::Any;
+            },
+        };
+
+        if sig.params.len() != args.len() {
+            self.diagnostics.push(CompileError::ArgumentCount(span, name.clone(), sig.params.len(), args.len()));
+        } else {
+            for (ind, (param, ty)) in sig.params.iter().zip(arg_tys).enumerate() {
+                let accepted = match param {
+                    Param::Number => ty.is_number(),
+                    Param::Str => ty == Type::Str || ty == Type::Any,
+                };
+                if !accepted {
+                    self.diagnostics.push(CompileError::ArgumentType(args[ind].span, name.clone(), ind+1, *param, ty));
+                }
+            }
+        }
+
+        match sig.results {
+            _ if statement => Type::Any,
+            [ty] => *ty,
+            results => {
+                self.diagnostics.push(CompileError::CommandResults(span, name, results.len()));
+                Type::Any
+            },
+        }
+    }
+}
+
+// the type of the binary operation; None if the interpreter rejects the operands
+fn binary_type(op: BinaryOp, lhs: Type, rhs: Type) -> Option<Type> {
+    use Type::{Int, Float, Str, List, Any};
+
+    if lhs == List || rhs == List {
+        return None;
+    }
+
+    match op {
+        BinaryOp::Eq | BinaryOp::NotEq | BinaryOp::And | BinaryOp::Or => Some(Int),
+        BinaryOp::Lt | BinaryOp::LtEq | BinaryOp::Gt | BinaryOp::GtEq => match (lhs, rhs) {
+            (Str, Str) | (Any, _) | (_, Any) => Some(Int),
+            (lhs, rhs) if lhs.is_number() && rhs.is_number() => Some(Int),
+            _ => None,
+        },
+        BinaryOp::Add if (lhs, rhs) == (Str, Str) => Some(Str),
+        BinaryOp::Add if (lhs == Any && rhs == Str) || (lhs == Str && rhs == Any) => Some(Any),
+        _ => match (lhs, rhs) {
+            (Int, Int) => Some(Int),
+            (Any, rhs) if rhs.is_number() => Some(Any),
+            (lhs, Any) if lhs.is_number() => Some(Any),
+            (lhs, rhs) if lhs.is_number() && rhs.is_number() => Some(Float),
+            _ => None,
+        },
+    }
+}
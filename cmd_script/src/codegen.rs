@@ -0,0 +1,288 @@
+/*
+The World Command Scripting Language Code Generator
+
+Lowers the checked syntax tree into the bytecode instructions. The main program comes first and ends
+with a `RET`; each function follows it as a `MRK #fn-<name>` block that is entered with `CALL`.
+
+Calls push their arguments from the left to the right, and the function stores them into its local
+variables from the last one to the first. Every function leaves exactly one value on the stack when it
+returns, which is `0` when it returns without a value. The values returned by the library commands are
+discarded when they are called as statements; any other namespace command is assumed to return nothing
+as a statement, and a single value within expressions.
+
+The generated names contain `-`, which the script names cannot, so they never collide with the
+variables and functions of the script.
+ */
+
+use crate::ast::{Script, Function, Stmt, Expr, ExprKind, UnaryOp, BinaryOp};
+use crate::ir::{Instr, Op, Arg};
+use crate::stack::Routine;
+use crate::library;
+use crate::Span;
+
+use std::collections::HashMap;
+
+
+struct Codegen<'a> {
+    functions: HashMap<&'a str, usize>,  // the function names to their number of parameters
+    namespaces: Vec<String>,  // the namespace roots used by the script in their first use order
+    instrs: Vec<Instr>,
+    label_counter: usize,
+
+    // states of the function (or the main program) being generated
+    in_function: bool,
+    scopes: Vec<HashMap<&'a str, String>>,  // the visible variables of each block to their local variable names
+    declared: HashMap<&'a str, usize>,  // the number of times each variable name was declared
+    loops: Vec<usize>,  // the labels of the enclosing loops; the innermost is the last
+}
+
+// generates the instructions of the script, which must have passed the checker
+// along with where each routine of the instructions starts, for checking their stack effects
+pub(crate) fn generate(script: &Script) -> (Vec<Instr>, Vec<Routine>) {
+    let mut gen = Codegen {
+        functions: script.functions.iter().map(|func| (func.name.as_str(), func.params.len())).collect(),
+        namespaces: Vec::new(),
+        instrs: Vec::new(),
+        label_counter: 0,
+        in_function: false,
+        scopes: vec![HashMap::new()],
+        declared: HashMap::new(),
+        loops: Vec::new(),
+    };
+
+    let mut routines = vec![Routine { label: None, depth: 0, returns: 0 }];
+    gen.block(&script.body);
+    gen.emit(Op::Ret, None, script.end);
+    for func in &script.functions {
+        routines.push(Routine { label: Some(format!("fn-{}", func.name)), depth: func.params.len(), returns: 1 });
+        gen.function(func);
+    }
+
+    // the namespaces are declared before everything else
+    let mut instrs = gen.namespaces.iter().map(|root| Instr {
+        op: Op::Namespace,
+        arg: Some(Arg::Namespace(vec![root.clone()])),
+        span: Span::new(1, 1, 1),
+    }).collect::<Vec<_>>();
+    instrs.extend(gen.instrs);
+
+    (instrs, routines)
+}
+
+impl<'a> Codegen<'a> {
+    fn emit(&mut self, op: Op, arg: Option<Arg>, span: Span) {
+        self.instrs.push(Instr { op, arg, span });
+    }
+
+    fn label(&mut self) -> usize {
+        self.label_counter += 1;
+        self.label_counter
+    }
+
+    // namespace paths also declare their roots to be used by the program
+    fn namespace(&mut self, path: &[String]) -> Arg {
+        if !self.namespaces.contains(&path[0]) {
+            self.namespaces.push(path[0].clone());
+        }
+        Arg::Namespace(path.to_vec())
+    }
+
+    fn function(&mut self, func: &'a Function) {
+        self.in_function = true;
+        self.scopes = vec![HashMap::new()];
+        self.declared.clear();
+
+        self.emit(Op::Mrk, Some(Arg::Label(format!("fn-{}", func.name))), func.span);
+        let params = func.params.iter().map(|(name, span)| (self.declare(name), *span)).collect::<Vec<_>>();
+        // the last argument is on the top of the stack
+        for (local, span) in params.into_iter().rev() {
+            self.emit(Op::Store, Some(Arg::Local(local)), span);
+        }
+
+        self.block(&func.body);
+        self.emit(Op::Push, Some(Arg::Int(0)), func.end);
+        self.emit(Op::Ret, None, func.end);
+    }
+
+    // declares the variable in the innermost block; redeclared names get their own local variables
+    fn declare(&mut self, name: &'a str) -> String {
+        let count = self.declared.entry(name).or_insert(0);
+        let local = if *count == 0 { name.to_string() } else { format!("{}-{}", name, count) };
+        *count += 1;
+
+        self.scopes.last_mut().unwrap().insert(name, local.clone());
+        local
+    }
+
+    fn variable(&self, name: &str) -> Arg {
+        let local = self.scopes.iter().rev().find_map(|scope| scope.get(name))
+            .expect("The checker has resolved all the variables");
+        Arg::Local(local.clone())
+    }
+
+    fn block(&mut self, stmts: &'a [Stmt]) {
+        self.scopes.push(HashMap::new());
+        for stmt in stmts {
+            self.statement(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::Let(name, val, span) => {
+                // the value is evaluated before the variable exists, so `let x = x + 1;` reads the outer `x`
+                self.expression(val);
+                let local = self.declare(name);
+                self.emit(Op::Store, Some(Arg::Local(local)), *span);
+            },
+            Stmt::Assign(name, val, span) => {
+                let local = self.variable(name);
+                self.expression(val);
+                self.emit(Op::Store, Some(local), *span);
+            },
+            Stmt::SetNamespace(path, val, span) => {
+                self.expression(val);
+                let path = self.namespace(path);
+                self.emit(Op::Pop, Some(path), *span);
+            },
+            Stmt::If(cond, then, els) => {
+                // the condition is left on the stack by `JMP_IF`, so both branches start by popping it
+                let label = self.label();
+                let span = cond.span;
+                self.expression(cond);
+                self.emit(Op::JmpIf, Some(Arg::Label(format!("then-{}", label))), span);
+                self.emit(Op::Pop, None, span);
+                self.block(els);
+                self.emit(Op::Jmp, Some(Arg::Label(format!("end-{}", label))), span);
+                self.emit(Op::Mrk, Some(Arg::Label(format!("then-{}", label))), span);
+                self.emit(Op::Pop, None, span);
+                self.block(then);
+                self.emit(Op::Mrk, Some(Arg::Label(format!("end-{}", label))), span);
+            },
+            Stmt::While(cond, body) => {
+                let label = self.label();
+                let span = cond.span;
+                self.emit(Op::Mrk, Some(Arg::Label(format!("loop-{}", label))), span);
+                self.expression(cond);
+                self.emit(Op::JmpIf, Some(Arg::Label(format!("body-{}", label))), span);
+                self.emit(Op::Pop, None, span);
+                self.emit(Op::Jmp, Some(Arg::Label(format!("end-{}", label))), span);
+                self.emit(Op::Mrk, Some(Arg::Label(format!("body-{}", label))), span);
+                self.emit(Op::Pop, None, span);
+
+                self.loops.push(label);
+                self.block(body);
+                self.loops.pop();
+
+                self.emit(Op::Jmp, Some(Arg::Label(format!("loop-{}", label))), span);
+                self.emit(Op::Mrk, Some(Arg::Label(format!("end-{}", label))), span);
+            },
+            Stmt::Break(span) => {
+                let label = *self.loops.last().expect("The checker has rejected `break` outside of loops");
+                self.emit(Op::Jmp, Some(Arg::Label(format!("end-{}", label))), *span);
+            },
+            Stmt::Continue(span) => {
+                let label = *self.loops.last().expect("The checker has rejected `continue` outside of loops");
+                self.emit(Op::Jmp, Some(Arg::Label(format!("loop-{}", label))), *span);
+            },
+            Stmt::Return(val, span) => {
+                match val {
+                    Some(val) => self.expression(val),
+                    // the main program has nothing to return its value to
+                    None if self.in_function => self.emit(Op::Push, Some(Arg::Int(0)), *span),
+                    None => {},
+                }
+                self.emit(Op::Ret, None, *span);
+            },
+            Stmt::Print(val) => {
+                self.expression(val);
+                self.emit(Op::COut, None, val.span);
+            },
+            Stmt::Expr(Expr { kind: ExprKind::NamespaceCall(path, args), span }) => {
+                let results = library::signature(path).map_or(0, |sig| sig.results.len());
+                self.namespace_call(path, args, results, *span);
+                for _ in 0..results {
+                    self.emit(Op::Pop, None, *span);
+                }
+            },
+            Stmt::Expr(val) => {
+                self.expression(val);
+                self.emit(Op::Pop, None, val.span);
+            },
+        }
+    }
+
+    // pushes the value of the expression on the stack
+    fn expression(&mut self, expr: &'a Expr) {
+        let span = expr.span;
+        match &expr.kind {
+            ExprKind::Int(val) => self.emit(Op::Push, Some(Arg::Int(*val)), span),
+            ExprKind::Float(val) => self.emit(Op::Push, Some(Arg::Float(*val)), span),
+            ExprKind::Str(val) => self.emit(Op::Push, Some(Arg::Str(val.clone())), span),
+            ExprKind::List(elems) => {
+                for elem in elems {
+                    self.expression(elem);
+                }
+                self.emit(Op::Pack, Some(Arg::Int(elems.len() as i64)), span);
+            },
+            ExprKind::Var(name) => {
+                let local = self.variable(name);
+                self.emit(Op::Load, Some(local), span);
+            },
+            ExprKind::Namespace(path) => {
+                let path = self.namespace(path);
+                self.emit(Op::Push, Some(path), span);
+            },
+            ExprKind::Unary(op, val) => {
+                self.expression(val);
+                self.emit(match op {
+                    UnaryOp::Neg => Op::Neg,
+                    UnaryOp::Not => Op::NotL,
+                }, None, span);
+            },
+            ExprKind::Binary(op, lhs, rhs) => {
+                self.expression(lhs);
+                self.expression(rhs);
+                // the comparisons without their own commands are the negations of the opposite ones
+                let (op, negate) = match op {
+                    BinaryOp::Add => (Op::Add, false),
+                    BinaryOp::Sub => (Op::Sub, false),
+                    BinaryOp::Mul => (Op::Mul, false),
+                    BinaryOp::Div => (Op::Div, false),
+                    BinaryOp::Mod => (Op::Mod, false),
+                    BinaryOp::Eq => (Op::Eq, false),
+                    BinaryOp::NotEq => (Op::Eq, true),
+                    BinaryOp::Lt => (Op::Lt, false),
+                    BinaryOp::LtEq => (Op::Gt, true),
+                    BinaryOp::Gt => (Op::Gt, false),
+                    BinaryOp::GtEq => (Op::Lt, true),
+                    // both sides are always evaluated; there is no short-circuiting
+                    BinaryOp::And => (Op::AndL, false),
+                    BinaryOp::Or => (Op::OrL, false),
+                };
+                self.emit(op, None, span);
+                if negate {
+                    self.emit(Op::NotL, None, span);
+                }
+            },
+            ExprKind::Call(name, args) => {
+                for arg in args {
+                    self.expression(arg);
+                }
+                let arity = self.functions[name.as_str()];
+                self.emit(Op::Call { args: arity }, Some(Arg::Label(format!("fn-{}", name))), span);
+            },
+            ExprKind::NamespaceCall(path, args) => self.namespace_call(path, args, 1, span),
+        }
+    }
+
+    // the namespace command takes its arguments from the bottom of the stack to the top
+    fn namespace_call(&mut self, path: &[String], args: &'a [Expr], results: usize, span: Span) {
+        for arg in args {
+            self.expression(arg);
+        }
+        let path = self.namespace(path);
+        self.emit(Op::CmdMove { args: args.len(), results }, Some(path), span);
+    }
+}
@@ -0,0 +1,169 @@
+/*
+The World Command Scripting Language Instructions
+
+The bytecode instructions generated from the script before they are written out as the bytecode
+text. Each instruction keeps the span of the script it was generated from, so the checks on the
+generated code can still point at the script.
+ */
+
+use crate::Span;
+
+
+const COMMAND_COLUMN: usize = 12;  // the column the arguments start at; same as the bytecode formatter
+const DUMP_COLUMN: usize = 30;  // the column the spans of the dump start at
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum Op {
+    Namespace,
+    Push,
+    Pop,
+    COut,
+    CmdMove { args: usize, results: usize },  // the values the command takes from and leaves on the stack
+    Ret,
+    Pack,
+    JmpIf,
+    Jmp,
+    Mrk,
+    Call { args: usize },
+    Load,
+    Store,
+
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Eq,
+    Lt,
+    Gt,
+    AndL,
+    OrL,
+    NotL,
+    Neg,
+
+    // the binary operations with their right side as the argument instead of on the stack
+    AddI,
+    SubI,
+    MulI,
+    DivI,
+    ModI,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Arg {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Local(String),  // local variable name
+    Label(String),  // marker name
+    Namespace(Vec<String>),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Instr {
+    pub(crate) op: Op,
+    pub(crate) arg: Option<Arg>,
+    pub(crate) span: Span,  // the script the instruction was generated from
+}
+
+impl Op {
+    fn name(&self) -> &'static str {
+        match self {
+            Op::Namespace => "NAMESPACE",
+            Op::Push => "PUSH",
+            Op::Pop => "POP",
+            Op::COut => "COUT",
+            Op::CmdMove { .. } => "CMD_MOVE",
+            Op::Ret => "RET",
+            Op::Pack => "PACK",
+            Op::JmpIf => "JMP_IF",
+            Op::Jmp => "JMP",
+            Op::Mrk => "MRK",
+            Op::Call { .. } => "CALL",
+            Op::Load => "LOAD",
+            Op::Store => "STORE",
+            Op::Add => "ADD",
+            Op::Sub => "SUB",
+            Op::Mul => "MUL",
+            Op::Div => "DIV",
+            Op::Mod => "MOD",
+            Op::Eq => "EQ",
+            Op::Lt => "LT",
+            Op::Gt => "GT",
+            Op::AndL => "ANDL",
+            Op::OrL => "ORL",
+            Op::NotL => "NOTL",
+            Op::Neg => "NEG",
+            Op::AddI => "ADDI",
+            Op::SubI => "SUBI",
+            Op::MulI => "MULI",
+            Op::DivI => "DIVI",
+            Op::ModI => "MODI",
+        }
+    }
+}
+
+impl Instr {
+    // the number of stack items the instruction takes, and the number of items it leaves afterwards
+    pub(crate) fn stack_effect(&self) -> (usize, usize) {
+        match (self.op, &self.arg) {
+            (Op::Namespace, _) | (Op::Mrk, _) | (Op::Jmp, _) | (Op::Ret, _) => (0, 0),
+            (Op::Push, _) | (Op::Load, _) => (0, 1),
+            (Op::Pop, _) | (Op::COut, _) | (Op::Store, _) => (1, 0),
+            // the condition is kept on the stack
+            (Op::JmpIf, _) => (1, 1),
+            (Op::CmdMove { args, results }, _) => (args, results),
+            (Op::Call { args }, _) => (args, 1),
+            (Op::Pack, Some(Arg::Int(len))) => (*len as usize, 1),
+            (Op::Pack, _) => (0, 1),
+            (Op::NotL, _) | (Op::Neg, _) => (1, 1),
+            (Op::AddI, _) | (Op::SubI, _) | (Op::MulI, _) | (Op::DivI, _) | (Op::ModI, _) => (1, 1),
+            _ => (2, 1),
+        }
+    }
+
+    // the marker the instruction jumps to
+    pub(crate) fn target(&self) -> Option<&str> {
+        match (self.op, &self.arg) {
+            (Op::Jmp, Some(Arg::Label(label))) | (Op::JmpIf, Some(Arg::Label(label))) => Some(label),
+            _ => None,
+        }
+    }
+}
+
+// writes the instructions out as the bytecode text; the arguments are aligned to the same column
+pub(crate) fn format(instrs: &[Instr]) -> String {
+    instrs.iter().map(|instr| format!("{}\n", format_instr(instr))).collect()
+}
+
+// lists the instructions with their indices and the script lines they were generated from
+//     0004  ADDI        1                 ; 3:9
+pub(crate) fn dump(instrs: &[Instr]) -> String {
+    let index_width = instrs.len().saturating_sub(1).to_string().len().max(4);
+    instrs.iter().enumerate().map(|(ind, instr)| {
+        format!("{:0w$}  {:<a$} ; {}:{}\n", ind, format_instr(instr), instr.span.line, instr.span.col_start,
+                w = index_width, a = DUMP_COLUMN)
+    }).collect()
+}
+
+fn format_instr(instr: &Instr) -> String {
+    match &instr.arg {
+        Some(arg) => format!("{:<w$}{}", instr.op.name(), format_arg(arg), w = COMMAND_COLUMN),
+        None => String::from(instr.op.name()),
+    }
+}
+
+fn format_arg(arg: &Arg) -> String {
+    match arg {
+        Arg::Int(val) => val.to_string(),
+        Arg::Float(val) => {
+            // floats always keep their decimal point, so they are read back as floats
+            let text = val.to_string();
+            if text.contains('.') { text } else { format!("{}.0", text) }
+        },
+        Arg::Str(val) => format!("\"{}\"", val),
+        Arg::Local(name) => format!("${}", name),
+        Arg::Label(name) => format!("#{}", name),
+        Arg::Namespace(path) => format!(":{}", path.join(":")),
+    }
+}
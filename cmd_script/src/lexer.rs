@@ -0,0 +1,309 @@
+/*
+The World Command Scripting Language Lexer
+
+Splits the script source into tokens along with their spans. Comments start with `//` and last until
+the end of the line.
+ */
+
+use crate::{CompileError, Span};
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Token {
+    Ident(String),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Namespace(Vec<String>),  // each name of the namespace path ordered from left to right
+
+    // keywords
+    Let,
+    Fn,
+    If,
+    Else,
+    While,
+    Break,
+    Continue,
+    Return,
+    Print,
+    True,
+    False,
+
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Comma,
+    Semicolon,
+    Assign,
+
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    EqEq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    AndAnd,
+    OrOr,
+    Bang,
+
+    Eof,
+}
+
+impl Token {
+    // the token as written in the source; for the error messages
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Token::Ident(name) => format!("`{}`", name),
+            Token::Int(val) => format!("`{}`", val),
+            Token::Float(val) => format!("`{:?}`", val),
+            Token::Str(val) => format!("`\"{}\"`", val),
+            Token::Namespace(path) => format!("`:{}`", path.join(":")),
+            Token::Eof => String::from("the end of the script"),
+            tkn => format!("`{}`", tkn.symbol()),
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Token::Let => "let",
+            Token::Fn => "fn",
+            Token::If => "if",
+            Token::Else => "else",
+            Token::While => "while",
+            Token::Break => "break",
+            Token::Continue => "continue",
+            Token::Return => "return",
+            Token::Print => "print",
+            Token::True => "true",
+            Token::False => "false",
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::LBrace => "{",
+            Token::RBrace => "}",
+            Token::LBracket => "[",
+            Token::RBracket => "]",
+            Token::Comma => ",",
+            Token::Semicolon => ";",
+            Token::Assign => "=",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Slash => "/",
+            Token::Percent => "%",
+            Token::EqEq => "==",
+            Token::NotEq => "!=",
+            Token::Lt => "<",
+            Token::LtEq => "<=",
+            Token::Gt => ">",
+            Token::GtEq => ">=",
+            Token::AndAnd => "&&",
+            Token::OrOr => "||",
+            Token::Bang => "!",
+            _ => "",
+        }
+    }
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    ind: usize,
+    line: u32,
+    col: u32,  // column of the next character
+}
+
+// splits the source into tokens; the last token is always `Token::Eof`
+pub(crate) fn tokenize(src: &str) -> Result<Vec<(Token, Span)>, CompileError> {
+    let mut lexer = Lexer {
+        chars: src.chars().collect(),
+        ind: 0,
+        line: 1,
+        col: 1,
+    };
+    let mut tokens = Vec::new();
+
+    loop {
+        lexer.skip_blank();
+        let (line, col) = (lexer.line, lexer.col);
+        let c = match lexer.bump() {
+            Some(c) => c,
+            None => {
+                tokens.push((Token::Eof, Span::new(line, col, col)));
+                return Ok(tokens);
+            },
+        };
+
+        let tkn = match c {
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            ',' => Token::Comma,
+            ';' => Token::Semicolon,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '%' => Token::Percent,
+            '=' => if lexer.eat('=') { Token::EqEq } else { Token::Assign },
+            '!' => if lexer.eat('=') { Token::NotEq } else { Token::Bang },
+            '<' => if lexer.eat('=') { Token::LtEq } else { Token::Lt },
+            '>' => if lexer.eat('=') { Token::GtEq } else { Token::Gt },
+            '&' if lexer.eat('&') => Token::AndAnd,
+            '|' if lexer.eat('|') => Token::OrOr,
+            '"' => lexer.string(line, col)?,
+            ':' => lexer.namespace(line, col)?,
+            '0'..='9' => lexer.number(c, line, col)?,
+            c if c.is_alphabetic() || c == '_' => lexer.word(c),
+            c => return Err(CompileError::UnexpectedCharacter(Span::new(line, col, col), c)),
+        };
+
+        tokens.push((tkn, Span::new(line, col, lexer.col-1)));
+    }
+}
+
+impl Lexer {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.ind).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.ind += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+        Some(c)
+    }
+
+    // consumes the next character only if it is the expected one
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    // skips the whitespaces and the comments
+    fn skip_blank(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.bump();
+            } else if c == '/' && self.chars.get(self.ind+1) == Some(&'/') {
+                while !matches!(self.peek(), Some('\n') | None) {
+                    self.bump();
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    // strings cannot contain any quotes or line breaks, same as the bytecode
+    fn string(&mut self, line: u32, col: u32) -> Result<Token, CompileError> {
+        let mut val = String::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.bump();
+                    return Ok(Token::Str(val));
+                },
+                Some('\n') | None => return Err(CompileError::UnterminatedString(Span::new(line, col, self.col-1))),
+                Some(c) => {
+                    self.bump();
+                    val.push(c);
+                },
+            }
+        }
+    }
+
+    // namespace paths like `:MTXG-CMD:Teleport`; the names can contain letters, digits, `_` and `-`
+    fn namespace(&mut self, line: u32, col: u32) -> Result<Token, CompileError> {
+        let mut path = vec![String::new()];
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                path.last_mut().unwrap().push(c);
+            } else if c == ':' && !path.last().unwrap().is_empty() {
+                path.push(String::new());
+            } else {
+                break;
+            }
+            self.bump();
+        }
+
+        if path.last().unwrap().is_empty() {
+            return Err(CompileError::EmptyNamespace(Span::new(line, col, self.col-1)));
+        }
+        Ok(Token::Namespace(path))
+    }
+
+    fn number(&mut self, first: char, line: u32, col: u32) -> Result<Token, CompileError> {
+        let mut text = first.to_string();
+        let mut float = false;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() {
+                text.push(c);
+            } else if c == '.' && !float && self.chars.get(self.ind+1).is_some_and(char::is_ascii_digit) {
+                float = true;
+                text.push(c);
+            } else if c.is_alphanumeric() || c == '_' {
+                // numbers running into names like `12ab`
+                text.push(c);
+                self.bump();
+                return Err(CompileError::InvalidNumber(Span::new(line, col, self.col-1), text));
+            } else {
+                break;
+            }
+            self.bump();
+        }
+
+        let span = Span::new(line, col, self.col-1);
+        if float {
+            text.parse().map(Token::Float).map_err(|_| CompileError::InvalidNumber(span, text))
+        } else {
+            text.parse().map(Token::Int).map_err(|_| CompileError::InvalidNumber(span, text))
+        }
+    }
+
+    // identifiers and keywords
+    fn word(&mut self, first: char) -> Token {
+        let mut word = first.to_string();
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '_' {
+                word.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+
+        match word.as_str() {
+            "let" => Token::Let,
+            "fn" => Token::Fn,
+            "if" => Token::If,
+            "else" => Token::Else,
+            "while" => Token::While,
+            "break" => Token::Break,
+            "continue" => Token::Continue,
+            "return" => Token::Return,
+            "print" => Token::Print,
+            "true" => Token::True,
+            "false" => Token::False,
+            _ => Token::Ident(word),
+        }
+    }
+}
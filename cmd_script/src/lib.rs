@@ -1,7 +1,542 @@
+/*
+The World Command Scripting Language
+
+A small structured language that compiles down to the World Command Bytecode, so the commands can be
+written without juggling the stack by hand.
+
+    // teleports the player 10 blocks up for every second argument
+    fn lift(times) {
+        let height = 0;
+        while times > 0 {
+            height = height + 10;
+            times = times - 2;
+        }
+        return height;
+    }
+
+    let y = :Matrixagon:world:player:main:pos:y + lift(4);
+    :MTXG-CMD:Teleport(:Matrixagon:world:player:main, 0.0, y, 0.0);
+    print("teleported");
+
+The script is made of statements separated by `;`, and functions declared with `fn` at the top level.
+The values are integers, floats and strings; `true` and `false` are the integers 1 and 0. Namespace
+properties are read and written through their paths like `:Matrixagon:world:player:main:pos:y`, and
+namespace commands are called with their arguments like functions.
+ */
+
+mod lexer;
+mod parser;
+mod checker;
+mod codegen;
+mod ir;
+mod stack;
+mod optimizer;
+pub mod ast;
+pub mod library;
+
+pub use crate::checker::Type;
+use crate::library::Param;
+
+use std::fmt;
+
+
+// The location of a token (or an error) in the script source
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Span {
+    pub line: u32,  // line number starting from 1
+    pub col_start: u32,  // column number of the first character starting from 1
+    pub col_end: u32,  // column number of the last character (inclusive)
+}
+
+impl Span {
+    pub fn new(line: u32, col_start: u32, col_end: u32) -> Self {
+        Self {
+            line,
+            col_start,
+            col_end,
+        }
+    }
+
+    // a span from the start of this span to the end of the other span; only the first line is kept
+    pub fn to(&self, end: &Span) -> Self {
+        if end.line == self.line {
+            Self { col_end: end.col_end, ..*self }
+        } else {
+            *self
+        }
+    }
+
+    // renders the message with the source line of the span and a caret underline below the spanned columns
+    //     error: <message>
+    //      --> script.wcs:3:5
+    //       |
+    //     3 | let x = y + 1;
+    //       |         ^
+    pub fn render(&self, fname: &str, src: &str, msg: &str) -> String {
+        self.render_as("error", fname, src, msg)
+    }
+
+    // renders the message the same way, as a note on another part of the script for the main message
+    pub fn render_note(&self, fname: &str, src: &str, msg: &str) -> String {
+        self.render_as("note", fname, src, msg)
+    }
+
+    fn render_as(&self, level: &str, fname: &str, src: &str, msg: &str) -> String {
+        let src_line = src.lines().nth(self.line.saturating_sub(1) as usize).unwrap_or("");
+        let gutter = self.line.to_string().len();
+
+        // keeps the tabs so the carets line up with the source line
+        let pad = src_line.chars().take(self.col_start.saturating_sub(1) as usize)
+            .map(|c| if c == '\t' {'\t'} else {' '})
+            .collect::<String>();
+        let carets = "^".repeat((self.col_end.saturating_sub(self.col_start) + 1) as usize);
+
+        format!("{}: {}\n{:g$}--> {}:{}:{}\n{:g$} |\n{} | {}\n{:g$} | {}{}",
+                level, msg, "", fname, self.line, self.col_start,
+                "", self.line, src_line,
+                "", pad, carets,
+                g = gutter,
+        )
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum CompileError {
+    // A character that does not start any token
+    UnexpectedCharacter(Span, char),
+    // A string that is missing its closing quote on the same line
+    UnterminatedString(Span),
+    // A number that is malformed or too large
+    InvalidNumber(Span, String),  // the number as written
+    // A namespace path with an empty name like `:` or `:MTXG-CMD:`
+    EmptyNamespace(Span),
+    // A token that does not fit the grammar
+    UnexpectedToken(Span, String, String),  // the found token and what was expected instead
+    // Using a variable that was never declared with `let` in any of the enclosing blocks
+    UnknownVariable(Span, String),  // the variable name
+    // Calling a function that was never declared with `fn`
+    UnknownFunction(Span, String),  // the function name
+    // Declaring the same function more than once
+    DuplicateFunction(Span, String, Span),  // the function name and where it was first declared
+    // Declaring the same parameter more than once in a function
+    DuplicateParameter(Span, String),  // the parameter name
+    // Calling a function or a command with the wrong number of arguments
+    ArgumentCount(Span, String, usize, usize),  // the function name, the expected and the given number of arguments
+    // `break` or `continue` outside of any `while` loop
+    OutsideOfLoop(Span, &'static str),  // the keyword
+    // Returning a value from the main program
+    ReturnValueOutsideOfFunction(Span),
+    // The operand of the unary operator is of the wrong type
+    OperandType(Span, &'static str, Type),  // the operator and the type of the operand
+    // The operands of the binary operator are of the wrong types
+    OperandTypes(Span, &'static str, (Type, Span), (Type, Span)),  // the operator and both of the operands
+    // Assigning a value of another type than the variable was declared with
+    AssignType(Span, String, Type, Type, Span),  // the variable name, its type, the assigned type and its declaration
+    // Using a list where only single values can be
+    ListValue(Span, &'static str),  // how the list was used
+    // Calling a command under `:MTXG-CMD:` that is not in the library
+    UnknownCommand(Span, String),  // the command path
+    // Passing a value of the wrong type to the library command
+    ArgumentType(Span, String, usize, Param, Type),  // the command path, the argument number, the expected and the given type
+    // Using a library command in an expression that does not return exactly one value
+    CommandResults(Span, String, usize),  // the command path and the number of values it returns
+    // An instruction that takes more values than there are on the stack
+    StackUnderflow(Span, usize, usize),  // the values taken and the values on the stack
+    // Returning with more or less values on the stack than expected
+    StackUnbalanced(Span, usize, usize),  // the expected and the actual number of values on the stack
+    // Paths joining with different numbers of values on the stack
+    StackJoin(Span, usize, usize),  // the numbers of values on the stack of both paths
+}
+
+impl CompileError {
+    pub fn span(&self) -> Span {
+        match self {
+            CompileError::UnexpectedCharacter(span, _) |
+            CompileError::UnterminatedString(span) |
+            CompileError::InvalidNumber(span, _) |
+            CompileError::EmptyNamespace(span) |
+            CompileError::UnexpectedToken(span, _, _) |
+            CompileError::UnknownVariable(span, _) |
+            CompileError::UnknownFunction(span, _) |
+            CompileError::DuplicateFunction(span, _, _) |
+            CompileError::DuplicateParameter(span, _) |
+            CompileError::ArgumentCount(span, _, _, _) |
+            CompileError::OutsideOfLoop(span, _) |
+            CompileError::ReturnValueOutsideOfFunction(span) |
+            CompileError::OperandType(span, _, _) |
+            CompileError::OperandTypes(span, _, _, _) |
+            CompileError::AssignType(span, _, _, _, _) |
+            CompileError::ListValue(span, _) |
+            CompileError::UnknownCommand(span, _) |
+            CompileError::ArgumentType(span, _, _, _, _) |
+            CompileError::CommandResults(span, _, _) |
+            CompileError::StackUnderflow(span, _, _) |
+            CompileError::StackUnbalanced(span, _, _) |
+            CompileError::StackJoin(span, _, _) => *span,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            CompileError::UnexpectedCharacter(_, c) => format!("unexpected character `{}`", c),
+            CompileError::UnterminatedString(_) => "string is missing its closing quote".into(),
+            CompileError::InvalidNumber(_, text) => format!("invalid number `{}`", text),
+            CompileError::EmptyNamespace(_) => "namespace path has an empty name".into(),
+            CompileError::UnexpectedToken(_, found, expected) => format!("expected {}, found {}", expected, found),
+            CompileError::UnknownVariable(_, name) => format!("unknown variable `{}`", name),
+            CompileError::UnknownFunction(_, name) => format!("unknown function `{}`", name),
+            CompileError::DuplicateFunction(_, name, _) => format!("function `{}` is declared more than once", name),
+            CompileError::DuplicateParameter(_, name) => format!("parameter `{}` is declared more than once", name),
+            CompileError::ArgumentCount(_, name, expected, given) => {
+                format!("`{}` takes {} arguments but {} were given", name, expected, given)
+            },
+            CompileError::OutsideOfLoop(_, keyword) => format!("`{}` outside of a `while` loop", keyword),
+            CompileError::ReturnValueOutsideOfFunction(_) => "cannot return a value outside of a function".into(),
+            CompileError::OperandType(_, op, ty) => format!("cannot apply `{}` to {}", op, ty),
+            CompileError::OperandTypes(_, op, (lhs, _), (rhs, _)) => format!("cannot apply `{}` to {} and {}", op, lhs, rhs),
+            CompileError::AssignType(_, name, var_ty, ty, _) => format!("variable `{}` holds {} but is assigned {}", name, var_ty, ty),
+            CompileError::ListValue(_, usage) => format!("lists cannot be {}", usage),
+            CompileError::UnknownCommand(_, path) => format!("unknown library command `{}`", path),
+            CompileError::ArgumentType(_, path, ind, param, ty) => {
+                format!("argument {} of `{}` must be {}, found {}", ind, path, param, ty)
+            },
+            CompileError::CommandResults(_, path, count) => {
+                format!("`{}` returns {} values but an expression needs exactly one", path, count)
+            },
+            CompileError::StackUnderflow(_, takes, depth) => {
+                format!("instruction takes {} values but the stack only holds {}", takes, depth)
+            },
+            CompileError::StackUnbalanced(_, expected, depth) => {
+                format!("stack holds {} values when returning but {} were expected", depth, expected)
+            },
+            CompileError::StackJoin(_, first, other) => {
+                format!("paths join with {} and {} values on the stack", first, other)
+            },
+        }
+    }
+
+    // the other parts of the script involved in the error
+    pub fn notes(&self) -> Vec<(Span, String)> {
+        match self {
+            CompileError::DuplicateFunction(_, name, first) => vec![(*first, format!("`{}` is first declared here", name))],
+            CompileError::OperandTypes(_, _, (lhs, lhs_span), (rhs, rhs_span)) => {
+                vec![(*lhs_span, format!("this is {}", lhs)), (*rhs_span, format!("this is {}", rhs))]
+            },
+            CompileError::AssignType(_, name, var_ty, _, decl) => vec![(*decl, format!("`{}` is declared as {} here", name, var_ty))],
+            _ => Vec::new(),
+        }
+    }
+
+    // renders the error with the offending line of the script, followed by its notes
+    pub fn render(&self, fname: &str, src: &str) -> String {
+        let mut text = self.span().render(fname, src, &self.message());
+        for (span, note) in self.notes() {
+            text.push('\n');
+            text.push_str(&span.render_note(fname, src, &note));
+        }
+        text
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.span();
+        write!(f, "{}:{}: {}", span.line, span.col_start, self.message())
+    }
+}
+
+// parses the script into its syntax tree
+pub fn parse(src: &str) -> Result<ast::Script, CompileError> {
+    parser::parse(lexer::tokenize(src)?)
+}
+
+// checks the script for any problems; empty if the script can be compiled
+pub fn check(script: &ast::Script) -> Vec<CompileError> {
+    checker::check(script)
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct CompileOptions {
+    pub optimize: bool,  // runs the optimizing passes over the generated instructions
+    pub dump_ir: bool,  // keeps the instructions before and after each of the optimizing passes
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            optimize: true,
+            dump_ir: false,
+        }
+    }
+}
+
+// the instructions before and after a single run of an optimizing pass
+#[derive(Clone, PartialEq, Debug)]
+pub struct IrDump {
+    pub pass: &'static str,
+    pub changed: bool,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Compiled {
+    pub bytecode: String,
+    pub ir_dumps: Vec<IrDump>,  // in the order the passes ran; empty unless `dump_ir` is set
+}
+
+// compiles the script into the World Command Bytecode text with the default options
+// syntax errors stop at the first one, while all the other problems are reported together
+pub fn compile(src: &str) -> Result<String, Vec<CompileError>> {
+    compile_with(src, &CompileOptions::default()).map(|compiled| compiled.bytecode)
+}
+
+pub fn compile_with(src: &str, options: &CompileOptions) -> Result<Compiled, Vec<CompileError>> {
+    let script = parse(src).map_err(|err| vec![err])?;
+    let diagnostics = check(&script);
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+
+    let (mut instrs, routines) = codegen::generate(&script);
+    let mut ir_dumps = Vec::new();
+    if options.optimize {
+        instrs = optimizer::optimize(instrs, if options.dump_ir { Some(&mut ir_dumps) } else { None });
+    }
+
+    // checked after the optimizations, so what is checked is exactly what runs
+    let diagnostics = stack::verify(&instrs, &routines);
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+    Ok(Compiled {
+        bytecode: ir::format(&instrs),
+        ir_dumps,
+    })
+}
+
+// renders all the diagnostics of the script one after another
+pub fn render_all(diagnostics: &[CompileError], fname: &str, src: &str) -> String {
+    diagnostics.iter().map(|diag| diag.render(fname, src)).collect::<Vec<_>>().join("\n\n")
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    // the lines of the bytecode without any optimizations
+    fn lines(src: &str) -> Vec<String> {
+        compile_with(src, &CompileOptions { optimize: false, dump_ir: false })
+            .expect("The test script is valid").bytecode.lines().map(|ln| {
+            ln.split_whitespace().collect::<Vec<_>>().join(" ")
+        }).collect()
+    }
+
+    #[test]
+    fn compiles_expressions_by_precedence() {
+        assert_eq!(lines("let x = 1 + 2 * -3; print(x >= 2.5 || !x);"), vec![
+            "PUSH 1", "PUSH 2", "PUSH -3", "MUL", "ADD", "STORE $x",
+            "LOAD $x", "PUSH 2.5", "LT", "NOTL", "LOAD $x", "NOTL", "ORL", "COUT",
+            "RET",
+        ]);
+    }
+
+    #[test]
+    fn compiles_control_flow() {
+        assert_eq!(lines("let i = 3; while i { if i == 2 { break; } else { i = i - 1; } }"), vec![
+            "PUSH 3", "STORE $i",
+            "MRK #loop-1", "LOAD $i", "JMP_IF #body-1", "POP", "JMP #end-1", "MRK #body-1", "POP",
+            "LOAD $i", "PUSH 2", "EQ", "JMP_IF #then-2", "POP",
+            "LOAD $i", "PUSH 1", "SUB", "STORE $i",
+            "JMP #end-2", "MRK #then-2", "POP", "JMP #end-1", "MRK #end-2",
+            "JMP #loop-1", "MRK #end-1",
+            "RET",
+        ]);
+    }
+
+    #[test]
+    fn compiles_functions_and_namespaces() {
+        let src = "fn add(a, b) { return a + b; }\n\
+                   :MTXG-CMD:Teleport(:Matrixagon:world:player:main, add(1, 2), 0, :Matrixagon:world:player:main:pos:z);\n\
+                   :Matrixagon:world:player:main:pos:x = 0.0;";
+        assert_eq!(lines(src), vec![
+            "NAMESPACE :Matrixagon", "NAMESPACE :MTXG-CMD",
+            "PUSH :Matrixagon:world:player:main", "PUSH 1", "PUSH 2", "CALL #fn-add", "PUSH 0",
+            "PUSH :Matrixagon:world:player:main:pos:z", "CMD_MOVE :MTXG-CMD:Teleport",
+            "PUSH 0.0", "POP :Matrixagon:world:player:main:pos:x",
+            "RET",
+            "MRK #fn-add", "STORE $b", "STORE $a", "LOAD $a", "LOAD $b", "ADD", "RET", "PUSH 0", "RET",
+        ]);
+    }
+
+    #[test]
+    fn shadowed_variables_get_their_own_locals() {
+        assert_eq!(lines("let x = 1; if x { let x = x + 1; print(x); } print(x);"), vec![
+            "PUSH 1", "STORE $x",
+            "LOAD $x", "JMP_IF #then-1", "POP", "JMP #end-1", "MRK #then-1", "POP",
+            "LOAD $x", "PUSH 1", "ADD", "STORE $x-1", "LOAD $x-1", "COUT", "MRK #end-1",
+            "LOAD $x", "COUT",
+            "RET",
+        ]);
+    }
+
+    #[test]
+    fn reports_errors_with_spans() {
+        let src = "let a = 1;\nprint(a + b);";
+        let errs = compile(src).unwrap_err();
+        assert_eq!(errs, vec![CompileError::UnknownVariable(Span::new(2, 11, 11), "b".into())]);
+        assert_eq!(errs[0].render("test.wcs", src),
+                   "error: unknown variable `b`\n --> test.wcs:2:11\n  |\n2 | print(a + b);\n  |           ^");
+
+        assert_eq!(compile("let = 4;"), Err(vec![CompileError::UnexpectedToken(Span::new(1, 5, 5), "`=`".into(), "a name".into())]));
+        assert_eq!(compile("print(\"oops);"), Err(vec![CompileError::UnterminatedString(Span::new(1, 7, 13))]));
+        assert_eq!(compile("fn f(a) { return a; } f(1, 2);"),
+                   Err(vec![CompileError::ArgumentCount(Span::new(1, 23, 29), "f".into(), 1, 2)]));
+        assert_eq!(compile("while 1 { } break;"), Err(vec![CompileError::OutsideOfLoop(Span::new(1, 13, 17), "break")]));
+        assert_eq!(compile("if 1 { let y = 2; } print(y);").unwrap_err()[0].message(), "unknown variable `y`");
+    }
+
+    #[test]
+    fn reports_all_type_errors() {
+        let src = "fn name() { return \"abc\"; }\n\
+                   let x = 1;\n\
+                   x = name();\n\
+                   print(x - \"a\");\n\
+                   print(-name() < 2);\n\
+                   let l = [1, [2.5, \"c\"]];\n\
+                   print([1] == [1]);";
+        let errs = compile(src).unwrap_err();
+        let messages = errs.iter().map(CompileError::message).collect::<Vec<_>>();
+        assert_eq!(messages, vec![
+            "variable `x` holds Int but is assigned Str",
+            "cannot apply `-` to Int and Str",
+            "cannot apply `-` to Str",
+            "lists cannot be stored in a variable",
+            "cannot apply `==` to List and List",
+        ]);
+
+        assert_eq!(errs[1].render("test.wcs", src),
+                   "error: cannot apply `-` to Int and Str\n --> test.wcs:4:7\n  |\n4 | print(x - \"a\");\n  |       ^^^^^^^\n\
+                    note: this is Int\n --> test.wcs:4:7\n  |\n4 | print(x - \"a\");\n  |       ^\n\
+                    note: this is Str\n --> test.wcs:4:11\n  |\n4 | print(x - \"a\");\n  |           ^^^");
+
+        // lists are fine for printing, conditions and nested within other lists
+        assert!(compile("print([1, [2.5, \"c\"]]); if [] { print(1); }").is_ok());
+    }
+
+    #[test]
+    fn checks_library_commands() {
+        let src = ":MTXG-CMD:Teleprot(1);\n\
+                   :MTXG-CMD:SetBlock(0, 0, \"0\", 4);\n\
+                   :MTXG-CMD:Fill(0, 0, 0);\n\
+                   let id = :MTXG-CMD:Teleport(\"p\", 0, 0, 0);\n\
+                   let name = :MTXG-CMD:Query(0, 0, 0) + 1;\n\
+                   let any = :Somewhere:else(1, 2) + 1;";
+        let messages = compile(src).unwrap_err().iter().map(CompileError::message).collect::<Vec<_>>();
+        assert_eq!(messages, vec![
+            "unknown library command `:MTXG-CMD:Teleprot`",
+            "argument 3 of `:MTXG-CMD:SetBlock` must be a number, found Str",
+            "argument 4 of `:MTXG-CMD:SetBlock` must be Str, found Int",
+            "`:MTXG-CMD:Fill` takes 7 arguments but 3 were given",
+            "`:MTXG-CMD:Teleport` returns 0 values but an expression needs exactly one",
+            "cannot apply `+` to Str and Int",
+        ]);
+
+        // the returned values of the commands called as statements are discarded
+        assert_eq!(lines(":MTXG-CMD:Query(1, 2, 3);"), vec![
+            "NAMESPACE :MTXG-CMD", "PUSH 1", "PUSH 2", "PUSH 3", "CMD_MOVE :MTXG-CMD:Query", "POP", "RET",
+        ]);
+    }
+
+    #[test]
+    fn reports_duplicates_with_notes() {
+        let src = "fn f() { }\nfn f(a, a) { }";
+        let errs = compile(src).unwrap_err();
+        assert_eq!(errs, vec![
+            CompileError::DuplicateFunction(Span::new(2, 4, 4), "f".into(), Span::new(1, 4, 4)),
+            CompileError::DuplicateParameter(Span::new(2, 9, 9), "a".into()),
+        ]);
+        assert!(render_all(&errs, "test.wcs", src).contains("note: `f` is first declared here\n --> test.wcs:1:4"));
+    }
+
+    #[test]
+    fn verifies_stack_effects() {
+        use crate::ir::{Instr, Op, Arg};
+        use crate::stack::{verify, Routine};
+
+        let span = Span::new(1, 1, 1);
+        let instr = |op, arg| Instr { op, arg, span };
+        let label = |name: &str| Some(Arg::Label(name.into()));
+        let main = [Routine { label: None, depth: 0, returns: 0 }];
+
+        // a loop pushing a value on each round
+        let growing = vec![
+            instr(Op::Mrk, label("loop")), instr(Op::Push, Some(Arg::Int(1))),
+            instr(Op::JmpIf, label("loop")), instr(Op::Pop, None), instr(Op::Ret, None),
+        ];
+        assert_eq!(verify(&growing, &main), vec![CompileError::StackJoin(span, 0, 1)]);
+
+        let unbalanced = vec![instr(Op::Push, Some(Arg::Int(1))), instr(Op::Ret, None)];
+        assert_eq!(verify(&unbalanced, &main), vec![CompileError::StackUnbalanced(span, 0, 1)]);
+
+        let underflow = vec![instr(Op::Push, Some(Arg::Int(1))), instr(Op::Add, None), instr(Op::Ret, None)];
+        assert_eq!(verify(&underflow, &main), vec![CompileError::StackUnderflow(span, 2, 1)]);
+
+        // the functions return with their value, and start with their arguments
+        let func = vec![
+            instr(Op::Ret, None),
+            instr(Op::Mrk, label("fn-f")), instr(Op::Store, Some(Arg::Local("a".into()))), instr(Op::Ret, None),
+        ];
+        let routines = [main[0].clone(), Routine { label: Some("fn-f".into()), depth: 1, returns: 1 }];
+        assert_eq!(verify(&func, &routines), vec![CompileError::StackUnbalanced(span, 1, 0)]);
+    }
+
+    fn optimized(src: &str) -> Vec<String> {
+        compile(src).expect("The test script is valid").lines().map(|ln| {
+            ln.split_whitespace().collect::<Vec<_>>().join(" ")
+        }).collect()
+    }
+
+    #[test]
+    fn folds_constants_into_immediates() {
+        assert_eq!(optimized("let x = 2 * 3 + 1; print(x + 1 - 2 * 2); print(-(2.5 * 2) / 4 + 1 == 0.25 - 0.5);"), vec![
+            "PUSH 7", "STORE $x", "LOAD $x", "ADDI 1", "SUBI 4", "COUT", "PUSH 1", "COUT", "RET",
+        ]);
+        // the failing operations are left for the interpreter to report
+        assert_eq!(optimized("print(1 / 0); print(1.0 % 0);"), vec![
+            "PUSH 1", "DIVI 0", "COUT", "PUSH 1.0", "MODI 0", "COUT", "RET",
+        ]);
+    }
+
+    #[test]
+    fn removes_dead_code_and_unused_functions() {
+        let src = "fn unused() { return 1; }\n\
+                   fn used() { return 2; print(3); }\n\
+                   while 1 { print(used()); break; }";
+        assert_eq!(optimized(src), vec![
+            // the loop never comes back to its start once it breaks
+            "PUSH 1", "JMP_IF #body-1", "POP", "JMP #end-1", "MRK #body-1", "POP",
+            "CALL #fn-used", "COUT", "MRK #end-1", "RET",
+            "MRK #fn-used", "PUSH 2", "RET",
+        ]);
+    }
+
     #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
+    fn dumps_ir_of_each_pass() {
+        let compiled = compile_with("print(1 + 2);", &CompileOptions { optimize: true, dump_ir: true }).unwrap();
+        let passes = compiled.ir_dumps.iter().map(|dump| (dump.pass, dump.changed)).collect::<Vec<_>>();
+        assert_eq!(passes, vec![
+            ("fold", true), ("immediate", false), ("dead-code", false), ("markers", false),
+            ("fold", false), ("immediate", false), ("dead-code", false), ("markers", false),
+        ]);
+        assert_eq!(compiled.ir_dumps[0].before,
+                   "0000  PUSH        1                  ; 1:7\n0001  PUSH        2                  ; 1:11\n\
+                    0002  ADD                            ; 1:7\n0003  COUT                           ; 1:7\n\
+                    0004  RET                            ; 1:14\n");
+        assert_eq!(compiled.ir_dumps[0].after, compiled.ir_dumps[1].before);
+        assert!(compiled.ir_dumps[0].after.starts_with("0000  PUSH        3 "));
+
+        // nothing is kept unless asked for
+        assert!(compile_with("print(1 + 2);", &CompileOptions::default()).unwrap().ir_dumps.is_empty());
     }
 }
@@ -0,0 +1,65 @@
+/*
+The World Command Library Signatures
+
+The standard commands of matrixagon under `:MTXG-CMD:` with the values they take and return, so the
+calls can be checked before the script ever runs. Keep this in sync with `matrixagon::world::commands::library`.
+ */
+
+use crate::checker::Type;
+use self::Param::{Number, Str};
+
+use std::fmt;
+
+
+pub const COMMAND_NMSPC: &str = "MTXG-CMD";
+
+// the kind of value each argument of a command accepts
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Param {
+    Number,  // an integer or a float
+    Str,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Signature {
+    pub name: &'static str,
+    pub params: &'static [Param],
+    pub results: &'static [Type],
+}
+
+pub const LIBRARY: [Signature; 6] = [
+    // [entity, x, y, z] -> []
+    Signature { name: "Teleport", params: &[Str, Number, Number, Number], results: &[] },
+    // [x, y, z, block] -> []
+    Signature { name: "SetBlock", params: &[Number, Number, Number, Str], results: &[] },
+    // [x1, y1, z1, x2, y2, z2, block] -> []
+    Signature { name: "Fill", params: &[Number, Number, Number, Number, Number, Number, Str], results: &[] },
+    // [x1, y1, z1, x2, y2, z2, old block, new block] -> []
+    Signature { name: "Replace", params: &[Number, Number, Number, Number, Number, Number, Str, Str], results: &[] },
+    // [x1, y1, z1, x2, y2, z2, x, y, z] -> []
+    Signature { name: "Clone", params: &[Number, Number, Number, Number, Number, Number, Number, Number, Number], results: &[] },
+    // [x, y, z] -> [block]
+    Signature { name: "Query", params: &[Number, Number, Number], results: &[Type::Str] },
+];
+
+// checks if the path is within the library namespace, where only the library commands exist
+pub fn is_library(path: &[String]) -> bool {
+    path.first().map(String::as_str) == Some(COMMAND_NMSPC)
+}
+
+// the signature of the library command on the path
+pub fn signature(path: &[String]) -> Option<&'static Signature> {
+    match path {
+        [root, name] if root == COMMAND_NMSPC => LIBRARY.iter().find(|sig| sig.name == name),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Param::Number => write!(f, "a number"),
+            Param::Str => write!(f, "Str"),
+        }
+    }
+}
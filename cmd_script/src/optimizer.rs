@@ -0,0 +1,269 @@
+/*
+The World Command Scripting Language Optimizer
+
+Passes over the generated instructions that make the scripts cheaper to run, since many of them run
+on every tick. The passes run in order, again and again until none of them changes anything:
+
+    fold        computes the operations on constants, `PUSH 2; PUSH 3; MUL` becomes `PUSH 6`
+    immediate   takes the constant right sides as arguments, `PUSH 1; ADD` becomes `ADDI 1`
+    dead-code   removes what can never run after a `JMP` or `RET`, and jumps to the very next line
+    markers     removes the markers nothing jumps to; the functions that are never called go with them
+
+The folding follows the interpreter exactly, and leaves the operations that would fail at run time
+(like dividing by zero) for the interpreter to report.
+ */
+
+use crate::ir::{self, Instr, Op, Arg};
+use crate::IrDump;
+
+use std::collections::HashSet;
+
+
+type Pass = fn(&mut Vec<Instr>) -> bool;
+
+const PASSES: [(&str, Pass); 4] = [
+    ("fold", fold),
+    ("immediate", immediate),
+    ("dead-code", dead_code),
+    ("markers", markers),
+];
+
+// optimizes the instructions; the IR before and after each pass is appended to the dumps if there are any
+pub(crate) fn optimize(mut instrs: Vec<Instr>, mut dumps: Option<&mut Vec<IrDump>>) -> Vec<Instr> {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (name, pass) in PASSES.iter() {
+            let before = dumps.as_ref().map(|_| ir::dump(&instrs));
+            let pass_changed = pass(&mut instrs);
+            changed |= pass_changed;
+
+            if let (Some(dumps), Some(before)) = (dumps.as_mut(), before) {
+                dumps.push(IrDump {
+                    pass: name,
+                    changed: pass_changed,
+                    before,
+                    after: ir::dump(&instrs),
+                });
+            }
+        }
+    }
+    instrs
+}
+
+// a constant the instructions can push
+#[derive(Clone, PartialEq, Debug)]
+enum Const {
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl Const {
+    fn from_arg(arg: &Option<Arg>) -> Option<Self> {
+        match arg {
+            Some(Arg::Int(val)) => Some(Const::Int(*val)),
+            Some(Arg::Float(val)) => Some(Const::Float(*val)),
+            Some(Arg::Str(val)) => Some(Const::Str(val.clone())),
+            _ => None,
+        }
+    }
+
+    fn into_arg(self) -> Arg {
+        match self {
+            Const::Int(val) => Arg::Int(val),
+            Const::Float(val) => Arg::Float(val),
+            Const::Str(val) => Arg::Str(val),
+        }
+    }
+
+    fn truthy(&self) -> bool {
+        match self {
+            Const::Int(val) => *val != 0,
+            Const::Float(val) => *val != 0.0,
+            Const::Str(val) => !val.is_empty(),
+        }
+    }
+}
+
+// the constant pushed by the instruction
+fn pushed(instr: &Instr) -> Option<Const> {
+    match instr.op {
+        Op::Push => Const::from_arg(&instr.arg),
+        _ => None,
+    }
+}
+
+fn fold(instrs: &mut Vec<Instr>) -> bool {
+    let mut changed = false;
+    let mut ind = 0;
+
+    while ind < instrs.len() {
+        // PUSH a; PUSH b; <binary>
+        if ind >= 2 {
+            if let (Some(a), Some(b)) = (pushed(&instrs[ind-2]), pushed(&instrs[ind-1])) {
+                if let Some(val) = binary(instrs[ind].op, a, b) {
+                    let span = instrs[ind].span;
+                    instrs.splice(ind-2..=ind, vec![Instr { op: Op::Push, arg: Some(val.into_arg()), span }]);
+                    ind -= 2;
+                    changed = true;
+                    continue;
+                }
+            }
+        }
+        // PUSH a; <unary>
+        if ind >= 1 {
+            if let Some(a) = pushed(&instrs[ind-1]) {
+                if let Some(val) = unary(instrs[ind].op, a) {
+                    let span = instrs[ind].span;
+                    instrs.splice(ind-1..=ind, vec![Instr { op: Op::Push, arg: Some(val.into_arg()), span }]);
+                    ind -= 1;
+                    changed = true;
+                    continue;
+                }
+            }
+        }
+        ind += 1;
+    }
+
+    changed
+}
+
+fn immediate(instrs: &mut Vec<Instr>) -> bool {
+    let mut changed = false;
+    let mut ind = 1;
+
+    while ind < instrs.len() {
+        let op = match instrs[ind].op {
+            Op::Add => Op::AddI,
+            Op::Sub => Op::SubI,
+            Op::Mul => Op::MulI,
+            Op::Div => Op::DivI,
+            Op::Mod => Op::ModI,
+            _ => {
+                ind += 1;
+                continue;
+            },
+        };
+
+        if let Some(val) = pushed(&instrs[ind-1]) {
+            let span = instrs[ind].span;
+            instrs.splice(ind-1..=ind, vec![Instr { op, arg: Some(val.into_arg()), span }]);
+            changed = true;
+        } else {
+            ind += 1;
+        }
+    }
+
+    changed
+}
+
+fn dead_code(instrs: &mut Vec<Instr>) -> bool {
+    let len = instrs.len();
+    let mut kept: Vec<Instr> = Vec::with_capacity(len);
+    let mut reachable = true;
+
+    for instr in instrs.drain(..) {
+        // the markers can be jumped to from anywhere
+        if instr.op == Op::Mrk {
+            reachable = true;
+            // a jump to the very next line does nothing
+            if let (Some(last), Some(Arg::Label(label))) = (kept.last(), &instr.arg) {
+                if last.op == Op::Jmp && last.target() == Some(label.as_str()) {
+                    kept.pop();
+                }
+            }
+        }
+        if !reachable {
+            continue;
+        }
+
+        if matches!(instr.op, Op::Jmp | Op::Ret) {
+            reachable = false;
+        }
+        kept.push(instr);
+    }
+
+    *instrs = kept;
+    instrs.len() != len
+}
+
+fn markers(instrs: &mut Vec<Instr>) -> bool {
+    let used = instrs.iter().filter(|instr| instr.op != Op::Mrk).filter_map(|instr| match &instr.arg {
+        Some(Arg::Label(label)) => Some(label.clone()),
+        _ => None,
+    }).collect::<HashSet<_>>();
+
+    let len = instrs.len();
+    instrs.retain(|instr| match (instr.op, &instr.arg) {
+        (Op::Mrk, Some(Arg::Label(label))) => used.contains(label),
+        _ => true,
+    });
+    instrs.len() != len
+}
+
+// the same as the binary operations of the interpreter; None when it should be left for the run time
+fn binary(op: Op, a: Const, b: Const) -> Option<Const> {
+    use Const::{Int, Float, Str};
+
+    match op {
+        Op::AndL => return Some(Int((a.truthy() && b.truthy()) as i64)),
+        Op::OrL => return Some(Int((a.truthy() || b.truthy()) as i64)),
+        Op::Eq => return Some(Int(match (&a, &b) {
+            (Int(a), Float(b)) | (Float(b), Int(a)) => *a as f64 == *b,
+            (a, b) => a == b,
+        } as i64)),
+        _ => {},
+    }
+
+    let val = match (a, b) {
+        (Int(a), Int(b)) => match op {
+            Op::Add => Int(a.wrapping_add(b)),
+            Op::Sub => Int(a.wrapping_sub(b)),
+            Op::Mul => Int(a.wrapping_mul(b)),
+            Op::Div if b != 0 => Int(a.wrapping_div(b)),
+            Op::Mod if b != 0 => Int(a.wrapping_rem(b)),
+            Op::Lt => Int((a < b) as i64),
+            Op::Gt => Int((a > b) as i64),
+            _ => return None,
+        },
+        (Int(a), Float(b)) => float_binary(op, a as f64, b)?,
+        (Float(a), Int(b)) => float_binary(op, a, b as f64)?,
+        (Float(a), Float(b)) => float_binary(op, a, b)?,
+        (Str(a), Str(b)) => match op {
+            Op::Add => Str(a + &b),
+            Op::Lt => Int((a < b) as i64),
+            Op::Gt => Int((a > b) as i64),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    // the bytecode text has no way to write the infinities and NaN
+    match val {
+        Float(val) if !val.is_finite() => None,
+        val => Some(val),
+    }
+}
+
+fn float_binary(op: Op, a: f64, b: f64) -> Option<Const> {
+    match op {
+        Op::Add => Some(Const::Float(a + b)),
+        Op::Sub => Some(Const::Float(a - b)),
+        Op::Mul => Some(Const::Float(a * b)),
+        Op::Div if b != 0.0 => Some(Const::Float(a / b)),
+        Op::Mod if b != 0.0 => Some(Const::Float(a % b)),
+        Op::Lt => Some(Const::Int((a < b) as i64)),
+        Op::Gt => Some(Const::Int((a > b) as i64)),
+        _ => None,
+    }
+}
+
+fn unary(op: Op, a: Const) -> Option<Const> {
+    match (op, a) {
+        (Op::NotL, a) => Some(Const::Int(!a.truthy() as i64)),
+        (Op::Neg, Const::Int(a)) => Some(Const::Int(a.wrapping_neg())),
+        (Op::Neg, Const::Float(a)) => Some(Const::Float(-a)),
+        _ => None,
+    }
+}
@@ -0,0 +1,302 @@
+/*
+The World Command Scripting Language Parser
+
+A recursive descent parser from the tokens into the syntax tree. The binary operators from the lowest
+to the highest precedence are `||`, `&&`, `== !=`, `< <= > >=`, `+ -` and `* / %`; all of them are
+left associative.
+ */
+
+use crate::ast::{Script, Function, Stmt, Expr, ExprKind, UnaryOp, BinaryOp};
+use crate::lexer::Token;
+use crate::{CompileError, Span};
+
+
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    ind: usize,
+}
+
+pub(crate) fn parse(tokens: Vec<(Token, Span)>) -> Result<Script, CompileError> {
+    let mut parser = Parser { tokens, ind: 0 };
+    let mut body = Vec::new();
+    let mut functions = Vec::new();
+
+    while *parser.peek() != Token::Eof {
+        if *parser.peek() == Token::Fn {
+            functions.push(parser.function()?);
+        } else {
+            body.push(parser.statement()?);
+        }
+    }
+
+    Ok(Script { body, functions, end: parser.span() })
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.ind].0
+    }
+
+    fn span(&self) -> Span {
+        self.tokens[self.ind].1
+    }
+
+    // the span of the previously consumed token
+    fn prev_span(&self) -> Span {
+        self.tokens[self.ind.saturating_sub(1)].1
+    }
+
+    fn bump(&mut self) -> (Token, Span) {
+        let tkn = self.tokens[self.ind].clone();
+        // the last token is always kept to be peeked at
+        if self.ind < self.tokens.len()-1 {
+            self.ind += 1;
+        }
+        tkn
+    }
+
+    fn eat(&mut self, expected: &Token) -> bool {
+        if self.peek() == expected {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<Span, CompileError> {
+        if self.peek() == expected {
+            Ok(self.bump().1)
+        } else {
+            Err(self.unexpected(&expected.describe()))
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<(String, Span), CompileError> {
+        match self.peek().clone() {
+            Token::Ident(name) => Ok((name, self.bump().1)),
+            _ => Err(self.unexpected("a name")),
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> CompileError {
+        CompileError::UnexpectedToken(self.span(), self.peek().describe(), String::from(expected))
+    }
+
+    // fn name(params, ...) { body }
+    fn function(&mut self) -> Result<Function, CompileError> {
+        self.expect(&Token::Fn)?;
+        let (name, span) = self.expect_ident()?;
+
+        self.expect(&Token::LParen)?;
+        let mut params = Vec::new();
+        if !self.eat(&Token::RParen) {
+            loop {
+                params.push(self.expect_ident()?);
+                if self.eat(&Token::RParen) {
+                    break;
+                }
+                self.expect(&Token::Comma)?;
+            }
+        }
+
+        let body = self.block()?;
+        Ok(Function {
+            name,
+            params,
+            body,
+            span,
+            end: self.prev_span(),
+        })
+    }
+
+    // { statements }
+    fn block(&mut self) -> Result<Vec<Stmt>, CompileError> {
+        self.expect(&Token::LBrace)?;
+        let mut stmts = Vec::new();
+        while !self.eat(&Token::RBrace) {
+            if *self.peek() == Token::Eof {
+                return Err(self.unexpected("`}`"));
+            }
+            stmts.push(self.statement()?);
+        }
+        Ok(stmts)
+    }
+
+    fn statement(&mut self) -> Result<Stmt, CompileError> {
+        let stmt = match self.peek().clone() {
+            Token::Let => {
+                self.bump();
+                let (name, span) = self.expect_ident()?;
+                self.expect(&Token::Assign)?;
+                Stmt::Let(name, self.expression()?, span)
+            },
+            Token::If => return self.if_statement(),
+            Token::While => {
+                self.bump();
+                let cond = self.expression()?;
+                return Ok(Stmt::While(cond, self.block()?));
+            },
+            Token::Break => Stmt::Break(self.bump().1),
+            Token::Continue => Stmt::Continue(self.bump().1),
+            Token::Return => {
+                let span = self.bump().1;
+                if *self.peek() == Token::Semicolon {
+                    Stmt::Return(None, span)
+                } else {
+                    Stmt::Return(Some(self.expression()?), span)
+                }
+            },
+            Token::Print => {
+                self.bump();
+                self.expect(&Token::LParen)?;
+                let val = self.expression()?;
+                self.expect(&Token::RParen)?;
+                Stmt::Print(val)
+            },
+            Token::Ident(name) if self.tokens[self.ind+1].0 == Token::Assign => {
+                let span = self.bump().1;
+                self.bump();
+                Stmt::Assign(name, self.expression()?, span)
+            },
+            Token::Namespace(path) if self.tokens[self.ind+1].0 == Token::Assign => {
+                let span = self.bump().1;
+                self.bump();
+                Stmt::SetNamespace(path, self.expression()?, span)
+            },
+            Token::Fn => return Err(self.unexpected("a statement (functions can only be declared at the top level)")),
+            _ => Stmt::Expr(self.expression()?),
+        };
+
+        self.expect(&Token::Semicolon)?;
+        Ok(stmt)
+    }
+
+    // if cond { ... } else if cond { ... } else { ... }
+    fn if_statement(&mut self) -> Result<Stmt, CompileError> {
+        self.expect(&Token::If)?;
+        let cond = self.expression()?;
+        let then = self.block()?;
+
+        let els = if self.eat(&Token::Else) {
+            if *self.peek() == Token::If {
+                vec![self.if_statement()?]
+            } else {
+                self.block()?
+            }
+        } else {
+            Vec::new()
+        };
+
+        Ok(Stmt::If(cond, then, els))
+    }
+
+    fn expression(&mut self) -> Result<Expr, CompileError> {
+        self.binary(0)
+    }
+
+    // parses the binary operators of the precedence level and all the higher levels
+    fn binary(&mut self, level: usize) -> Result<Expr, CompileError> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+
+        let mut lhs = self.binary(level+1)?;
+        while let Some(op) = PRECEDENCE[level].iter().find(|(tkn, _)| tkn == self.peek()).map(|(_, op)| *op) {
+            self.bump();
+            let rhs = self.binary(level+1)?;
+            let span = lhs.span.to(&rhs.span);
+            lhs = Expr { kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)), span };
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        let op = match self.peek() {
+            Token::Minus => UnaryOp::Neg,
+            Token::Bang => UnaryOp::Not,
+            _ => return self.primary(),
+        };
+        let span = self.bump().1;
+        let val = self.unary()?;
+        let span = span.to(&val.span);
+
+        // negative number literals are kept as literals
+        let kind = match (op, val.kind) {
+            (UnaryOp::Neg, ExprKind::Int(i)) => ExprKind::Int(i.wrapping_neg()),
+            (UnaryOp::Neg, ExprKind::Float(f)) => ExprKind::Float(-f),
+            (op, kind) => ExprKind::Unary(op, Box::new(Expr { kind, span: val.span })),
+        };
+        Ok(Expr { kind, span })
+    }
+
+    fn primary(&mut self) -> Result<Expr, CompileError> {
+        let span = self.span();
+        let kind = match self.bump().0 {
+            Token::Int(val) => ExprKind::Int(val),
+            Token::Float(val) => ExprKind::Float(val),
+            Token::Str(val) => ExprKind::Str(val),
+            Token::True => ExprKind::Int(1),
+            Token::False => ExprKind::Int(0),
+            Token::Ident(name) => {
+                if *self.peek() == Token::LParen {
+                    ExprKind::Call(name, self.arguments()?)
+                } else {
+                    ExprKind::Var(name)
+                }
+            },
+            Token::Namespace(path) => {
+                if *self.peek() == Token::LParen {
+                    ExprKind::NamespaceCall(path, self.arguments()?)
+                } else {
+                    ExprKind::Namespace(path)
+                }
+            },
+            Token::LBracket => {
+                self.ind -= 1;
+                ExprKind::List(self.sequence(&Token::LBracket, &Token::RBracket)?)
+            },
+            Token::LParen => {
+                let mut inner = self.expression()?;
+                self.expect(&Token::RParen)?;
+                inner.span = span.to(&self.prev_span());
+                return Ok(inner);
+            },
+            tkn => return Err(CompileError::UnexpectedToken(span, tkn.describe(), String::from("an expression"))),
+        };
+
+        Ok(Expr { kind, span: span.to(&self.prev_span()) })
+    }
+
+    // (expr, ...)
+    fn arguments(&mut self) -> Result<Vec<Expr>, CompileError> {
+        self.sequence(&Token::LParen, &Token::RParen)
+    }
+
+    // expressions separated by commas between the brackets like `(expr, ...)` and `[expr, ...]`
+    fn sequence(&mut self, open: &Token, close: &Token) -> Result<Vec<Expr>, CompileError> {
+        self.expect(open)?;
+        let mut args = Vec::new();
+        if !self.eat(close) {
+            loop {
+                args.push(self.expression()?);
+                if self.eat(close) {
+                    break;
+                }
+                self.expect(&Token::Comma)?;
+            }
+        }
+        Ok(args)
+    }
+}
+
+// the binary operators of each precedence level from the lowest to the highest
+const PRECEDENCE: [&[(Token, BinaryOp)]; 6] = [
+    &[(Token::OrOr, BinaryOp::Or)],
+    &[(Token::AndAnd, BinaryOp::And)],
+    &[(Token::EqEq, BinaryOp::Eq), (Token::NotEq, BinaryOp::NotEq)],
+    &[(Token::Lt, BinaryOp::Lt), (Token::LtEq, BinaryOp::LtEq), (Token::Gt, BinaryOp::Gt), (Token::GtEq, BinaryOp::GtEq)],
+    &[(Token::Plus, BinaryOp::Add), (Token::Minus, BinaryOp::Sub)],
+    &[(Token::Star, BinaryOp::Mul), (Token::Slash, BinaryOp::Div), (Token::Percent, BinaryOp::Mod)],
+];
@@ -0,0 +1,92 @@
+/*
+The World Command Scripting Language Stack Checker
+
+Follows every path through the generated instructions while counting the items on the stack, to verify
+that each `RET` leaves the stack balanced: empty at the end of the main program, and exactly the
+returned value at the end of a function. Paths that join at a marker must agree on the stack depth too,
+otherwise a loop would grow (or shrink) the stack on each round.
+ */
+
+use crate::ir::{Instr, Op, Arg};
+use crate::CompileError;
+
+use std::collections::HashMap;
+
+
+// where a routine (the main program or a function) starts, and the stack depths it starts and returns with
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Routine {
+    pub(crate) label: Option<String>,  // the marker of the function; None for the main program at the start
+    pub(crate) depth: usize,  // the arguments on the stack when the function is called
+    pub(crate) returns: usize,  // the values left on the stack by `RET`
+}
+
+pub(crate) fn verify(instrs: &[Instr], routines: &[Routine]) -> Vec<CompileError> {
+    let mut markers = HashMap::new();
+    for (ind, instr) in instrs.iter().enumerate() {
+        if let (Op::Mrk, Some(Arg::Label(label))) = (instr.op, &instr.arg) {
+            markers.insert(label.as_str(), ind);
+        }
+    }
+
+    let mut diagnostics = Vec::new();
+    let mut depths: Vec<Option<usize>> = vec![None; instrs.len()];
+
+    for routine in routines {
+        let start = match &routine.label {
+            Some(label) => match markers.get(label.as_str()) {
+                Some(ind) => *ind,
+                None => continue,
+            },
+            None => 0,
+        };
+
+        let mut pending = vec![(start, routine.depth)];
+        while let Some((ind, depth)) = pending.pop() {
+            let instr = match instrs.get(ind) {
+                Some(instr) => instr,
+                // running off the end of the instructions returns the same as `RET`
+                None => {
+                    if depth != routine.returns {
+                        if let Some(last) = instrs.last() {
+                            diagnostics.push(CompileError::StackUnbalanced(last.span, routine.returns, depth));
+                        }
+                    }
+                    continue;
+                },
+            };
+
+            match depths[ind] {
+                Some(known) if known != depth => {
+                    diagnostics.push(CompileError::StackJoin(instr.span, known, depth));
+                    continue;
+                },
+                Some(_) => continue,
+                None => depths[ind] = Some(depth),
+            }
+
+            let (takes, leaves) = instr.stack_effect();
+            if depth < takes {
+                diagnostics.push(CompileError::StackUnderflow(instr.span, takes, depth));
+                continue;
+            }
+            let next = depth - takes + leaves;
+
+            match instr.op {
+                Op::Ret => {
+                    if depth != routine.returns {
+                        diagnostics.push(CompileError::StackUnbalanced(instr.span, routine.returns, depth));
+                    }
+                },
+                Op::Jmp => pending.extend(instr.target().and_then(|label| markers.get(label)).map(|ind| (*ind, next))),
+                Op::JmpIf => {
+                    pending.extend(instr.target().and_then(|label| markers.get(label)).map(|ind| (*ind, next)));
+                    pending.push((ind+1, next));
+                },
+                _ => pending.push((ind+1, next)),
+            }
+        }
+    }
+
+    diagnostics
+}
@@ -4,6 +4,7 @@ use crate::ui::{App, Layout};
 use crate::ui::layout as lyt;
 use crate::datatype::Dimension;
 use crate::world::World;
+use crate::world::settings::WorldSettings;
 use crate::event::EventDispatcher;
 
 use vulkano::device::{Device, Queue};
@@ -92,7 +93,7 @@ impl MainApp<lyt::StackLayout> {
         let app_gp = app.render_gp(device.clone(), dimensions);
         // TODO: a separate struct to write pure ui code for the app?
 
-        let mut world = World::new(device.clone(), queue.clone(), evd.clone(), renderpass.clone(), dimensions);
+        let mut world = World::new(device.clone(), queue.clone(), evd.clone(), renderpass.clone(), dimensions, WorldSettings::from_env());
 
         let future = Some(world.bind_texture(future.unwrap()));
 
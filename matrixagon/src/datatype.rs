@@ -13,13 +13,6 @@ use std::ops::{Div, Mul, Add, Sub, Neg};
 use std::fmt::Debug;
 
 
-// TODO: in future, there will be a custom float and integer type or uses another library's numeric types
-// an empty trait for all standard float
-trait StdFloat {}
-
-impl StdFloat for f32 {}
-impl StdFloat for f64 {}
-
 pub enum BlockFace {
     Top, Bottom,
     Left, Right,
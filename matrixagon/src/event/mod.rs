@@ -1,190 +1,671 @@
-use std::collections::vec_deque::VecDeque;
-use std::collections::HashMap;
-use std::mem;
+/*
+The Event System
+
+Each event is its own type implementing `Event` (see `types`), so emitting and receiving an event are
+checked at compile time; a payload of the wrong type is a type error instead of a panic at emit time.
+
+The emitted events are buffered until `event_swap()`, so all the receivers of the same iteration see
+the same events no matter the order they were called in. The events can either be polled with `receive()`,
+or handled by the subscribers the dispatcher calls on each `event_swap()`.
+
+The dispatcher itself stays on the main thread, while the other threads (e.g. the chunk workers) post
+their events through an `EventSender` (see `sender`). Every event takes a number from a single sequence as it
+is buffered (the posted events when they are merged on the swap), and the events of each type are always
+handled in the order of their numbers.
+
+The events can also be recorded along with the frame (the `event_swap()`) they were handled on, then
+replayed into another dispatcher to reproduce the same session (see `record`).
+
+Besides the next swap, the events can be scheduled on a later tick (a swap), either once or repeatedly
+(see `schedule`), e.g. for the block updates and the timed scripts.
+
+The events no receiver nor subscriber has seen by the next swap are reported according to the
+`UnhandledPolicy` of the dispatcher; ignored by default, as many events are only handled when needed.
+
+Neither `emit()` nor `receive()` returns a `Result`, as the typed events leave them nothing to fail on: there
+are no event names to be unregistered, and no values to be popped one by one, of the wrong type or left
+unpopped (hence no `try_pop()`). The only errors left are the `EventError`s; the unhandled events reported
+by the policy, and the nested swaps returned by `event_swap()`.
+ */
+
+use std::collections::{HashMap, HashSet};
 use std::any::{TypeId, Any};
-use std::fmt::Debug;
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{RefCell, Cell};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::io::{self, Write};
+
+// declares each event type along with its name
+// the fields of the event are its data, e.g. `"MeshEvent/LoadChunk" => LoadChunk(id: u32)`
+macro_rules! events {
+    {$($(#[$meta:meta])* $name:literal => $event:ident $(($($field:ident: $ty:ty),* $(,)?))?,)*} => {
+        $(
+            #[derive(Clone)]
+            $(#[$meta])*
+            pub struct $event $(($(pub $ty),*))?;
+
+            impl $crate::event::Event for $event {
+                const NAME: &'static str = $name;
+
+                #[allow(unused_variables)]
+                fn write(&self, fields: &mut Vec<String>) {
+                    let $event $(($($field),*))? = self;
+                    $($($crate::event::record::EventField::write($field, fields);)*)?
+                }
+
+                #[allow(unused_variables)]
+                fn read(fields: &mut $crate::event::record::Fields) -> Option<Self> {
+                    Some($event $(($(<$ty as $crate::event::record::EventField>::read(fields)?),*))?)
+                }
+            }
+        )*
+
+        // registers the event types to be replayed
+        #[allow(dead_code)]
+        pub fn register_events(replay: &mut $crate::event::record::EventReplay) {
+            $(replay.register::<$event>();)*
+        }
+    }
+}
 
 pub mod types;
+pub mod sender;
+pub mod record;
+pub mod schedule;
+
+pub use sender::EventSender;
+pub use schedule::TimerID;
+use sender::Inbox;
+use record::Recorder;
+use schedule::Schedule;
+
+
+// a type that can be emitted through the EventDispatcher; its fields are the data of the event
+// usually declared with `events!`, which also writes and reads the fields for the recordings
+pub trait Event: Clone + 'static {
+    const NAME: &'static str;  // the name in the recordings
 
+    // writes the fields of the event as the text fields of the recording
+    fn write(&self, fields: &mut Vec<String>);
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
-pub struct EventName(pub &'static str);
-
-// link: https://play.rust-lang.org/?version=stable&mode=debug&edition=2018&gist=6b6fb317914af0be5545e90fe17fc472
-// A struct used for transmitting event data to the callback closures
-// The data is expected to be shared using Rc<> pointer, and are not mutably changed.
-#[derive(Clone, Debug)]
-pub struct EventData {
-    // just to compartmentalize code between adding data before emitting and using the data while emitting
-    packed: bool,
-    // to signify this EventData is to be released to the closures
-    released: bool,
-    data: Rc<RefCell<VecDeque<Box<dyn Any>>>>,
-    cycled: u8,  // an internal tracking number to track how many times the values was popped
-    type_info: Vec<TypeId>,
+    // reads the event back from the text fields of the recording
+    fn read(fields: &mut record::Fields) -> Option<Self>;
 }
 
-impl EventData {
-    pub fn new() -> Self {
-        Self {
-            packed: false,
-            released: false,
-            data: Rc::new(RefCell::new(VecDeque::new())),
-            cycled: 0,
-            type_info: Vec::new(),
+#[derive(Clone, PartialEq, Debug)]
+pub enum EventError {
+    // No receiver nor subscriber has seen the events before the next swap
+    Unhandled(&'static str, usize),  // the event name and the number of the events
+    // `event_swap()` was called by a subscriber while the dispatcher was swapping
+    NestedSwap,
+}
+
+impl EventError {
+    pub fn message(&self) -> String {
+        match self {
+            EventError::Unhandled(name, count) => format!("{} event(s) `{}` were not handled", count, name),
+            EventError::NestedSwap => "the events cannot be swapped by a subscriber".into(),
         }
     }
+}
 
-    // for literals, writing out the type is really important
-    // u32 vs u64 is really a big difference
-    pub fn push<T: Clone + 'static>(&mut self, param: T) {
-        if !self.packed {
-            (*self.data).borrow_mut().push_back(Box::new(param) as Box<dyn Any>);
-            self.type_info.push(TypeId::of::<T>());
-        } else {
-            panic!("Pushing new value after `pack()`");
-        }
+// what the dispatcher does with the unhandled events (see `EventError::Unhandled`)
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum UnhandledPolicy {
+    Panic,
+    Log,
+    Ignore,
+}
+
+// the events of a single event type along with their sequence numbers, ordered by the numbers
+type Queue<E> = Vec<(u64, E)>;
+
+// the queue of a single event type
+struct Queued {
+    name: &'static str,
+    len: usize,
+    events: Box<dyn Any>,  // the `Queue<E>`
+    handled: Cell<bool>,  // whether a receiver or subscriber has seen the events
+}
+
+// the events of each event type, under the type of the event
+type EventQueues = HashMap<TypeId, Queued>;
+// takes the `Queue<E>` of the events to be handled
+type Handler = Box<dyn FnMut(&dyn Any)>;
+
+// the handle of a subscriber for unsubscribing it
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct SubscriptionID(u32);
+
+// A long-lived handler of a single event type
+struct Subscriber {
+    id: SubscriptionID,
+    priority: i32,
+    event: TypeId,
+    handler: Handler,
+}
+
+// the events waiting for the next swap
+struct EventBuffer {
+    queues: EventQueues,
+    recorder: Option<Recorder>,  // only exists while recording
+}
+
+pub struct EventDispatcher {
+    // a synchronized way to dispatch all the emitted events at the same time
+    events_buf: RefCell<EventBuffer>,
+    // a list of all emitted events since last flushed
+    events: RefCell<EventQueues>,
+
+    // ordered from the highest priority; the same priorities are in the order they subscribed
+    subscribers: RefCell<Vec<Subscriber>>,
+    subscribed: RefCell<HashSet<SubscriptionID>>,  // the subscribers that have not unsubscribed
+    sub_counter: Cell<u32>,
+
+    inbox: Arc<Inbox>,  // the events posted from the other threads
+    schedule: RefCell<Schedule>,  // the events emitted ahead of time
+    frame: Cell<u64>,  // the number of swaps so far
+    swapping: Cell<bool>,
+    unhandled_policy: Cell<UnhandledPolicy>,
+}
+
+impl EventDispatcher {
+    pub fn new() -> Rc<Self> {
+        Rc::new(Self {
+            events_buf: RefCell::new(EventBuffer { queues: HashMap::new(), recorder: None }),
+            events: RefCell::new(HashMap::new()),
+            subscribers: RefCell::new(Vec::new()),
+            subscribed: RefCell::new(HashSet::new()),
+            sub_counter: Cell::new(0),
+            inbox: Arc::new(Inbox::new()),
+            schedule: RefCell::new(Schedule::new()),
+            frame: Cell::new(0),
+            swapping: Cell::new(false),
+            unhandled_policy: Cell::new(UnhandledPolicy::Ignore),
+        })
+    }
+
+    pub fn set_unhandled_policy(&self, policy: UnhandledPolicy) {
+        self.unhandled_policy.set(policy);
+    }
+
+    // the event will be received after the next `event_swap()`
+    pub fn emit<E: Event>(&self, event: E) {
+        let seq = self.inbox.sequence.fetch_add(1, Ordering::SeqCst);
+        insert_event(&mut self.events_buf.borrow_mut(), seq, event);
+    }
+
+    // the event will be received after the swap `ticks` swaps from now; `emit_in(1, ..)` is the same as `emit()`
+    pub fn emit_in<E: Event>(&self, ticks: u64, event: E) -> TimerID {
+        self.emit_at(self.frame.get() + ticks.max(1), event)
+    }
+
+    // the event will be received after the swap that makes `frame()` reach the tick, or the next swap if it has
+    pub fn emit_at<E: Event>(&self, tick: u64, event: E) -> TimerID {
+        self.schedule.borrow_mut().add(tick, None, event)
+    }
+
+    // the event will be received every `ticks` swaps until it is cancelled, starting `ticks` swaps from now
+    pub fn emit_every<E: Event>(&self, ticks: u64, event: E) -> TimerID {
+        self.schedule.borrow_mut().add(self.frame.get() + ticks.max(1), Some(ticks), event)
+    }
+
+    // stops the scheduled event; returns false if it was already emitted (once) or cancelled
+    pub fn cancel(&self, id: TimerID) -> bool {
+        self.schedule.borrow_mut().cancel(id)
+    }
+
+    // the number of the events waiting for their ticks, counting each repeating event once
+    pub fn scheduled(&self) -> usize {
+        self.schedule.borrow().len()
+    }
+
+    // a handle for emitting the events from the other threads
+    pub fn sender(&self) -> EventSender {
+        EventSender::new(self.inbox.clone())
+    }
+
+    // the number of swaps so far; the events are recorded with the frame they were handled on
+    pub fn frame(&self) -> u64 {
+        self.frame.get()
     }
 
-    // packs the value just makes it so there are only one area where you can push
-    // and another compartmentalized area to pop these values
-    // and also this produces type info that will be used later in EventDispatcher
-    fn pack(&mut self) {
-        self.packed = true;
+    // records all the events handled from the next swap onwards, each as a line of text
+    pub fn start_recording<W: Write + 'static>(&self, writer: W) {
+        self.events_buf.borrow_mut().recorder = Some(Recorder::new(Box::new(writer)));
     }
 
-    // used when the EventDispatcher calls clear on events, doesn't
-    // raise the EventData with error at Drop
-    fn release(&mut self) {
-        self.released = true;
+    // records the events into the file, replacing the file if it already exists
+    pub fn record_file(&self, fname: &str) -> io::Result<()> {
+        let file = std::fs::File::create(fname)?;
+        self.start_recording(io::BufWriter::new(file));
+        Ok(())
     }
 
-    fn type_info(&self) -> Vec<TypeId> {
-        if self.packed {
-            // the type info is assumed to always be Some(T) after `pack()`
-            let typ = self.type_info.clone();
-            // to make sure numbers of types is exact to the amount of the actual data
-            assert_eq!(typ.len(), (*self.data).borrow_mut().len());
-            typ
-        } else {
-            panic!("Retrieving type information of an Event Data without first calling `pack()`");
+    // stops recording; the events emitted since the last swap are not recorded
+    pub fn stop_recording(&self) -> io::Result<()> {
+        match self.events_buf.borrow_mut().recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
         }
     }
 
-    pub fn pop<T: Clone + 'static>(&mut self) -> T {
-        if self.packed && self.released {
-            if self.cycled > self.type_info.len() as u8 {
-                self.cycled = self.type_info.len() as u8;  // to satisfy the panic on Drop to avoid confusion due to cycles "cycling" again
-                panic!(format!("Too many values popped! There are only <{:?}> values within this EventData", self.type_info.len()));
+    // to receive and call the closure for all the selected events since last flushed
+    pub fn receive<E: Event, F: FnMut(&E)>(&self, mut closure: F) {
+        for (_, event) in self.received::<E>() {
+            closure(&event);
+        }
+    }
+
+    // to receive and call the closure once of the first selected event since last flushed
+    pub fn receive_once<E: Event, F: FnOnce(&E)>(&self, closure: F) {
+        if let Some((_, event)) = self.received::<E>().first() {
+            closure(event);
+        }
+    }
+
+    // calls the handler with each of the events on every `event_swap()` until it unsubscribes
+    // the handlers with higher priorities are called first
+    pub fn subscribe<E: Event, F: FnMut(&E) + 'static>(&self, priority: i32, handler: F) -> SubscriptionID {
+        self.subscribe_if(priority, |_: &E| true, handler)
+    }
+
+    // same as `subscribe()`, but only with the events passing the filter
+    pub fn subscribe_if<E, P, F>(&self, priority: i32, filter: P, mut handler: F) -> SubscriptionID
+        where E: Event, P: Fn(&E) -> bool + 'static, F: FnMut(&E) + 'static {
+        let id = SubscriptionID(self.sub_counter.get());
+        self.sub_counter.set(id.0 + 1);
+
+        let handler = move |queue: &dyn Any| {
+            let events = queue.downcast_ref::<Queue<E>>().expect("The events are stored by their own type");
+            for (_, event) in events.iter().filter(|(_, event)| filter(event)) {
+                handler(event);
             }
+        };
 
-            let dt = (*self.data).borrow_mut().pop_front().expect("No more values");
-            let param = *dt.downcast::<T>().expect("Invalid Type Parameter");
+        self.subscribed.borrow_mut().insert(id);
+        self.insert_subscriber(Subscriber { id, priority, event: TypeId::of::<E>(), handler: Box::new(handler) });
+        id
+    }
+
+    // removes the subscriber; returns false if it has already unsubscribed
+    pub fn unsubscribe(&self, id: SubscriptionID) -> bool {
+        // the subscribers being called by `event_swap()` are removed once they are all called
+        self.subscribers.borrow_mut().retain(|sub| sub.id != id);
+        self.subscribed.borrow_mut().remove(&id)
+    }
+
+    // clears the current running events (self.events) and replaces it with new events from previous "iteration" (self.events_buf)
+    // along with the events posted from the other threads since the last swap
+    // then calls the subscribers in their order, each with all of its events in the order they were emitted
+    // the events emitted by the subscribers are handled on the next swap, and so are the new subscribers
+    pub fn event_swap(&self) -> Result<(), EventError> {
+        if self.swapping.get() {
+            return Err(EventError::NestedSwap);
+        }
+        self.report_unhandled();
+        self.swapping.set(true);
+
+        {
+            let mut events_buf = self.events_buf.borrow_mut();
+            self.inbox.merge_into(&mut events_buf);
+            self.frame.set(self.frame.get() + 1);
+            self.schedule.borrow_mut().deliver(self.frame.get(), &mut events_buf, &self.inbox.sequence);
 
-            (*self.data).borrow_mut().push_back(Box::new(param.clone()) as Box<dyn Any>);
-            self.cycled += 1;
-            param
-        } else {
-            panic!("Retrieving parameter values without first calling `pack()` or `release()`");
+            if let Some(recorder) = &mut events_buf.recorder {
+                if let Err(err) = recorder.write_frame(self.frame.get()) {
+                    println!("[EVENT:RECORD] Recording the events failed, and has been stopped: {}", err);
+                    events_buf.recorder = None;
+                }
+            }
+            *self.events.borrow_mut() = std::mem::take(&mut events_buf.queues);
         }
+
+        // the subscribers are taken out while being called, so they are free to (un)subscribe
+        // they are put back by the guard, even when one of them panics
+        let mut swap = SwapGuard { evd: self, subscribers: std::mem::take(&mut *self.subscribers.borrow_mut()) };
+        let events = self.events.borrow();
+        for sub in swap.subscribers.iter_mut() {
+            if !self.subscribed.borrow().contains(&sub.id) {
+                continue;
+            }
+            if let Some(queued) = events.get(&sub.event) {
+                queued.handled.set(true);
+                (sub.handler)(queued.events.as_ref());
+            }
+        }
+        Ok(())
     }
-}
 
-impl Drop for EventData {
-    fn drop(&mut self) {
-        if self.released {
-            if self.cycled < self.type_info.len() as u8 {
-                panic!(format!("There are unused values in this EventData! <{:?}> values still went unused! There are <{:?}> elements in this EventData", self.type_info.len() as u8-self.cycled, self.type_info.len()));
+    // applies the policy to the events of the last swap that were not handled
+    fn report_unhandled(&self) {
+        let policy = self.unhandled_policy.get();
+        if policy == UnhandledPolicy::Ignore {
+            return;
+        }
+
+        let mut unhandled = self.events.borrow().values()
+            .filter(|queued| !queued.handled.get())
+            .map(|queued| (queued.name, queued.len))
+            .collect::<Vec<_>>();
+        unhandled.sort();
+
+        // the events are reported once, even if the policy panics
+        for queued in self.events.borrow().values() {
+            queued.handled.set(true);
+        }
+
+        for (name, count) in unhandled {
+            let err = EventError::Unhandled(name, count);
+            match policy {
+                UnhandledPolicy::Panic => panic!("[EVENT] {}", err.message()),
+                UnhandledPolicy::Log => println!("[EVENT] {}", err.message()),
+                UnhandledPolicy::Ignore => {},
             }
         }
     }
+
+    // keeps the subscribers ordered by their priorities, then by the order they subscribed
+    fn insert_subscriber(&self, sub: Subscriber) {
+        let mut subscribers = self.subscribers.borrow_mut();
+        let ind = subscribers.iter().position(|other| other.priority < sub.priority).unwrap_or(subscribers.len());
+        subscribers.insert(ind, sub);
+    }
+
+    // copies the events out, so the closures are free to emit and receive other events
+    fn received<E: Event>(&self) -> Queue<E> {
+        match self.events.borrow().get(&TypeId::of::<E>()) {
+            Some(queued) => {
+                queued.handled.set(true);
+                queued.events.downcast_ref::<Queue<E>>().cloned().unwrap_or_default()
+            },
+            None => Queue::new(),
+        }
+    }
 }
 
-// easily creates a Vec-based EventData
-#[macro_export]
-macro_rules! event_data {
-    [$($val:expr),* $(,)?] => {
-        {
-            let mut evd = crate::event::EventData::new();
-            $(evd.push($val);)*
-            evd
+// Ends the swap once the subscribers have been called, or one of them has panicked
+struct SwapGuard<'a> {
+    evd: &'a EventDispatcher,
+    subscribers: Vec<Subscriber>,  // the subscribers taken out for the swap
+}
+
+impl Drop for SwapGuard<'_> {
+    fn drop(&mut self) {
+        let evd = self.evd;
+        let added = std::mem::replace(&mut *evd.subscribers.borrow_mut(), std::mem::take(&mut self.subscribers));
+        evd.subscribers.borrow_mut().retain(|sub| evd.subscribed.borrow().contains(&sub.id));
+        for sub in added {
+            evd.insert_subscriber(sub);
         }
+        evd.swapping.set(false);
     }
 }
 
-pub struct EventDispatcher {
-    // a map of registered events with each of its respective type information
-    event_names: HashMap<EventName, Vec<TypeId>>,
+// inserts the event into the queue of its type, keeping the queue ordered by the sequence numbers
+fn insert_event<E: Event>(events_buf: &mut EventBuffer, seq: u64, event: E) {
+    if let Some(recorder) = &mut events_buf.recorder {
+        recorder.record(seq, &event);
+    }
 
-    // // a list of all emitted events since instanced
-    // record: Vec<(EventName, EventData)>,
+    let queued = events_buf.queues.entry(TypeId::of::<E>()).or_insert_with(|| Queued {
+        name: E::NAME,
+        len: 0,
+        events: Box::new(Queue::<E>::new()),
+        handled: Cell::new(false),
+    });
+    queued.len += 1;
 
-    // a synchronized way to dispatch all the emitted events at the same time
-    events_buf: RefCell<Vec<(EventName, EventData)>>,
-    // a list of all emitted events since last flushed
-    events: RefCell<Vec<(EventName, EventData)>>,
+    let queue = queued.events.downcast_mut::<Queue<E>>().expect("The events are stored by their own type");
+    // the events are numbered as they are buffered, so this is normally the end of the queue
+    let ind = match queue.binary_search_by_key(&seq, |(other, _)| *other) {
+        Ok(ind) | Err(ind) => ind,
+    };
+    queue.insert(ind, (seq, event));
 }
 
-impl EventDispatcher {
-    pub fn new(event_names: HashMap<EventName, Vec<TypeId>>) -> Rc<Self> {
-        Rc::new(Self {
-            event_names: event_names,
-            // record: Vec::new(),
-            events_buf: RefCell::new(Vec::new()),
-            events: RefCell::new(Vec::new()),
-        })
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::panic::{self, AssertUnwindSafe};
+
+    events! {
+        #[derive(PartialEq, Debug)]
+        "Test/Moved" => Moved(x: i32, y: i32),
+        #[derive(PartialEq, Debug)]
+        "Test/Reset" => Reset,
     }
 
-    pub fn emit(self: Rc<Self>, name: EventName, mut data: EventData) {
-        data.pack();
-        // self.record.push((name, data.clone()));
-        if self.event_names.keys().collect::<Vec<_>>().contains(&&name) {
-            if data.type_info() == self.event_names[&name] {
-                (*self.events_buf.borrow_mut()).push((name, data));
-            } else {
-                panic!(format!("[EVENT:EMIT] EventName <{:?}> emitted data with invalid types", name));
-            }
-        } else {
-            panic!(format!("[EVENT:EMIT] EventName <{:?}> is not a registered event within this EventDispatcher", name));
+    #[test]
+    fn receives_after_swap() {
+        let evd = EventDispatcher::new();
+        evd.emit(Moved(1, 2));
+        evd.emit(Moved(3, 4));
+
+        let mut moved = Vec::new();
+        evd.receive(|event: &Moved| moved.push(event.clone()));
+        assert!(moved.is_empty());
+
+        evd.event_swap().unwrap();
+        evd.receive(|event: &Moved| moved.push(event.clone()));
+        assert_eq!(moved, vec![Moved(1, 2), Moved(3, 4)]);
+
+        let mut resets = 0;
+        evd.receive(|_: &Reset| resets += 1);
+        assert_eq!(resets, 0);
+
+        // the events only last until the next swap
+        evd.event_swap().unwrap();
+        evd.receive_once(|_: &Moved| panic!("the events were already swapped out"));
+    }
+
+    #[test]
+    fn calls_subscribers_in_priority_order() {
+        let evd = EventDispatcher::new();
+        let calls = Rc::new(RefCell::new(Vec::new()));
+
+        let subscribe = |name: &'static str, priority: i32| {
+            let calls = calls.clone();
+            evd.subscribe(priority, move |event: &Moved| calls.borrow_mut().push((name, event.0)))
+        };
+        subscribe("low", -1);
+        let first = subscribe("first", 5);
+        subscribe("second", 5);
+        {
+            let calls = calls.clone();
+            evd.subscribe_if(0, |event: &Moved| event.0 > 1, move |event: &Moved| calls.borrow_mut().push(("filtered", event.0)));
         }
+
+        evd.emit(Moved(1, 0));
+        evd.emit(Moved(2, 0));
+        evd.emit(Reset);
+        evd.event_swap().unwrap();
+        assert_eq!(*calls.borrow(), vec![
+            ("first", 1), ("first", 2), ("second", 1), ("second", 2), ("filtered", 2), ("low", 1), ("low", 2),
+        ]);
+
+        calls.borrow_mut().clear();
+        assert!(evd.unsubscribe(first));
+        assert!(!evd.unsubscribe(first));
+        evd.emit(Moved(3, 0));
+        evd.event_swap().unwrap();
+        assert_eq!(*calls.borrow(), vec![("second", 3), ("filtered", 3), ("low", 3)]);
     }
 
-    // to receive and call the closure for all the selected events since last flushed
-    pub fn receive<T: FnMut(EventData)>(self: Rc<Self>, event: EventName, mut closure: T) {
-        if self.event_names.keys().collect::<Vec<_>>().contains(&&event) {
-            for (name, data) in &*self.events.borrow_mut() {
-                if name == &event {
-                    let mut param = data.clone();
-                    param.release();
-                    closure(param);
+    #[test]
+    fn subscribers_can_subscribe_and_unsubscribe() {
+        let evd = EventDispatcher::new();
+        let resets = Rc::new(Cell::new(0));
+        let later = Rc::new(Cell::new(None));
+
+        // unsubscribes the later subscriber, then subscribes another one in its place
+        {
+            let (evd2, resets, later) = (evd.clone(), resets.clone(), later.clone());
+            evd.subscribe(1, move |_: &Reset| {
+                if let Some(id) = later.take() {
+                    assert!(evd2.unsubscribe(id));
                 }
-            }
-        } else {
-            panic!(format!("[EVENT:RECEIVE] EventName <{:?}> is not a registered event within this EventDispatcher", event));
+                let resets = resets.clone();
+                evd2.subscribe(0, move |_: &Reset| resets.set(resets.get() + 10));
+            });
+        }
+        {
+            let resets = resets.clone();
+            later.set(Some(evd.subscribe(0, move |_: &Reset| resets.set(resets.get() + 1))));
         }
+
+        evd.emit(Reset);
+        evd.event_swap().unwrap();
+        assert_eq!(resets.get(), 0);
+
+        evd.emit(Reset);
+        evd.event_swap().unwrap();
+        assert_eq!(resets.get(), 10);
     }
 
-    // to receive and call the closure once of the first selected event since last flushed
-    pub fn receive_once<T: FnMut(EventData)>(self: Rc<Self>, event: EventName, mut closure: T) {
-        if self.event_names.keys().collect::<Vec<_>>().contains(&&event) {
-            for (name, data) in &*self.events.borrow_mut() {
-                if name == &event {
-                    closure(data.clone());
-                    break;
-                }
-            }
-        } else {
-            panic!(format!("[EVENT:RECEIVE_ONCE] EventName <{:?}> is not a registered event within this EventDispatcher", event));
+    #[test]
+    fn closures_can_emit() {
+        let evd = EventDispatcher::new();
+        evd.emit(Moved(0, 0));
+        evd.event_swap().unwrap();
+
+        evd.receive(|_: &Moved| evd.emit(Reset));
+        evd.event_swap().unwrap();
+
+        let mut resets = 0;
+        evd.receive(|_: &Reset| resets += 1);
+        assert_eq!(resets, 1);
+    }
+
+    #[test]
+    fn nested_swaps_fail() {
+        let evd = EventDispatcher::new();
+        let nested = Rc::new(RefCell::new(None));
+        {
+            let (evd2, nested) = (evd.clone(), nested.clone());
+            evd.subscribe(0, move |_: &Reset| *nested.borrow_mut() = Some(evd2.event_swap()));
         }
+
+        evd.emit(Reset);
+        assert_eq!(evd.event_swap(), Ok(()));
+        assert_eq!(*nested.borrow(), Some(Err(EventError::NestedSwap)));
+        assert_eq!(evd.frame(), 1);
     }
 
-    // clears the current running events (self.events) and replaces it with new events from previous "iteration" (self.events_buf)
-    pub fn event_swap(self: Rc<Self>) {
-        // println!("Event Buffer    : {:?}", *self.events_buf.borrow_mut());
-        // println!("Event Presenting: {:?}", *self.events.borrow_mut());
-        (*self.events.borrow_mut()).clear();
-        (*self.events.borrow_mut()).append(&mut *self.events_buf.borrow_mut());
+    #[test]
+    fn ignores_unhandled_events_by_default() {
+        let evd = EventDispatcher::new();
+        evd.emit(Moved(0, 0));
+        evd.event_swap().unwrap();
+        evd.event_swap().unwrap();
+    }
+
+    #[test]
+    fn handled_events_are_not_reported() {
+        let evd = EventDispatcher::new();
+        evd.set_unhandled_policy(UnhandledPolicy::Panic);
+        evd.subscribe(0, |_: &Reset| {});
+
+        evd.emit(Moved(0, 0));
+        evd.emit(Reset);
+        evd.event_swap().unwrap();
+        evd.receive(|_: &Moved| {});
+        evd.event_swap().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "2 event(s) `Test/Moved` were not handled")]
+    fn panics_on_unhandled_events() {
+        let evd = EventDispatcher::new();
+        evd.set_unhandled_policy(UnhandledPolicy::Panic);
+        evd.emit(Moved(0, 0));
+        evd.emit(Moved(1, 0));
+        evd.event_swap().unwrap();
+        evd.event_swap().unwrap();
+    }
+
+    #[test]
+    fn swaps_again_after_a_panic() {
+        let evd = EventDispatcher::new();
+        evd.set_unhandled_policy(UnhandledPolicy::Panic);
+        evd.emit(Moved(0, 0));
+        evd.event_swap().unwrap();
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| evd.event_swap())).is_err());
+        // the unhandled events are only reported once
+        assert_eq!(evd.event_swap(), Ok(()));
+
+        let panicked = Rc::new(Cell::new(false));
+        let resets = Rc::new(Cell::new(0));
+        {
+            let panicked = panicked.clone();
+            evd.subscribe(1, move |_: &Reset| if !panicked.replace(true) { panic!("faulty handler") });
+            let resets = resets.clone();
+            evd.subscribe(0, move |_: &Reset| resets.set(resets.get() + 1));
+        }
+
+        evd.emit(Reset);
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| evd.event_swap())).is_err());
+        assert_eq!(resets.get(), 0);
+
+        // the subscribers are still there after the panic
+        evd.emit(Reset);
+        assert_eq!(evd.event_swap(), Ok(()));
+        assert_eq!(resets.get(), 1);
+    }
+
+    #[test]
+    fn emits_scheduled_events() {
+        let evd = EventDispatcher::new();
+        let received = Rc::new(RefCell::new(Vec::new()));
+        {
+            let (evd2, received) = (evd.clone(), received.clone());
+            evd.subscribe(0, move |event: &Moved| received.borrow_mut().push((evd2.frame(), event.0)));
+        }
+
+        evd.emit_in(3, Moved(1, 0));
+        evd.emit_at(2, Moved(2, 0));
+        evd.emit_in(0, Moved(3, 0));  // the same as `emit()`
+        let cancelled = evd.emit_in(2, Moved(4, 0));
+        evd.emit(Moved(5, 0));
+        assert_eq!(evd.scheduled(), 4);
+
+        assert!(evd.cancel(cancelled));
+        assert!(!evd.cancel(cancelled));
+        for _ in 0..4 {
+            evd.event_swap().unwrap();
+        }
+        // the scheduled events come after the events emitted for the same swap
+        assert_eq!(*received.borrow(), vec![(1, 5), (1, 3), (2, 2), (3, 1)]);
+        assert_eq!(evd.scheduled(), 0);
+
+        // a tick that has already passed is the next swap
+        evd.emit_at(1, Moved(6, 0));
+        evd.event_swap().unwrap();
+        assert_eq!(received.borrow().last(), Some(&(5, 6)));
+    }
+
+    #[test]
+    fn repeats_until_cancelled() {
+        let evd = EventDispatcher::new();
+        let resets = Rc::new(RefCell::new(Vec::new()));
+        let timer = evd.emit_every(2, Reset);
+        {
+            // cancels the timer from the handler once it repeated three times
+            let (evd2, resets) = (evd.clone(), resets.clone());
+            evd.subscribe(0, move |_: &Reset| {
+                resets.borrow_mut().push(evd2.frame());
+                if resets.borrow().len() == 3 {
+                    assert!(evd2.cancel(timer));
+                }
+            });
+        }
+
+        for _ in 0..10 {
+            evd.event_swap().unwrap();
+        }
+        assert_eq!(*resets.borrow(), vec![2, 4, 6]);
+        assert_eq!(evd.scheduled(), 0);
     }
 }
@@ -0,0 +1,390 @@
+use crate::event::{Event, EventDispatcher};
+use crate::event::types::register_all;
+use crate::world::ChunkID;
+use crate::datatype::{Position, ChunkUnit, Dimension, Rotation};
+use crate::world::player::camera::Camera;
+
+use na::Point3;
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Write, BufRead, BufReader};
+use std::fmt::Debug;
+use std::str::SplitWhitespace;
+
+/*
+Event Recording
+---------------
+The recorder writes every event handled by the dispatcher as a line of text, in the order they were handled:
+
+    <frame> <event name> <fields...>
+
+where the frame is the `event_swap()` the event was handled on. The replay reads the file back and emits
+the events of each frame into another dispatcher right before its matching swap, so a session can be
+reproduced without the window or the player (e.g. to reproduce a bug of the chunk handler).
+ */
+
+// the text fields of a recorded event, after its frame and name
+pub type Fields<'a> = SplitWhitespace<'a>;
+
+// a field of an event that can be recorded
+pub trait EventField: Sized {
+    fn write(&self, fields: &mut Vec<String>);
+    fn read(fields: &mut Fields) -> Option<Self>;
+}
+
+macro_rules! parsed_fields {
+    ($($ty:ty),*) => {
+        $(
+            // the numbers are written in their shortest form that is still read back to the same value
+            impl EventField for $ty {
+                fn write(&self, fields: &mut Vec<String>) {
+                    fields.push(self.to_string());
+                }
+
+                fn read(fields: &mut Fields) -> Option<Self> {
+                    fields.next()?.parse().ok()
+                }
+            }
+        )*
+    }
+}
+
+parsed_fields!(i32, u32, i64, u64, usize, f32, f64);
+
+impl EventField for ChunkID {
+    fn write(&self, fields: &mut Vec<String>) {
+        self.0.write(fields);
+    }
+
+    fn read(fields: &mut Fields) -> Option<Self> {
+        Some(ChunkID(u32::read(fields)?))
+    }
+}
+
+impl EventField for ChunkUnit {
+    fn write(&self, fields: &mut Vec<String>) {
+        self.0.write(fields);
+    }
+
+    fn read(fields: &mut Fields) -> Option<Self> {
+        Some(ChunkUnit(f32::read(fields)?))
+    }
+}
+
+impl<T: EventField + Copy + PartialEq + Debug> EventField for Position<T> {
+    fn write(&self, fields: &mut Vec<String>) {
+        self.x.write(fields);
+        self.y.write(fields);
+        self.z.write(fields);
+    }
+
+    fn read(fields: &mut Fields) -> Option<Self> {
+        Some(Position { x: T::read(fields)?, y: T::read(fields)?, z: T::read(fields)? })
+    }
+}
+
+impl EventField for Dimension<u32> {
+    fn write(&self, fields: &mut Vec<String>) {
+        self.height.write(fields);
+        self.width.write(fields);
+    }
+
+    fn read(fields: &mut Fields) -> Option<Self> {
+        Some(Dimension { height: u32::read(fields)?, width: u32::read(fields)? })
+    }
+}
+
+impl EventField for Camera {
+    fn write(&self, fields: &mut Vec<String>) {
+        for ind in 0..3 {
+            self.position[ind].write(fields);
+        }
+        for rot in &[self.rotation.x, self.rotation.y, self.rotation.z] {
+            rot.write(fields);
+        }
+        for val in &[self.fovy, self.zfar, self.znear, self.rot_speed, self.trans_speed] {
+            val.write(fields);
+        }
+    }
+
+    fn read(fields: &mut Fields) -> Option<Self> {
+        let mut next = || f32::read(fields);
+        Some(Camera {
+            position: Point3::new(next()?, next()?, next()?),
+            rotation: Rotation::new(next()?, next()?, next()?),
+            fovy: next()?,
+            zfar: next()?,
+            znear: next()?,
+            rot_speed: next()?,
+            trans_speed: next()?,
+        })
+    }
+}
+
+
+// writes the events of the dispatcher while it is recording
+pub(super) struct Recorder {
+    writer: Box<dyn Write>,
+    pending: Vec<(u64, String)>,  // the events of the next frame with their sequence numbers
+}
+
+impl Recorder {
+    pub(super) fn new(writer: Box<dyn Write>) -> Self {
+        Self {
+            writer,
+            pending: Vec::new(),
+        }
+    }
+
+    pub(super) fn record<E: Event>(&mut self, seq: u64, event: &E) {
+        let mut fields = vec![E::NAME.to_string()];
+        event.write(&mut fields);
+        self.pending.push((seq, fields.join(" ")));
+    }
+
+    // writes the pending events in the order they will be handled on the frame
+    pub(super) fn write_frame(&mut self, frame: u64) -> io::Result<()> {
+        self.pending.sort_by_key(|(seq, _)| *seq);
+        for (_, line) in self.pending.drain(..) {
+            writeln!(self.writer, "{} {}", frame, line)?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ReplayError {
+    // The recording could not be read
+    Unreadable(String),  // the reason from the reader
+    // The line does not start with the frame and the name of the event
+    Malformed(usize),  // the line number
+    // The event was not registered to the replay
+    UnknownEvent(usize, String),  // the line number and the event name
+    // The fields of the event are missing, extra or of the wrong type
+    InvalidFields(usize, String),  // the line number and the event name
+}
+
+impl ReplayError {
+    pub fn message(&self) -> String {
+        match self {
+            ReplayError::Unreadable(reason) => format!("cannot read the recording: {}", reason),
+            ReplayError::Malformed(line) => format!("line {}: expected the frame and the event name", line),
+            ReplayError::UnknownEvent(line, name) => format!("line {}: unknown event `{}`", line, name),
+            ReplayError::InvalidFields(line, name) => format!("line {}: invalid fields for the event `{}`", line, name),
+        }
+    }
+}
+
+// emits a single recorded event into the dispatcher
+type Emit = Box<dyn Fn(&EventDispatcher)>;
+// reads the fields of the event of a single type
+type Reader = fn(&mut Fields) -> Option<Emit>;
+
+fn read_event<E: Event>(fields: &mut Fields) -> Option<Emit> {
+    let event = E::read(fields)?;
+    Some(Box::new(move |evd: &EventDispatcher| evd.emit(event.clone())))
+}
+
+// feeds a recording back into a dispatcher, frame by frame
+pub struct EventReplay {
+    readers: HashMap<&'static str, Reader>,
+    events: VecDeque<(u64, Emit)>,
+}
+
+impl EventReplay {
+    // knows all the events of the game (`types`)
+    pub fn new() -> Self {
+        let mut replay = Self::empty();
+        register_all(&mut replay);
+        replay
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            readers: HashMap::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn register<E: Event>(&mut self) {
+        self.readers.insert(E::NAME, read_event::<E>);
+    }
+
+    // appends the recorded events; nothing is added if any line is invalid
+    pub fn load<R: io::Read>(&mut self, reader: R) -> Result<(), ReplayError> {
+        let mut events = Vec::new();
+        for (ind, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line.map_err(|err| ReplayError::Unreadable(err.to_string()))?;
+            let mut fields = line.split_whitespace();
+            let (frame, name) = match (fields.next(), fields.next()) {
+                (None, _) => continue,  // blank line
+                (Some(frame), Some(name)) => match frame.parse::<u64>() {
+                    Ok(frame) => (frame, name),
+                    Err(_) => return Err(ReplayError::Malformed(ind+1)),
+                },
+                _ => return Err(ReplayError::Malformed(ind+1)),
+            };
+
+            let reader = self.readers.get(name).ok_or_else(|| ReplayError::UnknownEvent(ind+1, name.to_string()))?;
+            match reader(&mut fields) {
+                Some(emit) if fields.next().is_none() => events.push((frame, emit)),
+                _ => return Err(ReplayError::InvalidFields(ind+1, name.to_string())),
+            }
+        }
+
+        self.events.extend(events);
+        Ok(())
+    }
+
+    pub fn load_file(&mut self, fname: &str) -> Result<(), ReplayError> {
+        let file = std::fs::File::open(fname).map_err(|err| ReplayError::Unreadable(format!("`{}`: {}", fname, err)))?;
+        self.load(file)
+    }
+
+    // the number of events yet to be fed
+    pub fn remaining(&self) -> usize {
+        self.events.len()
+    }
+
+    // emits the events recorded for the upcoming frame, to be called right before `event_swap()`
+    // the events of the frames that were missed are emitted as well; returns false once all were fed
+    pub fn feed(&mut self, evd: &EventDispatcher) -> bool {
+        let next = evd.frame() + 1;
+        while let Some((frame, _)) = self.events.front() {
+            if *frame > next {
+                break;
+            }
+            if let Some((_, emit)) = self.events.pop_front() {
+                emit(evd);
+            }
+        }
+        !self.events.is_empty()
+    }
+}
+
+impl Default for EventReplay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+    use std::cell::RefCell;
+
+    events! {
+        #[derive(PartialEq, Debug)]
+        "Test/Placed" => Placed(id: ChunkID, pos: Position<ChunkUnit>),
+        #[derive(PartialEq, Debug)]
+        "Test/Scaled" => Scaled(factor: f32),
+        #[derive(PartialEq, Debug)]
+        "Test/Cleared" => Cleared,
+    }
+
+    // a writer that can still be read once the dispatcher is done with it
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn replay() -> EventReplay {
+        let mut replay = EventReplay::empty();
+        register_events(&mut replay);
+        replay
+    }
+
+    fn placed(id: u32, x: f32) -> Placed {
+        Placed(ChunkID(id), Position { x: ChunkUnit(x), y: ChunkUnit(-1.5), z: ChunkUnit(0.1) })
+    }
+
+    #[test]
+    fn records_events_by_frame() {
+        let evd = EventDispatcher::new();
+        let out = Shared::default();
+
+        evd.emit(Cleared);  // emitted before the recording started
+        evd.start_recording(out.clone());
+        evd.emit(placed(3, 2.0));
+        evd.event_swap().unwrap();
+        evd.event_swap().unwrap();
+        evd.emit(Scaled(0.25));
+        evd.event_swap().unwrap();
+        evd.emit(Cleared);
+        evd.stop_recording().unwrap();
+        evd.event_swap().unwrap();
+
+        let text = String::from_utf8(out.0.borrow().clone()).unwrap();
+        assert_eq!(text, "1 Test/Placed 3 2 -1.5 0.1\n3 Test/Scaled 0.25\n");
+    }
+
+    #[test]
+    fn replays_the_session() {
+        let recorded = EventDispatcher::new();
+        let out = Shared::default();
+        recorded.start_recording(out.clone());
+
+        let mut seen = Vec::new();
+        for frame in 0..4 {
+            if frame % 2 == 0 {
+                recorded.emit(placed(frame, frame as f32 * 0.3));
+            }
+            recorded.emit(Scaled(1.0 / (frame + 1) as f32));
+            recorded.event_swap().unwrap();
+            recorded.receive(|event: &Placed| seen.push(format!("{:?}", event)));
+            recorded.receive(|event: &Scaled| seen.push(format!("{:?}", event)));
+        }
+        recorded.stop_recording().unwrap();
+
+        let mut replay = replay();
+        replay.load(out.0.borrow().as_slice()).unwrap();
+        assert_eq!(replay.remaining(), 6);
+
+        let replayed = EventDispatcher::new();
+        let mut seen_again = Vec::new();
+        while replay.feed(&replayed) {
+            replayed.event_swap().unwrap();
+            replayed.receive(|event: &Placed| seen_again.push(format!("{:?}", event)));
+            replayed.receive(|event: &Scaled| seen_again.push(format!("{:?}", event)));
+        }
+        replayed.event_swap().unwrap();
+        replayed.receive(|event: &Placed| seen_again.push(format!("{:?}", event)));
+        replayed.receive(|event: &Scaled| seen_again.push(format!("{:?}", event)));
+
+        assert_eq!(seen, seen_again);
+        assert_eq!(replayed.frame(), 4);
+    }
+
+    #[test]
+    fn rejects_invalid_recordings() {
+        let load = |text: &str| replay().load(text.as_bytes());
+
+        assert_eq!(load("1 Test/Cleared\n\n2 Test/Scaled 2\n"), Ok(()));
+        assert_eq!(load("1 Test/Cleared\nTest/Scaled 2\n"), Err(ReplayError::Malformed(2)));
+        assert_eq!(load("1\n"), Err(ReplayError::Malformed(1)));
+        assert_eq!(load("1 Test/Moved 2\n"), Err(ReplayError::UnknownEvent(1, "Test/Moved".into())));
+        assert_eq!(load("1 Test/Scaled\n"), Err(ReplayError::InvalidFields(1, "Test/Scaled".into())));
+        assert_eq!(load("1 Test/Scaled 1 2\n"), Err(ReplayError::InvalidFields(1, "Test/Scaled".into())));
+        assert_eq!(load("1 Test/Placed 1 a 2 3\n"), Err(ReplayError::InvalidFields(1, "Test/Placed".into())));
+
+        // nothing is loaded from an invalid recording
+        let mut replay = replay();
+        assert!(replay.load("1 Test/Cleared\n2 Test/Cleared x\n".as_bytes()).is_err());
+        assert_eq!(replay.remaining(), 0);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::event::{Event, EventBuffer, insert_event};
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/*
+Event Schedule
+--------------
+The events emitted ahead of time. The ticks are the swaps of the dispatcher (`EventDispatcher::frame()`),
+so an event scheduled on a tick is handled on the swap that makes the frame reach the tick. The events
+due on a tick are handled after the events emitted normally for it, in the order they were scheduled.
+ */
+
+// the handle of a scheduled event for cancelling it; a repeating event keeps its handle
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct TimerID(u64);
+
+// inserts a copy of the event into the buffer, with the sequence number
+type Deliver = Box<dyn Fn(&mut EventBuffer, u64)>;
+
+struct Timer {
+    deliver: Deliver,
+    period: Option<u64>,  // the ticks between the repeats
+}
+
+pub(super) struct Schedule {
+    // ordered by the tick, then the order they were scheduled in
+    timers: BTreeMap<(u64, TimerID), Timer>,
+    ticks: HashMap<TimerID, u64>,  // the next tick of each timer for cancelling
+    counter: u64,
+}
+
+impl Schedule {
+    pub(super) fn new() -> Self {
+        Self {
+            timers: BTreeMap::new(),
+            ticks: HashMap::new(),
+            counter: 0,
+        }
+    }
+
+    pub(super) fn add<E: Event>(&mut self, tick: u64, period: Option<u64>, event: E) -> TimerID {
+        let id = TimerID(self.counter);
+        self.counter += 1;
+
+        let deliver = move |events_buf: &mut EventBuffer, seq: u64| insert_event(events_buf, seq, event.clone());
+        self.timers.insert((tick, id), Timer { deliver: Box::new(deliver), period: period.map(|period| period.max(1)) });
+        self.ticks.insert(id, tick);
+        id
+    }
+
+    // returns false if the event was already handled or cancelled
+    pub(super) fn cancel(&mut self, id: TimerID) -> bool {
+        match self.ticks.remove(&id) {
+            Some(tick) => self.timers.remove(&(tick, id)).is_some(),
+            None => false,
+        }
+    }
+
+    // the number of the events waiting for their ticks
+    pub(super) fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    // inserts the events due by the tick into the buffer, and schedules the repeating ones again
+    pub(super) fn deliver(&mut self, tick: u64, events_buf: &mut EventBuffer, sequence: &AtomicU64) {
+        while let Some(&(due, id)) = self.timers.keys().next() {
+            if due > tick {
+                break;
+            }
+            let timer = self.timers.remove(&(due, id)).expect("The key was just found");
+            (timer.deliver)(events_buf, sequence.fetch_add(1, Ordering::SeqCst));
+
+            match timer.period {
+                Some(period) => {
+                    self.timers.insert((tick + period, id), timer);
+                    self.ticks.insert(id, tick + period);
+                },
+                None => {
+                    self.ticks.remove(&id);
+                },
+            }
+        }
+    }
+}
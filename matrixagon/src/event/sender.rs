@@ -0,0 +1,172 @@
+use crate::event::{Event, EventBuffer, insert_event};
+
+use crossbeam::queue::SegQueue;
+
+use std::any::Any;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/*
+Event Sender
+------------
+Posts the events from any thread into a lock-free queue, which the EventDispatcher empties into its own
+buffer on the next `event_swap()`. The sender can be cloned and moved into the worker jobs freely.
+
+The posted events take their sequence numbers when they are merged rather than when they are posted, so an
+event posted just before a swap can never be handled after the events numbered later than itself.
+ */
+
+// inserts the type-erased event back into the queue of its own type
+type Merge = fn(&mut EventBuffer, u64, Box<dyn Any + Send>);
+
+// An event posted from another thread
+struct Posted {
+    event: Box<dyn Any + Send>,
+    merge: Merge,
+}
+
+// The events posted to a single dispatcher, along with the sequence shared by all of its events
+pub(super) struct Inbox {
+    pub(super) sequence: AtomicU64,
+    posted: SegQueue<Posted>,
+}
+
+impl Inbox {
+    pub(super) fn new() -> Self {
+        Self {
+            sequence: AtomicU64::new(0),
+            posted: SegQueue::new(),
+        }
+    }
+
+    // moves all the posted events into the queues of their types, numbering them in the order they were posted
+    pub(super) fn merge_into(&self, events_buf: &mut EventBuffer) {
+        while let Ok(Posted { event, merge }) = self.posted.pop() {
+            merge(events_buf, self.sequence.fetch_add(1, Ordering::SeqCst), event);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EventSender {
+    inbox: Arc<Inbox>,
+}
+
+impl EventSender {
+    pub(super) fn new(inbox: Arc<Inbox>) -> Self {
+        Self { inbox }
+    }
+
+    // the event will be received after the next `event_swap()` of the dispatcher
+    // the events posted by the same thread are received in the order they were posted,
+    // after the events emitted on the main thread before the swap
+    pub fn post<E: Event + Send>(&self, event: E) {
+        self.inbox.posted.push(Posted { event: Box::new(event), merge: merge::<E> });
+    }
+}
+
+fn merge<E: Event>(events_buf: &mut EventBuffer, seq: u64, event: Box<dyn Any + Send>) {
+    let event = event.downcast::<E>().expect("The merge function is of the type of the event");
+    insert_event(events_buf, seq, *event);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::EventDispatcher;
+
+    use std::thread;
+
+    events! {
+        // the thread and the number of the event within the thread
+        #[derive(PartialEq, Debug)]
+        "Test/Generated" => Generated(thread: usize, num: usize),
+    }
+
+    #[test]
+    fn merges_events_from_threads() {
+        let evd = EventDispatcher::new();
+        evd.emit(Generated(0, 0));
+
+        let workers = (1..=4).map(|thread| {
+            let sender = evd.sender();
+            thread::spawn(move || {
+                for num in 0..100 {
+                    sender.post(Generated(thread, num));
+                }
+            })
+        }).collect::<Vec<_>>();
+        for worker in workers {
+            worker.join().expect("The worker thread does not panic");
+        }
+        evd.emit(Generated(0, 1));
+
+        let mut received = Vec::new();
+        evd.receive(|event: &Generated| received.push(event.clone()));
+        assert!(received.is_empty());
+
+        evd.event_swap().unwrap();
+        evd.receive(|event: &Generated| received.push(event.clone()));
+        assert_eq!(received.len(), 402);
+        // the events emitted on the main thread come before the events merged on the swap
+        assert_eq!(received[..2], [Generated(0, 0), Generated(0, 1)]);
+        for thread in 1..=4 {
+            let nums = received.iter().filter(|event| event.0 == thread).map(|event| event.1).collect::<Vec<_>>();
+            assert_eq!(nums, (0..100).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn numbers_events_posted_during_swaps_after_the_handled_ones() {
+        let evd = EventDispatcher::new();
+
+        let workers = (1..=4).map(|thread| {
+            let sender = evd.sender();
+            thread::spawn(move || {
+                for num in 0..1000 {
+                    sender.post(Generated(thread, num));
+                }
+            })
+        }).collect::<Vec<_>>();
+
+        // keeps emitting and swapping while the workers are posting
+        let mut handled = Vec::new();
+        let mut emitted = 0;
+        while workers.iter().any(|worker| !worker.is_finished()) {
+            evd.emit(Generated(0, emitted));
+            emitted += 1;
+            evd.event_swap().unwrap();
+            handled.extend(evd.received::<Generated>());
+        }
+        for worker in workers {
+            worker.join().expect("The worker thread does not panic");
+        }
+        evd.event_swap().unwrap();
+        handled.extend(evd.received::<Generated>());
+
+        assert_eq!(handled.len(), 4000 + emitted);
+        // an event is never handled on a later swap than the events numbered after it
+        assert!(handled.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        for thread in 0..=4 {
+            let nums = handled.iter().filter(|(_, event)| event.0 == thread).map(|(_, event)| event.1).collect::<Vec<_>>();
+            assert_eq!(nums, (0..if thread == 0 { emitted } else { 1000 }).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn subscribers_see_posted_events() {
+        use std::rc::Rc;
+        use std::cell::Cell;
+
+        let evd = EventDispatcher::new();
+        let total = Rc::new(Cell::new(0));
+        {
+            let total = total.clone();
+            evd.subscribe(0, move |event: &Generated| total.set(total.get() + event.1));
+        }
+
+        let sender = evd.sender();
+        thread::spawn(move || sender.post(Generated(1, 42))).join().expect("The worker thread does not panic");
+        evd.event_swap().unwrap();
+        assert_eq!(total.get(), 42);
+    }
+}
@@ -1,37 +1,45 @@
-use crate::event::EventName;
 use crate::world::ChunkID;
 use crate::datatype::{Position, ChunkUnit, Dimension};
 use crate::world::player::camera::Camera;
+use crate::event::record::EventReplay;
 
-use std::any::TypeId;
-use std::collections::HashMap;
 
+// the events handled by the meshes of the chunk handler
+pub mod mesh {
+    use super::*;
 
-// creates a hashmap of event nametypes for the EventInterchange
-macro_rules! ename_insert {
-    {$($key:literal => [$($val:ty$(,)?)*],)*} => {
-        let mut map = HashMap::new();
-        $(map.insert(EventName($key), vec![$(TypeId::of::<$val>(),)*]);)*
-        map
+    events! {
+        "MeshEvent/NewChunk"            => NewChunk(pos: Position<ChunkUnit>),
+        "MeshEvent/LoadChunk"           => LoadChunk(id: u32),
+        "MeshEvent/OffloadChunk"        => OffloadChunk(id: ChunkID),
+        "MeshEvent/ReloadChunks"        => ReloadChunks,
+        "MeshEvent/ReloadChunk"         => ReloadChunk(id: ChunkID),
+        "MeshEvent/UpdateMesh"          => UpdateMesh,
+        "MeshEvent/UpdateDimensions"    => UpdateDimensions(dims: Dimension<u32>),
+        "MeshEvent/UpdateWorldStates"   => UpdateWorldStates(camera: Camera),
     }
 }
 
-// returns the event nametypes for this voxel applications
-pub fn global_enmtyp() -> HashMap<EventName, Vec<TypeId>> {
-    ename_insert! {
-        "MeshEvent/NewChunk"            => [Position<ChunkUnit>],
-        "MeshEvent/LoadChunk"           => [u32],
-        "MeshEvent/OffloadChunk"        => [ChunkID],
-        "MeshEvent/ReloadChunks"        => [],
-        "MeshEvent/ReloadChunk"         => [ChunkID],
-        "MeshEvent/UpdateMesh"          => [],
-        "MeshEvent/UpdateDimensions"    => [Dimension<u32>],
-        "MeshEvent/UpdateWorldStates"   => [Camera],
-        "WorldEvent/NewChunk"           => [Position<ChunkUnit>],
-        "WorldEvent/LoadChunk"          => [u32],
-        "WorldEvent/OffloadChunk"       => [ChunkID],
-        "WorldEvent/ReloadChunks"       => [],
-        "WorldEvent/ReloadChunk"        => [ChunkID],
-        "EventFinal"                    => [],
+// the events of the world
+pub mod world {
+    use super::*;
+
+    events! {
+        "WorldEvent/NewChunk"           => NewChunk(pos: Position<ChunkUnit>),
+        "WorldEvent/LoadChunk"          => LoadChunk(id: u32),
+        "WorldEvent/OffloadChunk"       => OffloadChunk(id: ChunkID),
+        "WorldEvent/ReloadChunks"       => ReloadChunks,
+        "WorldEvent/ReloadChunk"        => ReloadChunk(id: ChunkID),
     }
 }
+
+events! {
+    "EventFinal"                        => EventFinal,
+}
+
+// registers all the events of the game to be replayed
+pub fn register_all(replay: &mut EventReplay) {
+    mesh::register_events(replay);
+    world::register_events(replay);
+    register_events(replay);
+}
@@ -0,0 +1,11 @@
+extern crate nalgebra as na;
+
+/*
+The world, event and threadpool modules as a library, so their tests are built and run by `cargo test`
+while the binary (`main.rs`) is still the renderer demo without any of them.
+ */
+
+pub mod datatype;
+pub mod event;
+pub mod threadpool;
+pub mod world;
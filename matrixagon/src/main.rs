@@ -37,7 +37,7 @@ impl_vertex!(CubeVertex, pos => DataType::Vec3, col => DataType::Vec3, crd => Da
 
 type Matrix3D = [[f32; 4]; 4];
 
-pub fn gen_mvp(aspect: f32, fovy: f32, znear: f32, zfar: f32, tx: f32, ty: f32, tz: f32, rx: f32, ry: f32, rz: f32) -> (Matrix3D, Matrix3D, Matrix3D) {
+pub fn gen_mvp(aspect: f32, fovy: f32, znear: f32, zfar: f32, [tx, ty, tz]: [f32; 3], [rx, ry, rz]: [f32; 3]) -> (Matrix3D, Matrix3D, Matrix3D) {
     let sx = rx.sin();
     let cx = rx.cos();
     let sy = ry.sin();
@@ -94,12 +94,8 @@ pub fn gen_mvp(aspect: f32, fovy: f32, znear: f32, zfar: f32, tx: f32, ty: f32,
     (proj_dt, view_dt, model_dt)
 }
 
-#[derive(Copy, Clone, Debug)]
-struct MVPUniform {
-    model: [[f32; 4]; 4],
-    view: [[f32; 4]; 4],
-    proj: [[f32; 4]; 4],
-}
+// the model, view and projection matrices, in the order of the uniform of the vertex shader
+type MVPUniform = [Matrix3D; 3];
 
 
 fn main() {
@@ -127,12 +123,8 @@ fn main() {
     // MVP BUFFER
     let aspect_ratio = gfx.current_extent().width as f32 / gfx.current_extent().height as f32;
     // mvp from camera's position
-    let mvp = gen_mvp(aspect_ratio, 1.3, 0.1, 100.0, 0.0, 0.0, -10.0, 0.0, 0.0, 0.0);
-    let mvp_uniform = MVPUniform {
-        model: mvp.0,
-        view: mvp.1,
-        proj: mvp.2,
-    };
+    let mvp = gen_mvp(aspect_ratio, 1.3, 0.1, 100.0, [0.0, 0.0, -10.0], [0.0, 0.0, 0.0]);
+    let mvp_uniform: MVPUniform = [mvp.0, mvp.1, mvp.2];
 
     let mut mvp_ub = gfx.create_uniform_buffer::<MVPUniform>();
     mvp_ub.update(&device, mvp_uniform);
@@ -195,23 +187,13 @@ fn main() {
 
     event_loop.run(move |event, _, control_flow| {
         match event {
-            Event::WindowEvent {window_id, event} => {
+            Event::WindowEvent {event, ..} => {
                 match event {
                     WindowEvent::CloseRequested => { *control_flow = ControlFlow::Exit },
-                    WindowEvent::KeyboardInput { device_id, input, is_synthetic } => {
-                        match input {
-                            KeyboardInput { virtual_keycode: key, state: ElementState::Pressed, .. } => {
-                                if let Some(k) = key {
-                                    match k {
-                                        K::Escape => { *control_flow = ControlFlow::Exit },
-                                        _ => {},
-                                    }
-                                }
-                            }
-                            _ => {},
-                        }
-                    },
-                    WindowEvent::Resized(size) => {
+                    WindowEvent::KeyboardInput {
+                        input: KeyboardInput { virtual_keycode: Some(K::Escape), state: ElementState::Pressed, .. }, ..
+                    } => { *control_flow = ControlFlow::Exit },
+                    WindowEvent::Resized(_size) => {
                         // println!("{:?}", size);
                         window_resized = true;
                     },
@@ -219,40 +201,34 @@ fn main() {
                 }
             },
             // Called when the windows contents are invalidated (e.g.: dimensions changed)
-            Event::RedrawRequested(window_id) => {
-                if cmd_buf.suboptimal() || window_resized {
-                    window_resized = false;
-
-                    gfx.recreate(vec![&mut main_gp], vec![&cmd_buf, &main_descriptors]);
-
-                    unsafe { mvp_ub.cleanup(&device); }
-                    mvp_ub = gfx.create_uniform_buffer::<MVPUniform>();
-
-                    main_descriptors = gfx.create_descriptors();
-                    main_descriptors.bind_uniform(0,ShaderStages::Vertex,mvp_ub.clone());
-                    main_descriptors.bind_sampler(1, ShaderStages::Fragment, image.clone());
-                    main_descriptors.build(&device);
-
-                    gfx.replace_command_buffers(&mut cmd_buf,
-                                                main_renderpass,
-                                                (0.0, 0.0, 0.5),
-                                                vec![
-                                                    Commands::DrawIndexed(main_gp.clone(), 0, vert_buf.clone(), indx_buf.clone(), Some(main_descriptors.clone())),
-                                                ]
-                    );
-
-                    let aspect_ratio = gfx.current_extent().width as f32 / gfx.current_extent().height as f32;
-
-                    // mvp from camera's position
-                    let mvp = gen_mvp(aspect_ratio, 1.3, 0.1, 100.0, 0.0, 0.0, -1.0, 0.0, 0.0, t);
-                    let mvp_uniform = MVPUniform {
-                        model: mvp.0,
-                        view: mvp.1,
-                        proj: mvp.2,
-                    };
-
-                    mvp_ub.update(&device, mvp_uniform);
-                }
+            Event::RedrawRequested(_) if cmd_buf.suboptimal() || window_resized => {
+                window_resized = false;
+
+                gfx.recreate(vec![&mut main_gp], vec![&cmd_buf, &main_descriptors]);
+
+                unsafe { mvp_ub.cleanup(&device); }
+                mvp_ub = gfx.create_uniform_buffer::<MVPUniform>();
+
+                main_descriptors = gfx.create_descriptors();
+                main_descriptors.bind_uniform(0,ShaderStages::Vertex,mvp_ub.clone());
+                main_descriptors.bind_sampler(1, ShaderStages::Fragment, image.clone());
+                main_descriptors.build(&device);
+
+                gfx.replace_command_buffers(&mut cmd_buf,
+                                            main_renderpass,
+                                            (0.0, 0.0, 0.5),
+                                            vec![
+                                                Commands::DrawIndexed(main_gp.clone(), 0, vert_buf.clone(), indx_buf.clone(), Some(main_descriptors.clone())),
+                                            ]
+                );
+
+                let aspect_ratio = gfx.current_extent().width as f32 / gfx.current_extent().height as f32;
+
+                // mvp from camera's position
+                let mvp = gen_mvp(aspect_ratio, 1.3, 0.1, 100.0, [0.0, 0.0, -1.0], [0.0, 0.0, t]);
+                let mvp_uniform: MVPUniform = [mvp.0, mvp.1, mvp.2];
+
+                mvp_ub.update(&device, mvp_uniform);
             },
             // Calls after RedrawRequested, else calls after MainEventsCleared
             Event::RedrawEventsCleared => {
@@ -261,12 +237,8 @@ fn main() {
                 let aspect_ratio = gfx.current_extent().width as f32 / gfx.current_extent().height as f32;
 
                 // mvp from camera's position
-                let mvp = gen_mvp(aspect_ratio, 1.3, 0.1, 100.0, 0.0, 0.0, -1.0, 0.0, 0.0, t);
-                let mvp_uniform = MVPUniform {
-                    model: mvp.0,
-                    view: mvp.1,
-                    proj: mvp.2,
-                };
+                let mvp = gen_mvp(aspect_ratio, 1.3, 0.1, 100.0, [0.0, 0.0, -1.0], [0.0, 0.0, t]);
+                let mvp_uniform: MVPUniform = [mvp.0, mvp.1, mvp.2];
 
                 mvp_ub.update(&device, mvp_uniform);
 
@@ -296,10 +268,10 @@ fn main() {
 //
 // use crate::app::MainApp;
 // use crate::datatype::{CamDirection, Dimension};
-// use crate::event::{EventDispatcher, EventName};
-// use crate::event::types;
+// use crate::event::{EventDispatcher, UnhandledPolicy};
+// use crate::event::types::mesh;
 //
-// #[macro_use] mod event;
+// mod event;
 // mod threadpool;
 // mod ui;
 // mod world;
@@ -342,7 +314,16 @@ fn main() {
 //     // setting up for the program
 //     println!("PROGRAM - BEGIN MAIN PROGRAM");
 //
-//     let evd = EventDispatcher::new(types::global_enmtyp());
+//     let evd = EventDispatcher::new();
+//     if cfg!(debug_assertions) {
+//         evd.set_unhandled_policy(UnhandledPolicy::Log);
+//     }
+//     // opt-in recording of the session for reproducing bugs (see `event::record::EventReplay`)
+//     if let Ok(fname) = std::env::var("MATRIXAGON_RECORD") {
+//         if let Err(err) = evd.record_file(&fname) {
+//             println!("[EVENT:RECORD] Cannot record into `{}`: {}", fname, err);
+//         }
+//     }
 //
 //     // let mut textr: Texture<'static> = Texture::new(queue.clone());
 //     let mut app = MainApp::new(
@@ -365,7 +346,13 @@ fn main() {
 //         match event {
 //             Event::WindowEvent { event, .. } => {
 //                 match event {
-//                     WindowEvent::CloseRequested => { *control_flow = ControlFlow::Exit },
+//                     WindowEvent::CloseRequested => {
+//                         // the event loop never returns, so the recording is flushed here
+//                         if let Err(err) = evd.stop_recording() {
+//                             println!("[EVENT:RECORD] Cannot finish the recording: {}", err);
+//                         }
+//                         *control_flow = ControlFlow::Exit
+//                     },
 //                     WindowEvent::Resized(size) => {
 //                         // when screen gets resized
 //
@@ -377,7 +364,7 @@ fn main() {
 //                             println!("Screen un-minimized");
 //                             minimized = false;
 //                         } else {
-//                             evd.clone().emit(EventName("MeshEvent/UpdateDimensions"), event_data![Dimension::new(height, width)]);
+//                             evd.emit(mesh::UpdateDimensions(Dimension::new(height, width)));
 //                         }
 //                     },
 //                     WindowEvent::KeyboardInput { input, .. } => {
@@ -471,7 +458,9 @@ fn main() {
 //                 app.world.player.camera.travel(directions);
 //
 //                 // event dispatcher to event_swap() after all the events has been finished
-//                 evd.clone().event_swap();
+//                 if let Err(err) = evd.clone().event_swap() {
+//                     println!("[EVENT] {}", err.message());
+//                 }
 //             },
 //             Event::RedrawEventsCleared => {
 //                 if !minimized {
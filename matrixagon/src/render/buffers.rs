@@ -47,6 +47,7 @@ pub struct CreateSampler {
     pub(super) anistropy_enable: bool,
     pub(super) max_anistropy: f32,
     // TODO: mipmapping
+    #[allow(dead_code)]
     pub(super) mipmapping: bool,
     sampler: Option<vk::Sampler>,
 }
@@ -66,25 +67,26 @@ impl CreateSampler {
     }
 
     pub fn filter(&mut self, mag_filter: SamplerFilter, min_filter: SamplerFilter) {
-        if let Some(_) = self.sampler { panic!("Sampler has been created already"); }
+        if self.sampler.is_some() { panic!("Sampler has been created already"); }
         self.mag_filter = mag_filter.into();
         self.min_filter = min_filter.into();
     }
 
     pub fn address_mode(&mut self, u: SamplerAddressMode, v: SamplerAddressMode) {
-        if let Some(_) = self.sampler { panic!("Sampler has been created already"); }
+        if self.sampler.is_some() { panic!("Sampler has been created already"); }
         self.addr_mode_u = u.into();
         self.addr_mode_v = v.into();
     }
 
     pub fn anisotropy(&mut self, max_anisotropy: f32) {
-        if let Some(_) = self.sampler { panic!("Sampler has been created already"); }
+        if self.sampler.is_some() { panic!("Sampler has been created already"); }
         self.anistropy_enable = true;
         self.max_anistropy = max_anisotropy;
     }
 
+    #[allow(dead_code)]
     pub fn mipmapping(&mut self) {
-        if let Some(_) = self.sampler { panic!("Sampler has been created already"); }
+        if self.sampler.is_some() { panic!("Sampler has been created already"); }
         self.mipmapping = true;
     }
 
@@ -19,6 +19,7 @@ enum BindData {
 pub struct CreateDescriptors {
     descriptor: Option<DescriptorSetsMTXG>,
     binding_data: Vec<(SetBindingMTXG, BindData)>,  // a deferred creation of binding for creating the descriptor
+    #[allow(dead_code)]
     index: u32,
     copies: u32,  // how many copies of each of the uniform bindings (specifically) are needed
 }
@@ -29,7 +30,7 @@ impl CreateDescriptors {
             descriptor: None,
             binding_data: Vec::new(),
             index: 0,
-            copies: copies,
+            copies,
         }
     }
 
@@ -49,7 +50,7 @@ impl CreateDescriptors {
 
     pub fn build(&mut self, device: &DeviceMTXG) {
         // call build method only once
-        if let None = self.descriptor {
+        if self.descriptor.is_none() {
             let set_bindings = self.binding_data.iter().map(|bd|bd.0).collect::<Vec<_>>();
 
             let descriptors = DescriptorSetsMTXG::new(device, set_bindings.as_slice(), self.copies);
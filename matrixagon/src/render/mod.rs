@@ -27,7 +27,7 @@ pub mod pipeline;
 
 // TODO: replace all costly clones with references once possible
 
-#[allow(dead_code)]
+#[allow(dead_code, clippy::upper_case_acronyms)]
 pub enum TextureFormat {
     Grayscale,
     GrayscaleAlpha,
@@ -60,7 +60,7 @@ pub enum ShaderStages {
 }
 
 // used in the command buffer creation
-#[allow(dead_code)]
+#[allow(dead_code, clippy::large_enum_variant)]
 pub enum Commands {
     Draw(CreateGraphicPipelines, u32, CreateBufferMemory, Option<CreateDescriptors>),  // binding, vert buf, descriptors
     DrawIndexed(CreateGraphicPipelines, u32, CreateBufferMemory, CreateBufferMemory, Option<CreateDescriptors>),  // binding, vert buf, indx buf, descriptors
@@ -138,6 +138,7 @@ macro_rules! impl_vertex {
     };
 }
 
+#[allow(dead_code)]
 #[derive(Copy, Clone, Debug)]
 struct CubeVertex {
     pos: [f32; 3],
@@ -178,11 +179,11 @@ impl Renderer {
 
         (
             Self {
-                instance: instance,
+                instance,
                 device: device.clone(),
-                swapchain: swapchain,
-                renderpass: renderpass,
-                framebuffer: framebuffer,
+                swapchain,
+                renderpass,
+                framebuffer,
                 command_pool: cmd_pool,
             },
             device.clone(),
@@ -269,7 +270,7 @@ impl Renderer {
 
         CreateImageMemory {
             image_mem: texture_img,
-            image_view: image_view,
+            image_view,
             image_sampler: sampler,
         }
     }
@@ -311,6 +312,7 @@ impl Renderer {
         CreateDescriptors::new(self.swapchain.image_count() as u32)
     }
 
+    #[allow(dead_code)]
     pub fn create_renderpass(&self) -> CreateRenderpass {
         CreateRenderpass {
             renderpass: GraphicsPipelineMTXG::create_renderpass(&self.device, self.swapchain.image_format().format),
@@ -318,6 +320,7 @@ impl Renderer {
         }
     }
 
+    #[allow(dead_code)]
     pub fn create_renderpass_with_depth(&self) -> CreateRenderpass {
         CreateRenderpass {
             renderpass: GraphicsPipelineMTXG::create_renderpass_with_depth(&self.device, self.swapchain.image_format().format, self.device.find_depth_format()),
@@ -325,6 +328,7 @@ impl Renderer {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn create_graphics_pipeline<V: VertexInfo>(&self,
                                                    renderpass: &CreateRenderpass,
                                                    descriptors: &CreateDescriptors,
@@ -384,11 +388,11 @@ impl Renderer {
         for cmd in &cmds {
             match cmd {
                 Commands::Draw(grph_pipeline, bind, vb, dscrps) => {
-                    let dscrps = if let Some(d) = dscrps { Some(d.retrieve_descriptor()) } else { None };
+                    let dscrps = dscrps.as_ref().map(|d| d.retrieve_descriptor());
                     cmd_buf.draw_pipeline(&grph_pipeline.pipeline,*bind,&vb.buffer_mem,dscrps);
                 },
                 Commands::DrawIndexed(grph_pipeline, bind, vb, ib, dscrps) => {
-                    let dscrps = if let Some(d) = dscrps { Some(d.retrieve_descriptor()) } else { None };
+                    let dscrps = dscrps.as_ref().map(|d| d.retrieve_descriptor());
                     cmd_buf.draw_pipeline_indexed(&grph_pipeline.pipeline, *bind, &vb.buffer_mem, &ib.buffer_mem, vk::IndexType::UINT32, dscrps);
                 },
                 Commands::DrawInstanced => {
@@ -420,11 +424,11 @@ impl Renderer {
         for cmd in &pipelines {
             match cmd {
                 Commands::Draw(grph_pipeline, bind, vb, dscrps) => {
-                    let dscrps = if let Some(d) = dscrps { Some(d.retrieve_descriptor()) } else { None };
+                    let dscrps = dscrps.as_ref().map(|d| d.retrieve_descriptor());
                     cmd_buf.draw_pipeline(&grph_pipeline.pipeline,*bind,&vb.buffer_mem,dscrps);
                 },
                 Commands::DrawIndexed(grph_pipeline, bind, vb, ib, dscrps) => {
-                    let dscrps = if let Some(d) = dscrps { Some(d.retrieve_descriptor()) } else { None };
+                    let dscrps = dscrps.as_ref().map(|d| d.retrieve_descriptor());
                     cmd_buf.draw_pipeline_indexed(&grph_pipeline.pipeline, *bind, &vb.buffer_mem, &ib.buffer_mem, vk::IndexType::UINT32, dscrps);
                 },
                 Commands::DrawInstanced => {
@@ -8,6 +8,7 @@ use renderer::CleanupVkObj;
 #[derive(Copy, Clone)]
 pub struct CreateRenderpass {
     pub(super) renderpass: vk::RenderPass,
+    #[allow(dead_code)]
     pub(super) depth_enabled: bool,
 }
 
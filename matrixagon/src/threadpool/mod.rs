@@ -33,113 +33,476 @@ NOTE: No loops or MPSC or Channels.
 
  */
 
+/*
+Global Threadpool
+
+The tasks are submitted with a priority and wait in a queue in front of the rayon threadpool, so they
+can still be cancelled, postponed or reprioritized until a worker picks them up. Every submission spawns
+a single rayon job, which runs the task with the highest priority waiting at the time the job starts
+(the same priorities in the order they were submitted).
+
+Each task is awaited through its own `TaskHandle`; a panic inside the task is caught and returned as
+`TaskError::Panicked` rather than lost with the worker.
+ */
+
 use rayon::{ThreadPoolBuilder, ThreadPool};
 
-use std::sync::mpsc;
-use std::sync::mpsc::Receiver;
 use std::any::Any;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, Condvar};
 
 
-// (Chunk ID, (Vertex Data, Index Data) )
-// type ThreadPoolOutput = (ChunkID, (Box<dyn Any>, Box<dyn Any>));
 pub type ThreadPoolOutput = Box<dyn Any + Send>;
 pub type ThreadPoolInput = Box<dyn FnOnce() -> ThreadPoolOutput + Send>;
 
-// A threadpool using rayon to parallelize the work of chunk mesh generation
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct TaskID(u64);
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TaskStatus {
+    Idle,  // waiting for a worker
+    Postponed,  // kept from the workers until it is resumed
+    Processing,
+    Finished,
+    Cancelled,
+    Panicked,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum TaskError {
+    // The task was cancelled before it started
+    Cancelled,
+    // The task panicked
+    Panicked(String),  // the panic message
+    // The task is postponed, so awaiting it would never return
+    Postponed,
+    // The result was already taken from the handle
+    Taken,
+    // The result of the tagged task is not of the requested type
+    TypeMismatch,
+}
+
+impl TaskError {
+    pub fn message(&self) -> String {
+        match self {
+            TaskError::Cancelled => "the task was cancelled".into(),
+            TaskError::Panicked(msg) => format!("the task panicked: {}", msg),
+            TaskError::Postponed => "the task is postponed".into(),
+            TaskError::Taken => "the result of the task was already taken".into(),
+            TaskError::TypeMismatch => "the result of the task is of another type".into(),
+        }
+    }
+}
+
+enum TaskState<T> {
+    Idle,
+    Postponed,
+    Processing,
+    Done(Result<T, TaskError>),
+    Taken(TaskStatus),  // the status it was finished with
+}
+
+// the state of a single task, shared by its handle and the worker running it
+struct Slot<T> {
+    state: Mutex<TaskState<T>>,
+    changed: Condvar,
+}
+
+impl<T> Slot<T> {
+    // wakes up every waiter on any change, as a postponed task stops `join()` from waiting as well
+    fn set(&self, state: TaskState<T>) {
+        *self.state.lock().unwrap() = state;
+        self.changed.notify_all();
+    }
+}
+
+// changes the state of a waiting task from the pool, without knowing the type of its result
+trait Control: Send + Sync {
+    fn postpone(&self, postponed: bool);
+    fn cancel(&self);
+}
+
+impl<T: Send> Control for Slot<T> {
+    fn postpone(&self, postponed: bool) {
+        self.set(if postponed { TaskState::Postponed } else { TaskState::Idle });
+    }
+
+    fn cancel(&self) {
+        self.set(TaskState::Done(Err(TaskError::Cancelled)));
+    }
+}
+
+pub struct TaskHandle<T> {
+    id: TaskID,
+    slot: Arc<Slot<T>>,
+}
+
+impl<T> TaskHandle<T> {
+    pub fn id(&self) -> TaskID {
+        self.id
+    }
+
+    pub fn status(&self) -> TaskStatus {
+        match &*self.slot.state.lock().unwrap() {
+            TaskState::Idle => TaskStatus::Idle,
+            TaskState::Postponed => TaskStatus::Postponed,
+            TaskState::Processing => TaskStatus::Processing,
+            TaskState::Done(res) => done_status(res),
+            TaskState::Taken(status) => *status,
+        }
+    }
+
+    // whether the task will no longer run
+    pub fn is_done(&self) -> bool {
+        match self.status() {
+            TaskStatus::Idle | TaskStatus::Postponed | TaskStatus::Processing => false,
+            TaskStatus::Finished | TaskStatus::Cancelled | TaskStatus::Panicked => true,
+        }
+    }
+
+    // takes the result if the task is done, without blocking
+    pub fn try_join(&self) -> Option<Result<T, TaskError>> {
+        let mut state = self.slot.state.lock().unwrap();
+        match &*state {
+            TaskState::Done(_) => Some(take_result(&mut state)),
+            TaskState::Taken(_) => Some(Err(TaskError::Taken)),
+            _ => None,
+        }
+    }
+
+    // blocks until the task is done, then takes the result
+    pub fn join(&self) -> Result<T, TaskError> {
+        let mut state = self.slot.state.lock().unwrap();
+        loop {
+            match &*state {
+                TaskState::Done(_) => return take_result(&mut state),
+                TaskState::Taken(_) => return Err(TaskError::Taken),
+                TaskState::Postponed => return Err(TaskError::Postponed),
+                TaskState::Idle | TaskState::Processing => state = self.slot.changed.wait(state).unwrap(),
+            }
+        }
+    }
+}
+
+fn done_status<T>(res: &Result<T, TaskError>) -> TaskStatus {
+    match res {
+        Ok(_) => TaskStatus::Finished,
+        Err(TaskError::Panicked(_)) => TaskStatus::Panicked,
+        Err(_) => TaskStatus::Cancelled,
+    }
+}
+
+// takes the result out of the finished task, keeping the status it finished with
+fn take_result<T>(state: &mut TaskState<T>) -> Result<T, TaskError> {
+    let res = match std::mem::replace(state, TaskState::Taken(TaskStatus::Finished)) {
+        TaskState::Done(res) => res,
+        _ => unreachable!("Only the finished tasks have their results taken"),
+    };
+    *state = TaskState::Taken(done_status(&res));
+    res
+}
+
+// a task waiting for a worker
+struct Queued {
+    priority: i32,
+    postponed: bool,
+    job: Box<dyn FnOnce() + Send>,
+    control: Arc<dyn Control>,
+}
+
+type TaskQueue = Arc<Mutex<HashMap<TaskID, Queued>>>;
+
+// runs the waiting task with the highest priority, if there is any left
+fn run_next(queue: &TaskQueue) {
+    let next = {
+        let mut queue = queue.lock().unwrap();
+        let id = queue.iter()
+            .filter(|(_, task)| !task.postponed)
+            .max_by_key(|(id, task)| (task.priority, Reverse(**id)))
+            .map(|(id, _)| *id);
+        id.and_then(|id| queue.remove(&id))
+    };
+
+    if let Some(task) = next {
+        (task.job)();
+    }
+}
+
+// the message the panic was raised with
+pub(crate) fn panic_message(err: Box<dyn Any + Send>) -> String {
+    match err.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(err) => match err.downcast::<&'static str>() {
+            Ok(msg) => msg.to_string(),
+            Err(_) => "unknown panic".into(),
+        },
+    }
+}
+
+// A threadpool using rayon to run the tasks of the whole game
 pub struct ThreadPoolHandler {
     threadpool: ThreadPool,
-    // using String to tag a name for that specific task; a bool to identify the receiver has already received the results back
-    channels: Vec<(String, bool, Receiver<ThreadPoolOutput>)>,
+    queue: TaskQueue,
+    task_counter: u64,
+    // the tasks added by `add_work()` with their tags
+    tagged: Vec<(String, TaskHandle<ThreadPoolOutput>)>,
 }
 
-// TODO: Also handle cancel request for chunkloading
 impl ThreadPoolHandler {
     pub fn new(num: usize) -> Self {
         let threadpool = ThreadPoolBuilder::new()
             .num_threads(num)
             // .stack_size(4 * 1024 * 1024)
-            .thread_name(|id| format!("Chunk Threadpool: {}", id.to_string()))
+            .thread_name(|id| format!("Threadpool: {}", id))
             .build().unwrap();
 
         Self {
-            threadpool: threadpool,
-            channels: Vec::new(),
+            threadpool,
+            queue: Arc::new(Mutex::new(HashMap::new())),
+            task_counter: 0,
+            tagged: Vec::new(),
         }
     }
 
-    // note: adding work using the same tag but with an FnOnce closure returning different types can cause havoc
-    // add your task (FnOnce closure) to the Rayon threadpool
-    pub fn add_work<F: FnOnce() -> R + Send + 'static, R: Send + 'static>(&mut self, tag: &'static str, inp: F) {
-        // the retrieving data channel
-        let (tx, rx) = mpsc::channel();
-        self.channels.push((String::from(tag), false, rx));
-
-        self.threadpool.spawn(move || {
-            let res = tx.send(Box::new(inp()) as Box<dyn Any + Send>);
-            if let Err(e) = res {
-                println!("A thread in the chunk mesh generation threadpool has failed to send the data: {}", e);
-            }
-        });
+    // queues the task; the tasks with higher priorities are started first
+    pub fn submit<F, T>(&mut self, priority: i32, task: F) -> TaskHandle<T>
+        where F: FnOnce() -> T + Send + 'static, T: Send + 'static {
+        let id = TaskID(self.task_counter);
+        self.task_counter += 1;
+
+        let slot = Arc::new(Slot { state: Mutex::new(TaskState::Idle), changed: Condvar::new() });
+        let job_slot = slot.clone();
+        let job = move || {
+            job_slot.set(TaskState::Processing);
+            let res = panic::catch_unwind(AssertUnwindSafe(task))
+                .map_err(|err| TaskError::Panicked(panic_message(err)));
+            job_slot.set(TaskState::Done(res));
+        };
+
+        let control = slot.clone();
+        self.queue.lock().unwrap().insert(id, Queued { priority, postponed: false, job: Box::new(job), control });
+        self.spawn_worker();
+
+        TaskHandle { id, slot }
     }
 
-    // retrieve all the processed result (right now) with the same tag name
-    pub fn join_finished<T: 'static>(&mut self, tag: &'static str) -> Vec<T> {
-        let mut output_data = Vec::new();
+    // removes the task before it starts; returns false if it already started or was cancelled
+    pub fn cancel(&mut self, id: TaskID) -> bool {
+        match self.queue.lock().unwrap().remove(&id) {
+            Some(task) => {
+                task.control.cancel();
+                true
+            },
+            None => false,
+        }
+    }
 
-        for (chan_tag, mut recvd,recv) in self.channels.iter_mut() {
-            if !recvd && chan_tag == tag {
-                if let Ok(dt) = recv.try_recv() {
-                    let res = dt.downcast::<T>().expect("Type conversion of function `join_finished()` went wrong");
-                    output_data.push(*res);
-                    recvd = true;
-                }
-            }
+    // returns false if the task is no longer waiting
+    pub fn set_priority(&mut self, id: TaskID, priority: i32) -> bool {
+        match self.queue.lock().unwrap().get_mut(&id) {
+            Some(task) => {
+                task.priority = priority;
+                true
+            },
+            None => false,
         }
+    }
 
-        // clean-up all received channels
-        self.channels.retain(|(_tag,recvd,_recv)| !*recvd);
+    // keeps the waiting task from starting until it is resumed
+    pub fn postpone(&mut self, id: TaskID) -> bool {
+        match self.queue.lock().unwrap().get_mut(&id) {
+            Some(task) if !task.postponed => {
+                task.postponed = true;
+                task.control.postpone(true);
+                true
+            },
+            _ => false,
+        }
+    }
 
-        output_data
+    pub fn resume(&mut self, id: TaskID) -> bool {
+        let resumed = match self.queue.lock().unwrap().get_mut(&id) {
+            Some(task) if task.postponed => {
+                task.postponed = false;
+                task.control.postpone(false);
+                true
+            },
+            _ => false,
+        };
+
+        // the worker spawned for the task may have already found nothing to run
+        if resumed {
+            self.spawn_worker();
+        }
+        resumed
+    }
+
+    // the number of the tasks not yet started, including the postponed ones
+    pub fn waiting(&self) -> usize {
+        self.queue.lock().unwrap().len()
+    }
+
+    // note: adding work using the same tag but with an FnOnce closure returning different types returns `TaskError::TypeMismatch`
+    // add your task (FnOnce closure) to the Rayon threadpool
+    pub fn add_work<F: FnOnce() -> R + Send + 'static, R: Send + 'static>(&mut self, tag: &'static str, inp: F) -> TaskID {
+        let handle = self.submit(0, move || Box::new(inp()) as ThreadPoolOutput);
+        let id = handle.id();
+        self.tagged.push((String::from(tag), handle));
+        id
+    }
+
+    // retrieve all the processed result (right now) with the same tag name
+    pub fn join_finished<T: 'static>(&mut self, tag: &'static str) -> Vec<Result<T, TaskError>> {
+        self.join_tagged(tag, false)
     }
 
     // retrieve all the processed result regardless whether its finished or not (block until all results come) with the same tag name
     // basically a guarantee that all work added will receive a same lengthened output, unless you used `join_finished()` before this
-    pub fn join_block<T: 'static>(&mut self, tag: &'static str) -> Vec<T> {
+    pub fn join_block<T: 'static>(&mut self, tag: &'static str) -> Vec<Result<T, TaskError>> {
+        self.join_tagged(tag, true)
+    }
+
+    fn join_tagged<T: 'static>(&mut self, tag: &'static str, block: bool) -> Vec<Result<T, TaskError>> {
         let mut output_data = Vec::new();
 
-        for (chan_tag, mut recvd,recv) in self.channels.iter_mut() {
-            if !recvd && chan_tag == tag {
-                if let Ok(dt) = recv.recv() {
-                    let res = dt.downcast::<T>().expect("Type conversion of function `join_finished()` went wrong");
-                    output_data.push(*res);
-                    recvd = true;
-                }
+        self.tagged.retain(|(task_tag, handle)| {
+            if task_tag != tag {
+                return true;
             }
-        }
-
-        // clean-up all received channels
-        self.channels.retain(|(_tag,recvd,_recv)| !*recvd);
+            let res = if block { Some(handle.join()) } else { handle.try_join() };
+            match res {
+                Some(res) => {
+                    output_data.push(res.and_then(|data| data.downcast::<T>().map(|data| *data).map_err(|_| TaskError::TypeMismatch)));
+                    false
+                },
+                None => true,
+            }
+        });
 
         output_data
     }
+
+    fn spawn_worker(&self) {
+        let queue = self.queue.clone();
+        self.threadpool.spawn(move || run_next(&queue));
+    }
 }
 
-fn threadpool_testing() {
-    let mut thp = ThreadPoolHandler::new(4);
-    thp.add_work("Print", move || {
-        let mut r = 1u32;
-        for i in 0..100 {
-            r *= i;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    // a pool of a single worker, kept busy until the returned sender is dropped
+    fn blocked_pool() -> (ThreadPoolHandler, mpsc::Sender<()>) {
+        let mut pool = ThreadPoolHandler::new(1);
+        let (tx, rx) = mpsc::channel::<()>();
+        let gate = pool.submit(i32::MAX, move || { let _ = rx.recv(); });
+        while gate.status() != TaskStatus::Processing {
+            std::thread::sleep(Duration::from_millis(1));
         }
-        r
-    });
-    thp.add_work("Print", move || {
-        let mut r = 1;
-        for i in 0..100 {
-            r *= i;
+        (pool, tx)
+    }
+
+    #[test]
+    fn runs_by_priority() {
+        let (mut pool, gate) = blocked_pool();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let handles = [("low", -1), ("mid", 0), ("high", 5), ("mid2", 0), ("raised", -3)].iter().map(|&(name, priority)| {
+            let order = order.clone();
+            pool.submit(priority, move || order.lock().unwrap().push(name))
+        }).collect::<Vec<_>>();
+        assert!(pool.set_priority(handles[4].id(), 10));
+        assert_eq!(handles[0].status(), TaskStatus::Idle);
+
+        drop(gate);
+        for handle in &handles {
+            assert_eq!(handle.join(), Ok(()));
+            assert_eq!(handle.status(), TaskStatus::Finished);
         }
-        r
-    });
-    let res = thp.join_block::<i32>("Print");
+        assert_eq!(*order.lock().unwrap(), vec!["raised", "high", "mid", "mid2", "low"]);
+        assert_eq!(handles[0].join(), Err(TaskError::Taken));
+    }
+
+    #[test]
+    fn cancels_and_postpones_waiting_tasks() {
+        let (mut pool, gate) = blocked_pool();
+        let cancelled = pool.submit(0, || 1);
+        let postponed = pool.submit(0, || 2);
+        let other = pool.submit(0, || 3);
+
+        assert!(pool.cancel(cancelled.id()));
+        assert!(!pool.cancel(cancelled.id()));
+        assert!(pool.postpone(postponed.id()));
+        assert_eq!(postponed.status(), TaskStatus::Postponed);
+        assert_eq!(postponed.join(), Err(TaskError::Postponed));
+
+        drop(gate);
+        assert_eq!(other.join(), Ok(3));
+        assert_eq!(cancelled.status(), TaskStatus::Cancelled);
+        assert_eq!(cancelled.join(), Err(TaskError::Cancelled));
+        assert_eq!(pool.waiting(), 1);
+        assert_eq!(postponed.try_join(), None);
+
+        assert!(pool.resume(postponed.id()));
+        assert_eq!(postponed.join(), Ok(2));
+        assert_eq!(pool.waiting(), 0);
+        assert!(!pool.cancel(postponed.id()));
+    }
+
+    #[test]
+    fn wakes_up_joins_on_postpone() {
+        let (mut pool, gate) = blocked_pool();
+        let (waiting, joined) = mpsc::channel();
+        let task = pool.submit(0, || 1);
+        let id = task.id();
+
+        let joiner = std::thread::spawn(move || {
+            waiting.send(None).unwrap();
+            waiting.send(Some(task.join())).unwrap();
+        });
+        assert_eq!(joined.recv().unwrap(), None);
+        // gives the other thread the time to start waiting on the task
+        std::thread::sleep(Duration::from_millis(50));
+
+        assert!(pool.postpone(id));
+        assert_eq!(joined.recv_timeout(Duration::from_secs(10)), Ok(Some(Err(TaskError::Postponed))));
+        joiner.join().unwrap();
+        drop(gate);
+    }
+
+    #[test]
+    fn reports_panics() {
+        let mut pool = ThreadPoolHandler::new(2);
+        let failed = pool.submit(0, || -> u32 { panic!("chunk {} is broken", 3) });
+        let fine = pool.submit(0, || 4u32);
+
+        assert_eq!(failed.join(), Err(TaskError::Panicked("chunk 3 is broken".into())));
+        assert_eq!(failed.status(), TaskStatus::Panicked);
+        assert_eq!(fine.join(), Ok(4));
+    }
 
+    #[test]
+    fn joins_tagged_work() {
+        let mut pool = ThreadPoolHandler::new(4);
+        for i in 0..8u32 {
+            pool.add_work("Square", move || i * i);
+        }
+        pool.add_work("Other", || "text");
+        pool.add_work("Other", || panic!("failed"));
+
+        let mut squares = pool.join_block::<u32>("Square").into_iter().map(Result::unwrap).collect::<Vec<_>>();
+        squares.sort();
+        assert_eq!(squares, vec![0, 1, 4, 9, 16, 25, 36, 49]);
+        assert!(pool.join_finished::<u32>("Square").is_empty());
+
+        let others = pool.join_block::<u32>("Other");
+        assert_eq!(others.len(), 2);
+        assert!(others.contains(&Err(TaskError::TypeMismatch)));
+        assert!(others.contains(&Err(TaskError::Panicked("failed".into()))));
+    }
 }
@@ -0,0 +1,87 @@
+use crate::ui::{Widget, Context};
+use crate::world::commands::console::{Console, ConsoleKey};
+
+use winit::event::{Event, WindowEvent, KeyboardInput, ElementState, VirtualKeyCode as K};
+
+/*
+Console Widget
+--------------
+The screen of the command mode. Turns the window events into the keys of the world console,
+and shows the last lines of its scrollback with the line being typed.
+ */
+
+const VISIBLE_LINES: usize = 12;
+
+pub struct ConsoleWidget {
+    open: bool,  // only reads the keys while the command mode is on
+    ctrl: bool,
+    keys: Vec<ConsoleKey>,  // keys to be passed to the console
+    lines: Vec<String>,  // the lines shown on the screen
+}
+
+impl ConsoleWidget {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            ctrl: false,
+            keys: Vec::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+        self.keys.clear();
+    }
+
+    // the keys pressed since the last call, for `Console::press`
+    pub fn take_keys(&mut self) -> Vec<ConsoleKey> {
+        std::mem::take(&mut self.keys)
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    // copies the lines to be shown from the console
+    pub fn show(&mut self, console: &Console) {
+        let scrollback = console.scrollback().collect::<Vec<_>>();
+        let start = scrollback.len().saturating_sub(VISIBLE_LINES - 1);
+        self.lines = scrollback[start..].iter().map(|line| line.to_string()).collect();
+        self.lines.push(console.input_line());
+    }
+}
+
+impl Widget for ConsoleWidget {
+    fn update(&mut self, e: &Event<()>) {
+        let event = match e {
+            Event::WindowEvent { event, .. } => event,
+            _ => return,
+        };
+
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => self.ctrl = modifiers.ctrl(),
+            // the typed text comes from the characters rather than the keys, so it follows the keyboard layout
+            WindowEvent::ReceivedCharacter(c) if self.open && !c.is_control() && !self.ctrl => {
+                self.keys.push(ConsoleKey::Char(*c));
+            },
+            WindowEvent::KeyboardInput { input: KeyboardInput { virtual_keycode: Some(key), state: ElementState::Pressed, .. }, .. } if self.open => {
+                let key = match key {
+                    K::Back => ConsoleKey::Backspace,
+                    K::Return | K::NumpadEnter => ConsoleKey::Enter,
+                    K::Tab => ConsoleKey::Tab,
+                    K::Up => ConsoleKey::Up,
+                    K::Down => ConsoleKey::Down,
+                    K::C if self.ctrl => ConsoleKey::Interrupt,
+                    _ => return,
+                };
+                self.keys.push(key);
+            },
+            _ => {},
+        }
+    }
+
+    fn render(&self, _ctx: &mut Context) {
+        // TODO: draw the lines while open, once the characters can be added to the context (`Context::add_char`)
+    }
+}
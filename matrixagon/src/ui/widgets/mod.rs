@@ -1,2 +1,4 @@
 pub mod text;
+pub mod console;
 pub use text::*;
+pub use console::*;
@@ -1,7 +1,9 @@
 use crate::world::block::Block;
 use crate::world::mesh::MeshType;
-use crate::world::texture::Texture;
+use crate::world::texture::{Texture, TextureID};
 use crate::world::block::state::{BlockState, Matter};
+use crate::world::commands::bytecode::StackType;
+use crate::world::commands::namespace::{Namespace, NmspcRes, NamespaceError, names, path_str};
 
 use std::collections::HashMap;
 use std::ops::Index;
@@ -19,6 +21,11 @@ impl BlockRegistry {
     // initiates the block registry
     // adds all the basic blocks of matrixagon
     pub fn new(texture: &Texture) -> Self {
+        Self::with_textures(|name| texture.id_name(name))
+    }
+
+    // adds all the basic blocks of matrixagon, with their textures looked up by the texture name
+    pub fn with_textures<F: Fn(&str) -> Option<TextureID>>(texture: F) -> Self {
         let mut reg = Self {
             blocks: HashMap::new(),
             id_counter: 1,  // 0 BlockID is null
@@ -30,39 +37,39 @@ impl BlockRegistry {
             BlockState {matter: Matter::Gas, transparent: true, ..Default::default()},
         );
         reg.add_block("dirt".into(),
-                      MeshType::cube_all(texture.id_name("dirt".into()).unwrap()),
+                      MeshType::cube_all(texture("dirt").unwrap()),
                       BlockState {..Default::default()},
         );
         reg.add_block("grass_block".into(),
                       MeshType::Cube {
-                              top: texture.id_name("grass_top".into()).unwrap(),
-                              bottom: texture.id_name("dirt".into()).unwrap(),
-                              left: texture.id_name("grass_side".into()).unwrap(),
-                              right: texture.id_name("grass_side".into()).unwrap(),
-                              front: texture.id_name("grass_side".into()).unwrap(),
-                              back: texture.id_name("grass_side".into()).unwrap()
+                              top: texture("grass_top").unwrap(),
+                              bottom: texture("dirt").unwrap(),
+                              left: texture("grass_side").unwrap(),
+                              right: texture("grass_side").unwrap(),
+                              front: texture("grass_side").unwrap(),
+                              back: texture("grass_side").unwrap()
                       },
                       BlockState {..Default::default()}
         );
         reg.add_block("stone".into(),
-                      MeshType::cube_all(texture.id_name("stone".into()).unwrap()),
+                      MeshType::cube_all(texture("stone").unwrap()),
                       BlockState {..Default::default()}
         );
         reg.add_block("sand".into(),
-                      MeshType::cube_all(texture.id_name("sand".into()).unwrap()),
+                      MeshType::cube_all(texture("sand").unwrap()),
                       BlockState {..Default::default()}
         );
         reg.add_block("grass".into(),
                       MeshType::FloraX {
-                          positive: texture.id_name("grass_flora".into()).unwrap(),
-                          negative: texture.id_name("grass_flora".into()).unwrap(),
+                          positive: texture("grass_flora").unwrap(),
+                          negative: texture("grass_flora").unwrap(),
                       },
                       BlockState {transparent: true, ..Default::default()}
         );
         reg.add_block("flower".into(),
                       MeshType::FloraX {
-                          positive: texture.id_name("flower".into()).unwrap(),
-                          negative: texture.id_name("flower".into()).unwrap(),
+                          positive: texture("flower").unwrap(),
+                          negative: texture("flower").unwrap(),
                       },
                       BlockState {transparent: true, ..Default::default()}
         );
@@ -99,6 +106,39 @@ impl BlockRegistry {
     }
 }
 
+// registered as a read-only namespace (e.g. `:Matrixagon:world:blocks`)
+impl Namespace for BlockRegistry {
+    fn entries(&self, path: &[String]) -> Vec<String> {
+        match path_str(path).as_slice() {
+            [] => {
+                let mut entries = self.blocks.values().map(|block| String::from(block.name)).collect::<Vec<_>>();
+                entries.push("count".into());
+                entries
+            },
+            [name] if self.block_id(String::from(*name)).is_some() => names(&["id", "transparent", "placeable", "breakable"]),
+            _ => Vec::new(),
+        }
+    }
+
+    fn get(&self, path: &[String]) -> NmspcRes<StackType> {
+        match path_str(path).as_slice() {
+            ["count"] => Ok(StackType::Int(self.blocks.len() as i64)),
+            [name, prop] => {
+                let id = self.block_id(String::from(*name)).ok_or(NamespaceError::Unknown)?;
+                let block = &self.blocks[&id];
+                match *prop {
+                    "id" => Ok(StackType::Int(id.0 as i64)),
+                    "transparent" => Ok(StackType::Int(block.state.transparent as i64)),
+                    "placeable" => Ok(StackType::Int(block.state.placeable as i64)),
+                    "breakable" => Ok(StackType::Int(block.state.breakable as i64)),
+                    _ => Err(NamespaceError::Unknown),
+                }
+            },
+            _ => Err(NamespaceError::Unknown),
+        }
+    }
+}
+
 impl Index<String> for BlockRegistry {
     type Output = Block;
 
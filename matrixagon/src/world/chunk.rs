@@ -35,7 +35,7 @@ impl Chunk {
                 }
             }
             if opaque {
-                layers = layers | (1 << l)
+                layers |= 1 << l
             }
         }
 
@@ -46,17 +46,29 @@ impl Chunk {
         // println!("");
 
         Self {
-            id: id,
+            id,
             visible: true,  // TODO: Conditional; For frustum culling
-            position: position,
-            block_data: block_data,
-            layers: layers,
+            position,
+            block_data,
+            layers,
         }
     }
 
     // TODO: temporary; will create a proper chunk interface; or use the event system
     pub fn update(&mut self, remove_block_pos: Position<LocalBU>, block_to_be_replaced: Block) {
-        (*self.block_data)[remove_block_pos.into_vec_pos()] = block_to_be_replaced;
+        let ind = remove_block_pos.into_vec_pos();
+        (*self.block_data)[ind] = block_to_be_replaced;
+
+        // the opaque layer bit of the replaced block needs to be re-evaluated
+        let l = (ind / CHUNK_SIZE) % CHUNK_SIZE;
+        let opaque = (0..CHUNK_SIZE).all(|x| (0..CHUNK_SIZE).all(|z| {
+            !self.block_data[x*CHUNK_SIZE*CHUNK_SIZE+l*CHUNK_SIZE+z].state.transparent
+        }));
+        if opaque {
+            self.layers |= 1 << l;
+        } else {
+            self.layers &= !(1 << l);
+        }
     }
 
     #[inline(always)]
@@ -67,6 +79,7 @@ impl Chunk {
 
 pub enum ChunkError {
     Invalid,  // TODO invalid chunk when reading
+    Unloaded,  // the chunk at the position is not loaded
 
     // related to Chunk ID's
     DuplicateID,  // if there were multiple same ID
@@ -0,0 +1,273 @@
+use crate::world::block::Block;
+use crate::world::chunk::{ChunkError, CHUNK_BLOCKS};
+use crate::world::commands::bytecode::StackType;
+use crate::world::commands::namespace::{NmspcRes, NamespaceError, number};
+
+/*
+Chunk Editing Functions
+-----------------------
+The chunk editing functions of the world namespace (e.g. `:Matrixagon:world:chunks:fill`) used by the world
+command library. They work on the world block positions, so an edit can span over any number of chunks,
+and none of the blocks are edited if any of them are within a chunk that is not loaded.
+ */
+
+const MAX_EDIT_BLOCKS: usize = CHUNK_BLOCKS;  // the most blocks a single command can edit at once
+
+// the world the chunk editing functions work on (e.g. the chunk handler)
+pub trait ChunkEdit {
+    // the block at the world block position, if its chunk is loaded
+    fn block(&self, pos: [i64; 3]) -> Option<Block>;
+
+    // none of the blocks are set if any of them are within a chunk that is not loaded
+    fn set_blocks(&mut self, blocks: Vec<([i64; 3], Block)>) -> Result<(), ChunkError>;
+
+    // the block of the registry name used by the commands
+    fn registry_block(&self, name: &StackType) -> NmspcRes<Block>;
+}
+
+// calls the chunk editing function (the path relative to `chunks`)
+pub fn call<W: ChunkEdit>(world: &mut W, func: &str, args: &[StackType]) -> NmspcRes<Vec<StackType>> {
+    match (func, args) {
+        ("set", [x, y, z, name]) => {
+            let block = world.registry_block(name)?;
+            edit_blocks(world, vec![(block_pos(x, y, z)?, block)])
+        },
+        ("fill", [x1, y1, z1, x2, y2, z2, name]) => {
+            let block = world.registry_block(name)?;
+            let blocks = region(block_pos(x1, y1, z1)?, block_pos(x2, y2, z2)?)?
+                .into_iter().map(|pos| (pos, block)).collect();
+            edit_blocks(world, blocks)
+        },
+        ("replace", [x1, y1, z1, x2, y2, z2, old, new]) => {
+            let old = world.registry_block(old)?;
+            let new = world.registry_block(new)?;
+
+            let mut blocks = Vec::new();
+            for pos in region(block_pos(x1, y1, z1)?, block_pos(x2, y2, z2)?)? {
+                if world.block(pos).ok_or_else(unloaded)?.id == old.id {
+                    blocks.push((pos, new));
+                }
+            }
+            edit_blocks(world, blocks)
+        },
+        ("clone", [x1, y1, z1, x2, y2, z2, x, y, z]) => {
+            let (corner_a, corner_b) = (block_pos(x1, y1, z1)?, block_pos(x2, y2, z2)?);
+            let dest = block_pos(x, y, z)?;
+            let lowest = [corner_a[0].min(corner_b[0]), corner_a[1].min(corner_b[1]), corner_a[2].min(corner_b[2])];
+
+            // all the source blocks are read before writing, so overlapping regions are copied as they were
+            let mut blocks = Vec::new();
+            for pos in region(corner_a, corner_b)? {
+                let block = world.block(pos).ok_or_else(unloaded)?;
+                let mut target = [0; 3];
+                for axis in 0..3 {
+                    target[axis] = pos[axis].checked_sub(lowest[axis])
+                        .and_then(|offset| dest[axis].checked_add(offset))
+                        .ok_or_else(|| NamespaceError::Failed("clone destination is out of the world".into()))?;
+                }
+                blocks.push((target, block));
+            }
+            edit_blocks(world, blocks)
+        },
+        ("query", [x, y, z]) => {
+            let block = world.block(block_pos(x, y, z)?).ok_or_else(unloaded)?;
+            Ok(vec![StackType::Str(block.name.into())])
+        },
+        _ => Err(NamespaceError::Unknown),
+    }
+}
+
+// sets the blocks edited by the commands
+fn edit_blocks<W: ChunkEdit>(world: &mut W, blocks: Vec<([i64; 3], Block)>) -> NmspcRes<Vec<StackType>> {
+    world.set_blocks(blocks).map_err(|_| unloaded())?;
+    Ok(Vec::new())
+}
+
+// the world block position from the command values
+fn block_pos(x: &StackType, y: &StackType, z: &StackType) -> NmspcRes<[i64; 3]> {
+    Ok([coordinate(x)?, coordinate(y)?, coordinate(z)?])
+}
+
+// the values too large for a block position are saturated, and rejected later by the size of the region
+fn coordinate(val: &StackType) -> NmspcRes<i64> {
+    let val = number(val)?;
+    if !val.is_finite() {
+        return Err(NamespaceError::TypeMismatch);
+    }
+    Ok(val.floor() as i64)
+}
+
+// all the world block positions within the cuboid region between the two corners (inclusive)
+fn region(corner_a: [i64; 3], corner_b: [i64; 3]) -> NmspcRes<Vec<[i64; 3]>> {
+    let low = [corner_a[0].min(corner_b[0]), corner_a[1].min(corner_b[1]), corner_a[2].min(corner_b[2])];
+    let high = [corner_a[0].max(corner_b[0]), corner_a[1].max(corner_b[1]), corner_a[2].max(corner_b[2])];
+
+    // rejected as soon as the volume exceeds the limit, before any of the spans can overflow the volume
+    let mut volume = 1i64;
+    for axis in 0..3 {
+        volume = high[axis].checked_sub(low[axis])
+            .and_then(|span| span.checked_add(1))
+            .and_then(|span| span.checked_mul(volume))
+            .filter(|volume| *volume <= MAX_EDIT_BLOCKS as i64)
+            .ok_or_else(|| NamespaceError::Failed(format!("region exceeds the limit of {} blocks", MAX_EDIT_BLOCKS)))?;
+    }
+
+    let mut positions = Vec::with_capacity(volume as usize);
+    for x in low[0]..=high[0] {
+        for y in low[1]..=high[1] {
+            for z in low[2]..=high[2] {
+                positions.push([x, y, z]);
+            }
+        }
+    }
+    Ok(positions)
+}
+
+fn unloaded() -> NamespaceError {
+    NamespaceError::Failed("block position is not within a loaded chunk".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block::registry::BlockID;
+    use crate::world::block::state::BlockState;
+    use crate::world::mesh::MeshType;
+
+    use std::collections::HashMap;
+
+    const BLOCKS: [&str; 3] = ["air", "stone", "dirt"];
+
+    // the blocks within `0..16` on each axis are loaded, all of them air at first
+    struct World {
+        blocks: HashMap<[i64; 3], Block>,
+    }
+
+    impl World {
+        fn new() -> Self {
+            let mut blocks = HashMap::new();
+            for x in 0..16 {
+                for y in 0..16 {
+                    for z in 0..16 {
+                        blocks.insert([x, y, z], block("air"));
+                    }
+                }
+            }
+            Self { blocks }
+        }
+
+        fn name(&self, pos: [i64; 3]) -> &'static str {
+            self.blocks[&pos].name
+        }
+    }
+
+    impl ChunkEdit for World {
+        fn block(&self, pos: [i64; 3]) -> Option<Block> {
+            self.blocks.get(&pos).copied()
+        }
+
+        fn set_blocks(&mut self, blocks: Vec<([i64; 3], Block)>) -> Result<(), ChunkError> {
+            if blocks.iter().any(|(pos, _)| !self.blocks.contains_key(pos)) {
+                return Err(ChunkError::Unloaded);
+            }
+            self.blocks.extend(blocks);
+            Ok(())
+        }
+
+        fn registry_block(&self, name: &StackType) -> NmspcRes<Block> {
+            match name {
+                StackType::Str(name) if BLOCKS.contains(&name.as_str()) => Ok(block(name)),
+                StackType::Str(name) => Err(NamespaceError::Failed(format!("unknown block `{}`", name))),
+                _ => Err(NamespaceError::TypeMismatch),
+            }
+        }
+    }
+
+    fn block(name: &str) -> Block {
+        let id = BLOCKS.iter().position(|other| *other == name).unwrap();
+        Block::new(BlockID(id as u32 + 1), BLOCKS[id], MeshType::Air, BlockState::default())
+    }
+
+    fn args(vals: &[f64], names: &[&str]) -> Vec<StackType> {
+        vals.iter().map(|val| StackType::Float(*val))
+            .chain(names.iter().map(|name| StackType::Str(name.to_string())))
+            .collect()
+    }
+
+    fn count(world: &World, name: &str) -> usize {
+        world.blocks.values().filter(|block| block.name == name).count()
+    }
+
+    #[test]
+    fn sets_and_queries_blocks() {
+        let mut world = World::new();
+        assert_eq!(call(&mut world, "set", &args(&[1.0, 2.9, -0.0], &["stone"])), Ok(Vec::new()));
+        assert_eq!(world.name([1, 2, 0]), "stone");
+        assert_eq!(call(&mut world, "query", &args(&[1.5, 2.0, 0.0], &[])), Ok(vec![StackType::Str("stone".into())]));
+
+        assert_eq!(call(&mut world, "set", &args(&[1.0, 2.0, 0.0], &["glass"])), Err(NamespaceError::Failed("unknown block `glass`".into())));
+        assert_eq!(call(&mut world, "set", &args(&[1.0, 2.0, 0.0, 4.0], &[])), Err(NamespaceError::TypeMismatch));
+        assert_eq!(call(&mut world, "query", &args(&[1.0, 2.0], &[])), Err(NamespaceError::Unknown));
+    }
+
+    #[test]
+    fn fills_and_replaces_regions() {
+        let mut world = World::new();
+        // the corners can be given in any order
+        call(&mut world, "fill", &args(&[3.0, 0.0, 3.0, 0.0, 1.0, 0.0], &["stone"])).unwrap();
+        assert_eq!(count(&world, "stone"), 4*2*4);
+        assert_eq!(world.name([3, 1, 0]), "stone");
+        assert_eq!(world.name([4, 1, 0]), "air");
+
+        call(&mut world, "replace", &args(&[0.0, 1.0, 0.0, 15.0, 1.0, 15.0], &["stone", "dirt"])).unwrap();
+        assert_eq!(count(&world, "stone"), 4*4);
+        assert_eq!(count(&world, "dirt"), 4*4);
+        assert_eq!(world.name([0, 1, 0]), "dirt");
+        assert_eq!(world.name([0, 0, 0]), "stone");
+    }
+
+    #[test]
+    fn clones_overlapping_regions() {
+        let mut world = World::new();
+        call(&mut world, "set", &args(&[0.0, 0.0, 0.0], &["stone"])).unwrap();
+        call(&mut world, "set", &args(&[1.0, 0.0, 0.0], &["dirt"])).unwrap();
+
+        // shifting the row by one reads the blocks before overwriting them
+        call(&mut world, "clone", &args(&[0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 1.0, 0.0, 0.0], &[])).unwrap();
+        let row = (0..4).map(|x| world.name([x, 0, 0])).collect::<Vec<_>>();
+        assert_eq!(row, vec!["stone", "stone", "dirt", "air"]);
+
+        // the destination is the lowest corner of the copy
+        call(&mut world, "clone", &args(&[2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 5.0, 3.0, 5.0], &[])).unwrap();
+        assert_eq!((5..8).map(|x| world.name([x, 3, 5])).collect::<Vec<_>>(), vec!["stone", "stone", "dirt"]);
+    }
+
+    #[test]
+    fn rejects_unloaded_blocks() {
+        let mut world = World::new();
+        let unloaded = Err(unloaded());
+
+        assert_eq!(call(&mut world, "set", &args(&[0.0, 16.0, 0.0], &["stone"])), unloaded);
+        assert_eq!(call(&mut world, "query", &args(&[-1.0, 0.0, 0.0], &[])), unloaded);
+        // none of the blocks are edited if any of them are unloaded
+        assert_eq!(call(&mut world, "fill", &args(&[0.0, 0.0, 0.0, 0.0, 0.0, 16.0], &["stone"])), unloaded);
+        assert_eq!(call(&mut world, "clone", &args(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 15.0, 0.0, 0.0], &[])), unloaded);
+        assert_eq!(count(&world, "stone"), 0);
+    }
+
+    #[test]
+    fn limits_the_region_size() {
+        let mut world = World::new();
+        let too_large = Err(NamespaceError::Failed(format!("region exceeds the limit of {} blocks", MAX_EDIT_BLOCKS)));
+
+        assert_eq!(call(&mut world, "fill", &args(&[0.0, 0.0, 0.0, 31.0, 31.0, 32.0], &["stone"])), too_large);
+        assert_eq!(call(&mut world, "fill", &args(&[-1e19, 0.0, 0.0, 1e19, 0.0, 0.0], &["stone"])), too_large);
+        assert_eq!(call(&mut world, "replace", &args(&[-1e18, -1e18, -1e18, 1e18, 1e18, 1e18], &["air", "stone"])), too_large);
+        assert_eq!(call(&mut world, "fill", &args(&[f64::NAN, 0.0, 0.0, 1.0, 0.0, 0.0], &["stone"])), Err(NamespaceError::TypeMismatch));
+        assert_eq!(call(&mut world, "query", &args(&[0.0, f64::INFINITY, 0.0], &[])), Err(NamespaceError::TypeMismatch));
+
+        let out_of_world = Err(NamespaceError::Failed("clone destination is out of the world".into()));
+        assert_eq!(call(&mut world, "clone", &args(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1e19, 0.0, 0.0], &[])), out_of_world);
+        assert_eq!(count(&world, "stone"), 0);
+    }
+}
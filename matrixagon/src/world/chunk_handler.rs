@@ -1,21 +1,30 @@
 use crate::world::chunk::Chunk;
-use crate::event::{EventDispatcher, EventName};
+use crate::world::block::Block;
+use crate::event::EventDispatcher;
+use crate::event::types::mesh;
 use crate::world::WorldStateUpd;
 use crate::world::ChunkID;
-use crate::datatype::{Position, ChunkUnit, Dimension};
-use crate::world::player::CHUNK_RADIUS;
+use crate::datatype::{Position, ChunkUnit, LocalBU};
 use crate::world::chunk::{ChunkError, CHUNK_SIZE};
 use crate::world::terrain::Terrain;
-use crate::world::mesh::{MeshesStructType, MeshesDataType};
+use crate::world::mesh::{MeshesStructType, MeshesDataType, ChunkMeshes};
 use crate::world::chunk_threadpool::ChunkThreadPool;
+use crate::world::chunk_queue::ChunkQueue;
+use crate::world::chunk_map::{ChunkMap, to_chunk_pos};
+use crate::world::chunk_edit::{self, ChunkEdit};
+use crate::world::settings::{WorldSettings, SettingsError};
 use crate::world::player::camera::Camera;
+use crate::world::commands::bytecode::StackType;
+use crate::world::commands::namespace::{Namespace, NmspcRes, NamespaceError, names, path_str};
 
-use vulkano::device::{Device, Queue};
+use vulkano::device::Device;
 
 use std::sync::Arc;
 use std::rc::Rc;
 
 
+const MAX_GENERATING_CHUNKS: usize = 16;  // the most chunks generated (until meshed) at the same time
+
 pub type ThreadInput = WorldStateUpd;
 pub type ThreadOutput<'b> = (MeshesDataType, ChunkStatusInfo);
 
@@ -29,7 +38,7 @@ pub struct ChunkStatusInfo {
 }
 
 impl ChunkStatusInfo {
-    fn from_chunk_handler(handler: &ChunkHandler, chunks_ld: u32, chunks_offld: u32, chunks_upd: u32) -> Self {
+    fn from_chunk_handler<M>(handler: &ChunkHandler<M>, chunks_ld: u32, chunks_offld: u32, chunks_upd: u32) -> Self {
         Self {
             chunks: handler.chunks.iter().map(|c| (c.id, c.position)).collect::<Vec<_>>(),
             total_chunks_loaded: handler.chunks.len() as u32,
@@ -41,192 +50,245 @@ impl ChunkStatusInfo {
 }
 
 
-pub struct ChunkHandler {
-    device: Arc<Device>,
-    queue: Arc<Queue>,
-
+// the meshes are only rendered with the concrete `MeshesStructType`
+pub struct ChunkHandler<M = MeshesStructType> {
     event: Rc<EventDispatcher>,  // event queue
-    chunks: Vec<Chunk>,  // vectors of chunks
-    meshes: MeshesStructType,  // world meshes
+    settings: WorldSettings,
+    chunks: Arc<ChunkMap>,  // the loaded chunks; shared with the meshing workers
+    meshes: M,  // world meshes
     terrain: Terrain,  // terrain of the world
 
     cid_counter: u32,  // chunk id counter
     chunk_threadpool: ChunkThreadPool,
+    chunk_queue: ChunkQueue,  // the order the missing chunks are generated in
     reload_chunks: bool,
+    stale: bool,  // the chunks around the player may still have to be loaded or offloaded
+    reloads_pending: usize,  // the edited chunks whose `ReloadChunk` event is yet to be handled
 
     chunks_loaded: u32,
     chunks_offloaded: u32,
 }
 
-impl ChunkHandler {
+impl<M: ChunkMeshes> ChunkHandler<M> {
     // creating a chunk handler requires you to communicate through mspc's
-    pub fn new(device: Arc<Device>, queue: Arc<Queue>, evd: Rc<EventDispatcher>,
-               meshes: MeshesStructType, terrain: Terrain) -> Self {
-        // chunk_obsv.subscribe(String::from("a"), a, a);
+    pub fn new(evd: Rc<EventDispatcher>, meshes: M, terrain: Terrain, settings: WorldSettings) -> Self {
 
         Self {
-            device: device.clone(),
-            queue: queue.clone(),
-
             event: evd.clone(),
-            chunks: Vec::new(),
-            meshes: meshes,
-            terrain: terrain,
+            chunk_threadpool: ChunkThreadPool::new(settings.threads()),
+            chunk_queue: ChunkQueue::new(settings.chunk_radius, MAX_GENERATING_CHUNKS),
+            settings,
+            chunks: Arc::new(ChunkMap::new()),
+            meshes,
+            terrain,
 
             cid_counter: 0,
-            // high number: faster chunk generation but laggier across the whole computer
-            // low number: slower chunk generation (maybe even stack overflow) but smoother across the whole computer
-            chunk_threadpool: ChunkThreadPool::new(8),
             reload_chunks: false,
+            stale: true,
+            reloads_pending: 0,
 
             chunks_loaded: 0,
             chunks_offloaded: 0,
         }
     }
 
-    // updates every game tick, then returns the World Mesh Data
-    pub fn update(&mut self, state: WorldStateUpd) -> (MeshesDataType, ChunkStatusInfo) {
-        /*
-        // once for receive_once();
-        event_receiver!(self.event){
-            "MeshEvent/NewChunk" => once |mut param| {
+    // loads, offloads and remeshes the chunks around the camera every game tick, without rendering them
+    pub fn update_chunks(&mut self, cam: &Camera) -> ChunkStatusInfo {
+        // the closures borrow the handler mutably, so the events are received through a clone of the dispatcher
+        let evd = self.event.clone();
 
-            },
-            "OtherEvents" => |mut param| {
-
-            },
-        }
-        self.event.clone().receive_event()
-            .receive(EventName(), mut || {
-            })
-            .receive_once(name, closure)
-
-         */
-
-        self.event.clone().receive(EventName("MeshEvent/NewChunk"),  |mut param| {
-            let pos = param.pop::<Position<ChunkUnit>>();
+        evd.receive(|event: &mesh::NewChunk| {
+            let pos = event.0;
 
             if let Ok(id) = self.chunk_id(pos) {
                 let new_chunk = Chunk::new(id, pos, self.terrain.generate_chunk(pos));
                 self.meshes.add_chunk(new_chunk.id);
-                self.chunks.push(new_chunk);
-                self.chunks_loaded += 1;
+                // the workers still meshing the other chunks keep the map as it was
+                if Arc::make_mut(&mut self.chunks).insert(new_chunk).is_ok() {
+                    self.chunks_loaded += 1;
+                }
+            } else {
+                self.chunk_queue.finish(to_chunk_pos(pos));
             }
             self.reload_chunks = true;
         });
-        self.event.clone().receive(EventName("MeshEvent/LoadChunk"), |mut param| {
-            let _ = param.pop::<u32>();
+        evd.receive(|_: &mesh::LoadChunk| {
             self.reload_chunks = true;
         });
-        self.event.clone().receive(EventName("MeshEvent/OffloadChunk"), |mut param| {
-            let id = param.pop::<ChunkID>();
+        evd.receive(|event: &mesh::OffloadChunk| {
+            let id = event.0;
 
+            // the player has left the chunk, so its meshing work is no longer needed
+            self.chunk_threadpool.cancel(id);
             self.meshes.remv_chunk(id);
 
-            for ind in 0..self.chunks.len() {
-                if self.chunks[ind].id == id {
-                    self.chunks.swap_remove(ind);
-                    break;
-                }
+            if let Some(chunk) = Arc::make_mut(&mut self.chunks).remove(id) {
+                self.chunk_queue.finish(to_chunk_pos(chunk.position));
+                self.chunks_offloaded += 1;
             }
-            self.chunks_offloaded += 1;
             self.reload_chunks = true;
         });
-        self.event.clone().receive(EventName("MeshEvent/ReloadChunks"), |mut param| {
+        evd.receive(|_: &mesh::ReloadChunks| {
             self.reload_chunks = true;
         });
-        self.event.clone().receive(EventName("MeshEvent/ReloadChunk"), |mut param| {
-            let _ = param.pop::<ChunkID>();
-
+        // the edited chunk (and its neighbours) are regenerated once the meshes are loaded on `UpdateMesh`
+        evd.receive(|event: &mesh::ReloadChunk| {
+            self.meshes.mark_dirty(event.0);
+            self.reloads_pending = self.reloads_pending.saturating_sub(1);
             self.reload_chunks = true;
         });
         // Updates mesh with reloading all necessary chunks
-        self.event.clone().receive(EventName("MeshEvent/UpdateMesh"), |mut param| {
-            println!("begn");
+        evd.receive(|_: &mesh::UpdateMesh| {
             self.meshes.load_chunks(self.chunks.clone(), &mut self.chunk_threadpool);
-            println!("endn");
             self.reload_chunks = true;
         });
         //TODO: maybe directly hook-up the events to each of the meshes directly
-        self.event.clone().receive(EventName("MeshEvent/UpdateDimensions"), |mut param| {
-            let dimn = param.pop::<Dimension<u32>>();
-
-            self.meshes.update(Some(dimn), None);
+        evd.receive(|event: &mesh::UpdateDimensions| {
+            self.meshes.update(Some(event.0), None);
         });
-        self.event.clone().receive(EventName("MeshEvent/UpdateWorldStates"), |mut param| {
-            let cam = param.pop::<Camera>();
-
-            self.meshes.update(None, Some(&cam));
+        evd.receive(|event: &mesh::UpdateWorldStates| {
+            self.meshes.update(None, Some(&event.0));
         });
 
+        // loads the data of each chunk into its mesh as soon as it is generated, rather than waiting on all of them
+        for (id, mesh_kind, res) in self.chunk_threadpool.collect_finished() {
+            match res {
+                Ok(data) => {
+                    self.meshes.load_chunk_data(mesh_kind, id, data);
+                    self.reload_chunks = true;
+                },
+                Err(err) => println!("[CHUNK] Generating the {:?} mesh of the chunk {:?} failed: {}", mesh_kind, id, err.message()),
+            }
+
+            // the chunk is done generating once all of its meshes are
+            if !self.chunk_threadpool.is_pending(id) {
+                if let Some(chunk) = self.chunks.by_id(id) {
+                    self.chunk_queue.finish(to_chunk_pos(chunk.position));
+                }
+            }
+        }
+
         let mut chunk_loaded = 0;
         let mut chunk_offloaded = 0;
 
         // world.player position in chunk position
         let chunk_pos: Position<i64> = Position::new(
-            (state.cam.position.coords.data[0] / CHUNK_SIZE as f32).floor() as i64,
-            (state.cam.position.coords.data[1] / CHUNK_SIZE as f32).floor() as i64,
-            (state.cam.position.coords.data[2] / CHUNK_SIZE as f32).floor() as i64,
+            (cam.position.coords.data[0] / CHUNK_SIZE as f32).floor() as i64,
+            (cam.position.coords.data[1] / CHUNK_SIZE as f32).floor() as i64,
+            (cam.position.coords.data[2] / CHUNK_SIZE as f32).floor() as i64,
         );
 
-        for x in -(CHUNK_RADIUS as i64)..=CHUNK_RADIUS as i64 {
-            for y in -(CHUNK_RADIUS as i64)..=CHUNK_RADIUS as i64 {
-                for z in -(CHUNK_RADIUS as i64)..=CHUNK_RADIUS as i64 {
-                    // prevent chunk generation below y-level 0
-                    if (chunk_pos.y+y) >= 0 {
-                        let new_pos = Position::new(
-                            ChunkUnit((chunk_pos.x+x) as f32),
-                            ChunkUnit((chunk_pos.y+y) as f32),
-                            ChunkUnit((chunk_pos.z+z) as f32),
-                        );
-
-                        // checks for duplicated position before submitting an event
-                        if self.chunks.iter().all(|x| x.position != new_pos) {
-                            self.event.clone().emit(EventName("MeshEvent/NewChunk"), event_data![new_pos]);
-                            chunk_loaded += 1;
-                        }
-                    }
-                }
-            }
+        // generates the missing chunks closest to the player (and in its view) first
+        let cam_pos = &cam.position.coords.data;
+        let forward = cam.forward();
+        self.chunk_queue.update(
+            [cam_pos[0] / CHUNK_SIZE as f32, cam_pos[1] / CHUNK_SIZE as f32, cam_pos[2] / CHUNK_SIZE as f32],
+            [forward.x, forward.y, forward.z],
+        );
+
+        let chunks = &self.chunks;
+        while let Some(pos) = self.chunk_queue.next(|pos| chunks.contains(pos)) {
+            let new_pos = Position::new(ChunkUnit(pos[0] as f32), ChunkUnit(pos[1] as f32), ChunkUnit(pos[2] as f32));
+            self.event.emit(mesh::NewChunk(new_pos));
+            chunk_loaded += 1;
         }
 
         // due to high numbers of chunk, there will be brutally optimized code here
+        // the chunks outside of the radius along any of the axes are offloaded
+        let radius = self.settings.chunk_radius as i64;
         // lower bound
-        let lb_x = ChunkUnit((chunk_pos.x-(CHUNK_RADIUS as i64)) as f32);
-        let lb_y = ChunkUnit((chunk_pos.y-(CHUNK_RADIUS as i64)) as f32);
-        let lb_z = ChunkUnit((chunk_pos.z-(CHUNK_RADIUS as i64)) as f32);
+        let lb_x = ChunkUnit((chunk_pos.x-radius) as f32);
+        let lb_y = ChunkUnit((chunk_pos.y-radius) as f32);
+        let lb_z = ChunkUnit((chunk_pos.z-radius) as f32);
         // upper bound
-        let ub_x = ChunkUnit((chunk_pos.x+(CHUNK_RADIUS as i64)) as f32);
-        let ub_y = ChunkUnit((chunk_pos.y+(CHUNK_RADIUS as i64)) as f32);
-        let ub_z = ChunkUnit((chunk_pos.z+(CHUNK_RADIUS as i64)) as f32);
+        let ub_x = ChunkUnit((chunk_pos.x+radius) as f32);
+        let ub_y = ChunkUnit((chunk_pos.y+radius) as f32);
+        let ub_z = ChunkUnit((chunk_pos.z+radius) as f32);
 
-        for chunk in &self.chunks {
-            if  lb_x > chunk.position.x || chunk.position.x > ub_x &&
-                lb_y > chunk.position.y || chunk.position.y > ub_y &&
+        for chunk in self.chunks.iter() {
+            if  lb_x > chunk.position.x || chunk.position.x > ub_x ||
+                lb_y > chunk.position.y || chunk.position.y > ub_y ||
                 lb_z > chunk.position.z || chunk.position.z > ub_z {
-                self.event.clone().emit(EventName("MeshEvent/OffloadChunk"), event_data![chunk.id]);
+                self.event.emit(mesh::OffloadChunk(chunk.id));
                 chunk_offloaded += 1;
             }
         }
 
+        // the emitted events are only handled on the next update
+        self.stale = chunk_loaded > 0 || chunk_offloaded > 0;
         if chunk_loaded > 0 || chunk_offloaded > 0 {
-            println!("L {:?} O {:?}", chunk_loaded, chunk_offloaded);
-            self.event.clone().emit(EventName("MeshEvent/UpdateMesh"), event_data![]);
+            self.event.emit(mesh::UpdateMesh);
         }
 
-        println!("vvvvvvvvvvvvvvvv P");
+        ChunkStatusInfo::from_chunk_handler(self, chunk_loaded, chunk_offloaded, 0)
+    }
 
-        // TODO: Calling this is really slow, once threadpool is completed, use threadpool
-        let mesh_datas = self.meshes.render(self.device.clone(), state.renderpass.clone(), state.rerender, self.reload_chunks);
+    // whether the chunks are still being loaded, offloaded or remeshed; the handler has to keep being updated
+    // until they are done, even if the world state stays the same
+    pub fn busy(&self) -> bool {
+        self.stale || self.reloads_pending > 0 || self.chunk_queue.in_flight() > 0 || self.chunk_threadpool.pending() > 0
+    }
 
-        println!("^^^^^^^^^^^^^^^^ P");
+    pub fn settings(&self) -> &WorldSettings {
+        &self.settings
+    }
+
+    // changes the setting while the game is running
+    // the chunks are loaded and offloaded on the next update after changing the chunk radius
+    pub fn set_setting(&mut self, name: &str, value: &str) -> Result<(), SettingsError> {
+        self.settings.set(name, value)?;
+        if name == "chunk_radius" {
+            self.chunk_queue.set_radius(self.settings.chunk_radius);
+            self.stale = true;
+        }
+        Ok(())
+    }
 
-        (mesh_datas, ChunkStatusInfo::from_chunk_handler(&self, chunk_loaded, chunk_offloaded, 0))
+    // the block at the world block position, if its chunk is loaded
+    pub fn block(&self, pos: [i64; 3]) -> Option<Block> {
+        let (chunk_pos, local_pos) = block_location(pos);
+        self.chunks.get(to_chunk_pos(chunk_pos)).map(|chunk| chunk.block_data[local_pos.into_vec_pos()])
+    }
+
+    // sets the blocks at their world block positions, then marks the edited chunks to be remeshed
+    // none of the blocks are set if any of them are within a chunk that is not loaded
+    pub fn set_blocks(&mut self, blocks: Vec<([i64; 3], Block)>) -> Result<(), ChunkError> {
+        let mut edits = Vec::with_capacity(blocks.len());
+        for (pos, block) in blocks {
+            let (chunk_pos, local_pos) = block_location(pos);
+            let chunk_pos = to_chunk_pos(chunk_pos);
+            if !self.chunks.contains(chunk_pos) {
+                return Err(ChunkError::Unloaded);
+            }
+            edits.push((chunk_pos, local_pos, block));
+        }
+
+        // only the edited chunks are copied if the workers are still reading them
+        let chunks = Arc::make_mut(&mut self.chunks);
+        let mut edited = Vec::new();
+        for (chunk_pos, local_pos, block) in edits {
+            if let Some(chunk) = chunks.get_mut(chunk_pos) {
+                chunk.update(local_pos, block);
+                if !edited.contains(&chunk.id) {
+                    edited.push(chunk.id);
+                }
+            }
+        }
+
+        for id in &edited {
+            self.event.emit(mesh::ReloadChunk(*id));
+        }
+        self.reloads_pending += edited.len();
+        if !edited.is_empty() {
+            self.event.emit(mesh::UpdateMesh);
+        }
+
+        Ok(())
     }
 
     fn chunk_id(&mut self, position: Position<ChunkUnit>) -> Result<ChunkID, ChunkError> {
-        // checking if *all* of the chunks have *different* positions; no duplicate position
-        if self.chunks.iter().all(|x| x.position != position) {
+        // no duplicate position
+        if !self.chunks.contains(to_chunk_pos(position)) {
             self.cid_counter += 1;
             Ok(ChunkID(self.cid_counter))
         } else {
@@ -234,3 +296,219 @@ impl ChunkHandler {
         }
     }
 }
+
+impl ChunkHandler {
+    // updates every game tick, then returns the World Mesh Data
+    pub fn update(&mut self, device: Arc<Device>, state: WorldStateUpd) -> (MeshesDataType, ChunkStatusInfo) {
+        let status = self.update_chunks(&state.cam);
+
+        // TODO: Calling this is really slow, once threadpool is completed, use threadpool
+        let mesh_datas = self.meshes.render(device, state.renderpass.clone(), state.rerender, self.reload_chunks);
+
+        (mesh_datas, status)
+    }
+}
+
+// registered as the world namespace (e.g. `:Matrixagon:world`)
+impl<M: ChunkMeshes> Namespace for ChunkHandler<M> {
+    fn entries(&self, path: &[String]) -> Vec<String> {
+        match path_str(path).as_slice() {
+            [] => names(&["chunks", "settings", "terrain"]),
+            ["chunks"] => names(&["loaded", "radius"]),
+            ["settings"] => names(&WorldSettings::NAMES),
+            ["terrain", ..] => self.terrain.entries(&path[1..]),
+            _ => Vec::new(),
+        }
+    }
+
+    fn get(&self, path: &[String]) -> NmspcRes<StackType> {
+        match path_str(path).as_slice() {
+            ["chunks", "loaded"] => Ok(StackType::Int(self.chunks.len() as i64)),
+            ["chunks", "radius"] => Ok(StackType::Int(self.settings.chunk_radius as i64)),
+            ["settings", name] => {
+                let value = self.settings.get(name).ok_or(NamespaceError::Unknown)?;
+                Ok(value.parse::<i64>().map(StackType::Int).unwrap_or(StackType::Str(value)))
+            },
+            ["terrain", ..] => self.terrain.get(&path[1..]),
+            _ => Err(NamespaceError::Unknown),
+        }
+    }
+
+    // only the settings taking effect right away can be changed, the rest are read when the world is created
+    fn set(&mut self, path: &[String], val: StackType) -> NmspcRes<()> {
+        let name = match path_str(path).as_slice() {
+            ["chunks", "radius"] | ["settings", "chunk_radius"] => "chunk_radius",
+            ["settings", "edit_radius"] => "edit_radius",
+            ["settings", name] if WorldSettings::NAMES.contains(name) => return Err(NamespaceError::ReadOnly),
+            _ => return Err(NamespaceError::Unknown),
+        };
+
+        let value = match val {
+            StackType::Int(i) => i.to_string(),
+            StackType::Float(f) if f.fract() == 0.0 => (f as i64).to_string(),
+            StackType::Float(f) => f.to_string(),
+            _ => return Err(NamespaceError::TypeMismatch),
+        };
+        self.set_setting(name, &value).map_err(|err| NamespaceError::Failed(err.message()))
+    }
+
+    // the chunk editing functions used by the world command library
+    fn call(&mut self, path: &[String], args: Vec<StackType>) -> NmspcRes<Vec<StackType>> {
+        match path_str(path).as_slice() {
+            ["chunks", func] => chunk_edit::call(self, func, &args),
+            _ => Err(NamespaceError::Unknown),
+        }
+    }
+}
+
+impl<M: ChunkMeshes> ChunkEdit for ChunkHandler<M> {
+    fn block(&self, pos: [i64; 3]) -> Option<Block> {
+        ChunkHandler::block(self, pos)
+    }
+
+    fn set_blocks(&mut self, blocks: Vec<([i64; 3], Block)>) -> Result<(), ChunkError> {
+        ChunkHandler::set_blocks(self, blocks)
+    }
+
+    fn registry_block(&self, name: &StackType) -> NmspcRes<Block> {
+        let name = match name {
+            StackType::Str(name) => name,
+            _ => return Err(NamespaceError::TypeMismatch),
+        };
+
+        let registry = self.terrain.registry();
+        match registry.block_id(name.clone()) {
+            Some(_) => Ok(registry.block(name.clone())),
+            None => Err(NamespaceError::Failed(format!("unknown block `{}`", name))),
+        }
+    }
+}
+
+// splits the world block position into the position of its chunk and the local block position within that chunk
+fn block_location(pos: [i64; 3]) -> (Position<ChunkUnit>, Position<LocalBU>) {
+    let size = CHUNK_SIZE as i64;
+    (
+        Position::new(
+            ChunkUnit(pos[0].div_euclid(size) as f32),
+            ChunkUnit(pos[1].div_euclid(size) as f32),
+            ChunkUnit(pos[2].div_euclid(size) as f32),
+        ),
+        Position::new(
+            LocalBU(pos[0].rem_euclid(size) as f32),
+            LocalBU(pos[1].rem_euclid(size) as f32),
+            LocalBU(pos[2].rem_euclid(size) as f32),
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block::registry::BlockRegistry;
+    use crate::datatype::Dimension;
+    use crate::world::chunk_threadpool::ThreadPoolOutput;
+    use crate::world::mesh::MeshKind;
+    use crate::world::texture::TextureID;
+
+    use std::any::Any;
+    use std::thread;
+    use std::time::Duration;
+
+    // records the chunks submitted to be meshed and the data loaded back, instead of meshing them
+    #[derive(Default)]
+    struct Meshes {
+        chunks: Vec<(ChunkID, bool)>,  // the chunk and whether it is dirty
+        submitted: Vec<ChunkID>,
+        loaded: Vec<ChunkID>,
+    }
+
+    impl ChunkMeshes for Meshes {
+        fn add_chunk(&mut self, chunk_id: ChunkID) {
+            self.chunks.push((chunk_id, true));
+        }
+
+        fn load_chunks(&mut self, _chunks: Arc<ChunkMap>, pool: &mut ChunkThreadPool) {
+            for (id, dirty) in self.chunks.iter_mut().filter(|(_, dirty)| *dirty) {
+                pool.add_work((*id, MeshKind::Cube, Box::new(|| {
+                    (Box::new(()) as Box<dyn Any + Send>, Box::new(()) as Box<dyn Any + Send>)
+                })));
+                self.submitted.push(*id);
+                *dirty = false;
+            }
+        }
+
+        fn load_chunk_data(&mut self, _mesh: MeshKind, id: ChunkID, _data: ThreadPoolOutput) {
+            self.loaded.push(id);
+        }
+
+        fn mark_dirty(&mut self, id: ChunkID) {
+            if let Some(chunk) = self.chunks.iter_mut().find(|(other, _)| *other == id) {
+                chunk.1 = true;
+            }
+        }
+
+        fn remv_chunk(&mut self, id: ChunkID) {
+            self.chunks.retain(|(other, _)| *other != id);
+        }
+
+        fn update(&mut self, _dimensions: Option<Dimension<u32>>, _cam: Option<&Camera>) {}
+    }
+
+    // updates the handler, a frame at a time, until it is no longer busy
+    fn settle(handler: &mut ChunkHandler<Meshes>, evd: &EventDispatcher, cam: &Camera) {
+        for _ in 0..1000 {
+            if !handler.busy() {
+                return;
+            }
+            handler.update_chunks(cam);
+            evd.event_swap().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        panic!("the chunks are still being loaded");
+    }
+
+    #[test]
+    fn remeshes_edited_chunks() {
+        let evd = EventDispatcher::new();
+        let registry = Arc::new(BlockRegistry::with_textures(|_| Some(TextureID(0, "test"))));
+        let settings = WorldSettings { chunk_radius: 1, mesh_threads: 2, ..Default::default() };
+        let mut handler = ChunkHandler::new(evd.clone(), Meshes::default(), Terrain::new(0, registry.clone()), settings);
+        let cam = Camera::default();
+
+        settle(&mut handler, &evd, &cam);
+        assert_eq!(handler.chunks.len(), 18);  // no chunks below y-level 0
+        let id = handler.chunks.get(to_chunk_pos(block_location([0, 0, 0]).0)).unwrap().id;
+        assert!(handler.meshes.loaded.contains(&id));
+        handler.meshes.submitted.clear();
+        handler.meshes.loaded.clear();
+
+        // the chunk is remeshed even though the camera stays in place
+        assert!(handler.set_blocks(vec![([0, 0, 0], registry.block("air".into()))]).is_ok());
+        assert!(handler.busy());
+        settle(&mut handler, &evd, &cam);
+
+        assert_eq!(handler.block([0, 0, 0]).map(|block| block.name), Some("air"));
+        assert_eq!(handler.meshes.submitted, vec![id]);
+        assert_eq!(handler.meshes.loaded, vec![id]);
+    }
+
+    #[test]
+    fn counts_each_offloaded_chunk_once() {
+        let evd = EventDispatcher::new();
+        let registry = Arc::new(BlockRegistry::with_textures(|_| Some(TextureID(0, "test"))));
+        let settings = WorldSettings { chunk_radius: 1, mesh_threads: 2, ..Default::default() };
+        let mut handler = ChunkHandler::new(evd.clone(), Meshes::default(), Terrain::new(0, registry), settings);
+        let cam = Camera::default();
+
+        settle(&mut handler, &evd, &cam);
+        let offloaded = handler.chunks_offloaded;
+        let id = handler.chunks.get(to_chunk_pos(block_location([0, 0, 0]).0)).unwrap().id;
+
+        evd.emit(mesh::OffloadChunk(id));
+        evd.emit(mesh::OffloadChunk(id));
+        evd.event_swap().unwrap();
+        handler.update_chunks(&cam);
+        assert!(handler.chunks.get(to_chunk_pos(block_location([0, 0, 0]).0)).is_none());
+        assert_eq!(handler.chunks_offloaded, offloaded + 1);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::world::chunk::{Chunk, ChunkError};
+use crate::world::ChunkID;
+use crate::world::chunk_queue::ChunkPos;
+use crate::datatype::{Position, ChunkUnit};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/*
+Chunk Map
+---------
+The loaded chunks, looked up by their integer chunk coordinates (or their IDs) in constant time. The chunks
+are iterated in the order they were loaded in, no matter which ones were offloaded in between.
+
+The map is shared read-only with the meshing workers (as `Arc<ChunkMap>`), and each chunk is shared on its
+own, so cloning the map while the workers are still reading it only copies the references to the chunks.
+Editing a chunk still shared with a worker copies only that chunk (`get_mut`).
+ */
+
+// the offsets of the neighbours sharing a face with the chunk
+pub const NEIGHBOURS: [[i64; 3]; 6] = [
+    [ 1,  0,  0],  // LEFT
+    [-1,  0,  0],  // RIGHT
+    [ 0,  1,  0],  // UP
+    [ 0, -1,  0],  // DOWN
+    [ 0,  0,  1],  // BACK
+    [ 0,  0, -1],  // FRONT
+];
+
+#[derive(Clone, Default)]
+pub struct ChunkMap {
+    slots: Vec<Option<Arc<Chunk>>>,  // in the order loaded; the offloaded chunks leave holes until compacted
+    positions: HashMap<ChunkPos, usize>,  // the slot of the chunk at the position
+    ids: HashMap<ChunkID, ChunkPos>,
+}
+
+impl ChunkMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    // fails if a chunk with the same position or ID is already loaded
+    pub fn insert(&mut self, chunk: Chunk) -> Result<(), ChunkError> {
+        let pos = to_chunk_pos(chunk.position);
+        if self.positions.contains_key(&pos) {
+            return Err(ChunkError::DuplicateChunkPos);
+        }
+        if self.ids.contains_key(&chunk.id) {
+            return Err(ChunkError::DuplicateID);
+        }
+
+        self.positions.insert(pos, self.slots.len());
+        self.ids.insert(chunk.id, pos);
+        self.slots.push(Some(Arc::new(chunk)));
+        Ok(())
+    }
+
+    // the chunk is returned as is, as the meshing workers might still be reading it
+    pub fn remove(&mut self, id: ChunkID) -> Option<Arc<Chunk>> {
+        let pos = self.ids.remove(&id)?;
+        let slot = self.positions.remove(&pos)?;
+        let chunk = self.slots[slot].take();
+
+        // compacts once at least half of the slots are holes, keeping the removal amortized constant time
+        if self.slots.len() >= 2 * self.positions.len().max(8) {
+            self.compact();
+        }
+        chunk
+    }
+
+    pub fn contains(&self, pos: ChunkPos) -> bool {
+        self.positions.contains_key(&pos)
+    }
+
+    pub fn get(&self, pos: ChunkPos) -> Option<&Chunk> {
+        self.get_arc(pos).map(|chunk| &**chunk)
+    }
+
+    // the shared chunk, to be handed to the meshing workers without copying its blocks
+    pub fn get_arc(&self, pos: ChunkPos) -> Option<&Arc<Chunk>> {
+        let slot = *self.positions.get(&pos)?;
+        self.slots[slot].as_ref()
+    }
+
+    // copies the chunk first if it is still shared (e.g. being meshed)
+    pub fn get_mut(&mut self, pos: ChunkPos) -> Option<&mut Chunk> {
+        let slot = *self.positions.get(&pos)?;
+        self.slots[slot].as_mut().map(Arc::make_mut)
+    }
+
+    pub fn by_id(&self, id: ChunkID) -> Option<&Chunk> {
+        self.ids.get(&id).and_then(|pos| self.get(*pos))
+    }
+
+    pub fn by_id_arc(&self, id: ChunkID) -> Option<&Arc<Chunk>> {
+        self.ids.get(&id).and_then(|pos| self.get_arc(*pos))
+    }
+
+    // the loaded chunk at the offset (in chunks) from the position
+    pub fn neighbour(&self, pos: ChunkPos, offset: [i64; 3]) -> Option<&Chunk> {
+        self.get([pos[0]+offset[0], pos[1]+offset[1], pos[2]+offset[2]])
+    }
+
+    // the loaded chunks sharing a face with the position, in the order of `NEIGHBOURS`
+    pub fn neighbours(&self, pos: ChunkPos) -> [Option<&Chunk>; 6] {
+        let mut neighbours = [None; 6];
+        for (neighbour, offset) in neighbours.iter_mut().zip(NEIGHBOURS.iter()) {
+            *neighbour = self.neighbour(pos, *offset);
+        }
+        neighbours
+    }
+
+    // in the order the chunks were loaded in
+    pub fn iter(&self) -> impl Iterator<Item = &Chunk> {
+        self.slots.iter().filter_map(|slot| slot.as_deref())
+    }
+
+    fn compact(&mut self) {
+        self.slots.retain(Option::is_some);
+        for (slot, chunk) in self.slots.iter().enumerate() {
+            if let Some(chunk) = chunk {
+                self.positions.insert(to_chunk_pos(chunk.position), slot);
+            }
+        }
+    }
+}
+
+// the integer coordinates of the chunk position
+pub fn to_chunk_pos(pos: Position<ChunkUnit>) -> ChunkPos {
+    [pos.x.0 as i64, pos.y.0 as i64, pos.z.0 as i64]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block::Block;
+    use crate::world::chunk::CHUNK_BLOCKS;
+
+    use std::convert::TryInto;
+
+    fn chunk(id: u32, pos: ChunkPos) -> Chunk {
+        Chunk {
+            id: ChunkID(id),
+            visible: true,
+            position: Position::new(ChunkUnit(pos[0] as f32), ChunkUnit(pos[1] as f32), ChunkUnit(pos[2] as f32)),
+            block_data: vec![Block::null(); CHUNK_BLOCKS].into_boxed_slice().try_into().ok().unwrap(),
+            layers: 0,
+        }
+    }
+
+    fn ids(map: &ChunkMap) -> Vec<u32> {
+        map.iter().map(|chunk| chunk.id.0).collect()
+    }
+
+    #[test]
+    fn looks_up_by_position_and_id() {
+        let mut map = ChunkMap::new();
+        map.insert(chunk(1, [0, 2, -1])).ok().unwrap();
+        map.insert(chunk(2, [-3, 0, 5])).ok().unwrap();
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get([0, 2, -1]).map(|chunk| chunk.id), Some(ChunkID(1)));
+        assert_eq!(map.by_id(ChunkID(2)).map(|chunk| to_chunk_pos(chunk.position)), Some([-3, 0, 5]));
+        assert!(map.get([0, 2, 1]).is_none());
+        assert!(map.by_id(ChunkID(3)).is_none());
+
+        assert!(matches!(map.insert(chunk(3, [0, 2, -1])), Err(ChunkError::DuplicateChunkPos)));
+        assert!(matches!(map.insert(chunk(1, [9, 9, 9])), Err(ChunkError::DuplicateID)));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn keeps_the_load_order() {
+        let mut map = ChunkMap::new();
+        for id in 0..20 {
+            map.insert(chunk(id, [id as i64, 0, 0])).ok().unwrap();
+        }
+        for id in (0..20).filter(|id| id % 3 != 0) {
+            assert!(map.remove(ChunkID(id)).is_some());
+        }
+        assert!(map.remove(ChunkID(1)).is_none());
+        map.insert(chunk(1, [1, 0, 0])).ok().unwrap();
+
+        // compacting in between keeps the chunks findable
+        assert_eq!(ids(&map), vec![0, 3, 6, 9, 12, 15, 18, 1]);
+        assert!(map.slots.len() < 20);
+        for id in [0, 3, 6, 9, 12, 15, 18, 1].iter() {
+            assert_eq!(map.get([*id as i64, 0, 0]).map(|chunk| chunk.id), Some(ChunkID(*id)));
+        }
+    }
+
+    #[test]
+    fn finds_neighbours() {
+        let mut map = ChunkMap::new();
+        map.insert(chunk(1, [0, 0, 0])).ok().unwrap();
+        map.insert(chunk(2, [1, 0, 0])).ok().unwrap();
+        map.insert(chunk(3, [0, -1, 0])).ok().unwrap();
+        map.insert(chunk(4, [1, 1, 0])).ok().unwrap();  // only touches by an edge
+
+        let neighbours = map.neighbours([0, 0, 0]).iter()
+            .map(|chunk| chunk.map(|chunk| chunk.id.0))
+            .collect::<Vec<_>>();
+        assert_eq!(neighbours, vec![Some(2), None, None, Some(3), None, None]);
+        assert_eq!(map.neighbour([0, 0, 0], [1, 1, 0]).map(|chunk| chunk.id), Some(ChunkID(4)));
+    }
+
+    #[test]
+    fn copies_shared_chunks_on_edit() {
+        let mut map = ChunkMap::new();
+        map.insert(chunk(1, [0, 0, 0])).ok().unwrap();
+        map.insert(chunk(2, [1, 0, 0])).ok().unwrap();
+        let shared = Arc::new(map.clone());
+
+        map.get_mut([0, 0, 0]).unwrap().layers = 1;
+        assert_eq!(map.get([0, 0, 0]).unwrap().layers, 1);
+        // the workers keep seeing the chunk as it was, and the unedited chunk is not copied
+        assert_eq!(shared.get([0, 0, 0]).unwrap().layers, 0);
+        assert!(Arc::ptr_eq(map.get_arc([1, 0, 0]).unwrap(), shared.get_arc([1, 0, 0]).unwrap()));
+    }
+}
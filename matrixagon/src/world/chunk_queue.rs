@@ -0,0 +1,245 @@
+use std::collections::HashSet;
+use std::cmp::Ordering;
+
+/*
+Chunk Generation Queue
+----------------------
+Orders the missing chunks around the player, so the chunk the player is standing in is generated first,
+then the closest ones, preferring the ones in front of the camera over the ones behind it. Only a limited
+number of chunks are generated at once; the order is recomputed whenever the player moves into another
+chunk or turns around.
+ */
+
+// the integer chunk coordinates of a chunk
+pub type ChunkPos = [i64; 3];
+
+// a chunk right behind the player is treated as if it were this many times farther away
+const BEHIND_WEIGHT: f32 = 2.0;
+// the queue is re-sorted once the view turns by more than ~25 degrees (the cosine of the angle)
+const TURN_THRESHOLD: f32 = 0.9;
+
+pub struct ChunkQueue {
+    radius: i64,
+    max_in_flight: usize,
+    center: Option<ChunkPos>,  // the chunk the player was in when the queue was sorted
+    forward: [f32; 3],  // the normalized view direction the queue was sorted with
+    queue: Vec<ChunkPos>,  // ordered from the last to be generated; the next one is at the end
+    in_flight: HashSet<ChunkPos>,  // handed out, but not yet finished
+}
+
+impl ChunkQueue {
+    pub fn new(radius: u32, max_in_flight: usize) -> Self {
+        Self {
+            radius: radius as i64,
+            max_in_flight: max_in_flight.max(1),
+            center: None,
+            forward: [0.0, 0.0, 1.0],
+            queue: Vec::new(),
+            in_flight: HashSet::new(),
+        }
+    }
+
+    // the position is in chunk units (e.g. the block position divided by the chunk size)
+    // returns true if the queue was re-sorted
+    pub fn update(&mut self, position: [f32; 3], forward: [f32; 3]) -> bool {
+        let center = [position[0].floor() as i64, position[1].floor() as i64, position[2].floor() as i64];
+        let forward = normalize(forward).unwrap_or(self.forward);
+
+        if self.center == Some(center) && dot(forward, self.forward) >= TURN_THRESHOLD {
+            return false;
+        }
+        self.center = Some(center);
+        self.forward = forward;
+
+        let r = self.radius;
+        let mut queue = Vec::with_capacity(((2*r+1) * (2*r+1) * (2*r+1)) as usize);
+        for x in -r..=r {
+            for y in -r..=r {
+                for z in -r..=r {
+                    // prevent chunk generation below y-level 0
+                    if center[1]+y >= 0 {
+                        queue.push([center[0]+x, center[1]+y, center[2]+z]);
+                    }
+                }
+            }
+        }
+
+        let mut scores = queue.into_iter().map(|pos| (pos, score(pos, position, forward))).collect::<Vec<_>>();
+        // the farthest first, so the next chunk can be popped off the end; the ties in the order of the positions
+        scores.sort_by(|(pos_a, a), (pos_b, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal).then(pos_b.cmp(pos_a)));
+        self.queue = scores.into_iter().map(|(pos, _)| pos).collect();
+        true
+    }
+
+    // the next chunk to be generated, unless too many are being generated already
+    // the chunks that are already loaded are skipped
+    pub fn next<F: Fn(ChunkPos) -> bool>(&mut self, loaded: F) -> Option<ChunkPos> {
+        if self.in_flight.len() >= self.max_in_flight {
+            return None;
+        }
+
+        while let Some(pos) = self.queue.pop() {
+            if !loaded(pos) && !self.in_flight.contains(&pos) {
+                self.in_flight.insert(pos);
+                return Some(pos);
+            }
+        }
+        None
+    }
+
+    // the chunks are re-sorted around the player on the next update
+    pub fn set_radius(&mut self, radius: u32) {
+        self.radius = radius as i64;
+        self.center = None;
+    }
+
+    pub fn radius(&self) -> u32 {
+        self.radius as u32
+    }
+
+    // the chunk has been generated (or dropped), making room for the next one
+    // returns false if the chunk was not being generated
+    pub fn finish(&mut self, pos: ChunkPos) -> bool {
+        self.in_flight.remove(&pos)
+    }
+
+    // the number of the chunks being generated
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    // the number of the positions yet to be checked
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+// the lower, the sooner the chunk is generated
+fn score(pos: ChunkPos, position: [f32; 3], forward: [f32; 3]) -> f32 {
+    // from the player to the center of the chunk
+    let offset = [
+        pos[0] as f32 + 0.5 - position[0],
+        pos[1] as f32 + 0.5 - position[1],
+        pos[2] as f32 + 0.5 - position[2],
+    ];
+    let dist = dot(offset, offset).sqrt();
+    let cos = match normalize(offset) {
+        Some(dir) => dot(dir, forward),
+        None => 1.0,
+    };
+
+    // 1 right in front of the player, `BEHIND_WEIGHT` right behind
+    dist * (1.0 + (BEHIND_WEIGHT - 1.0) * (1.0 - cos) / 2.0)
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0]*b[0] + a[1]*b[1] + a[2]*b[2]
+}
+
+fn normalize(v: [f32; 3]) -> Option<[f32; 3]> {
+    let len = dot(v, v).sqrt();
+    if len > f32::EPSILON {
+        Some([v[0]/len, v[1]/len, v[2]/len])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(queue: &mut ChunkQueue, loaded: &HashSet<ChunkPos>) -> Vec<ChunkPos> {
+        let mut order = Vec::new();
+        while let Some(pos) = queue.next(|pos| loaded.contains(&pos)) {
+            order.push(pos);
+            queue.finish(pos);
+        }
+        order
+    }
+
+    #[test]
+    fn orders_by_distance_then_view() {
+        let mut queue = ChunkQueue::new(1, 100);
+        assert!(queue.update([0.5, 1.5, 0.5], [0.0, 0.0, 1.0]));
+        let order = drain(&mut queue, &HashSet::new());
+
+        assert_eq!(order.len(), 27);
+        assert_eq!(order[0], [0, 1, 0]);  // the chunk the player is in
+        assert_eq!(order[1], [0, 1, 1]);  // right in front
+        // then the neighbours on the sides, the diagonals in front, and only then the neighbour behind
+        let at = |pos: ChunkPos| order.iter().position(|other| *other == pos).unwrap();
+        for side in [[1, 1, 0], [-1, 1, 0], [0, 2, 0], [0, 0, 0]].iter() {
+            assert!(at(*side) < 6, "{:?} is not right after the chunk in front: {:?}", side, &order[..6]);
+        }
+        assert!(at([1, 1, 1]) < at([0, 1, -1]));
+        assert!(at([0, 1, -1]) < at([1, 1, -1]));
+    }
+
+    #[test]
+    fn skips_loaded_chunks_and_ground() {
+        let mut queue = ChunkQueue::new(1, 100);
+        queue.update([0.5, 0.5, 0.5], [1.0, 0.0, 0.0]);
+        let loaded = [[0, 0, 0], [1, 0, 0]].iter().cloned().collect::<HashSet<_>>();
+        let order = drain(&mut queue, &loaded);
+
+        assert_eq!(order.len(), 18 - 2);  // no chunks below y-level 0
+        assert!(order.iter().all(|pos| pos[1] >= 0 && !loaded.contains(pos)));
+    }
+
+    #[test]
+    fn caps_chunks_in_flight() {
+        let mut queue = ChunkQueue::new(1, 2);
+        queue.update([0.5, 1.5, 0.5], [0.0, 0.0, 1.0]);
+
+        let first = queue.next(|_| false).unwrap();
+        let second = queue.next(|_| false).unwrap();
+        assert_eq!(queue.next(|_| false), None);
+        assert_eq!(queue.in_flight(), 2);
+
+        assert!(queue.finish(first));
+        assert!(!queue.finish(first));
+        assert!(queue.next(|_| false).is_some());
+        assert_eq!(queue.next(|_| false), None);
+        assert!(queue.finish(second));
+    }
+
+    #[test]
+    fn resorts_when_moving_or_turning() {
+        let mut queue = ChunkQueue::new(1, 100);
+        assert!(queue.update([0.5, 1.5, 0.5], [0.0, 0.0, 1.0]));
+        // moving within the chunk or turning slightly keeps the order
+        assert!(!queue.update([0.9, 1.2, 0.1], [0.1, 0.0, 1.0]));
+
+        assert!(queue.update([0.5, 1.5, 0.5], [0.0, 0.0, -1.0]));
+        assert_eq!(queue.next(|pos| pos == [0, 1, 0]), Some([0, 1, -1]));
+
+        assert!(queue.update([5.5, 1.5, 0.5], [0.0, 0.0, -1.0]));
+        assert_eq!(queue.next(|_| false), Some([5, 1, 0]));
+        // the chunks in flight are not handed out again, even after re-sorting
+        assert!(queue.update([0.5, 1.5, 0.5], [0.0, 0.0, -1.0]));
+        // the one in front is still in flight, leaving the neighbours on the sides (the ties in the order of the positions)
+        assert_eq!(queue.next(|pos| pos == [0, 1, 0]), Some([-1, 1, 0]));
+    }
+
+    #[test]
+    fn changes_radius() {
+        let mut queue = ChunkQueue::new(1, 100);
+        queue.update([0.5, 1.5, 0.5], [0.0, 0.0, 1.0]);
+        let loaded = drain(&mut queue, &HashSet::new()).into_iter().collect::<HashSet<_>>();
+        assert_eq!(loaded.len(), 27);
+
+        // only the chunks of the outer shell are missing after growing
+        queue.set_radius(2);
+        assert!(queue.update([0.5, 1.5, 0.5], [0.0, 0.0, 1.0]));
+        let added = drain(&mut queue, &loaded);
+        assert_eq!(added.len(), 4*5*5 - 3*3*3);
+        assert!(added.iter().all(|pos| (0..3).any(|axis| (pos[axis] - [0, 1, 0][axis]).abs() == 2)));
+
+        // nothing is missing after shrinking
+        queue.set_radius(0);
+        assert!(queue.update([0.5, 1.5, 0.5], [0.0, 0.0, 1.0]));
+        assert_eq!(queue.next(|pos| loaded.contains(&pos)), None);
+        assert_eq!(queue.radius(), 0);
+    }
+}
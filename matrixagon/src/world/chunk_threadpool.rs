@@ -25,85 +25,274 @@ everything here is under one mesh (param: ctp):
  */
 
 /*
-THreadpool for Rayon
+Chunk Threadpool
 
-Using threadpool.install to run new threads to pass in the closure/function with required parameters
-and then return the vertex and index data.
-NOTE: No loops or MPSC or Channels.
+Runs the closures generating the vertex and index data of the chunks on the worker threads of the
+threadpool. Each work signals its number through a single channel once it ends (even by panicking),
+so the finished works are collected as they come in, without polling every task.
 
  */
 
 use crate::world::ChunkID;
+use crate::world::mesh::MeshKind;
+use crate::threadpool::{ThreadPoolHandler, TaskHandle, TaskError};
 
-use rayon::{ThreadPoolBuilder, ThreadPool};
-
-use std::sync::mpsc;
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{self, Sender, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
 use std::any::Any;
 
 
 // (Chunk ID, (Vertex Data, Index Data) )
 // type ThreadPoolOutput = (ChunkID, (Box<dyn Any>, Box<dyn Any>));
 pub type ThreadPoolOutput = (Box<dyn Any + Send>, Box<dyn Any + Send>);
-pub type ThreadPoolInput = (ChunkID, Box<dyn FnOnce() -> ThreadPoolOutput + Send>);
+// the chunk, the mesh the data is generated for, and the closure generating the data
+pub type ThreadPoolInput = (ChunkID, MeshKind, Box<dyn FnOnce() -> ThreadPoolOutput + Send>);
+// the data of a finished work, or why the work failed
+pub type ChunkResult = (ChunkID, MeshKind, Result<ThreadPoolOutput, TaskError>);
+
+// a work not yet collected
+struct Work {
+    id: ChunkID,
+    mesh_kind: MeshKind,
+    num: u64,  // the number the work signals once it ends
+    task: TaskHandle<ThreadPoolOutput>,
+    superseded: bool,  // a newer work of the same chunk and mesh was added; the result is dropped
+}
+
+// signals the number of the work once it is dropped, so a panicking work still signals while unwinding
+struct Ended {
+    num: u64,
+    sender: Sender<u64>,
+}
+
+impl Drop for Ended {
+    fn drop(&mut self) {
+        // only fails once the pool is dropped, when there is no one to collect the result anyway
+        let _ = self.sender.send(self.num);
+    }
+}
 
-// A threadpool using rayon to parallelize the work of chunk mesh generation
+// A threadpool parallelizing the work of chunk mesh generation
+// the works are signalled through a single channel as soon as each of them ends, so their results can be
+// collected (and uploaded) per chunk without waiting on the rest
 pub struct ChunkThreadPool {
-    threadpool: ThreadPool,
-    channels: Vec<(ChunkID, Receiver<ThreadPoolOutput>)>,
+    threadpool: ThreadPoolHandler,
+    works: Vec<Work>,
+    work_counter: u64,
+    sender: Sender<u64>,
+    ended: Receiver<u64>,  // the numbers of the ended works
 }
 
-// TODO: Also handle cancel request for chunkloading
 impl ChunkThreadPool {
     pub fn new(num: usize) -> Self {
-        let threadpool = ThreadPoolBuilder::new()
-            .num_threads(num)
-            // .stack_size(4 * 1024 * 1024)
-            .thread_name(|id| format!("Chunk Threadpool: {}", id.to_string()))
-            .build().unwrap();
+        let (sender, ended) = mpsc::channel();
 
         Self {
-            threadpool: threadpool,
-            channels: Vec::new(),
+            threadpool: ThreadPoolHandler::new(num),
+            works: Vec::new(),
+            work_counter: 0,
+            sender,
+            ended,
         }
     }
 
     // adds a new chunk struct to the thread pool to generate mesh data via closure
-    // spawns new threads to the thread pool to generate mesh data via closure
+    // the older works of the same chunk and mesh are superseded, since they read the chunks before the new work did
     pub fn add_work(&mut self, inp: ThreadPoolInput) {
-        // the retrieving data channel
-        let (tx, rx) = mpsc::channel();
-        self.channels.push((inp.0, rx));
-
-        self.threadpool.spawn(move || {
-            let res = tx.send(inp.1());
-            if let Err(e) = res {
-                println!("A thread in the chunk mesh generation threadpool has failed to send the data: {}", e);
-            }
+        let (id, mesh_kind, work) = inp;
+
+        // the superseded works still waiting are cancelled, the running ones are left to end on their own
+        let threadpool = &mut self.threadpool;
+        for work in self.works.iter_mut().filter(|work| work.id == id && work.mesh_kind == mesh_kind) {
+            work.superseded = true;
+        }
+        self.works.retain(|work| !work.superseded || !threadpool.cancel(work.task.id()));
+
+        let num = self.work_counter;
+        self.work_counter += 1;
+
+        // the panics are caught by the threadpool, and end up as the result of the task
+        let ended = Ended { num, sender: self.sender.clone() };
+        let task = self.threadpool.submit(0, move || {
+            let _ended = ended;
+            work()
         });
+        self.works.push(Work { id, mesh_kind, num, task, superseded: false });
+    }
+
+    // drops the work of the chunk that has not started yet (e.g. the chunk was offloaded in the meantime)
+    // returns the number of the cancelled works
+    pub fn cancel(&mut self, id: ChunkID) -> usize {
+        let threadpool = &mut self.threadpool;
+        let before = self.works.len();
+        self.works.retain(|work| work.id != id || !threadpool.cancel(work.task.id()));
+        before - self.works.len()
     }
 
-    // generates chunk data to all the chunks concurrently
-    pub fn join(&mut self) -> Vec<(ChunkID, ThreadPoolOutput)> {
-        let threads = self.channels.len();
+    // the number of the works not yet collected
+    pub fn pending(&self) -> usize {
+        self.works.len()
+    }
+
+    // whether any work of the chunk is yet to be collected
+    pub fn is_pending(&self, id: ChunkID) -> bool {
+        self.works.iter().any(|work| work.id == id)
+    }
+
+    // the results of the works finished so far, without blocking
+    pub fn collect_finished(&mut self) -> Vec<ChunkResult> {
         let mut output_data = Vec::new();
+        while let Ok(num) = self.ended.try_recv() {
+            self.accept(num, &mut output_data);
+        }
+        output_data
+    }
 
-        while threads > output_data.len() {
-            // grabs the data for each thread
-            for chan in self.channels.iter() {
-                match chan.1.try_recv() {
-                    Ok(data) => {
-                        output_data.push((chan.0, data))
-                    },
-                    Err(_) => {
-                        continue;
-                    }
-                }
+    // blocks until all the works are finished or the time runs out, then returns the results finished by then
+    pub fn collect_timeout(&mut self, timeout: Duration) -> Vec<ChunkResult> {
+        let deadline = Instant::now() + timeout;
+        let mut output_data = self.collect_finished();
+
+        while !self.works.is_empty() {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.ended.recv_timeout(left) {
+                Ok(num) => self.accept(num, &mut output_data),
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => break,
             }
         }
+        output_data
+    }
 
-        self.channels.clear();
+    // blocks until all the works are finished
+    pub fn join(&mut self) -> Vec<ChunkResult> {
+        let mut output_data = self.collect_finished();
 
+        while !self.works.is_empty() {
+            match self.ended.recv() {
+                Ok(num) => self.accept(num, &mut output_data),
+                Err(_) => break,
+            }
+        }
         output_data
     }
+
+    // the work signals before the threadpool stores its result, so joining it only waits for the store
+    fn accept(&mut self, num: u64, output_data: &mut Vec<ChunkResult>) {
+        if let Some(ind) = self.works.iter().position(|work| work.num == num) {
+            let work = self.works.swap_remove(ind);
+            let res = work.task.join();
+            if !work.superseded {
+                output_data.push((work.id, work.mesh_kind, res));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Barrier};
+
+    fn work(id: u32, value: u32) -> ThreadPoolInput {
+        (ChunkID(id), MeshKind::Cube, Box::new(move || (Box::new(value) as Box<dyn Any + Send>, Box::new(()) as Box<dyn Any + Send>)))
+    }
+
+    fn values(results: Vec<ChunkResult>) -> Vec<(u32, u32)> {
+        let mut values = results.into_iter()
+            .map(|(id, _, res)| (id.0, *res.unwrap().0.downcast::<u32>().unwrap()))
+            .collect::<Vec<_>>();
+        values.sort();
+        values
+    }
+
+    #[test]
+    fn reports_failed_works() {
+        let mut pool = ChunkThreadPool::new(2);
+        pool.add_work(work(0, 10));
+        pool.add_work((ChunkID(1), MeshKind::FloraX, Box::new(|| panic!("no blocks"))));
+        pool.add_work(work(2, 12));
+
+        let mut results = pool.join();
+        assert_eq!(pool.pending(), 0);
+        let failed = results.iter().position(|(_, _, res)| res.is_err()).unwrap();
+        let (id, mesh_kind, res) = results.swap_remove(failed);
+        assert_eq!((id, mesh_kind), (ChunkID(1), MeshKind::FloraX));
+        assert_eq!(res.err(), Some(TaskError::Panicked("no blocks".into())));
+        assert_eq!(values(results), vec![(0, 10), (2, 12)]);
+    }
+
+    #[test]
+    fn collects_partial_results() {
+        let mut pool = ChunkThreadPool::new(2);
+        let barrier = Arc::new(Barrier::new(2));
+        {
+            let barrier = barrier.clone();
+            pool.add_work((ChunkID(0), MeshKind::Cube, Box::new(move || {
+                barrier.wait();
+                (Box::new(1u32) as Box<dyn Any + Send>, Box::new(()) as Box<dyn Any + Send>)
+            })));
+        }
+        pool.add_work(work(1, 2));
+
+        // the blocked work is left pending once the time runs out
+        assert_eq!(values(pool.collect_timeout(Duration::from_millis(200))), vec![(1, 2)]);
+        assert_eq!(pool.pending(), 1);
+        assert!(pool.collect_finished().is_empty());
+
+        barrier.wait();
+        assert_eq!(values(pool.collect_timeout(Duration::from_secs(10))), vec![(0, 1)]);
+        assert_eq!(pool.pending(), 0);
+    }
+
+    #[test]
+    fn cancels_waiting_works() {
+        let mut pool = ChunkThreadPool::new(1);
+        let barrier = Arc::new(Barrier::new(2));
+        {
+            let barrier = barrier.clone();
+            pool.add_work((ChunkID(0), MeshKind::Cube, Box::new(move || {
+                barrier.wait();
+                (Box::new(0u32) as Box<dyn Any + Send>, Box::new(()) as Box<dyn Any + Send>)
+            })));
+        }
+        pool.add_work(work(1, 1));
+        pool.add_work((ChunkID(1), MeshKind::FloraX, Box::new(|| (Box::new(2u32) as Box<dyn Any + Send>, Box::new(()) as Box<dyn Any + Send>))));
+        pool.add_work(work(2, 3));
+
+        // once the work of the first chunk has started, only the works waiting behind it can be cancelled
+        while pool.threadpool.waiting() == 4 {
+            std::thread::yield_now();
+        }
+        assert_eq!(pool.cancel(ChunkID(0)), 0);
+        assert_eq!(pool.cancel(ChunkID(1)), 2);
+        assert_eq!(pool.pending(), 2);
+
+        barrier.wait();
+        assert_eq!(values(pool.join()), vec![(0, 0), (2, 3)]);
+    }
+
+    #[test]
+    fn supersedes_older_works() {
+        let mut pool = ChunkThreadPool::new(1);
+        let barrier = Arc::new(Barrier::new(2));
+        {
+            let barrier = barrier.clone();
+            pool.add_work((ChunkID(0), MeshKind::Cube, Box::new(move || {
+                barrier.wait();
+                (Box::new(0u32) as Box<dyn Any + Send>, Box::new(()) as Box<dyn Any + Send>)
+            })));
+        }
+        while pool.threadpool.waiting() == 1 {
+            std::thread::yield_now();
+        }
+        pool.add_work(work(1, 1));
+        // the running work is left to end, while the waiting one is cancelled right away
+        pool.add_work(work(0, 2));
+        pool.add_work(work(1, 3));
+        assert_eq!(pool.pending(), 3);
+
+        barrier.wait();
+        assert_eq!(values(pool.join()), vec![(0, 2), (1, 3)]);
+    }
 }
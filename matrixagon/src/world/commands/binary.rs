@@ -0,0 +1,474 @@
+/*
+The World Command Binary Bytecode
+
+A compact binary encoding of the compiled (and linked) bytecode tokens, so the shipped scripts can be
+loaded without going through the text tokenizer.
+
+Layout (all integers are little endian):
+    header:  magic "WCBB" | version u16 | payload length u32 | FNV-1a 64 checksum of the payload u64
+    payload: string table | marker table | lines
+        string table: count u32 | (length u32 | UTF-8 bytes) per string
+        marker table: count u32 | (scope u32 | name u32 | line index u32) per marker
+        lines:        count u32 | (scope u32 | token count u16 | (token | span) per token) per line
+        token:        tag u8 | opcode u8, or the typed immediate of the argument
+        span:         file u32 | line u32 | first column u32 | last column u32
+ */
+
+use crate::world::commands::bytecode::{Tokens, Commands, Arguments, ValType, CompiledTokens, TokenError, Span};
+
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::collections::HashMap;
+
+
+const MAGIC: &[u8; 4] = b"WCBB";
+const FORMAT_VERSION: u16 = 1;
+const HEADER_SIZE: usize = 18;
+
+// token tags
+const TAG_COMMAND: u8 = 0x01;
+const TAG_STR: u8 = 0x10;
+const TAG_INT: u8 = 0x11;
+const TAG_FLOAT: u8 = 0x12;
+const TAG_STATIC_VAR: u8 = 0x13;
+const TAG_MARKER: u8 = 0x14;
+const TAG_NAMESPACE: u8 = 0x15;
+const TAG_PSEUDO: u8 = 0x16;
+
+// the opcode of each command is its index; new commands must only be appended to keep the opcodes stable
+pub(super) const OPCODES: [Commands; 49] = [
+    Commands::Push, Commands::Static, Commands::Namespace, Commands::Include, Commands::Pop,
+    Commands::COut, Commands::CIn, Commands::CmdCopy, Commands::CmdMove, Commands::Ret, Commands::Event,
+    Commands::Pack, Commands::Unpack, Commands::RotTwo, Commands::RotThree, Commands::RotFour,
+    Commands::JmpIf, Commands::Jmp, Commands::Mrk,
+    Commands::Add, Commands::Sub, Commands::Mul, Commands::Div, Commands::Mod, Commands::ShL, Commands::ShR,
+    Commands::And, Commands::Or, Commands::Xor, Commands::Not, Commands::Neg,
+    Commands::AndL, Commands::OrL, Commands::XorL, Commands::NotL,
+    Commands::AddI, Commands::SubI, Commands::MulI, Commands::DivI, Commands::ModI,
+    Commands::AndI, Commands::OrI, Commands::XorI,
+    Commands::Load, Commands::Store, Commands::Call, Commands::Eq, Commands::Lt, Commands::Gt,
+];
+
+
+#[derive(PartialEq, Debug)]
+pub enum BinaryError {
+    // The file could not be read or written
+    FileIo(String, String),  // the file name and the reason from the file system
+    // The source file to be encoded had errors
+    Token(TokenError),
+    // The file does not start with the binary bytecode magic number
+    InvalidMagic,
+    // The file was encoded by an incompatible version of the format
+    UnsupportedVersion(u16),  // the version of the file
+    // The payload does not match its checksum
+    ChecksumMismatch,
+    // The file ended before the data it declares
+    Truncated,
+    // There are bytes left over after all the declared data
+    TrailingBytes,
+    // Unknown token tag or command opcode
+    InvalidTag(u8),
+    InvalidOpcode(u8),
+    // A reference into the string table is out of its bounds
+    InvalidString(u32),
+    // A string of the string table is not valid UTF-8
+    InvalidUtf8,
+    // An entry of the marker table does not point to its `MRK` line
+    InvalidMarker(String),  // the marker name
+}
+
+impl BinaryError {
+    pub fn message(&self) -> String {
+        match self {
+            BinaryError::FileIo(fname, reason) => format!("cannot access the file `{}`: {}", fname, reason),
+            BinaryError::Token(err) => err.message(),
+            BinaryError::InvalidMagic => "not a binary bytecode file".into(),
+            BinaryError::UnsupportedVersion(ver) => format!("unsupported binary bytecode version {} (expected {})", ver, FORMAT_VERSION),
+            BinaryError::ChecksumMismatch => "checksum mismatch; the file is corrupted".into(),
+            BinaryError::Truncated => "unexpected end of the file".into(),
+            BinaryError::TrailingBytes => "unexpected bytes after the end of the program".into(),
+            BinaryError::InvalidTag(tag) => format!("invalid token tag 0x{:02X}", tag),
+            BinaryError::InvalidOpcode(op) => format!("invalid command opcode 0x{:02X}", op),
+            BinaryError::InvalidString(ind) => format!("string index {} is out of the string table", ind),
+            BinaryError::InvalidUtf8 => "string table contains invalid UTF-8".into(),
+            BinaryError::InvalidMarker(name) => format!("marker table entry `#{}` does not point to its marker", name),
+        }
+    }
+
+    // renders the error with the offending file
+    pub fn render(&self, fname: &str) -> String {
+        match self {
+            BinaryError::Token(err) => err.render(),
+            err => Span::file(fname).render(&err.message()),
+        }
+    }
+}
+
+// the FNV-1a 64 bits hash used as the checksum of the payload
+pub fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325u64, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+
+// compiles the text bytecode file (with its includes) and writes it as a binary bytecode file
+pub fn compile_file(fname: String, dest: String) -> Result<(), BinaryError> {
+    let compiled = super::linker::link_file(fname).map_err(BinaryError::Token)?;
+    fs::write(Path::new(&dest), encode(&compiled)).map_err(|err| BinaryError::FileIo(dest, err.to_string()))
+}
+
+// reads and validates the binary bytecode file
+pub(super) fn load_file(fname: String) -> Result<CompiledTokens, BinaryError> {
+    let bytes = fs::read(Path::new(&fname)).map_err(|err| BinaryError::FileIo(fname, err.to_string()))?;
+    decode(&bytes)
+}
+
+fn opcode(cmd: Commands) -> u8 {
+    OPCODES.iter().position(|op| *op == cmd).expect("Every command has an opcode") as u8
+}
+
+// encodes the compiled tokens into the binary bytecode
+pub(super) fn encode(compiled: &CompiledTokens) -> Vec<u8> {
+    let mut strings = StringTable::default();
+    let mut lines = Vec::new();
+    let mut markers = Vec::new();
+
+    put_u32(&mut lines, compiled.tokens.len() as u32);
+    for (ind, (line, spans)) in compiled.tokens.iter().zip(&compiled.spans).enumerate() {
+        let scope = compiled.scopes[ind] as u32;
+        put_u32(&mut lines, scope);
+        put_u16(&mut lines, line.len() as u16);
+
+        for (tkn, span) in line.iter().zip(spans) {
+            match tkn {
+                Tokens::Command(cmd) => {
+                    lines.push(TAG_COMMAND);
+                    lines.push(opcode(*cmd));
+                },
+                Tokens::Argument(Arguments::Values(ValType::Str(val))) => {
+                    lines.push(TAG_STR);
+                    put_u32(&mut lines, strings.index(val));
+                },
+                Tokens::Argument(Arguments::Values(ValType::Int(val))) => {
+                    lines.push(TAG_INT);
+                    lines.extend_from_slice(&val.to_le_bytes());
+                },
+                Tokens::Argument(Arguments::Values(ValType::Float(val))) => {
+                    lines.push(TAG_FLOAT);
+                    lines.extend_from_slice(&val.to_bits().to_le_bytes());
+                },
+                Tokens::Argument(Arguments::StaticVar(name)) => {
+                    lines.push(TAG_STATIC_VAR);
+                    put_u32(&mut lines, strings.index(name));
+                },
+                Tokens::Argument(Arguments::Marker(name)) => {
+                    lines.push(TAG_MARKER);
+                    put_u32(&mut lines, strings.index(name));
+                    if line.first() == Some(&Tokens::Command(Commands::Mrk)) {
+                        markers.push((scope, strings.index(name), ind as u32));
+                    }
+                },
+                Tokens::Argument(Arguments::Namespace(path)) => {
+                    lines.push(TAG_NAMESPACE);
+                    put_u16(&mut lines, path.len() as u16);
+                    for name in path {
+                        put_u32(&mut lines, strings.index(name));
+                    }
+                },
+                Tokens::PseudoArguments(val) => {
+                    lines.push(TAG_PSEUDO);
+                    put_u32(&mut lines, strings.index(val));
+                },
+            }
+
+            put_u32(&mut lines, strings.index(&span.file));
+            put_u32(&mut lines, span.line);
+            put_u32(&mut lines, span.col_start);
+            put_u32(&mut lines, span.col_end);
+        }
+    }
+
+    let mut payload = Vec::new();
+    put_u32(&mut payload, strings.strings.len() as u32);
+    for string in &strings.strings {
+        put_u32(&mut payload, string.len() as u32);
+        payload.extend_from_slice(string.as_bytes());
+    }
+    put_u32(&mut payload, markers.len() as u32);
+    for (scope, name, line) in markers {
+        put_u32(&mut payload, scope);
+        put_u32(&mut payload, name);
+        put_u32(&mut payload, line);
+    }
+    payload.append(&mut lines);
+
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    put_u16(&mut bytes, FORMAT_VERSION);
+    put_u32(&mut bytes, payload.len() as u32);
+    bytes.extend_from_slice(&checksum(&payload).to_le_bytes());
+    bytes.append(&mut payload);
+    bytes
+}
+
+// validates and decodes the binary bytecode back into the compiled tokens
+pub(super) fn decode(bytes: &[u8]) -> Result<CompiledTokens, BinaryError> {
+    let mut header = Reader::new(bytes);
+    if header.take(MAGIC.len())? != MAGIC {
+        return Err(BinaryError::InvalidMagic);
+    }
+    let version = header.u16()?;
+    if version != FORMAT_VERSION {
+        return Err(BinaryError::UnsupportedVersion(version));
+    }
+    let payload_len = header.u32()? as usize;
+    let sum = header.u64()?;
+
+    let payload = header.take(payload_len)?;
+    if !header.is_empty() {
+        return Err(BinaryError::TrailingBytes);
+    }
+    if checksum(payload) != sum {
+        return Err(BinaryError::ChecksumMismatch);
+    }
+
+    let mut rdr = Reader::new(payload);
+
+    let string_count = rdr.u32()?;
+    let mut strings = Vec::new();
+    for _ in 0..string_count {
+        let len = rdr.u32()? as usize;
+        let string = std::str::from_utf8(rdr.take(len)?).map_err(|_| BinaryError::InvalidUtf8)?;
+        strings.push(String::from(string));
+    }
+    let string = |ind: u32| strings.get(ind as usize).cloned().ok_or(BinaryError::InvalidString(ind));
+
+    let marker_count = rdr.u32()?;
+    let mut markers = Vec::new();
+    for _ in 0..marker_count {
+        markers.push((rdr.u32()? as usize, string(rdr.u32()?)?, rdr.u32()? as usize));
+    }
+
+    // the span files are shared the same way the tokenizer shares them
+    let mut files: HashMap<u32, Rc<str>> = HashMap::new();
+    let no_source: Rc<str> = Rc::from("");
+
+    let line_count = rdr.u32()?;
+    let mut compiled = CompiledTokens {
+        tokens: Vec::new(),
+        spans: Vec::new(),
+        scopes: Vec::new(),
+    };
+    for _ in 0..line_count {
+        let scope = rdr.u32()? as usize;
+        let token_count = rdr.u16()?;
+
+        let mut line = Vec::with_capacity(token_count as usize);
+        let mut spans = Vec::with_capacity(token_count as usize);
+        for _ in 0..token_count {
+            let tkn = match rdr.u8()? {
+                TAG_COMMAND => {
+                    let op = rdr.u8()?;
+                    Tokens::Command(*OPCODES.get(op as usize).ok_or(BinaryError::InvalidOpcode(op))?)
+                },
+                TAG_STR => Tokens::Argument(Arguments::Values(ValType::Str(string(rdr.u32()?)?))),
+                TAG_INT => Tokens::Argument(Arguments::Values(ValType::Int(rdr.u64()? as i64))),
+                TAG_FLOAT => Tokens::Argument(Arguments::Values(ValType::Float(f64::from_bits(rdr.u64()?)))),
+                TAG_STATIC_VAR => Tokens::Argument(Arguments::StaticVar(string(rdr.u32()?)?)),
+                TAG_MARKER => Tokens::Argument(Arguments::Marker(string(rdr.u32()?)?)),
+                TAG_NAMESPACE => {
+                    let len = rdr.u16()?;
+                    let mut path = Vec::with_capacity(len as usize);
+                    for _ in 0..len {
+                        path.push(string(rdr.u32()?)?);
+                    }
+                    Tokens::Argument(Arguments::Namespace(path))
+                },
+                TAG_PSEUDO => Tokens::PseudoArguments(string(rdr.u32()?)?),
+                tag => return Err(BinaryError::InvalidTag(tag)),
+            };
+
+            let file_ind = rdr.u32()?;
+            let file = match files.get(&file_ind) {
+                Some(file) => file.clone(),
+                None => {
+                    let file: Rc<str> = Rc::from(string(file_ind)?);
+                    files.insert(file_ind, file.clone());
+                    file
+                },
+            };
+            let span = Span::new(file, no_source.clone(), rdr.u32()?, rdr.u32()?, rdr.u32()?);
+
+            line.push(tkn);
+            spans.push(span);
+        }
+
+        compiled.tokens.push(line);
+        compiled.spans.push(spans);
+        compiled.scopes.push(scope);
+    }
+    if !rdr.is_empty() {
+        return Err(BinaryError::TrailingBytes);
+    }
+
+    // every marker of the table must point to its own `MRK` line within the same scope
+    for (scope, name, line) in markers {
+        let valid = compiled.scopes.get(line) == Some(&scope) &&
+            compiled.tokens[line].first() == Some(&Tokens::Command(Commands::Mrk)) &&
+            compiled.tokens[line].get(1) == Some(&Tokens::Argument(Arguments::Marker(name.clone())));
+        if !valid {
+            return Err(BinaryError::InvalidMarker(name));
+        }
+    }
+
+    Ok(compiled)
+}
+
+// deduplicated strings referenced by their index
+#[derive(Default)]
+struct StringTable {
+    strings: Vec<String>,
+    indices: HashMap<String, u32>,
+}
+
+impl StringTable {
+    fn index(&mut self, string: &str) -> u32 {
+        if let Some(ind) = self.indices.get(string) {
+            return *ind;
+        }
+        let ind = self.strings.len() as u32;
+        self.strings.push(String::from(string));
+        self.indices.insert(String::from(string), ind);
+        ind
+    }
+}
+
+fn put_u16(bytes: &mut Vec<u8>, val: u16) {
+    bytes.extend_from_slice(&val.to_le_bytes());
+}
+
+fn put_u32(bytes: &mut Vec<u8>, val: u32) {
+    bytes.extend_from_slice(&val.to_le_bytes());
+}
+
+// reads the little endian values while checking for the end of the bytes
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], BinaryError> {
+        if self.bytes.len() < len {
+            return Err(BinaryError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, BinaryError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, BinaryError> {
+        let mut buf = [0u8; 2];
+        buf.copy_from_slice(self.take(2)?);
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    fn u32(&mut self) -> Result<u32, BinaryError> {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn u64(&mut self) -> Result<u64, BinaryError> {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::commands::bytecode;
+
+    const SOURCE: &str = "NAMESPACE :Matrixagon:world
+STATIC $greeting \"héllo ; wörld\"
+PUSH $greeting
+COUT
+PUSH 3
+MRK #loop
+SUBI 1
+JMP_IF #loop
+POP
+PUSH -1.25
+PUSH :Matrixagon:world:player:main:pos:x
+ADD
+PACK 2
+COUT
+RET
+";
+
+    fn compiled() -> CompiledTokens {
+        bytecode::compile_command(SOURCE.chars().collect()).expect("The test source is valid")
+    }
+
+    fn positions(compiled: &CompiledTokens) -> Vec<Vec<(String, u32, u32, u32)>> {
+        compiled.spans.iter()
+            .map(|spans| spans.iter().map(|span| (span.file.to_string(), span.line, span.col_start, span.col_end)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let original = compiled();
+        let decoded = decode(&encode(&original)).unwrap();
+
+        assert_eq!(decoded.tokens, original.tokens);
+        assert_eq!(decoded.scopes, original.scopes);
+        assert_eq!(positions(&decoded), positions(&original));
+    }
+
+    #[test]
+    fn encoding_is_deterministic() {
+        assert_eq!(encode(&compiled()), encode(&compiled()));
+    }
+
+    #[test]
+    fn opcodes_cover_every_command() {
+        for cmd in OPCODES.iter() {
+            assert_eq!(OPCODES[opcode(*cmd) as usize], *cmd);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_header() {
+        let mut bytes = encode(&compiled());
+        bytes[0] = b'X';
+        assert_eq!(decode(&bytes).unwrap_err(), BinaryError::InvalidMagic);
+
+        let mut bytes = encode(&compiled());
+        bytes[4] = FORMAT_VERSION as u8 + 1;
+        assert_eq!(decode(&bytes).unwrap_err(), BinaryError::UnsupportedVersion(FORMAT_VERSION + 1));
+    }
+
+    #[test]
+    fn rejects_corrupted_payload() {
+        let mut bytes = encode(&compiled());
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        assert_eq!(decode(&bytes).unwrap_err(), BinaryError::ChecksumMismatch);
+
+        let bytes = encode(&compiled());
+        assert_eq!(decode(&bytes[..bytes.len() - 1]).unwrap_err(), BinaryError::Truncated);
+    }
+}
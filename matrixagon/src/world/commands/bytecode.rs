@@ -4,9 +4,11 @@ The World Command Bytecode
 This is the internal command language for world commands.
  */
 
+
 use std::fs;
 use std::path::Path;
-use std::char;
+use std::fmt;
+use std::rc::Rc;
 
 type TokenErrorRes = Result<(), TokenError>;
 
@@ -14,34 +16,178 @@ const COMMENT_LITERAL: char = ';';
 const STRING_LITERAL: char = '"';
 const STATIC_VAR: char = '$';
 const MARKER: char = '#';
+const BYTE_ORDER_MARK: char = '\u{FEFF}';
+
+// the file name used for spans of commands not from a file
+const COMMAND_FILE_NAME: &str = "<command>";
+
+
+// The location of a token (or an error) in its original source file
+#[derive(Clone, PartialEq)]
+pub struct Span {
+    pub file: Rc<str>,  // the file name
+    source: Rc<str>,  // the whole source of the file; shared by all the spans of the file
+    pub line: u32,  // line number starting from 1
+    pub col_start: u32,  // column number of the first character starting from 1
+    pub col_end: u32,  // column number of the last character (inclusive)
+}
+
+impl Span {
+    pub fn new(file: Rc<str>, source: Rc<str>, line: u32, col_start: u32, col_end: u32) -> Self {
+        Self {
+            file,
+            source,
+            line,
+            col_start,
+            col_end,
+        }
+    }
+
+    // a span for tokens that do not come from any source
+    pub fn unknown() -> Self {
+        Self::new(Rc::from("<unknown>"), Rc::from(""), 0, 0, 0)
+    }
+
+    // a span of the whole file, for errors that are not on any line of the file
+    pub fn file(file: &str) -> Self {
+        Self::new(Rc::from(file), Rc::from(""), 0, 0, 0)
+    }
 
+    // a span from the start of this span to the end of the other span on the same line
+    pub fn to(&self, end: &Span) -> Self {
+        Self {
+            col_end: end.col_end,
+            ..self.clone()
+        }
+    }
+
+    // the original source line the span is on
+    pub fn source_line(&self) -> &str {
+        if self.line == 0 {
+            return "";
+        }
+        self.source.lines().nth(self.line as usize - 1).unwrap_or("")
+    }
+
+    // renders the message with the source line of the span and a caret underline below the spanned columns
+    //     error: <message>
+    //      --> file.wcb:3:5
+    //       |
+    //     3 | PUSH "never closed
+    //       |      ^^^^^^^^^^^^^
+    pub fn render(&self, msg: &str) -> String {
+        if self.line == 0 {
+            return format!("error: {}\n --> {}", msg, self.file);
+        }
+
+        let src_line = self.source_line();
+        let gutter = self.line.to_string().len();
+
+        // keeps the tabs so the carets line up with the source line
+        let pad = src_line.chars().take(self.col_start.saturating_sub(1) as usize)
+            .map(|c| if c == '\t' {'\t'} else {' '})
+            .collect::<String>();
+        let carets = "^".repeat((self.col_end.saturating_sub(self.col_start) + 1) as usize);
+
+        format!("error: {}\n{:g$}--> {}:{}:{}\n{:g$} |\n{} | {}\n{:g$} | {}{}",
+                msg, "", self.file, self.line, self.col_start,
+                "", self.line, src_line,
+                "", pad, carets,
+                g = gutter,
+        )
+    }
+}
+
+impl fmt::Debug for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}-{}", self.file, self.line, self.col_start, self.col_end)
+    }
+}
 
 #[derive(PartialEq, Debug)]
 pub enum TokenError {
     // Theres part of a string identified as a arguments in a command section
-    ArgumentInCommandSection(u32),  // line number
+    ArgumentInCommandSection(Span),
     // Theres a missing space either before a starting double-quote mark or after a ending double-quote mark
-    MissingSpaceAroundStringQuotes(u32, bool),  // line number, false: before; true: after
+    MissingSpaceAroundStringQuotes(Span, bool),  // false: before; true: after
     // A string must be properly closed before the end of line (EOL)
-    StringsNotEnclosedAfterEOL(u32),  // line number
+    StringsNotEnclosedAfterEOL(Span),
     // There must not be any whitespaces before any command
-    WhitespacesBeforeCommands(u32),  // line number
+    WhitespacesBeforeCommands(Span),
     // The command name must be valid; read the documentation on what commands are available and what are their usage
-    InvalidCommandName(u32, String),  // line number, the invalid command name
+    InvalidCommandName(Span, String),  // the invalid command name
     // The command name can and must only contain A-Z and _ (underscore), anything else is an error
-    CommandNameInvalidCharacters(u32, char),  // line number, the invalid character
+    CommandNameInvalidCharacters(Span, char),  // the invalid character
     // The argument values contains some invalid characters, mainly those of '#', '$', and ':'
-    ArgumentValInvalidCharacters(u32, char),  // line number, the invalid character
+    ArgumentValInvalidCharacters(Span, char),  // the invalid character
     // The namespace argument value contains empty namespaces
-    NamespaceEmpty(u32), // line number
+    NamespaceEmpty(Span),
     // There are only a limited number of argument types; The argument type was invalid or invalid usage of the argument
-    InvalidArgumentTypes(u32),  // line number
+    InvalidArgumentTypes(Span),
     // The number had some problems
-    InvalidNumber(u32),  // line number
+    InvalidNumber(Span),
     // The decimal had some problems
-    InvalidDecimal(u32),  // line number
+    InvalidDecimal(Span),
     // TODO: remove it later? should theoretically not happen.
-    EmptyArguments(u32),  // line number
+    EmptyArguments(Span),
+    // The include must have a single string argument of the file path
+    InvalidInclude(Span),
+    // The file includes itself through its includes
+    IncludeCycle(Span, String),  // the file path that was already being included
+    // The file could not be read (e.g. it does not exist)
+    FileUnreadable(Span, String),  // the reason from the file system
+    // The file is not valid UTF-8 text
+    InvalidEncoding(Span),
+}
+
+impl TokenError {
+    pub fn span(&self) -> &Span {
+        match self {
+            TokenError::ArgumentInCommandSection(span) |
+            TokenError::MissingSpaceAroundStringQuotes(span, _) |
+            TokenError::StringsNotEnclosedAfterEOL(span) |
+            TokenError::WhitespacesBeforeCommands(span) |
+            TokenError::InvalidCommandName(span, _) |
+            TokenError::CommandNameInvalidCharacters(span, _) |
+            TokenError::ArgumentValInvalidCharacters(span, _) |
+            TokenError::NamespaceEmpty(span) |
+            TokenError::InvalidArgumentTypes(span) |
+            TokenError::InvalidNumber(span) |
+            TokenError::InvalidDecimal(span) |
+            TokenError::EmptyArguments(span) |
+            TokenError::InvalidInclude(span) |
+            TokenError::IncludeCycle(span, _) |
+            TokenError::FileUnreadable(span, _) |
+            TokenError::InvalidEncoding(span) => span,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            TokenError::ArgumentInCommandSection(_) => "argument found in the command section".into(),
+            TokenError::MissingSpaceAroundStringQuotes(_, false) => "missing space before the string quotes".into(),
+            TokenError::MissingSpaceAroundStringQuotes(_, true) => "missing space after the string quotes".into(),
+            TokenError::StringsNotEnclosedAfterEOL(_) => "string is not closed before the end of line".into(),
+            TokenError::WhitespacesBeforeCommands(_) => "whitespaces before the command".into(),
+            TokenError::InvalidCommandName(_, name) => format!("invalid command name `{}`", name),
+            TokenError::CommandNameInvalidCharacters(_, c) => format!("invalid character `{}` in the command name", c),
+            TokenError::ArgumentValInvalidCharacters(_, c) => format!("invalid character `{}` in the argument", c),
+            TokenError::NamespaceEmpty(_) => "empty namespace in the namespace path".into(),
+            TokenError::InvalidArgumentTypes(_) => "invalid argument type".into(),
+            TokenError::InvalidNumber(_) => "invalid integer".into(),
+            TokenError::InvalidDecimal(_) => "invalid decimal".into(),
+            TokenError::EmptyArguments(_) => "empty argument".into(),
+            TokenError::InvalidInclude(_) => "include takes a single string of the file path".into(),
+            TokenError::IncludeCycle(_, path) => format!("cyclic include of `{}`", path),
+            TokenError::FileUnreadable(_, reason) => format!("cannot read the file: {}", reason),
+            TokenError::InvalidEncoding(_) => "file is not valid UTF-8 text".into(),
+        }
+    }
+
+    // renders the error with the offending source line
+    pub fn render(&self) -> String {
+        self.span().render(&self.message())
+    }
 }
 
 #[derive(PartialEq)]
@@ -62,58 +208,77 @@ enum ConstValType {
     Float,
 }
 
-// compiles a multiple lines of commands down to computer readable tokens
-pub (super) fn compile_command(char_stream: Vec<char>) -> Result<Vec<Vec<Tokens>>, TokenError> {
-    println!("File character stream: {:?}", char_stream);
-
-    let tokens = bytecode_tokenizer(char_stream);
+// All the tokens of a program line by line; each token's span is at the same index in `spans`
+#[derive(Clone, Debug)]
+pub (super) struct CompiledTokens {
+    pub (super) tokens: Vec<Vec<Tokens>>,
+    pub (super) spans: Vec<Vec<Span>>,
+    // the file scope of each line; markers and static variables are only visible within their own scope
+    pub (super) scopes: Vec<usize>,
+}
 
-    tokens
+// compiles a multiple lines of commands down to computer readable tokens
+pub (super) fn compile_command(char_stream: Vec<char>) -> Result<CompiledTokens, TokenError> {
+    bytecode_tokenizer(COMMAND_FILE_NAME, char_stream)
 }
 
 // compiles the file down to computer readable tokens
-pub (super) fn compile_file(fname: String) -> Result<Vec<Vec<Tokens>>, TokenError> {
+pub (super) fn compile_file(fname: String) -> Result<CompiledTokens, TokenError> {
     // Read File: reads each file into a character stream
     // numeric byte stream
-    let byte_stream = fs::read(Path::new(&fname)).expect(&format!("Command file '{}' not found!", fname)[..]);
-    // TODO: assume its a text file for now
+    let byte_stream = fs::read(Path::new(&fname))
+        .map_err(|err| TokenError::FileUnreadable(Span::file(&fname), err.to_string()))?;
     // convert it too a character stream
-    let char_stream;
-    unsafe {
-        char_stream = byte_stream.iter().map(|c|char::from_u32_unchecked(*c as u32)).collect::<Vec<_>>();
-    };
+    let char_stream = decode_utf8(&fname, byte_stream)?;
 
-    println!("File character stream: {:?}", char_stream);
-
-    let tokens = bytecode_tokenizer(char_stream);
-
-    println!("File compilation successfully converted bytecode file to tokens!");
+    bytecode_tokenizer(&fname, char_stream)
+}
 
-    tokens
+// decodes the UTF-8 text file into a character stream, without the byte order mark
+fn decode_utf8(fname: &str, byte_stream: Vec<u8>) -> Result<Vec<char>, TokenError> {
+    match String::from_utf8(byte_stream) {
+        Ok(text) => Ok(text.strip_prefix(BYTE_ORDER_MARK).unwrap_or(&text).chars().collect()),
+        Err(err) => {
+            // points at the first invalid byte, with the invalid bytes shown as replacement characters
+            let bytes = err.as_bytes();
+            let valid = String::from_utf8_lossy(&bytes[..err.utf8_error().valid_up_to()]);
+            let line = valid.matches('\n').count() as u32 + 1;
+            let col = valid.rsplit('\n').next().unwrap_or("").chars().count() as u32 + 1;
+
+            let source: Rc<str> = Rc::from(String::from_utf8_lossy(bytes).as_ref());
+            Err(TokenError::InvalidEncoding(Span::new(Rc::from(fname), source, line, col, col)))
+        },
+    }
 }
 
-// TODO: when tokenizing, save each line's line number in Vec so we have an accurate repr of the line # as the tokenizer eliminates lines and to improve error correction
 // reads the file and returns a formatted tokens
-fn bytecode_tokenizer(char_stream: Vec<char>) -> Result<Vec<Vec<Tokens>>, TokenError> {
+fn bytecode_tokenizer(fname: &str, char_stream: Vec<char>) -> Result<CompiledTokens, TokenError> {
     let mut tkn_err: TokenErrorRes = Result::Ok(());
 
-    println!("\n ****** Raw file data ****** ");
-    for c in char_stream.clone() { print!("{}", c); }
+    let file: Rc<str> = Rc::from(fname);
+    let source: Rc<str> = Rc::from(char_stream.iter().collect::<String>());
+    let span = |line: u32, col_start: u32, col_end: u32| Span::new(file.clone(), source.clone(), line, col_start, col_end);
 
     // Pre-Processing:
     //      removes comments,
     //      then removes any trailing spaces,
     //      then remove any empty lines,
     //      then compress all whitespaces into a single space,
+    // each character keeps its original line and column number for the spans
 
-    // compressed chr stream
-    let mut compr_char_stream: Vec<char> = Vec::new();
+    // compressed chr stream: (character, line number, column number)
+    let mut compr_char_stream: Vec<(char, u32, u32)> = Vec::new();
 
     let mut first_literals = FirstLiterals::None;
     let mut empty_space = false;  // checks for multiple empty spaces; compresses all the whitespace into a single space
     let mut empty_lines = true;  // checks if the current line contains nothing or just whitespaces and comments
 
+    let mut line = 1u32;  // original line number
+    let mut col = 0u32;  // original column number
+    let mut space_col = 0u32;  // column number of the first whitespace of the compressed space
+
     for c in char_stream {
+        col += 1;
         match c {
             COMMENT_LITERAL => {
                 if first_literals == FirstLiterals::None {
@@ -121,10 +286,10 @@ fn bytecode_tokenizer(char_stream: Vec<char>) -> Result<Vec<Vec<Tokens>>, TokenE
                 }
                 if first_literals != FirstLiterals::Comment {
                     if empty_space {
-                        compr_char_stream.push(' ');
+                        compr_char_stream.push((' ', line, space_col));
                         empty_space = false;
                     }
-                    compr_char_stream.push(c);
+                    compr_char_stream.push((c, line, col));
                     empty_lines = false;
                 }
             },
@@ -136,39 +301,45 @@ fn bytecode_tokenizer(char_stream: Vec<char>) -> Result<Vec<Vec<Tokens>>, TokenE
                 }
                 if first_literals != FirstLiterals::Comment {
                     if empty_space {
-                        compr_char_stream.push(' ');
+                        compr_char_stream.push((' ', line, space_col));
                         empty_space = false;
                     }
-                    compr_char_stream.push(c);
+                    compr_char_stream.push((c, line, col));
                     empty_lines = false;
                 }
             },
             '\n' => {
                 if !empty_lines {
-                    compr_char_stream.push('\n');
+                    compr_char_stream.push(('\n', line, col));
                 }
                 first_literals = FirstLiterals::None;
                 empty_space = false;
                 empty_lines = true;
+                line += 1;
+                col = 0;
             },
             ' ' | '\t' | '\r' => {
+                if !empty_space {
+                    space_col = col;
+                }
                 empty_space = true;
             },
             _ => {
                 if first_literals != FirstLiterals::Comment {
                     if empty_space {
-                        compr_char_stream.push(' ');
+                        compr_char_stream.push((' ', line, space_col));
                         empty_space = false;
                     }
-                    compr_char_stream.push(c);
+                    compr_char_stream.push((c, line, col));
                     empty_lines = false;
                 }
             },
         }
     }
-
-    println!("\n ****** Compressed file data ****** ");
-    for c in compr_char_stream.clone() { print!("{}", c); }
+    // ends the last line even if the file does not end with a line feed
+    if !empty_lines {
+        compr_char_stream.push(('\n', line, col+1));
+    }
 
     // Tokenizer:
     //     tokenizes each parts of a text into valid rust type
@@ -176,25 +347,29 @@ fn bytecode_tokenizer(char_stream: Vec<char>) -> Result<Vec<Vec<Tokens>>, TokenE
 
     // [commands, arguments, arguments, arguments...]
     let mut raw_tokens: Vec<Vec<Tokens>> = Vec::new();
+    let mut raw_spans: Vec<Vec<Span>> = Vec::new();
     let mut tknl_buf: Vec<Tokens> = Vec::new();  // buffer for the current line of tokens
+    let mut spnl_buf: Vec<Span> = Vec::new();  // buffer for the spans of the current line of tokens
     let mut tkni_buf = String::new();  // buffer for the current individual token
+    let mut tkn_start = 0u32;  // column number of where the current individual token starts
+    let mut tkn_end = 0u32;  // column number of where the current individual token ends
 
-    let mut line_no = 1u32;  // line number
     let mut cmd_sect = true;  // true: currently in command section, false: currently in arguments section
     let mut in_string = false;  // currently within a string enclosure
     let mut first_char = true;  // first character of the file or the character after line feed
     let mut space_bef = false;  // is there a space before; checking for spaces around string
     let mut str_quote_bef = false;  // is there a double string quote ending before; checking for spaces around string
 
-    for c in compr_char_stream {
+    for (c, line_no, col_no) in compr_char_stream {
         match c {
             STRING_LITERAL => {
                 if cmd_sect {
-                    tkn_err = Err(TokenError::ArgumentInCommandSection(line_no));
+                    tkn_err = Err(TokenError::ArgumentInCommandSection(span(line_no, col_no, col_no)));
                     break;
                 }
                 if in_string {
                     tknl_buf.push(Tokens::Argument(Arguments::Values(ValType::Str(tkni_buf.clone()))));
+                    spnl_buf.push(span(line_no, tkn_start, col_no));
                     tkni_buf.clear();
                     in_string = false;
                     str_quote_bef = true;
@@ -203,100 +378,130 @@ fn bytecode_tokenizer(char_stream: Vec<char>) -> Result<Vec<Vec<Tokens>>, TokenE
                     // checks if there are spaces before
                     if space_bef {
                         in_string = true;
+                        tkn_start = col_no;
                     } else {
-                        tkn_err = Err(TokenError::MissingSpaceAroundStringQuotes(line_no, false));
+                        tkn_err = Err(TokenError::MissingSpaceAroundStringQuotes(span(line_no, col_no, col_no), false));
                         break;
                     }
                 }
             },
             '\n' => {
                 if in_string {
-                    tkn_err = Err(TokenError::StringsNotEnclosedAfterEOL(line_no));
+                    tkn_err = Err(TokenError::StringsNotEnclosedAfterEOL(span(line_no, tkn_start, tkn_end.max(tkn_start))));
                     break;
                 } else if cmd_sect {
                     // to add commands that are not added through space such as argumentless commands
-                    if let Ok(cmd) = command_name_conv(tkni_buf.as_str(), line_no) {
-                        tknl_buf.push(Tokens::Command(cmd));
-                        tkni_buf.clear();
-                    } else if let Err(err) = command_name_conv(tkni_buf.as_str(), line_no) {
-                        tkn_err = Err(err);
-                        break;
+                    let tkn_span = span(line_no, tkn_start, tkn_end);
+                    match command_name_conv(tkni_buf.as_str(), &tkn_span) {
+                        Ok(cmd) => {
+                            tknl_buf.push(Tokens::Command(cmd));
+                            spnl_buf.push(tkn_span);
+                            tkni_buf.clear();
+                        },
+                        Err(err) => {
+                            tkn_err = Err(err);
+                            break;
+                        },
                     }
-                    cmd_sect = false;
                 } else if !cmd_sect {
                     // also to add arguments at EOL
                     if !tkni_buf.is_empty() {
-                        if let Ok(arg) = arguments_str_eval(tkni_buf.as_str(), line_no) {
-                            tknl_buf.push(Tokens::Argument(arg));
-                            tkni_buf.clear();
-                        } else if let Err(err) = arguments_str_eval(tkni_buf.as_str(), line_no) {
-                            tkn_err = Err(err);
-                            break;
+                        let tkn_span = span(line_no, tkn_start, tkn_end);
+                        match arguments_str_eval(tkni_buf.as_str(), &tkn_span) {
+                            Ok(arg) => {
+                                tknl_buf.push(Tokens::Argument(arg));
+                                spnl_buf.push(tkn_span);
+                                tkni_buf.clear();
+                            },
+                            Err(err) => {
+                                tkn_err = Err(err);
+                                break;
+                            },
                         }
                     }
                 }
                 raw_tokens.push(tknl_buf.clone());
+                raw_spans.push(spnl_buf.clone());
                 tknl_buf.clear();
+                spnl_buf.clear();
                 tkni_buf.clear();
 
                 cmd_sect = true;
                 first_char = true;
                 space_bef = false;
                 str_quote_bef = false;
-                line_no += 1;
                 continue;  // to prevent it reset the first_char
             },
             ' ' => {
                 if first_char {
-                    tkn_err = Err(TokenError::WhitespacesBeforeCommands(line_no));
+                    tkn_err = Err(TokenError::WhitespacesBeforeCommands(span(line_no, col_no, col_no)));
                     break;
                 }
                 if cmd_sect {
-                    if let Ok(cmd) = command_name_conv(tkni_buf.as_str(), line_no) {
-                        tknl_buf.push(Tokens::Command(cmd));
-                        tkni_buf.clear();
-                    } else if let Err(err) = command_name_conv(tkni_buf.as_str(), line_no) {
-                        tkn_err = Err(err);
-                        break;
+                    let tkn_span = span(line_no, tkn_start, tkn_end);
+                    match command_name_conv(tkni_buf.as_str(), &tkn_span) {
+                        Ok(cmd) => {
+                            tknl_buf.push(Tokens::Command(cmd));
+                            spnl_buf.push(tkn_span);
+                            tkni_buf.clear();
+                        },
+                        Err(err) => {
+                            tkn_err = Err(err);
+                            break;
+                        },
                     }
                     cmd_sect = false;
                 } else {  // arguments section
                     if !in_string {
-                        if let Ok(arg) = arguments_str_eval(tkni_buf.as_str(), line_no) {
-                            tknl_buf.push(Tokens::Argument(arg));
-                            tkni_buf.clear();
-                        } else if let Err(err) = arguments_str_eval(tkni_buf.as_str(), line_no) {
-                            tkn_err = Err(err);
-                            break;
+                        // a string argument was already added once its closing quote was found
+                        if !tkni_buf.is_empty() {
+                            let tkn_span = span(line_no, tkn_start, tkn_end);
+                            match arguments_str_eval(tkni_buf.as_str(), &tkn_span) {
+                                Ok(arg) => {
+                                    tknl_buf.push(Tokens::Argument(arg));
+                                    spnl_buf.push(tkn_span);
+                                    tkni_buf.clear();
+                                },
+                                Err(err) => {
+                                    tkn_err = Err(err);
+                                    break;
+                                },
+                            }
                         }
                     } else {
                         tkni_buf.push(' ');
                     }
                 }
                 space_bef = true;
+                // the space after a closing string quote is the one it requires
+                str_quote_bef = false;
                 continue;
             },
             'A'..='Z' | '_' => {  // these are the all the valid characters of a command
                 if str_quote_bef {
-                    tkn_err = Err(TokenError::MissingSpaceAroundStringQuotes(line_no, true));
+                    tkn_err = Err(TokenError::MissingSpaceAroundStringQuotes(span(line_no, col_no, col_no), true));
                     break;
                 }
-                if cmd_sect {
-                    tkni_buf.push(c);
-                } else {
-                    tkni_buf.push(c);
+                if tkni_buf.is_empty() && !in_string {
+                    tkn_start = col_no;
                 }
+                tkni_buf.push(c);
+                tkn_end = col_no;
             },
             _ => {
                 if str_quote_bef {
-                    tkn_err = Err(TokenError::MissingSpaceAroundStringQuotes(line_no, true));
+                    tkn_err = Err(TokenError::MissingSpaceAroundStringQuotes(span(line_no, col_no, col_no), true));
                     break;
                 }
                 if cmd_sect {
-                    tkn_err = Err(TokenError::CommandNameInvalidCharacters(line_no, c));
+                    tkn_err = Err(TokenError::CommandNameInvalidCharacters(span(line_no, col_no, col_no), c));
                     break;
                 }
+                if tkni_buf.is_empty() && !in_string {
+                    tkn_start = col_no;
+                }
                 tkni_buf.push(c);
+                tkn_end = col_no;
             },
         };
         space_bef = false;
@@ -304,16 +509,16 @@ fn bytecode_tokenizer(char_stream: Vec<char>) -> Result<Vec<Vec<Tokens>>, TokenE
         first_char = false;
     }
 
-    println!("\n ****** Computer-readble Tokens ****** ");
-    for tkn in raw_tokens.clone() { println!("{:?}", tkn); }
-
     match tkn_err {
-        Ok(_) => Ok(raw_tokens),
+        Ok(_) => {
+            let scopes = vec![0; raw_tokens.len()];
+            Ok(CompiledTokens { tokens: raw_tokens, spans: raw_spans, scopes })
+        },
         Err(e) => Err(e),
     }
 }
 
-fn arguments_str_eval(arg_name: &str, cur_line_no: u32) -> Result<Arguments, TokenError>{
+fn arguments_str_eval(arg_name: &str, span: &Span) -> Result<Arguments, TokenError>{
     // Individual Arguments Value Type Identifier
 
     let mut interp_type = ConstValType::None;  // the interpreted type for the argument str
@@ -328,14 +533,14 @@ fn arguments_str_eval(arg_name: &str, cur_line_no: u32) -> Result<Arguments, Tok
                 if first_char {
                     interp_type = ConstValType::StaticVar;
                 } else {
-                    return Err(TokenError::ArgumentValInvalidCharacters(cur_line_no, c));
+                    return Err(TokenError::ArgumentValInvalidCharacters(span.clone(), c));
                 }
             },
             MARKER => {  // marker tag; globally defined
                 if first_char {
                     interp_type = ConstValType::Marker;
                 } else {
-                    return Err(TokenError::ArgumentValInvalidCharacters(cur_line_no, c));
+                    return Err(TokenError::ArgumentValInvalidCharacters(span.clone(), c));
                 }
             },
             ':' => {  // external namespace operator/prefixes; prefix in this case
@@ -344,12 +549,12 @@ fn arguments_str_eval(arg_name: &str, cur_line_no: u32) -> Result<Arguments, Tok
                 } else if interp_type == ConstValType::Namespace {
                     // only an external namespace can contain a ':' within
                     if arg_str.last().unwrap().is_empty() {
-                        return Err(TokenError::NamespaceEmpty(cur_line_no));
+                        return Err(TokenError::NamespaceEmpty(span.clone()));
                     }
 
                     arg_str.push(String::new());
                 } else {
-                    return Err(TokenError::ArgumentValInvalidCharacters(cur_line_no, c));
+                    return Err(TokenError::ArgumentValInvalidCharacters(span.clone(), c));
                 }
             },
             '-' | '0'..='9' => {  // 10-based integers with sign
@@ -367,7 +572,7 @@ fn arguments_str_eval(arg_name: &str, cur_line_no: u32) -> Result<Arguments, Tok
             },
             _ => {
                 if first_char {
-                    return Err(TokenError::InvalidArgumentTypes(cur_line_no));
+                    return Err(TokenError::InvalidArgumentTypes(span.clone()));
                 }
                 arg_str.last_mut().unwrap().push(c);
             },
@@ -376,7 +581,7 @@ fn arguments_str_eval(arg_name: &str, cur_line_no: u32) -> Result<Arguments, Tok
     }
 
     if arg_str.is_empty() {
-        return Err(TokenError::EmptyArguments(cur_line_no));
+        return Err(TokenError::EmptyArguments(span.clone()));
     }
 
     // Individual Arguments Value Parser
@@ -395,8 +600,7 @@ fn arguments_str_eval(arg_name: &str, cur_line_no: u32) -> Result<Arguments, Tok
                     Some(Arguments::Values(ValType::Int(val)))
                 },
                 Result::Err(_e) => {
-                    println!("Integer parser error: o-val: {:?}", val);
-                    return Err(TokenError::InvalidNumber(cur_line_no));
+                    return Err(TokenError::InvalidNumber(span.clone()));
                 },
             }
         },
@@ -408,18 +612,78 @@ fn arguments_str_eval(arg_name: &str, cur_line_no: u32) -> Result<Arguments, Tok
                     Some(Arguments::Values(ValType::Float(val)))
                 },
                 Result::Err(_e) => {
-                    println!("Float parser error: o-val: {:?}", val);
-                    return Err(TokenError::InvalidDecimal(cur_line_no));
+                    return Err(TokenError::InvalidDecimal(span.clone()));
                 },
             }
         },
     };
 
-    Ok(arg_val.expect("The argument resulted a `None`"))
+    arg_val.ok_or(TokenError::EmptyArguments(span.clone()))
+}
+
+// converts a command name back into its command text
+pub (super) fn command_name(cmd: Commands) -> &'static str {
+    match cmd {
+        Commands::Push      => "PUSH",
+        Commands::Static    => "STATIC",
+        Commands::Namespace => "NAMESPACE",
+        Commands::Include   => "INCL",
+        Commands::Pop       => "POP",
+
+        Commands::COut      => "COUT",
+        Commands::CIn       => "CIN",
+        Commands::CmdCopy   => "CMD_COPY",
+        Commands::CmdMove   => "CMD_MOVE",
+        Commands::Ret       => "RET",
+        Commands::Event     => "EVNT",
+
+        Commands::Pack      => "PACK",
+        Commands::Unpack    => "UNPK",
+        Commands::RotTwo    => "ROT_TWO",
+        Commands::RotThree  => "ROT_THREE",
+        Commands::RotFour   => "ROT_FOUR",
+        Commands::JmpIf     => "JMP_IF",
+        Commands::Jmp       => "JMP",
+        Commands::Mrk       => "MRK",
+
+        Commands::Add       => "ADD",
+        Commands::Sub       => "SUB",
+        Commands::Mul       => "MUL",
+        Commands::Div       => "DIV",
+        Commands::Mod       => "MOD",
+        Commands::ShL       => "SHL",
+        Commands::ShR       => "SHR",
+        Commands::And       => "AND",
+        Commands::Or        => "OR",
+        Commands::Xor       => "XOR",
+        Commands::Not       => "NOT",
+        Commands::Neg       => "NEG",
+
+        Commands::AndL      => "ANDL",
+        Commands::OrL       => "ORL",
+        Commands::XorL      => "XORL",
+        Commands::NotL      => "NOTL",
+
+        Commands::AddI      => "ADDI",
+        Commands::SubI      => "SUBI",
+        Commands::MulI      => "MULI",
+        Commands::DivI      => "DIVI",
+        Commands::ModI      => "MODI",
+        Commands::AndI      => "ANDI",
+        Commands::OrI       => "ORI",
+        Commands::XorI      => "XORI",
+
+        Commands::Load      => "LOAD",
+        Commands::Store     => "STORE",
+        Commands::Call      => "CALL",
+        Commands::Eq        => "EQ",
+        Commands::Lt        => "LT",
+        Commands::Gt        => "GT",
+    }
 }
 
 // converts a command text into a command name
-fn command_name_conv(cmd_name: &str, cur_line_no: u32) -> Result<Commands, TokenError> {
+fn command_name_conv(cmd_name: &str, span: &Span) -> Result<Commands, TokenError> {
     match cmd_name {
         "PUSH"      => Ok(Commands::Push),
         "STATIC"    => Ok(Commands::Static),
@@ -470,8 +734,15 @@ fn command_name_conv(cmd_name: &str, cur_line_no: u32) -> Result<Commands, Token
         "ORI"       => Ok(Commands::OrI),
         "XORI"      => Ok(Commands::XorI),
 
+        "LOAD"      => Ok(Commands::Load),
+        "STORE"     => Ok(Commands::Store),
+        "CALL"      => Ok(Commands::Call),
+        "EQ"        => Ok(Commands::Eq),
+        "LT"        => Ok(Commands::Lt),
+        "GT"        => Ok(Commands::Gt),
+
         _ => {
-            Err(TokenError::InvalidCommandName(cur_line_no, String::from(cmd_name)))
+            Err(TokenError::InvalidCommandName(span.clone(), String::from(cmd_name)))
         }
     }
 }
@@ -503,9 +774,9 @@ pub (super) enum ValType {
 
 // Types that only exists on stacks
 #[derive(Clone, PartialEq, Debug)]
-pub (super) enum StackType {
+pub enum StackType {
     Str(String), // String
-    Int(u64), // Integer
+    Int(i64), // Integer
     Float(f64), // Float
     List(u16), // Length of the lists
 }
@@ -560,4 +831,102 @@ pub (super) enum Commands {
     AndI,
     OrI,
     XorI,
+
+    Load,
+    Store,
+    Call,
+    Eq,
+    Lt,
+    Gt,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenize_err(src: &str) -> TokenError {
+        bytecode_tokenizer("test.wcb", src.chars().collect()).expect_err("The test source is invalid")
+    }
+
+    #[test]
+    fn renders_spans_with_carets() {
+        let source: Rc<str> = Rc::from("PUSH 1\n\tPUSH \"a\"\nCOUT\n");
+        let span = Span::new(Rc::from("test.wcb"), source.clone(), 2, 7, 9);
+        assert_eq!(span.source_line(), "\tPUSH \"a\"");
+        // the tabs before the span are kept so the carets line up
+        assert_eq!(span.render("oops"), "error: oops\n --> test.wcb:2:7\n  |\n2 | \tPUSH \"a\"\n  | \t     ^^^");
+
+        // the gutter widens with the line number
+        let source: Rc<str> = Rc::from(format!("{}PUSH \"x\"\n", "COUT\n".repeat(11)).as_str());
+        let span = Span::new(Rc::from("test.wcb"), source, 12, 1, 4);
+        assert_eq!(span.render("oops"), "error: oops\n  --> test.wcb:12:1\n   |\n12 | PUSH \"x\"\n   | ^^^^");
+
+        assert_eq!(Span::file("missing.wcb").render("oops"), "error: oops\n --> missing.wcb");
+        assert_eq!(Span::unknown().source_line(), "");
+    }
+
+    #[test]
+    fn points_token_errors_at_the_source() {
+        let err = tokenize_err("PUSH 1\nPUSH 2\nPUSH \"never closed\n");
+        assert_eq!(err, TokenError::StringsNotEnclosedAfterEOL(err.span().clone()));
+        assert_eq!(err.render(), "\
+error: string is not closed before the end of line
+ --> test.wcb:3:6
+  |
+3 | PUSH \"never closed
+  |      ^^^^^^^^^^^^^");
+
+        let err = tokenize_err("PUSH 1\nPUHS 2\n");
+        assert_eq!(err, TokenError::InvalidCommandName(err.span().clone(), "PUHS".into()));
+        assert!(err.render().ends_with("2 | PUHS 2\n  | ^^^^"), "{}", err.render());
+    }
+
+    #[test]
+    fn spans_each_kind_of_token_error() {
+        let cases = [
+            ("PUSH 1\n PUSH 2\n", (2, 1, 1), "whitespaces before the command"),
+            ("PUSH 1\nPU5H 2\n", (2, 3, 3), "invalid character `5` in the command name"),
+            ("PUSH 1\nPUSH\"a\"\n", (2, 5, 5), "argument found in the command section"),
+            ("PUSH 1\nPUSH \"a\"b\n", (2, 9, 9), "missing space after the string quotes"),
+            ("PUSH 1\nPUSH 12a\n", (2, 6, 8), "invalid integer"),
+            ("PUSH 1\nPUSH 1.2.3\n", (2, 6, 10), "invalid decimal"),
+            ("PUSH 1\nPUSH :a::b\n", (2, 6, 10), "empty namespace in the namespace path"),
+            ("PUSH 1\nPUSH a#b\n", (2, 6, 8), "invalid argument type"),
+        ];
+
+        for (src, (line, col_start, col_end), msg) in cases.iter() {
+            let err = tokenize_err(src);
+            let span = err.span();
+            assert_eq!((span.line, span.col_start, span.col_end), (*line, *col_start, *col_end), "{}", src);
+            assert_eq!(err.message(), *msg);
+            assert_eq!(&*span.file, "test.wcb");
+        }
+    }
+
+    #[test]
+    fn strips_the_byte_order_mark() {
+        let bytes = "\u{FEFF}PUSH \"é\"\n".as_bytes().to_vec();
+        assert_eq!(decode_utf8("test.wcb", bytes), Ok("PUSH \"é\"\n".chars().collect()));
+
+        // only a leading mark is stripped
+        let bytes = "PUSH 1\n\u{FEFF}".as_bytes().to_vec();
+        assert_eq!(decode_utf8("test.wcb", bytes).unwrap().last(), Some(&BYTE_ORDER_MARK));
+
+        // and only one of them, as the text itself can start with the same character
+        let bytes = "\u{FEFF}\u{FEFF}PUSH 1\n".as_bytes().to_vec();
+        assert_eq!(decode_utf8("test.wcb", bytes), Ok("\u{FEFF}PUSH 1\n".chars().collect()));
+    }
+
+    #[test]
+    fn points_at_the_first_invalid_byte() {
+        let mut bytes = "PUSH 1\nPUSH \"ü".as_bytes().to_vec();
+        bytes.push(0xFF);
+        bytes.extend_from_slice(b"\"\nCOUT\n");
+
+        let err = decode_utf8("test.wcb", bytes).expect_err("The bytes are invalid");
+        assert_eq!(err, TokenError::InvalidEncoding(err.span().clone()));
+        // the column counts the characters before the invalid byte, not the bytes
+        assert_eq!((err.span().line, err.span().col_start, err.span().col_end), (2, 8, 8));
+        assert!(err.render().ends_with("2 | PUSH \"ü\u{FFFD}\"\n  |        ^"), "{}", err.render());
+    }
 }
@@ -0,0 +1,465 @@
+/*
+The World Command Console
+
+The command mode of the game. Each typed line is a bytecode program of its own, loaded into the executor
+once it is entered, e.g. `CMD_MOVE :MTXG-CMD:Query` after its arguments. A line ending with `\` continues
+on the next line, so a program can span several lines before it runs:
+
+    > PUSH 1 \
+    ... ADDI 2 \
+    ... COUT
+    3
+
+While a program waits on `CIN`, the entered line is sent to it as the input instead. The console only
+keeps the text and the states; drawing it and reading the keys are left to the UI.
+ */
+
+use crate::world::commands::{WorldCommandExecutor, CommandProgRes, ProgramSuccess};
+use crate::world::commands::namespace::{NamespaceRegistry, split_path};
+use crate::world::commands::scheduler::TaskID;
+use crate::world::commands::bytecode;
+use crate::world::commands::binary::OPCODES;
+
+use std::collections::VecDeque;
+
+
+const MAX_SCROLLBACK: usize = 500;  // the most lines kept in the scrollback
+const MAX_HISTORY: usize = 100;  // the most entered lines kept in the history
+
+const PROMPT: &str = "> ";
+const CONTINUE_PROMPT: &str = "... ";  // while a program continues over several lines
+const INPUT_PROMPT: &str = "? ";  // while a program waits for an input
+const CONTINUE_LINE: char = '\\';
+
+// the keys the console reacts to
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ConsoleKey {
+    Char(char),
+    Backspace,
+    Enter,  // runs the line
+    Tab,  // completes the last word of the line
+    Up,  // the previous line of the history
+    Down,  // the next line of the history
+    Interrupt,  // ends the programs started from the console
+}
+
+pub struct Console {
+    line: String,  // the line being typed
+    pending: Vec<String>,  // the lines of the program continued with `\`
+    keys: VecDeque<ConsoleKey>,  // pressed keys to be handled on the next update
+    scrollback: VecDeque<String>,
+    history: Vec<String>,
+    browsing: Option<(usize, String)>,  // the shown history index, with the line typed before browsing
+    tasks: Vec<TaskID>,  // the programs started from the console that have not ended yet
+    output_seen: usize,  // the outputs of the executor already copied into the scrollback
+    awaiting_input: bool,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self {
+            line: String::new(),
+            pending: Vec::new(),
+            keys: VecDeque::new(),
+            scrollback: VecDeque::new(),
+            history: Vec::new(),
+            browsing: None,
+            tasks: Vec::new(),
+            output_seen: 0,
+            awaiting_input: false,
+        }
+    }
+
+    // queues the key to be handled on the next update
+    pub fn press(&mut self, key: ConsoleKey) {
+        self.keys.push_back(key);
+    }
+
+    // handles the pressed keys, then executes the programs in place of `WorldCommandExecutor::update`
+    // the outputs and the errors of all the programs are added to the scrollback
+    pub fn update(&mut self, exec: &mut WorldCommandExecutor, nmspc: &mut NamespaceRegistry) -> Vec<(TaskID, CommandProgRes)> {
+        while let Some(key) = self.keys.pop_front() {
+            self.key(key, exec, nmspc);
+        }
+
+        let results = exec.update(nmspc);
+        self.collect_output(exec);
+        for (id, res) in &results {
+            self.tasks.retain(|task| task != id);
+            match res {
+                Ok(ProgramSuccess::Success) => {},
+                Ok(ProgramSuccess::Interrupt) => self.print("interrupted"),
+                Err(err) => self.print(&err.render()),
+            }
+        }
+
+        self.awaiting_input = exec.awaiting_input();
+        results
+    }
+
+    // handles a single key right away
+    pub fn key(&mut self, key: ConsoleKey, exec: &mut WorldCommandExecutor, nmspc: &NamespaceRegistry) {
+        match key {
+            ConsoleKey::Char(c) => {
+                self.line.push(c);
+                self.browsing = None;
+            },
+            ConsoleKey::Backspace => {
+                self.line.pop();
+                self.browsing = None;
+            },
+            ConsoleKey::Enter => {
+                let line = std::mem::take(&mut self.line);
+                self.submit(&line, exec);
+            },
+            ConsoleKey::Tab => self.complete(nmspc),
+            ConsoleKey::Up => self.browse(true),
+            ConsoleKey::Down => self.browse(false),
+            ConsoleKey::Interrupt => {
+                self.print("^C");
+                for id in self.tasks.drain(..) {
+                    exec.interrupt_task(id);
+                }
+                self.pending.clear();
+                self.line.clear();
+            },
+        }
+    }
+
+    // enters the line; either sends it to the program waiting for an input, or runs it as a program
+    pub fn submit(&mut self, line: &str, exec: &mut WorldCommandExecutor) {
+        self.print(&format!("{}{}", self.prompt(), line));
+        self.browsing = None;
+
+        if exec.awaiting_input() {
+            exec.send_input(line.to_string());
+            self.awaiting_input = exec.awaiting_input();
+            return;
+        }
+
+        if !line.trim().is_empty() && self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_string());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+
+        match line.strip_suffix(CONTINUE_LINE) {
+            Some(part) => self.pending.push(part.to_string()),
+            None => {
+                self.pending.push(line.to_string());
+                let program = self.pending.drain(..).collect::<Vec<_>>().join("\n");
+                if program.trim().is_empty() {
+                    return;
+                }
+
+                match exec.load_commands_bytc(program.chars().collect()) {
+                    Ok(id) => self.tasks.push(id),
+                    Err(err) => self.print(&err.render()),
+                }
+            },
+        }
+    }
+
+    // completes the last word of the line with the command names or the registered namespaces
+    // the candidates are listed in the scrollback when the word cannot be completed any further
+    pub fn complete(&mut self, nmspc: &NamespaceRegistry) {
+        let start = self.line.rfind(' ').map_or(0, |ind| ind + 1);
+        let word = self.line[start..].to_string();
+
+        let (candidates, prefix) = if let Some(path) = word.strip_prefix(':') {
+            let (parent, name) = match path.rfind(':') {
+                Some(ind) => (split_path(&path[..ind]), &path[ind+1..]),
+                None => (Vec::new(), path),
+            };
+            let candidates = nmspc.entries(&parent).into_iter()
+                .filter(|entry| entry.starts_with(name))
+                .map(|entry| {
+                    // the namespaces are followed by their entries, anything else by the next argument
+                    let mut full = parent.clone();
+                    full.push(entry.clone());
+                    let end = if nmspc.is_namespace(&full) { ":" } else { " " };
+                    format!("{}{}{}", &word[..word.len()-name.len()], entry, end)
+                })
+                .collect::<Vec<_>>();
+            (candidates, word.clone())
+        } else if start == 0 {
+            // only the first word of the line is a command
+            let upper = word.to_uppercase();
+            let candidates = OPCODES.iter().map(|cmd| bytecode::command_name(*cmd))
+                .filter(|name| name.starts_with(&upper))
+                .map(|name| format!("{} ", name))
+                .collect::<Vec<_>>();
+            (candidates, upper)
+        } else {
+            return;
+        };
+
+        match candidates.as_slice() {
+            [] => {},
+            [only] => self.replace_word(start, only),
+            _ => {
+                let common = common_prefix(&candidates);
+                if common.len() > prefix.len() {
+                    self.replace_word(start, &common);
+                } else {
+                    let names = candidates.iter().map(|cand| cand.trim_end()).collect::<Vec<_>>();
+                    self.print(&names.join("  "));
+                }
+            },
+        }
+    }
+
+    // the lines of the scrollback from the oldest
+    pub fn scrollback(&self) -> impl Iterator<Item = &String> {
+        self.scrollback.iter()
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    // the line being typed along with its prompt
+    pub fn input_line(&self) -> String {
+        format!("{}{}", self.prompt(), self.line)
+    }
+
+    pub fn clear(&mut self) {
+        self.scrollback.clear();
+    }
+
+    fn prompt(&self) -> &'static str {
+        if self.awaiting_input {
+            INPUT_PROMPT
+        } else if !self.pending.is_empty() {
+            CONTINUE_PROMPT
+        } else {
+            PROMPT
+        }
+    }
+
+    // adds the text to the scrollback; each line of the text takes its own line
+    pub fn print(&mut self, text: &str) {
+        for line in text.lines() {
+            self.scrollback.push_back(line.to_string());
+        }
+        while self.scrollback.len() > MAX_SCROLLBACK {
+            self.scrollback.pop_front();
+        }
+    }
+
+    // copies the new outputs of all the programs into the scrollback
+    fn collect_output(&mut self, exec: &WorldCommandExecutor) {
+        let output = exec.output();
+        let new = output[self.output_seen.min(output.len())..].to_vec();
+        self.output_seen = output.len();
+        for line in new {
+            self.print(&line);
+        }
+    }
+
+    // moves through the history; the line typed before browsing comes back after the newest line
+    fn browse(&mut self, older: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+
+        let next = match (self.browsing.as_ref().map(|(ind, _)| *ind), older) {
+            (None, true) => Some(self.history.len() - 1),
+            (None, false) => return,
+            (Some(ind), true) => Some(ind.saturating_sub(1)),
+            (Some(ind), false) if ind + 1 < self.history.len() => Some(ind + 1),
+            (Some(_), false) => None,
+        };
+
+        match next {
+            Some(ind) => {
+                let typed = match self.browsing.take() {
+                    Some((_, typed)) => typed,
+                    None => self.line.clone(),
+                };
+                self.line = self.history[ind].clone();
+                self.browsing = Some((ind, typed));
+            },
+            None => {
+                if let Some((_, typed)) = self.browsing.take() {
+                    self.line = typed;
+                }
+            },
+        }
+    }
+
+    fn replace_word(&mut self, start: usize, word: &str) {
+        self.line.truncate(start);
+        self.line.push_str(word);
+    }
+}
+
+// the longest beginning shared by all the texts
+fn common_prefix(texts: &[String]) -> String {
+    let mut prefix = texts[0].as_str();
+    for text in &texts[1..] {
+        let len = prefix.chars().zip(text.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum();
+        prefix = &prefix[..len];
+    }
+    prefix.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::commands::library::register_library;
+    use crate::world::commands::ProgramError;
+    use crate::world::commands::bytecode::TokenError;
+
+    fn type_line(console: &mut Console, line: &str) {
+        for c in line.chars() {
+            console.press(ConsoleKey::Char(c));
+        }
+        console.press(ConsoleKey::Enter);
+    }
+
+    fn lines(console: &Console) -> Vec<&str> {
+        console.scrollback().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn runs_typed_lines() {
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = WorldCommandExecutor::new();
+        let mut console = Console::new();
+
+        type_line(&mut console, "PUSH 1 \\");
+        type_line(&mut console, "ADDI 2 \\");
+        assert_eq!(console.input_line(), "> ");
+        console.update(&mut exec, &mut nmspc);
+        assert_eq!(console.input_line(), "... ");
+
+        type_line(&mut console, "COUT");
+        type_line(&mut console, "PUSH \"unclosed");
+        console.update(&mut exec, &mut nmspc);
+
+        let scrollback = lines(&console);
+        assert_eq!(scrollback[..4], ["> PUSH 1 \\", "... ADDI 2 \\", "... COUT", "> PUSH \"unclosed"]);
+        // the line is rejected right away, before the programs run
+        assert!(scrollback[4].starts_with("error: "), "{:?}", scrollback);
+        assert_eq!(scrollback[5], " --> <command>:1:6");
+        assert_eq!(scrollback.last(), Some(&"3"));
+        assert_eq!(console.history(), ["PUSH 1 \\", "ADDI 2 \\", "COUT", "PUSH \"unclosed"]);
+    }
+
+    #[test]
+    fn shows_program_errors_once() {
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = WorldCommandExecutor::new();
+        let mut console = Console::new();
+
+        // the programs loaded outside of the console report their errors in it too
+        exec.load_commands_bytc("POP\n".chars().collect()).expect("The test source is valid");
+        console.update(&mut exec, &mut nmspc);
+        let scrollback = lines(&console);
+        assert_eq!(scrollback.iter().filter(|line| line.starts_with("error: ")).count(), 1, "{:?}", scrollback);
+
+        match exec.load_file_bytc("resource/commands/missing.wcb".into()) {
+            Err(ProgramError::TokenErr(TokenError::FileUnreadable(..))) => {},
+            other => panic!("expected an unreadable file error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn sends_lines_to_cin() {
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = WorldCommandExecutor::new();
+        let mut console = Console::new();
+
+        type_line(&mut console, "CIN \\");
+        type_line(&mut console, "PUSH \"!\" \\");
+        type_line(&mut console, "ADD \\");
+        type_line(&mut console, "COUT");
+        for _ in 0..3 {
+            console.update(&mut exec, &mut nmspc);
+            assert_eq!(console.input_line(), "? ");
+        }
+
+        type_line(&mut console, "hello");
+        console.update(&mut exec, &mut nmspc);
+        assert_eq!(lines(&console)[4..], ["? hello", "hello!"]);
+        assert_eq!(console.input_line(), "> ");
+        assert!(exec.tasks().is_empty());
+        // the inputs are not commands to be recalled
+        assert_eq!(console.history().len(), 4);
+    }
+
+    #[test]
+    fn interrupts_its_programs() {
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = WorldCommandExecutor::new();
+        let mut console = Console::new();
+
+        type_line(&mut console, "CIN");
+        console.update(&mut exec, &mut nmspc);
+        console.press(ConsoleKey::Interrupt);
+        console.update(&mut exec, &mut nmspc);
+
+        assert!(exec.tasks().is_empty());
+        assert_eq!(lines(&console), ["> CIN", "^C"]);
+        assert_eq!(console.input_line(), "> ");
+    }
+
+    #[test]
+    fn browses_history() {
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = WorldCommandExecutor::new();
+        let mut console = Console::new();
+
+        for line in ["PUSH 1", "PUSH 1", "RET"].iter() {
+            console.submit(line, &mut exec);
+        }
+        assert_eq!(console.history(), ["PUSH 1", "RET"]);
+
+        console.key(ConsoleKey::Char('P'), &mut exec, &nmspc);
+        let mut shown = Vec::new();
+        for key in [ConsoleKey::Up, ConsoleKey::Up, ConsoleKey::Up, ConsoleKey::Down, ConsoleKey::Down].iter() {
+            console.key(*key, &mut exec, &nmspc);
+            shown.push(console.input_line());
+        }
+        assert_eq!(shown, ["> RET", "> PUSH 1", "> PUSH 1", "> RET", "> P"]);
+        console.update(&mut exec, &mut nmspc);
+    }
+
+    #[test]
+    fn completes_commands_and_namespaces() {
+        let mut nmspc = NamespaceRegistry::new();
+        register_library(&mut nmspc);
+        let mut exec = WorldCommandExecutor::new();
+        let mut console = Console::new();
+
+        let mut complete = |console: &mut Console, typed: &str| {
+            for c in typed.chars() {
+                console.key(ConsoleKey::Char(c), &mut exec, &nmspc);
+            }
+            console.key(ConsoleKey::Tab, &mut exec, &nmspc);
+            console.input_line()
+        };
+
+        assert_eq!(complete(&mut console, "cmd_m"), "> CMD_MOVE ");
+        assert_eq!(complete(&mut console, ":MT"), "> CMD_MOVE :MTXG-CMD:");
+        assert_eq!(complete(&mut console, "Te"), "> CMD_MOVE :MTXG-CMD:Teleport ");
+        // the arguments are not commands
+        assert_eq!(complete(&mut console, "PU"), "> CMD_MOVE :MTXG-CMD:Teleport PU");
+
+        console.line.clear();
+        assert_eq!(complete(&mut console, "R"), "> R");
+        assert_eq!(lines(&console), ["RET  ROT_TWO  ROT_THREE  ROT_FOUR"]);
+        assert_eq!(complete(&mut console, "OT"), "> ROT_");
+    }
+}
@@ -0,0 +1,221 @@
+/*
+The World Command Debugger
+
+Breakpoints, single stepping, stack inspection and a bounded execution trace for the command programs.
+The debugger is driven through `WorldCommandExecutor` once it is enabled; while a program is paused, the
+executor only advances it through `step` and `resume`.
+ */
+
+use crate::world::commands::bytecode::{Tokens, Commands, Arguments, StackType, Span};
+use crate::world::commands::interpreter::Program;
+use crate::world::commands::disassembler::format_line;
+use crate::world::commands::CommandProgRes;
+
+use std::collections::VecDeque;
+use std::path::Path;
+
+
+const DEFAULT_TRACE_LIMIT: usize = 256;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Breakpoint {
+    Line(String, u32),  // the file (or the ending of its path) and the line number
+    Marker(String),  // the marker name; pauses on the `MRK` line of the marker
+}
+
+// a single executed instruction
+#[derive(Clone, PartialEq, Debug)]
+pub struct TraceEntry {
+    pub index: usize,  // the instruction index of the program
+    pub instruction: String,  // the instruction in the canonical text
+    pub span: Span,
+    pub stack_before: Vec<StackType>,
+    pub stack_after: Vec<StackType>,
+}
+
+#[derive(PartialEq, Debug)]
+pub enum DebugStatus {
+    Paused(usize, Span),  // paused before executing the instruction at the index
+    Finished(CommandProgRes),  // the current program has ended
+    Yielded,  // the budget of a single update ran out; the program continues on the next update
+    Idle,  // there are no programs to debug
+}
+
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    trace: VecDeque<TraceEntry>,
+    trace_limit: usize,  // the most trace entries kept; the oldest ones are discarded first
+    pub(super) paused: bool,  // the current program is only advanced through the debugger
+}
+
+impl Debugger {
+    pub(super) fn new() -> Self {
+        Self {
+            breakpoints: Vec::new(),
+            trace: VecDeque::new(),
+            trace_limit: DEFAULT_TRACE_LIMIT,
+            paused: false,
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, brk: Breakpoint) {
+        if !self.breakpoints.contains(&brk) {
+            self.breakpoints.push(brk);
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, brk: &Breakpoint) {
+        self.breakpoints.retain(|other| other != brk);
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    // the executed instructions from the oldest to the newest
+    pub fn trace(&self) -> &VecDeque<TraceEntry> {
+        &self.trace
+    }
+
+    pub fn clear_trace(&mut self) {
+        self.trace.clear();
+    }
+
+    pub fn set_trace_limit(&mut self, limit: usize) {
+        self.trace_limit = limit;
+        while self.trace.len() > limit {
+            self.trace.pop_front();
+        }
+    }
+
+    // checks if any of the breakpoints are on the next instruction of the program
+    pub(super) fn hits(&self, prog: &Program) -> bool {
+        let (line, span) = match prog.line(prog.pc()) {
+            Some(line) => line,
+            None => return false,
+        };
+
+        self.breakpoints.iter().any(|brk| match brk {
+            Breakpoint::Line(file, line_no) => {
+                span.line == *line_no && (&*span.file == file || Path::new(&*span.file).ends_with(file))
+            },
+            Breakpoint::Marker(name) => {
+                matches!(line, [Tokens::Command(Commands::Mrk), Tokens::Argument(Arguments::Marker(mrk))] if mrk == name)
+            },
+        })
+    }
+
+    // records the instruction executed by the program
+    pub(super) fn record(&mut self, index: usize, prog: &Program, stack_before: Vec<StackType>) {
+        if self.trace_limit == 0 {
+            return;
+        }
+
+        let (line, span) = prog.line(index).expect("The executed instruction is within the program");
+        if self.trace.len() >= self.trace_limit {
+            self.trace.pop_front();
+        }
+        self.trace.push_back(TraceEntry {
+            index,
+            instruction: format_line(line),
+            span,
+            stack_before,
+            stack_after: prog.stack().to_vec(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::commands::{WorldCommandExecutor, ProgramSuccess};
+    use crate::world::commands::namespace::NamespaceRegistry;
+
+    const COUNTDOWN: &str = "PUSH 3\nMRK #loop\nSUBI 1\nJMP_IF #loop\nPOP\n";
+
+    fn executor() -> WorldCommandExecutor {
+        let mut exec = WorldCommandExecutor::new();
+        exec.load_commands_bytc(COUNTDOWN.chars().collect()).expect("The test source is valid");
+        exec.enable_debugger();
+        exec
+    }
+
+    #[test]
+    fn pauses_on_marker() {
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = executor();
+        exec.enable_debugger().add_breakpoint(Breakpoint::Marker("loop".into()));
+
+        assert!(exec.update(&mut nmspc).is_empty());
+        assert_eq!(exec.location().map(|(ind, _)| ind), Some(1));
+        assert_eq!(exec.stack(), Some(&[StackType::Int(3)][..]));
+
+        // each round of the loop comes back to the marker
+        match exec.resume(&mut nmspc) {
+            DebugStatus::Paused(1, span) => assert_eq!(span.line, 2),
+            status => panic!("unexpected status {:?}", status),
+        }
+        assert_eq!(exec.stack(), Some(&[StackType::Int(2)][..]));
+        // the paused program is not advanced by the updates
+        assert!(exec.update(&mut nmspc).is_empty());
+
+        exec.debugger().unwrap().remove_breakpoint(&Breakpoint::Marker("loop".into()));
+        assert_eq!(exec.resume(&mut nmspc), DebugStatus::Finished(Ok(ProgramSuccess::Success)));
+        assert_eq!(exec.resume(&mut nmspc), DebugStatus::Idle);
+    }
+
+    #[test]
+    fn pauses_on_line() {
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = executor();
+        exec.enable_debugger().add_breakpoint(Breakpoint::Line("<command>".into(), 5));
+
+        assert!(exec.update(&mut nmspc).is_empty());
+        assert_eq!(exec.location().map(|(ind, _)| ind), Some(4));
+        assert_eq!(exec.stack(), Some(&[StackType::Int(0)][..]));
+    }
+
+    #[test]
+    fn steps_with_bounded_trace() {
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = executor();
+        exec.enable_debugger().set_trace_limit(2);
+
+        for ind in 1..=3 {
+            match exec.step(&mut nmspc) {
+                DebugStatus::Paused(pc, _) => assert_eq!(pc, ind),
+                status => panic!("unexpected status {:?}", status),
+            }
+        }
+
+        let trace = exec.debugger().unwrap().trace().iter().cloned().collect::<Vec<_>>();
+        assert_eq!(trace.len(), 2);
+        assert_eq!(trace[0].index, 1);
+        assert_eq!(trace[1].index, 2);
+        assert_eq!(trace[1].instruction, "SUBI        1");
+        assert_eq!(trace[1].stack_before, vec![StackType::Int(3)]);
+        assert_eq!(trace[1].stack_after, vec![StackType::Int(2)]);
+    }
+
+    #[test]
+    fn skips_blocked_programs() {
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = WorldCommandExecutor::new();
+        let waiting = exec.load_commands_bytc("CIN\nCOUT\n".chars().collect()).expect("The test source is valid");
+        let counting = exec.load_commands_bytc(COUNTDOWN.chars().collect()).expect("The test source is valid");
+        exec.enable_debugger();
+
+        // the program waiting for an input does not hold back the others
+        assert_eq!(exec.update(&mut nmspc), vec![(counting, Ok(ProgramSuccess::Success))]);
+        assert_eq!(exec.step(&mut nmspc), DebugStatus::Yielded);
+        assert_eq!(exec.tasks(), vec![waiting]);
+
+        exec.send_input("typed".into());
+        match exec.step(&mut nmspc) {
+            DebugStatus::Paused(1, span) => assert_eq!(span.line, 2),
+            status => panic!("unexpected status {:?}", status),
+        }
+        assert_eq!(exec.resume(&mut nmspc), DebugStatus::Finished(Ok(ProgramSuccess::Success)));
+        assert_eq!(exec.output(), &vec!["typed".to_string()]);
+    }
+}
@@ -0,0 +1,183 @@
+/*
+The World Command Disassembler
+
+Turns the bytecode tokens back into readable text; either as the canonical `.wcb` source (aligned
+columns, normalized numbers) or as a listing with the instruction indices and the resolved jump targets.
+ */
+
+use crate::world::commands::bytecode::{self, Tokens, Commands, Arguments, ValType, CompiledTokens, TokenError};
+use crate::world::commands::linker;
+
+use std::collections::HashMap;
+
+
+const COMMAND_COLUMN: usize = 12;  // the column the arguments start at; fits the longest command name
+const ARGUMENT_COLUMN: usize = 40;  // the column the listing comments start at
+
+// reads the bytecode file and formats it into the canonical text
+pub fn format_file(fname: String) -> Result<String, TokenError> {
+    Ok(format_lines(&bytecode::compile_file(fname)?.tokens))
+}
+
+// reads the bytecode file along with its includes and lists the linked program
+pub fn disassemble_file(fname: String) -> Result<String, TokenError> {
+    Ok(disassemble(&linker::link_file(fname)?))
+}
+
+// formats the lines of tokens into the canonical bytecode text; one command per line
+pub(super) fn format_lines(lines: &[Vec<Tokens>]) -> String {
+    lines.iter().map(|line| format!("{}\n", format_line(line))).collect()
+}
+
+// lists each instruction with its index, the jump target of the jumps and calls, and where it came from
+//     0005  JMP_IF      #for_loop                   ; -> 0003  test00.wcb:27
+pub(super) fn disassemble(compiled: &CompiledTokens) -> String {
+    // the markers are only visible within their own scope, same as the interpreter
+    let mut markers = HashMap::new();
+    for (ind, line) in compiled.tokens.iter().enumerate() {
+        if let [Tokens::Command(Commands::Mrk), Tokens::Argument(Arguments::Marker(name))] = line.as_slice() {
+            markers.entry((compiled.scopes[ind], name.as_str())).or_insert(ind);
+        }
+    }
+
+    let index_width = compiled.tokens.len().saturating_sub(1).to_string().len().max(4);
+    let mut listing = String::new();
+
+    for (ind, line) in compiled.tokens.iter().enumerate() {
+        let mut comment = Vec::new();
+        if let [Tokens::Command(Commands::Jmp), Tokens::Argument(Arguments::Marker(name))] |
+               [Tokens::Command(Commands::JmpIf), Tokens::Argument(Arguments::Marker(name))] |
+               [Tokens::Command(Commands::Call), Tokens::Argument(Arguments::Marker(name))] = line.as_slice() {
+            comment.push(match markers.get(&(compiled.scopes[ind], name.as_str())) {
+                Some(target) => format!("-> {:0w$}", target, w = index_width),
+                None => String::from("-> ?"),
+            });
+        }
+        if let Some(span) = compiled.spans[ind].first() {
+            comment.push(format!("{}:{}", span.file, span.line));
+        }
+
+        listing.push_str(&format!("{:0w$}  {:<a$} ; {}\n",
+                                  ind, format_line(line), comment.join("  "),
+                                  w = index_width, a = ARGUMENT_COLUMN));
+    }
+
+    listing
+}
+
+// a single line of tokens in the canonical text
+pub(super) fn format_line(line: &[Tokens]) -> String {
+    let mut text = String::new();
+
+    for (ind, tkn) in line.iter().enumerate() {
+        let tkn_text = match tkn {
+            Tokens::Command(cmd) => String::from(bytecode::command_name(*cmd)),
+            Tokens::Argument(arg) => format_argument(arg),
+            Tokens::PseudoArguments(val) => val.clone(),
+        };
+
+        match ind {
+            0 => text.push_str(&tkn_text),
+            1 => text.push_str(&format!("{:<w$}{}", "", tkn_text, w = COMMAND_COLUMN.saturating_sub(text.chars().count()).max(1))),
+            _ => text.push_str(&format!(" {}", tkn_text)),
+        }
+    }
+
+    text
+}
+
+fn format_argument(arg: &Arguments) -> String {
+    match arg {
+        Arguments::Values(ValType::Str(val)) => format!("\"{}\"", val),
+        Arguments::Values(ValType::Int(val)) => val.to_string(),
+        Arguments::Values(ValType::Float(val)) => format_float(*val),
+        Arguments::StaticVar(name) => format!("${}", name),
+        Arguments::Marker(name) => format!("#{}", name),
+        Arguments::Namespace(path) => format!(":{}", path.join(":")),
+    }
+}
+
+// floats always keep their decimal point, so they are read back as floats instead of integers
+fn format_float(val: f64) -> String {
+    let text = val.to_string();
+    if text.contains('.') {
+        text
+    } else {
+        format!("{}.0", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(src: &str) -> Vec<Vec<Tokens>> {
+        bytecode::compile_command(src.chars().collect()).expect("The test source is valid").tokens
+    }
+
+    #[test]
+    fn formats_canonical_text() {
+        let src = "NAMESPACE :MTXG-CMD\nPUSH    \"a ; b\"\nPUSH 10\nPUSH -1.0\nPUSH 2.50\nMRK #loop\nSUBI 1\nJMP_IF #loop\nCOUT\n";
+        assert_eq!(format_lines(&tokens(src)),
+                   "NAMESPACE   :MTXG-CMD\nPUSH        \"a ; b\"\nPUSH        10\nPUSH        -1.0\nPUSH        2.5\n\
+                    MRK         #loop\nSUBI        1\nJMP_IF      #loop\nCOUT\n");
+    }
+
+    #[test]
+    fn lists_jump_targets() {
+        let listing = disassemble(&bytecode::compile_command("PUSH 3\nMRK #loop\nSUBI 1\nJMP_IF #loop\nJMP #nowhere\n".chars().collect()).unwrap());
+        let lines = listing.lines().collect::<Vec<_>>();
+
+        assert!(lines[3].starts_with("0003  JMP_IF      #loop"));
+        assert!(lines[3].ends_with("; -> 0001  <command>:4"));
+        assert!(lines[4].ends_with("; -> ?  <command>:5"));
+    }
+
+    // a tiny deterministic random generator, so the generated programs are the same on every run
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((self.0 >> 33) as usize) % bound
+        }
+
+        fn name(&mut self) -> String {
+            let chars = ['a', 'b', 'z', 'A', 'Z', '_', '-', 'é'];
+            (0..1+self.next(6)).map(|_| chars[self.next(chars.len())]).collect()
+        }
+    }
+
+    fn random_argument(rng: &mut Lcg) -> Arguments {
+        match rng.next(6) {
+            0 => Arguments::Values(ValType::Str(format!("{} ; {}", rng.name(), rng.name()))),
+            1 => Arguments::Values(ValType::Int(rng.next(2_000_000) as i64 - 1_000_000)),
+            2 => Arguments::Values(ValType::Float((rng.next(2_000_000) as f64 - 1_000_000.0) / 64.0)),
+            3 => Arguments::StaticVar(rng.name()),
+            4 => Arguments::Marker(rng.name()),
+            _ => Arguments::Namespace((0..1+rng.next(4)).map(|_| rng.name()).collect()),
+        }
+    }
+
+    #[test]
+    fn round_trips_random_programs() {
+        let cmds = [Commands::Push, Commands::Pop, Commands::COut, Commands::CmdCopy, Commands::RotThree,
+                    Commands::JmpIf, Commands::Mrk, Commands::AddI, Commands::Namespace, Commands::Static];
+        let mut rng = Lcg(0x5eed);
+
+        for _ in 0..200 {
+            let program = (0..1+rng.next(12)).map(|_| {
+                let mut line = vec![Tokens::Command(cmds[rng.next(cmds.len())])];
+                for _ in 0..rng.next(3) {
+                    line.push(Tokens::Argument(random_argument(&mut rng)));
+                }
+                line
+            }).collect::<Vec<_>>();
+
+            let text = format_lines(&program);
+            assert_eq!(tokens(&text), program, "formatted program:\n{}", text);
+            // formatting the canonical text again changes nothing
+            assert_eq!(format_lines(&tokens(&text)), text);
+        }
+    }
+}
@@ -0,0 +1,879 @@
+/*
+The World Command Interpreter
+
+A stack machine that walks through each line of the bytecode tokens and executes it.
+ */
+
+use crate::world::commands::bytecode::{Tokens, Commands, Arguments, ValType, StackType, CompiledTokens, Span};
+use crate::world::commands::{ProgramSuccess, ProgExecutionError, ProgramIO};
+use crate::world::commands::namespace::{NamespaceRegistry, NamespaceError, join_path};
+use crate::world::commands::scheduler::ExecutionLimits;
+
+use std::collections::HashMap;
+use std::fmt;
+
+
+type ExecRes<T> = Result<T, ProgExecutionError>;
+
+// what the interpreter should do after executing a single line
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Flow {
+    Next,  // continue to the next line
+    Jump(usize),  // jump to the line index
+    Return,  // stops the program
+    Wait,  // stays on the line until it can be executed; for `CIN` without any input
+}
+
+// a function call made with `CALL`; the program itself runs in the outermost frame
+#[derive(Clone, PartialEq, Debug, Default)]
+struct Frame {
+    ret: Option<usize>,  // the line index `RET` returns to; None for the outermost frame
+    locals: HashMap<String, StackType>,  // local variables stored with `STORE`
+}
+
+// A single loaded program with its own stack, markers and static variables
+pub(super) struct Program {
+    lines: Vec<Vec<Tokens>>,
+    spans: Vec<Vec<Span>>,  // spans of each token in `lines`
+    scopes: Vec<usize>,  // file scope of each line in `lines`
+    markers: HashMap<(usize, String), usize>,  // scoped marker name to its line index
+    statics: HashMap<(usize, String), StackType>,  // scoped static variable name to its value
+    namespaces: Vec<Vec<String>>,  // namespaces declared to be used by the program
+    stack: Vec<StackType>,
+    frames: Vec<Frame>,  // the innermost call is the last
+    max_stack: usize,  // the most values the stack can hold
+    max_call_depth: usize,  // the most calls the program can be in at once
+    pc: usize,  // program counter; index of the next line to be executed
+}
+
+impl Program {
+    // pre-processes the tokens by collecting all the markers and static variables of each file scope
+    pub(super) fn new(compiled: CompiledTokens) -> ExecRes<Self> {
+        let CompiledTokens { tokens: lines, spans, scopes } = compiled;
+        let mut markers = HashMap::new();
+        let mut statics = HashMap::new();
+        let mut namespaces = Vec::new();
+
+        for (ind, line) in lines.iter().enumerate() {
+            let line_span = line_span(&spans[ind]);
+
+            match line.first() {
+                Some(Tokens::Command(Commands::Mrk)) => {
+                    if let Some(Tokens::Argument(Arguments::Marker(name))) = line.get(1) {
+                        if markers.insert((scopes[ind], name.clone()), ind).is_some() {
+                            return Err(ProgExecutionError::DuplicateMarker(spans[ind][1].clone(), name.clone()));
+                        }
+                    } else {
+                        return Err(ProgExecutionError::InvalidArguments(line_span.clone()));
+                    }
+                },
+                Some(Tokens::Command(Commands::Static)) => {
+                    if let (Some(Tokens::Argument(Arguments::StaticVar(name))),
+                            Some(Tokens::Argument(Arguments::Values(val)))) = (line.get(1), line.get(2)) {
+                        statics.insert((scopes[ind], name.clone()), StackType::from(val.clone()));
+                    } else {
+                        return Err(ProgExecutionError::InvalidArguments(line_span.clone()));
+                    }
+                },
+                Some(Tokens::Command(Commands::Namespace)) => {
+                    if let Some(Tokens::Argument(Arguments::Namespace(path))) = line.get(1) {
+                        namespaces.push(path.clone());
+                    } else {
+                        return Err(ProgExecutionError::InvalidArguments(line_span.clone()));
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        Ok(Self {
+            lines,
+            spans,
+            scopes,
+            markers,
+            statics,
+            namespaces,
+            stack: Vec::new(),
+            frames: vec![Frame::default()],
+            max_stack: usize::MAX,
+            max_call_depth: usize::MAX,
+            pc: 0,
+        })
+    }
+
+    // executes the next line of the program; returns the result once the program has ended (the end of the program or a `RET`)
+    // any outputs from `COUT` are appended to the output, and the inputs of `CIN` are taken from the input
+    pub(super) fn advance(&mut self, nmspc: &mut NamespaceRegistry, io: &mut ProgramIO) -> ExecRes<Option<ProgramSuccess>> {
+        if self.pc < self.lines.len() {
+            let flow = self.step(nmspc, io)?;
+            if self.stack.len() > self.max_stack {
+                return Err(ProgExecutionError::StackOverflow(line_span(&self.spans[self.pc])));
+            }
+
+            match flow {
+                Flow::Next => self.pc += 1,
+                Flow::Jump(ind) => self.pc = ind,
+                Flow::Return => self.pc = self.lines.len(),
+                Flow::Wait => {},
+            }
+        }
+
+        if self.pc < self.lines.len() {
+            Ok(None)
+        } else {
+            Ok(Some(ProgramSuccess::Success))
+        }
+    }
+
+    pub(super) fn set_limits(&mut self, limits: &ExecutionLimits) {
+        self.max_stack = limits.max_stack;
+        self.max_call_depth = limits.max_call_depth;
+    }
+
+    // index of the next line to be executed
+    pub(super) fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub(super) fn stack(&self) -> &[StackType] {
+        &self.stack
+    }
+
+    // checks if the next line reads an input with `CIN`
+    pub(super) fn awaiting_input(&self) -> bool {
+        matches!(self.lines.get(self.pc).and_then(|line| line.first()), Some(Tokens::Command(Commands::CIn)))
+    }
+
+    // the tokens and the span of the line at the index
+    pub(super) fn line(&self, ind: usize) -> Option<(&[Tokens], Span)> {
+        self.lines.get(ind).map(|line| (line.as_slice(), line_span(&self.spans[ind])))
+    }
+
+    // executes a single line of the program
+    fn step(&mut self, nmspc: &mut NamespaceRegistry, io: &mut ProgramIO) -> ExecRes<Flow> {
+        let span = &line_span(&self.spans[self.pc]);
+        let line = self.lines[self.pc].clone();
+
+        let cmd = match line.first() {
+            Some(Tokens::Command(cmd)) => *cmd,
+            _ => return Err(ProgExecutionError::InvalidArguments(span.clone())),
+        };
+        let args = line[1..].iter().map(|tkn| {
+            if let Tokens::Argument(arg) = tkn {
+                Ok(arg)
+            } else {
+                Err(ProgExecutionError::InvalidArguments(span.clone()))
+            }
+        }).collect::<ExecRes<Vec<_>>>()?;
+
+        match cmd {
+            // declarations are already handled before the program starts
+            Commands::Static | Commands::Namespace | Commands::Mrk => {},
+            // includes are already spliced in by the linker
+            Commands::Include => {},
+            Commands::Push => {
+                let val = self.argument(nmspc, &args, 0, span)?;
+                self.stack.push(val);
+            },
+            Commands::Pop => {
+                // popping into a namespace property writes the value to it
+                if let Some(Arguments::Namespace(path)) = args.first() {
+                    self.declared(path, 0, span)?;
+                    let val = self.pop_value(span)?;
+                    nmspc.set(path, val).map_err(|err| self.nmspc_err(err, path, 0, span))?;
+                } else {
+                    self.pop_item(span)?;
+                }
+            },
+            Commands::COut => {
+                let item = self.pop_item(span)?;
+                io.output.push(format_item(&item));
+            },
+            Commands::CIn => {
+                // pushes the next line of the input as a string
+                match io.input.pop_front() {
+                    Some(line) => self.stack.push(StackType::Str(line)),
+                    None => return Ok(Flow::Wait),
+                }
+            },
+            Commands::Ret => {
+                // returns from the innermost call, or stops the program outside of any calls
+                if self.frames.len() > 1 {
+                    let frame = self.frames.pop().expect("There is more than one frame");
+                    return Ok(Flow::Jump(frame.ret.expect("Only the outermost frame has no return")));
+                }
+                return Ok(Flow::Return);
+            },
+
+            Commands::Pack => {
+                let len = match self.argument(nmspc, &args, 0, span)? {
+                    StackType::Int(len) if 0 <= len && len <= u16::MAX as i64 => len as u16,
+                    _ => return Err(ProgExecutionError::InvalidArguments(span.clone())),
+                };
+                // checks there are enough items to be packed into the list
+                let mut slots = 0;
+                for _ in 0..len {
+                    slots += item_slots(&self.stack[..self.stack.len()-slots])
+                        .ok_or(ProgExecutionError::StackUnderflow(span.clone()))?;
+                }
+                self.stack.push(StackType::List(len));
+            },
+            Commands::Unpack => {
+                match self.stack.last() {
+                    Some(StackType::List(_)) => { self.stack.pop(); },
+                    Some(_) => return Err(ProgExecutionError::TypeMismatch(span.clone())),
+                    None => return Err(ProgExecutionError::StackUnderflow(span.clone())),
+                }
+            },
+            Commands::RotTwo => self.rotate(2, span)?,
+            Commands::RotThree => self.rotate(3, span)?,
+            Commands::RotFour => self.rotate(4, span)?,
+            Commands::Jmp => {
+                return Ok(Flow::Jump(self.marker(&args, span)?));
+            },
+            Commands::Call => {
+                let target = self.marker(&args, span)?;
+                // the outermost frame is not a call
+                if self.frames.len() > self.max_call_depth {
+                    return Err(ProgExecutionError::CallDepthExceeded(span.clone()));
+                }
+                self.frames.push(Frame { ret: Some(self.pc+1), locals: HashMap::new() });
+                return Ok(Flow::Jump(target));
+            },
+            Commands::JmpIf => {
+                // the condition is kept on the stack so loop counters can be reused
+                let cond = match self.stack.last() {
+                    Some(val) => truthy(val),
+                    None => return Err(ProgExecutionError::StackUnderflow(span.clone())),
+                };
+                if cond {
+                    return Ok(Flow::Jump(self.marker(&args, span)?));
+                }
+            },
+
+            Commands::Add | Commands::Sub | Commands::Mul | Commands::Div | Commands::Mod |
+            Commands::ShL | Commands::ShR | Commands::And | Commands::Or | Commands::Xor |
+            Commands::AndL | Commands::OrL | Commands::XorL | Commands::Eq | Commands::Lt | Commands::Gt => {
+                let b = self.pop_value(span)?;
+                let a = self.pop_value(span)?;
+                self.stack.push(binary_op(cmd, a, b, span)?);
+            },
+            Commands::Not | Commands::Neg | Commands::NotL => {
+                let a = self.pop_value(span)?;
+                self.stack.push(unary_op(cmd, a, span)?);
+            },
+            Commands::AddI | Commands::SubI | Commands::MulI | Commands::DivI | Commands::ModI |
+            Commands::AndI | Commands::OrI | Commands::XorI => {
+                let b = self.argument(nmspc, &args, 0, span)?;
+                let a = self.pop_value(span)?;
+                self.stack.push(binary_op(immediate_op(cmd), a, b, span)?);
+            },
+
+            Commands::Load => {
+                let name = self.variable(&args, span)?;
+                let val = self.frames.last().and_then(|frame| frame.locals.get(name)).cloned()
+                    .ok_or_else(|| ProgExecutionError::UnknownVariable(self.arg_span(0, span), name.clone()))?;
+                self.stack.push(val);
+            },
+            Commands::Store => {
+                let name = self.variable(&args, span)?.clone();
+                let val = self.pop_value(span)?;
+                self.frames.last_mut().expect("There is always the outermost frame").locals.insert(name, val);
+            },
+
+            Commands::CmdCopy | Commands::CmdMove => {
+                let path = match args.first() {
+                    Some(Arguments::Namespace(path)) => path,
+                    _ => return Err(ProgExecutionError::InvalidArguments(span.clone())),
+                };
+                self.declared(path, 0, span)?;
+                let (arity, command) = nmspc.command(path)
+                    .ok_or_else(|| ProgExecutionError::UnknownNamespace(self.arg_span(0, span), join_path(path)))?;
+
+                // the values used by the command; ordered from the bottom of the stack to the top
+                let mut cmd_args = Vec::with_capacity(arity);
+                for _ in 0..arity {
+                    cmd_args.push(self.pop_value(span)?);
+                }
+                cmd_args.reverse();
+                // copying the arguments leaves the values on the stack
+                if cmd == Commands::CmdCopy {
+                    self.stack.extend(cmd_args.iter().cloned());
+                }
+
+                let results = command(nmspc, cmd_args).map_err(|err| match err {
+                    NamespaceError::TypeMismatch => ProgExecutionError::TypeMismatch(span.clone()),
+                    err => ProgExecutionError::CommandFailed(span.clone(), err.message()),
+                })?;
+                self.stack.extend(results);
+            },
+
+            Commands::Event => {
+                return Err(ProgExecutionError::UnsupportedCommand(span.clone()));
+            },
+        }
+
+        Ok(Flow::Next)
+    }
+
+    // resolves the argument at the index into a value that can be placed on the stack
+    fn argument(&self, nmspc: &NamespaceRegistry, args: &[&Arguments], ind: usize, span: &Span) -> ExecRes<StackType> {
+        match args.get(ind) {
+            Some(Arguments::Values(val)) => Ok(StackType::from(val.clone())),
+            Some(Arguments::StaticVar(name)) => {
+                self.statics.get(&(self.scopes[self.pc], name.clone())).cloned().ok_or_else(|| ProgExecutionError::UnknownStaticVar(self.arg_span(ind, span), name.clone()))
+            },
+            Some(Arguments::Namespace(path)) => {
+                self.declared(path, ind, span)?;
+                if nmspc.is_namespace(path) {
+                    // a namespace itself is passed around as a reference by its full path
+                    Ok(StackType::Str(join_path(path)))
+                } else {
+                    nmspc.get(path).map_err(|err| self.nmspc_err(err, path, ind, span))
+                }
+            },
+            Some(Arguments::Marker(_)) | None => Err(ProgExecutionError::InvalidArguments(span.clone())),
+        }
+    }
+
+    // checks the namespace path was declared with `NAMESPACE` by the program
+    fn declared(&self, path: &[String], ind: usize, span: &Span) -> ExecRes<()> {
+        if self.namespaces.iter().any(|decl| path.starts_with(decl)) {
+            Ok(())
+        } else {
+            Err(ProgExecutionError::UndeclaredNamespace(self.arg_span(ind, span), join_path(path)))
+        }
+    }
+
+    // converts the namespace errors of the argument at the index to an execution error
+    fn nmspc_err(&self, err: NamespaceError, path: &[String], ind: usize, span: &Span) -> ProgExecutionError {
+        let arg_span = self.arg_span(ind, span);
+        match err {
+            NamespaceError::Unknown => ProgExecutionError::UnknownNamespace(arg_span, join_path(path)),
+            NamespaceError::ReadOnly => ProgExecutionError::ReadOnlyNamespace(arg_span, join_path(path)),
+            NamespaceError::TypeMismatch => ProgExecutionError::TypeMismatch(span.clone()),
+            NamespaceError::Failed(reason) => ProgExecutionError::CommandFailed(span.clone(), reason),
+        }
+    }
+
+    // resolves the marker argument into the line index it marks
+    fn marker(&self, args: &[&Arguments], span: &Span) -> ExecRes<usize> {
+        match args.first() {
+            Some(Arguments::Marker(name)) => {
+                self.markers.get(&(self.scopes[self.pc], name.clone())).copied().ok_or_else(|| ProgExecutionError::UnknownMarker(self.arg_span(0, span), name.clone()))
+            },
+            _ => Err(ProgExecutionError::InvalidArguments(span.clone())),
+        }
+    }
+
+    // the name of the local variable argument of `LOAD` and `STORE`
+    fn variable<'b>(&self, args: &[&'b Arguments], span: &Span) -> ExecRes<&'b String> {
+        match args.first() {
+            Some(Arguments::StaticVar(name)) => Ok(name),
+            _ => Err(ProgExecutionError::InvalidArguments(span.clone())),
+        }
+    }
+
+    // the span of the argument at the index of the current line, or the span of the whole line
+    fn arg_span(&self, ind: usize, span: &Span) -> Span {
+        self.spans[self.pc].get(ind+1).cloned().unwrap_or_else(|| span.clone())
+    }
+
+    // pops the item on the top of the stack; a list item includes all of its elements
+    fn pop_item(&mut self, span: &Span) -> ExecRes<Vec<StackType>> {
+        let slots = item_slots(&self.stack).ok_or(ProgExecutionError::StackUnderflow(span.clone()))?;
+        Ok(self.stack.split_off(self.stack.len()-slots))
+    }
+
+    // pops the item on the top of the stack that must be a single value (not a list)
+    fn pop_value(&mut self, span: &Span) -> ExecRes<StackType> {
+        let mut item = self.pop_item(span)?;
+        if item.len() == 1 && !matches!(item[0], StackType::List(_)) {
+            Ok(item.remove(0))
+        } else {
+            Err(ProgExecutionError::TypeMismatch(span.clone()))
+        }
+    }
+
+    // moves the top item of the stack down by (count-1) items, lifting the rest up by one
+    fn rotate(&mut self, count: usize, span: &Span) -> ExecRes<()> {
+        let mut items = Vec::with_capacity(count);
+        for _ in 0..count {
+            items.push(self.pop_item(span)?);
+        }
+
+        // items[0] is the original top of the stack
+        let top = items.remove(0);
+        self.stack.extend(top);
+        for item in items.into_iter().rev() {
+            self.stack.extend(item);
+        }
+        Ok(())
+    }
+}
+
+// the span of a whole line of tokens
+fn line_span(spans: &[Span]) -> Span {
+    match (spans.first(), spans.last()) {
+        (Some(first), Some(last)) => first.to(last),
+        _ => Span::unknown(),
+    }
+}
+
+// the number of stack slots the top item takes up; None if the stack does not hold a complete item
+fn item_slots(stack: &[StackType]) -> Option<usize> {
+    match stack.last()? {
+        StackType::List(len) => {
+            let mut slots = 1;
+            for _ in 0..*len {
+                slots += item_slots(&stack[..stack.len()-slots])?;
+            }
+            Some(slots)
+        },
+        _ => Some(1),
+    }
+}
+
+// the truthiness of a value on the stack
+fn truthy(val: &StackType) -> bool {
+    match val {
+        StackType::Str(s) => !s.is_empty(),
+        StackType::Int(i) => *i != 0,
+        StackType::Float(f) => *f != 0.0,
+        StackType::List(len) => *len != 0,
+    }
+}
+
+// converts the immediate variant of the command to its regular binary command
+fn immediate_op(cmd: Commands) -> Commands {
+    match cmd {
+        Commands::AddI => Commands::Add,
+        Commands::SubI => Commands::Sub,
+        Commands::MulI => Commands::Mul,
+        Commands::DivI => Commands::Div,
+        Commands::ModI => Commands::Mod,
+        Commands::AndI => Commands::And,
+        Commands::OrI => Commands::Or,
+        Commands::XorI => Commands::Xor,
+        _ => cmd,
+    }
+}
+
+// binary operations where `a` is the value below the top of the stack and `b` the top of the stack
+fn binary_op(cmd: Commands, a: StackType, b: StackType, span: &Span) -> ExecRes<StackType> {
+    use StackType::{Str, Int, Float};
+
+    // logical operations works with any values
+    match cmd {
+        Commands::Eq => return Ok(Int(values_eq(&a, &b) as i64)),
+        Commands::AndL => return Ok(Int((truthy(&a) && truthy(&b)) as i64)),
+        Commands::OrL => return Ok(Int((truthy(&a) || truthy(&b)) as i64)),
+        Commands::XorL => return Ok(Int((truthy(&a) != truthy(&b)) as i64)),
+        _ => {},
+    }
+
+    match (a, b) {
+        (Int(a), Int(b)) => {
+            match cmd {
+                Commands::Add => Ok(Int(a.wrapping_add(b))),
+                Commands::Sub => Ok(Int(a.wrapping_sub(b))),
+                Commands::Mul => Ok(Int(a.wrapping_mul(b))),
+                Commands::Div if b == 0 => Err(ProgExecutionError::DivisionByZero(span.clone())),
+                Commands::Div => Ok(Int(a.wrapping_div(b))),
+                Commands::Mod if b == 0 => Err(ProgExecutionError::DivisionByZero(span.clone())),
+                Commands::Mod => Ok(Int(a.wrapping_rem(b))),
+                Commands::ShL => Ok(Int(a.wrapping_shl(b as u32))),
+                Commands::ShR => Ok(Int(a.wrapping_shr(b as u32))),
+                Commands::And => Ok(Int(a & b)),
+                Commands::Or => Ok(Int(a | b)),
+                Commands::Xor => Ok(Int(a ^ b)),
+                Commands::Lt => Ok(Int((a < b) as i64)),
+                Commands::Gt => Ok(Int((a > b) as i64)),
+                _ => Err(ProgExecutionError::TypeMismatch(span.clone())),
+            }
+        },
+        // integers are upgraded to floats when any side is a float
+        (Int(a), Float(b)) => float_op(cmd, a as f64, b, span),
+        (Float(a), Int(b)) => float_op(cmd, a, b as f64, span),
+        (Float(a), Float(b)) => float_op(cmd, a, b, span),
+        (Str(a), Str(b)) if cmd == Commands::Add => Ok(Str(a + &b)),
+        (Str(a), Str(b)) if cmd == Commands::Lt => Ok(Int((a < b) as i64)),
+        (Str(a), Str(b)) if cmd == Commands::Gt => Ok(Int((a > b) as i64)),
+        _ => Err(ProgExecutionError::TypeMismatch(span.clone())),
+    }
+}
+
+// equality of two values; integers and floats are compared by their numeric values
+fn values_eq(a: &StackType, b: &StackType) -> bool {
+    match (a, b) {
+        (StackType::Int(a), StackType::Float(b)) | (StackType::Float(b), StackType::Int(a)) => *a as f64 == *b,
+        (a, b) => a == b,
+    }
+}
+
+fn float_op(cmd: Commands, a: f64, b: f64, span: &Span) -> ExecRes<StackType> {
+    match cmd {
+        Commands::Add => Ok(StackType::Float(a + b)),
+        Commands::Sub => Ok(StackType::Float(a - b)),
+        Commands::Mul => Ok(StackType::Float(a * b)),
+        Commands::Div | Commands::Mod if b == 0.0 => Err(ProgExecutionError::DivisionByZero(span.clone())),
+        Commands::Div => Ok(StackType::Float(a / b)),
+        Commands::Mod => Ok(StackType::Float(a % b)),
+        Commands::Lt => Ok(StackType::Int((a < b) as i64)),
+        Commands::Gt => Ok(StackType::Int((a > b) as i64)),
+        _ => Err(ProgExecutionError::TypeMismatch(span.clone())),
+    }
+}
+
+fn unary_op(cmd: Commands, a: StackType, span: &Span) -> ExecRes<StackType> {
+    match (cmd, a) {
+        (Commands::NotL, a) => Ok(StackType::Int(!truthy(&a) as i64)),
+        (Commands::Not, StackType::Int(a)) => Ok(StackType::Int(!a)),
+        (Commands::Neg, StackType::Int(a)) => Ok(StackType::Int(a.wrapping_neg())),
+        (Commands::Neg, StackType::Float(a)) => Ok(StackType::Float(-a)),
+        _ => Err(ProgExecutionError::TypeMismatch(span.clone())),
+    }
+}
+
+// formats the stack item (a single value or a list with all of its elements) for the console
+fn format_item(item: &[StackType]) -> String {
+    fn inner(item: &[StackType], end: usize, out: &mut String) -> usize {
+        match &item[end-1] {
+            StackType::List(len) => {
+                let mut elems = Vec::new();
+                let mut slots = 1;
+                for _ in 0..*len {
+                    let mut elem = String::new();
+                    slots += inner(item, end-slots, &mut elem);
+                    elems.push(elem);
+                }
+                elems.reverse();
+                out.push_str(&format!("[{}]", elems.join(", ")));
+                slots
+            },
+            val => {
+                out.push_str(&val.to_string());
+                1
+            },
+        }
+    }
+
+    let mut out = String::new();
+    if !item.is_empty() {
+        inner(item, item.len(), &mut out);
+    }
+    out
+}
+
+impl From<ValType> for StackType {
+    fn from(val: ValType) -> Self {
+        match val {
+            ValType::Str(s) => StackType::Str(s),
+            ValType::Int(i) => StackType::Int(i),
+            ValType::Float(f) => StackType::Float(f),
+        }
+    }
+}
+
+impl fmt::Display for StackType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StackType::Str(s) => write!(f, "{}", s),
+            StackType::Int(i) => write!(f, "{}", i),
+            StackType::Float(fl) => write!(f, "{:?}", fl),
+            StackType::List(len) => write!(f, "<list of {}>", len),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::commands::WorldCommandExecutor;
+    use crate::world::commands::ProgramError;
+    use crate::world::commands::namespace::{Namespace, NmspcRes, path_str};
+    use crate::world::commands::library::register_library;
+    use crate::world::player::Player;
+
+    use std::fs;
+
+    fn run(exec: &mut WorldCommandExecutor) -> Vec<String> {
+        run_with(exec, &mut NamespaceRegistry::new())
+    }
+
+    fn run_with(exec: &mut WorldCommandExecutor, nmspc: &mut NamespaceRegistry) -> Vec<String> {
+        let results = exec.update(nmspc);
+        assert!(results.iter().all(|(_, res)| *res == Ok(ProgramSuccess::Success)), "{:?}", results);
+        exec.output().clone()
+    }
+
+    fn run_src(src: &str) -> Vec<String> {
+        let mut exec = WorldCommandExecutor::new();
+        exec.load_commands_bytc(src.chars().collect()).expect("The test source is valid");
+        run(&mut exec)
+    }
+
+    // the error the program ends with
+    fn fails(src: &str) -> ProgExecutionError {
+        let mut exec = WorldCommandExecutor::new();
+        exec.load_commands_bytc(src.chars().collect()).expect("The test source is valid");
+        match exec.update(&mut NamespaceRegistry::new()).pop() {
+            Some((_, Err(ProgramError::ExecErr(err)))) => err,
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    // a namespace with a single property the programs can read and write
+    struct Counter(i64);
+
+    impl Namespace for Counter {
+        fn entries(&self, path: &[String]) -> Vec<String> {
+            match path {
+                [] => vec!["counter".into()],
+                _ => Vec::new(),
+            }
+        }
+
+        fn get(&self, path: &[String]) -> NmspcRes<StackType> {
+            match path_str(path).as_slice() {
+                ["counter"] => Ok(StackType::Int(self.0)),
+                _ => Err(NamespaceError::Unknown),
+            }
+        }
+
+        fn set(&mut self, path: &[String], val: StackType) -> NmspcRes<()> {
+            match (path_str(path).as_slice(), val) {
+                (["counter"], StackType::Int(i)) => { self.0 = i; Ok(()) },
+                (["counter"], _) => Err(NamespaceError::TypeMismatch),
+                _ => Err(NamespaceError::Unknown),
+            }
+        }
+    }
+
+    #[test]
+    fn runs_the_test_script() {
+        // the test script with an ending loop, relative to the `matrixagon` directory the game is run from
+        let mut camera = Player::new().camera;
+        let mut exec = WorldCommandExecutor::new();
+        exec.load_file_bytc("resource/commands/test01.wcb".into()).expect("The test script is valid");
+
+        let mut nmspc = NamespaceRegistry::new();
+        nmspc.register("Matrixagon:world:player:main", &mut camera);
+        register_library(&mut nmspc);
+        let mut results = Vec::new();
+        while results.is_empty() {
+            results = exec.update(&mut nmspc);
+        }
+        assert_eq!(exec.output(), &vec!["58"]);
+        // the trailing command is called again after the previous call moved its arguments off the stack,
+        // and takes the values left before them for its arguments
+        match results.pop() {
+            Some((_, Err(ProgramError::ExecErr(ProgExecutionError::CommandFailed(span, msg))))) => {
+                assert_eq!((span.line, msg.as_str()), (54, "unknown namespace"));
+            },
+            res => panic!("unexpected result {:?}", res),
+        }
+
+        // teleported twice, as the first command leaves its arguments on the stack
+        drop(nmspc);
+        assert_ne!(camera.position, Player::new().camera.position);
+    }
+
+    #[test]
+    fn cuts_off_the_loop_of_the_world_script() {
+        // the script loaded by the world never ends its loop, and the copy at the repository root is the same
+        let script = fs::read("resource/commands/test00.wcb").expect("The world script exists");
+        assert_eq!(script, fs::read("../resource/commands/test00.wcb").expect("The root copy exists"));
+
+        let mut exec = WorldCommandExecutor::new();
+        exec.set_limits(ExecutionLimits { max_instructions: Some(10_000), ..Default::default() });
+        exec.load_file_bytc("resource/commands/test00.wcb".into()).expect("The test script is valid");
+        let mut nmspc = NamespaceRegistry::new();
+        let mut results = Vec::new();
+        while results.is_empty() {
+            results = exec.update(&mut nmspc);
+        }
+        assert_eq!(exec.output(), &vec!["58"]);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1, Ok(ProgramSuccess::Interrupt));
+    }
+
+    #[test]
+    fn moves_stack_items() {
+        assert_eq!(run_src("PUSH 1\nPUSH 2\nPOP\nCOUT\n"), vec!["1"]);
+        assert_eq!(run_src("PUSH 1\nPUSH 2\nROT_TWO\nCOUT\nCOUT\n"), vec!["1", "2"]);
+        assert_eq!(run_src("PUSH 1\nPUSH 2\nPUSH 3\nROT_THREE\nCOUT\nCOUT\nCOUT\n"), vec!["2", "1", "3"]);
+        assert_eq!(run_src("PUSH 1\nPUSH 2\nPUSH 3\nPUSH 4\nROT_FOUR\nCOUT\nCOUT\nCOUT\nCOUT\n"), vec!["3", "2", "1", "4"]);
+    }
+
+    #[test]
+    fn packs_lists() {
+        // a list moves and prints as a single item
+        assert_eq!(run_src("PUSH 1\nPUSH 2\nPACK 2\nPUSH 3\nPACK 2\nPUSH \"x\"\nROT_TWO\nCOUT\nCOUT\n"), vec!["[[1, 2], 3]", "x"]);
+        assert_eq!(run_src("PUSH 4\nPUSH 5\nPACK 2\nUNPK\nCOUT\nCOUT\n"), vec!["5", "4"]);
+        assert_eq!(run_src("PACK 0\nCOUT\n"), vec!["[]"]);
+    }
+
+    #[test]
+    fn computes_arithmetic() {
+        assert_eq!(run_src("PUSH 7\nPUSH 2\nDIV\nCOUT\nPUSH -7\nPUSH 2\nMOD\nCOUT\nPUSH 7\nPUSH 2.0\nDIV\nCOUT\n"), vec!["3", "-1", "3.5"]);
+        assert_eq!(run_src("PUSH 3\nPUSH 4\nMUL\nPUSH 5\nSUB\nPUSH 1.5\nADD\nCOUT\n"), vec!["8.5"]);
+        assert_eq!(run_src("PUSH 1\nPUSH 4\nSHL\nPUSH 2\nSHR\nCOUT\nPUSH 2.5\nNEG\nCOUT\nPUSH \"a\"\nPUSH \"b\"\nADD\nCOUT\n"), vec!["4", "-2.5", "ab"]);
+        // the integers wrap around instead of overflowing
+        assert_eq!(run_src("PUSH 9223372036854775807\nADDI 1\nCOUT\n"), vec!["-9223372036854775808"]);
+        assert_eq!(run_src("PUSH 10\nADDI 5\nSUBI 3\nMULI 2\nDIVI 5\nMODI 3\nCOUT\nPUSH 12\nANDI 10\nORI 1\nXORI 3\nCOUT\n"), vec!["1", "10"]);
+    }
+
+    #[test]
+    fn computes_logic() {
+        assert_eq!(run_src("PUSH 12\nPUSH 10\nAND\nCOUT\nPUSH 12\nPUSH 10\nOR\nCOUT\nPUSH 12\nPUSH 10\nXOR\nCOUT\nPUSH 0\nNOT\nCOUT\n"), vec!["8", "14", "6", "-1"]);
+        assert_eq!(run_src("PUSH 2\nPUSH \"\"\nANDL\nCOUT\nPUSH 0\nPUSH 0.5\nORL\nCOUT\nPUSH 1\nPUSH 1\nXORL\nCOUT\nPUSH \"a\"\nNOTL\nCOUT\n"), vec!["0", "1", "0", "0"]);
+    }
+
+    #[test]
+    fn jumps_to_markers() {
+        // the condition stays on the stack as the loop counter
+        assert_eq!(run_src("PUSH 3\nMRK #loop\nPUSH \"tick\"\nCOUT\nSUBI 1\nJMP_IF #loop\nJMP #end\nPUSH \"skipped\"\nCOUT\nMRK #end\nCOUT\n"), vec!["tick", "tick", "tick", "0"]);
+        assert_eq!(run_src("CALL #f\nPUSH \"after\"\nCOUT\nRET\nMRK #f\nPUSH \"in f\"\nCOUT\nRET\n"), vec!["in f", "after"]);
+    }
+
+    #[test]
+    fn reads_console_inputs() {
+        let mut exec = WorldCommandExecutor::new();
+        exec.load_commands_bytc("CIN\nPUSH \"!\"\nADD\nCOUT\n".chars().collect()).expect("The test source is valid");
+        assert!(exec.update(&mut NamespaceRegistry::new()).is_empty());
+        assert!(exec.awaiting_input());
+
+        exec.send_input("hi".into());
+        assert_eq!(run(&mut exec), vec!["hi!"]);
+    }
+
+    #[test]
+    fn uses_statics_and_namespaces() {
+        assert_eq!(run_src("STATIC $greeting \"hello\"\nPUSH $greeting\nCOUT\n"), vec!["hello"]);
+
+        fn sum(_: &mut NamespaceRegistry<'_>, args: Vec<StackType>) -> NmspcRes<Vec<StackType>> {
+            match args.as_slice() {
+                [StackType::Int(a), StackType::Int(b)] => Ok(vec![StackType::Int(a + b)]),
+                _ => Err(NamespaceError::TypeMismatch),
+            }
+        }
+
+        let mut counter = Counter(5);
+        {
+            let mut nmspc = NamespaceRegistry::new();
+            nmspc.register("test:stats", &mut counter);
+            nmspc.register_command("test:Sum", 2, sum);
+
+            // copying the arguments leaves them on the stack, moving them takes them off
+            let mut exec = WorldCommandExecutor::new();
+            exec.load_commands_bytc("NAMESPACE :test\nPUSH :test:stats:counter\nPUSH 2\nCMD_COPY :test:Sum\nCOUT\nCMD_MOVE :test:Sum\nPOP :test:stats:counter\n".chars().collect()).expect("The test source is valid");
+            assert_eq!(run_with(&mut exec, &mut nmspc), vec!["7"]);
+        }
+        assert_eq!(counter.0, 7);
+    }
+
+    #[test]
+    fn fails_on_stack_underflow() {
+        let err = fails("PUSH 1\nADD\n");
+        assert_eq!((err.message(), err.span().line), ("not enough values on the stack".into(), 2));
+        assert!(matches!(fails("POP\n"), ProgExecutionError::StackUnderflow(_)));
+        assert!(matches!(fails("PUSH 1\nPACK 2\n"), ProgExecutionError::StackUnderflow(_)));
+        assert!(matches!(fails("PUSH 1\nPUSH 2\nROT_THREE\n"), ProgExecutionError::StackUnderflow(_)));
+    }
+
+    #[test]
+    fn fails_on_type_mismatch() {
+        let err = fails("PUSH \"a\"\nNEG\n");
+        assert_eq!((err.message(), err.span().line), ("mismatched types for the command".into(), 2));
+        assert!(matches!(fails("PUSH \"a\"\nPUSH 1\nSUB\n"), ProgExecutionError::TypeMismatch(_)));
+        assert!(matches!(fails("PUSH 1\nUNPK\n"), ProgExecutionError::TypeMismatch(_)));
+        // a list is not a single value
+        assert!(matches!(fails("PUSH 1\nPACK 1\nADDI 1\n"), ProgExecutionError::TypeMismatch(_)));
+    }
+
+    #[test]
+    fn fails_on_unknown_marker() {
+        match fails("PUSH 1\nJMP_IF #nowhere\n") {
+            ProgExecutionError::UnknownMarker(span, name) => assert_eq!((span.line, span.col_start, name.as_str()), (2, 8, "nowhere")),
+            err => panic!("unexpected error {:?}", err),
+        }
+        assert!(matches!(fails("CALL #missing\n"), ProgExecutionError::UnknownMarker(..)));
+    }
+
+    #[test]
+    fn fails_on_division_by_zero() {
+        let err = fails("PUSH 1\nPUSH 0\nDIV\n");
+        assert_eq!((err.message(), err.span().line), ("division by zero".into(), 3));
+        assert!(matches!(fails("PUSH 1\nMODI 0\n"), ProgExecutionError::DivisionByZero(_)));
+        assert!(matches!(fails("PUSH 1.5\nPUSH 0.0\nDIV\n"), ProgExecutionError::DivisionByZero(_)));
+    }
+
+    #[test]
+    fn calls_keep_their_own_locals() {
+        let mut exec = WorldCommandExecutor::new();
+        exec.load_commands_bytc("PUSH 1\nSTORE $x\nPUSH 5\nCALL #double\nCOUT\nLOAD $x\nCOUT\nRET\n\
+                                 MRK #double\nSTORE $x\nLOAD $x\nLOAD $x\nADD\nRET\n".chars().collect()).expect("The test source is valid");
+        assert_eq!(run(&mut exec), vec!["10", "1"]);
+
+        exec.load_commands_bytc("CALL #f\nRET\nMRK #f\nLOAD $missing\nRET\n".chars().collect()).expect("The test source is valid");
+        match exec.update(&mut NamespaceRegistry::new()).as_slice() {
+            [(_, Err(ProgramError::ExecErr(ProgExecutionError::UnknownVariable(span, name))))] => {
+                assert_eq!((span.line, name.as_str()), (4, "missing"));
+            },
+            res => panic!("unexpected results {:?}", res),
+        }
+    }
+
+    #[test]
+    fn compares_values() {
+        let mut exec = WorldCommandExecutor::new();
+        exec.load_commands_bytc("PUSH 2\nPUSH 2.0\nEQ\nCOUT\nPUSH \"a\"\nPUSH \"b\"\nLT\nCOUT\nPUSH 1.5\nPUSH 2\nGT\nCOUT\n".chars().collect()).expect("The test source is valid");
+        assert_eq!(run(&mut exec), vec!["1", "1", "0"]);
+    }
+
+    #[test]
+    fn runs_compiled_scripts() {
+        let mut exec = WorldCommandExecutor::new();
+        exec.load_commands(String::from("
+            fn fib(n) {
+                if n < 2 { return n; }
+                return fib(n - 1) + fib(n - 2);
+            }
+
+            let i = 0;
+            while i <= 10 {
+                if i % 5 == 0 { print(fib(i)); }
+                i = i + 1;
+            }
+        ")).expect("The test script is valid");
+        assert_eq!(run(&mut exec), vec!["0", "5", "55"]);
+    }
+
+    #[test]
+    fn optimized_scripts_behave_the_same() {
+        let src = "
+            fn scale(v, by) { return v * by - 0.5; }
+            let i = 0;
+            let total = 1 + 2 * 3;
+            while i < 6 {
+                i = i + 1;
+                if i % 2 == 0 { continue; }
+                total = total + scale(i, 2 * 2);
+            }
+            print(total);
+            print(\"a\" + \"b\" == \"ab\");
+        ";
+
+        let mut outputs = Vec::new();
+        for optimize in [false, true].iter() {
+            let options = cmd_script::CompileOptions { optimize: *optimize, dump_ir: false };
+            let bytc = cmd_script::compile_with(src, &options).expect("The test script is valid").bytecode;
+            let mut exec = WorldCommandExecutor::new();
+            exec.load_commands_bytc(bytc.chars().collect()).expect("The test source is valid");
+            outputs.push(run(&mut exec));
+        }
+        assert_eq!(outputs[0], vec!["41.5", "1"]);
+        assert_eq!(outputs[0], outputs[1]);
+    }
+}
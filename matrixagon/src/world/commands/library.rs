@@ -0,0 +1,168 @@
+/*
+The World Command Library
+
+The standard commands of matrixagon reachable through `CMD_COPY` and `CMD_MOVE` (e.g. `:MTXG-CMD:Teleport`).
+The commands do not own any game states; they reach the subsystems through the namespaces registered
+for the update, so editing the world goes through the chunks of the world namespace.
+ */
+
+use crate::world::commands::bytecode::StackType;
+use crate::world::commands::namespace::{NamespaceRegistry, NmspcRes, NamespaceError, NmspcCommand, split_path, number};
+
+
+// where the library commands are registered under
+pub const COMMAND_NMSPC: &str = "MTXG-CMD";
+// where the world (the chunk handler) is expected to be registered under
+pub const WORLD_NMSPC: &str = "Matrixagon:world";
+
+// registers all the library commands with the number of values each of them takes from the stack
+pub fn register_library(nmspc: &mut NamespaceRegistry) {
+    let commands: [(&str, usize, NmspcCommand); 6] = [
+        ("Teleport", 4, teleport),
+        ("SetBlock", 4, set_block),
+        ("Fill", 7, fill),
+        ("Replace", 8, replace),
+        ("Clone", 9, clone),
+        ("Query", 3, query),
+    ];
+
+    for (name, arity, cmd) in commands.iter() {
+        nmspc.register_command(&format!("{}:{}", COMMAND_NMSPC, name), *arity, *cmd);
+    }
+}
+
+// the path of a chunk editing function of the world namespace
+fn world_chunks(func: &str) -> Vec<String> {
+    split_path(&format!("{}:chunks:{}", WORLD_NMSPC, func))
+}
+
+// Teleport: [entity, x, y, z] -> []
+// moves the entity (e.g. `:Matrixagon:world:player:main`) to the absolute position
+fn teleport(nmspc: &mut NamespaceRegistry<'_>, args: Vec<StackType>) -> NmspcRes<Vec<StackType>> {
+    let entity = match &args[0] {
+        StackType::Str(path) => split_path(path),
+        _ => return Err(NamespaceError::TypeMismatch),
+    };
+
+    // all the coordinates are checked first, so the entity is never left partially moved
+    let mut coords = Vec::with_capacity(3);
+    for (axis, val) in ["x", "y", "z"].iter().zip(&args[1..]) {
+        let mut path = entity.clone();
+        path.push("pos".into());
+        path.push(String::from(*axis));
+
+        nmspc.get(&path)?;
+        let val = number(val)?;
+        if !val.is_finite() {
+            return Err(NamespaceError::TypeMismatch);
+        }
+        coords.push((path, val));
+    }
+
+    for (path, val) in coords {
+        nmspc.set(&path, StackType::Float(val))?;
+    }
+
+    Ok(Vec::new())
+}
+
+// SetBlock: [x, y, z, block] -> []
+// sets the block at the position to the block of the registry name
+fn set_block(nmspc: &mut NamespaceRegistry<'_>, args: Vec<StackType>) -> NmspcRes<Vec<StackType>> {
+    nmspc.call(&world_chunks("set"), args)
+}
+
+// Fill: [x1, y1, z1, x2, y2, z2, block] -> []
+// fills the cuboid region between the two corners with the block
+fn fill(nmspc: &mut NamespaceRegistry<'_>, args: Vec<StackType>) -> NmspcRes<Vec<StackType>> {
+    nmspc.call(&world_chunks("fill"), args)
+}
+
+// Replace: [x1, y1, z1, x2, y2, z2, old block, new block] -> []
+// replaces only the old blocks within the cuboid region with the new block
+fn replace(nmspc: &mut NamespaceRegistry<'_>, args: Vec<StackType>) -> NmspcRes<Vec<StackType>> {
+    nmspc.call(&world_chunks("replace"), args)
+}
+
+// Clone: [x1, y1, z1, x2, y2, z2, x, y, z] -> []
+// copies the cuboid region so that its lowest corner is at the destination position
+fn clone(nmspc: &mut NamespaceRegistry<'_>, args: Vec<StackType>) -> NmspcRes<Vec<StackType>> {
+    nmspc.call(&world_chunks("clone"), args)
+}
+
+// Query: [x, y, z] -> [block]
+// the registry name of the block at the position
+fn query(nmspc: &mut NamespaceRegistry<'_>, args: Vec<StackType>) -> NmspcRes<Vec<StackType>> {
+    nmspc.call(&world_chunks("query"), args)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::commands::namespace::{Namespace, names, path_str};
+
+    struct Entity {
+        pos: [f64; 3],
+    }
+
+    impl Namespace for Entity {
+        fn entries(&self, path: &[String]) -> Vec<String> {
+            match path_str(path).as_slice() {
+                [] => names(&["pos"]),
+                ["pos"] => names(&["x", "y", "z"]),
+                _ => Vec::new(),
+            }
+        }
+
+        fn get(&self, path: &[String]) -> NmspcRes<StackType> {
+            match path_str(path).as_slice() {
+                ["pos", "x"] => Ok(StackType::Float(self.pos[0])),
+                ["pos", "y"] => Ok(StackType::Float(self.pos[1])),
+                ["pos", "z"] => Ok(StackType::Float(self.pos[2])),
+                _ => Err(NamespaceError::Unknown),
+            }
+        }
+
+        fn set(&mut self, path: &[String], val: StackType) -> NmspcRes<()> {
+            let axis = match path_str(path).as_slice() {
+                ["pos", "x"] => 0,
+                ["pos", "y"] => 1,
+                ["pos", "z"] => 2,
+                _ => return Err(NamespaceError::Unknown),
+            };
+            self.pos[axis] = number(&val)?;
+            Ok(())
+        }
+    }
+
+    fn teleport_to(entity: &mut Entity, args: Vec<StackType>) -> NmspcRes<Vec<StackType>> {
+        let mut nmspc = NamespaceRegistry::new();
+        nmspc.register("Matrixagon:world:player:main", entity);
+        register_library(&mut nmspc);
+
+        let mut args = args;
+        args.insert(0, StackType::Str(":Matrixagon:world:player:main".into()));
+        let (arity, cmd) = nmspc.command(&split_path(":MTXG-CMD:Teleport")).unwrap();
+        assert_eq!(arity, args.len());
+        cmd(&mut nmspc, args)
+    }
+
+    #[test]
+    fn teleports_the_entity() {
+        let mut entity = Entity { pos: [1.0, 2.0, 3.0] };
+        assert_eq!(teleport_to(&mut entity, vec![StackType::Int(-4), StackType::Float(5.5), StackType::Int(6)]), Ok(Vec::new()));
+        assert_eq!(entity.pos, [-4.0, 5.5, 6.0]);
+    }
+
+    #[test]
+    fn rejects_teleports_without_moving() {
+        let mut entity = Entity { pos: [1.0, 2.0, 3.0] };
+
+        let args = vec![StackType::Int(7), StackType::Int(8), StackType::Str("up".into())];
+        assert_eq!(teleport_to(&mut entity, args), Err(NamespaceError::TypeMismatch));
+        let args = vec![StackType::Int(7), StackType::Float(f64::NAN), StackType::Int(9)];
+        assert_eq!(teleport_to(&mut entity, args), Err(NamespaceError::TypeMismatch));
+        assert_eq!(entity.pos, [1.0, 2.0, 3.0]);
+    }
+}
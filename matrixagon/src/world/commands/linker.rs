@@ -0,0 +1,178 @@
+/*
+The World Command Linker
+
+Splices the files included with `INCL "path.wcb"` into the including program at load time. The
+include paths are relative to the including file, and each spliced file gets its own scope so the
+markers and static variables of different files never collide.
+ */
+
+use crate::world::commands::bytecode::{self, Tokens, Commands, Arguments, ValType, CompiledTokens, TokenError};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+
+struct Linker {
+    scope_counter: usize,  // the scope to be given to the next spliced file
+    including: Vec<PathBuf>,  // the chain of files currently being spliced; for detecting cycles
+    linked: CompiledTokens,
+}
+
+// compiles the file along with all of its includes into a single program
+pub(super) fn link_file(fname: String) -> Result<CompiledTokens, TokenError> {
+    let compiled = bytecode::compile_file(fname.clone())?;
+    let path = Path::new(&fname);
+
+    let mut linker = Linker::new();
+    // the file is already known to be readable by now
+    linker.including.push(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()));
+    linker.splice(compiled, path.parent().unwrap_or(Path::new("")))?;
+    Ok(linker.linked)
+}
+
+// compiles the commands along with all of its includes into a single program
+// the include paths of the commands are relative to the working directory
+pub(super) fn link_command(char_stream: Vec<char>) -> Result<CompiledTokens, TokenError> {
+    let compiled = bytecode::compile_command(char_stream)?;
+
+    let mut linker = Linker::new();
+    linker.splice(compiled, Path::new(""))?;
+    Ok(linker.linked)
+}
+
+impl Linker {
+    fn new() -> Self {
+        Self {
+            scope_counter: 0,
+            including: Vec::new(),
+            linked: CompiledTokens {
+                tokens: Vec::new(),
+                spans: Vec::new(),
+                scopes: Vec::new(),
+            },
+        }
+    }
+
+    // appends the lines of the compiled file to the program, replacing each include with the lines of that file
+    fn splice(&mut self, compiled: CompiledTokens, dir: &Path) -> Result<(), TokenError> {
+        let scope = self.scope_counter;
+        self.scope_counter += 1;
+
+        for (line, spans) in compiled.tokens.into_iter().zip(compiled.spans) {
+            if line.first() != Some(&Tokens::Command(Commands::Include)) {
+                self.linked.tokens.push(line);
+                self.linked.spans.push(spans);
+                self.linked.scopes.push(scope);
+                continue;
+            }
+
+            let (fname, arg_span) = match (line.as_slice(), spans.get(1)) {
+                ([_, Tokens::Argument(Arguments::Values(ValType::Str(fname)))], Some(arg_span)) => (fname, arg_span),
+                _ => return Err(TokenError::InvalidInclude(spans[0].to(&spans[spans.len()-1]))),
+            };
+
+            let path = dir.join(fname);
+            let canonical = fs::canonicalize(&path)
+                .map_err(|err| TokenError::FileUnreadable(arg_span.clone(), err.to_string()))?;
+            if self.including.contains(&canonical) {
+                return Err(TokenError::IncludeCycle(arg_span.clone(), path.display().to_string()));
+            }
+
+            let included = bytecode::compile_file(path.display().to_string())?;
+            let included_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+            self.including.push(canonical);
+            self.splice(included, &included_dir)?;
+            self.including.pop();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::commands::{WorldCommandExecutor, ProgramSuccess};
+    use crate::world::commands::namespace::NamespaceRegistry;
+
+    // a directory of bytecode files, removed after the test
+    struct Files(PathBuf);
+
+    impl Files {
+        fn new(test: &str, files: &[(&str, &str)]) -> Self {
+            let dir = std::env::temp_dir().join(format!("matrixagon-linker-{}-{}", test, std::process::id()));
+            for (fname, src) in files {
+                let path = dir.join(fname);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, src).unwrap();
+            }
+            Files(dir)
+        }
+
+        fn path(&self, fname: &str) -> String {
+            self.0.join(fname).display().to_string()
+        }
+    }
+
+    impl Drop for Files {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn run_file(fname: String) -> Vec<String> {
+        let mut exec = WorldCommandExecutor::new();
+        exec.load_file_bytc(fname).expect("The test files are valid");
+        let results = exec.update(&mut NamespaceRegistry::new());
+        assert!(results.iter().all(|(_, res)| *res == Ok(ProgramSuccess::Success)), "{:?}", results);
+        exec.output().clone()
+    }
+
+    #[test]
+    fn includes_relative_to_the_including_file() {
+        let files = Files::new("relative", &[
+            ("main.wcb", "INCL \"lib/greet.wcb\"\nPUSH \"main\"\nCOUT\n"),
+            ("lib/greet.wcb", "INCL \"name.wcb\"\nPUSH \"greet\"\nCOUT\n"),
+            ("lib/name.wcb", "PUSH \"name\"\nCOUT\n"),
+        ]);
+        assert_eq!(run_file(files.path("main.wcb")), vec!["name", "greet", "main"]);
+    }
+
+    #[test]
+    fn scopes_the_markers_of_each_file() {
+        let files = Files::new("scopes", &[
+            ("main.wcb", "PUSH 2\nMRK #loop\nINCL \"count.wcb\"\nSUBI 1\nJMP_IF #loop\n"),
+            ("count.wcb", "PUSH 3\nMRK #loop\nPUSH \"tick\"\nCOUT\nSUBI 1\nJMP_IF #loop\nPOP\n"),
+        ]);
+        // each `JMP_IF #loop` jumps to the marker of its own file
+        assert_eq!(run_file(files.path("main.wcb")), vec!["tick"; 6]);
+    }
+
+    #[test]
+    fn detects_include_cycles() {
+        let files = Files::new("cycle", &[
+            ("a.wcb", "PUSH 1\nINCL \"b.wcb\"\n"),
+            ("b.wcb", "INCL \"a.wcb\"\n"),
+        ]);
+        match link_file(files.path("a.wcb")) {
+            Err(TokenError::IncludeCycle(span, path)) => {
+                assert_eq!((span.file.as_ref(), span.line), (files.path("b.wcb").as_str(), 1));
+                assert_eq!(path, files.path("a.wcb"));
+            },
+            res => panic!("unexpected result {:?}", res.map(|linked| linked.tokens)),
+        }
+    }
+
+    #[test]
+    fn points_errors_into_the_included_file() {
+        let files = Files::new("error", &[
+            ("main.wcb", "PUSH 1\nINCL \"lib/broken.wcb\"\n"),
+            ("lib/broken.wcb", "PUSH 2\n\nPUHS 3\n"),
+        ]);
+        let err = link_file(files.path("main.wcb")).expect_err("The included file is invalid");
+        assert_eq!(err, TokenError::InvalidCommandName(err.span().clone(), "PUHS".into()));
+        assert_eq!((err.span().file.as_ref(), err.span().line), (files.path("lib/broken.wcb").as_str(), 3));
+        assert!(err.render().ends_with("3 | PUHS 3\n  | ^^^^"), "{}", err.render());
+    }
+}
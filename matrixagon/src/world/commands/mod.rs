@@ -5,66 +5,439 @@ A place where all the command bytecodes gets executed.
  */
 
 use std::char;
+use std::collections::VecDeque;
+use std::fs;
 
 pub mod bytecode;
+pub mod namespace;
+pub mod library;
+pub mod binary;
+pub mod disassembler;
+pub mod debugger;
+pub mod scheduler;
+pub mod console;
 mod tokenizer;
+mod interpreter;
+mod linker;
 
-use crate::world::commands::bytecode::{Tokens, TokenError};
+use crate::world::commands::bytecode::{TokenError, CompiledTokens, Span, StackType};
+use crate::world::commands::interpreter::Program;
+use crate::world::commands::namespace::NamespaceRegistry;
+use crate::world::commands::debugger::{Debugger, DebugStatus};
+use crate::world::commands::scheduler::{Task, TaskID, ExecutionLimits};
+use crate::world::commands::binary::BinaryError;
 
+use cmd_script::CompileError;
 
-type CommandProgRes = Result<ProgramSuccess, ProgramError>;
 
+pub type CommandProgRes = Result<ProgramSuccess, ProgramError>;
+
+#[derive(PartialEq, Debug)]
 pub enum ProgramSuccess {
     Success,  // the program executed flawlessly
-    Interrupt,  // program ended with a user key interrupt
+    Interrupt,  // program ended with a user key interrupt, or was cut off by the execution limits
 }
 
+#[derive(PartialEq, Debug)]
 pub enum ProgramError {
     TokenErr(TokenError),
     ExecErr(ProgExecutionError),
+    ScriptErr(Vec<CompileError>, String, String),  // the errors of the script, its file name and its source
+    BinaryErr(BinaryError, String),  // the error of the binary bytecode file and its file name
+}
+
+impl ProgramError {
+    // renders the error with the offending source line
+    pub fn render(&self) -> String {
+        match self {
+            ProgramError::TokenErr(err) => err.render(),
+            ProgramError::ExecErr(err) => err.render(),
+            ProgramError::ScriptErr(errs, fname, script) => cmd_script::render_all(errs, fname, script),
+            ProgramError::BinaryErr(err, fname) => err.render(fname),
+        }
+    }
 }
 
+#[derive(PartialEq, Debug)]
 pub enum ProgExecutionError {
+    // Popping more values than there are on the stack
+    StackUnderflow(Span),
+    // Pushing more values than the maximum stack depth
+    StackOverflow(Span),
+    // Calling deeper than the maximum call depth
+    CallDepthExceeded(Span),
+    // The values on the stack or the arguments are of the wrong types for the command
+    TypeMismatch(Span),
+    // Jumping to a marker that was never declared with `MRK`
+    UnknownMarker(Span, String),  // the marker name
+    // Declaring the same marker more than once
+    DuplicateMarker(Span, String),  // the marker name
+    // Using a static variable that was never declared with `STATIC`
+    UnknownStaticVar(Span, String),  // the static variable name
+    // Loading a local variable that was never stored with `STORE` in the current call
+    UnknownVariable(Span, String),  // the local variable name
+    // Dividing or taking the modulo of a value by zero
+    DivisionByZero(Span),
+    // The command is missing its arguments or has the wrong kind of arguments
+    InvalidArguments(Span),
+    // The command is valid but cannot be executed yet
+    UnsupportedCommand(Span),
+    // Using a namespace that was never declared with `NAMESPACE`
+    UndeclaredNamespace(Span, String),  // the namespace path
+    // Nothing is registered on the namespace path
+    UnknownNamespace(Span, String),  // the namespace path
+    // Writing to a namespace property that can only be read
+    ReadOnlyNamespace(Span, String),  // the namespace path
+    // The namespace command failed to execute
+    CommandFailed(Span, String),  // the reason of the failure
+}
 
+impl ProgExecutionError {
+    pub fn span(&self) -> &Span {
+        match self {
+            ProgExecutionError::StackUnderflow(span) |
+            ProgExecutionError::StackOverflow(span) |
+            ProgExecutionError::CallDepthExceeded(span) |
+            ProgExecutionError::TypeMismatch(span) |
+            ProgExecutionError::UnknownMarker(span, _) |
+            ProgExecutionError::DuplicateMarker(span, _) |
+            ProgExecutionError::UnknownStaticVar(span, _) |
+            ProgExecutionError::UnknownVariable(span, _) |
+            ProgExecutionError::DivisionByZero(span) |
+            ProgExecutionError::InvalidArguments(span) |
+            ProgExecutionError::UnsupportedCommand(span) |
+            ProgExecutionError::UndeclaredNamespace(span, _) |
+            ProgExecutionError::UnknownNamespace(span, _) |
+            ProgExecutionError::ReadOnlyNamespace(span, _) |
+            ProgExecutionError::CommandFailed(span, _) => span,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ProgExecutionError::StackUnderflow(_) => "not enough values on the stack".into(),
+            ProgExecutionError::StackOverflow(_) => "too many values on the stack".into(),
+            ProgExecutionError::CallDepthExceeded(_) => "too many nested calls".into(),
+            ProgExecutionError::TypeMismatch(_) => "mismatched types for the command".into(),
+            ProgExecutionError::UnknownMarker(_, name) => format!("unknown marker `#{}`", name),
+            ProgExecutionError::DuplicateMarker(_, name) => format!("marker `#{}` is declared more than once", name),
+            ProgExecutionError::UnknownStaticVar(_, name) => format!("unknown static variable `${}`", name),
+            ProgExecutionError::UnknownVariable(_, name) => format!("unknown local variable `${}`", name),
+            ProgExecutionError::DivisionByZero(_) => "division by zero".into(),
+            ProgExecutionError::InvalidArguments(_) => "invalid arguments for the command".into(),
+            ProgExecutionError::UnsupportedCommand(_) => "command is not supported yet".into(),
+            ProgExecutionError::UndeclaredNamespace(_, path) => format!("namespace `{}` is not declared with `NAMESPACE`", path),
+            ProgExecutionError::UnknownNamespace(_, path) => format!("unknown namespace `{}`", path),
+            ProgExecutionError::ReadOnlyNamespace(_, path) => format!("namespace `{}` can only be read", path),
+            ProgExecutionError::CommandFailed(_, reason) => format!("command failed: {}", reason),
+        }
+    }
+
+    // renders the error with the offending source line
+    pub fn render(&self) -> String {
+        self.span().render(&self.message())
+    }
+}
+
+// The console lines shared by all the programs
+#[derive(Default)]
+struct ProgramIO {
+    output: Vec<String>,  // console outputs from `COUT`
+    input: VecDeque<String>,  // console inputs waiting to be read by `CIN`
 }
 
 pub struct WorldCommandExecutor {
-    glob_nmspc: u8,
-    exec_tkn: Vec<Vec<Tokens>>,
+    tasks: VecDeque<Task>,  // loaded programs in the order of their next turn
+    task_counter: u32,
+    limits: ExecutionLimits,
+    io: ProgramIO,
+    debugger: Option<Debugger>,  // only exists while the debugging mode is enabled
+}
+
+impl Default for WorldCommandExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl WorldCommandExecutor {
     pub fn new() -> Self {
         Self {
-            glob_nmspc: 0,
-            exec_tkn: Vec::new(),
+            tasks: VecDeque::new(),
+            task_counter: 0,
+            limits: ExecutionLimits::default(),
+            io: ProgramIO::default(),
+            debugger: None,
+        }
+    }
+
+    // executes the loaded programs within the limits, taking turns in the order they were loaded
+    // the unfinished programs continue on the next update
+    // the namespaces binds the programs to the world for the duration of the update
+    pub fn update(&mut self, nmspc: &mut NamespaceRegistry) -> Vec<(TaskID, CommandProgRes)> {
+        if self.debugger.is_some() {
+            return self.debug_update(nmspc);
+        }
+
+        let mut budget = self.limits.tick_budget;
+        let mut results = Vec::new();
+        let mut skipped = 0;  // blocked tasks skipped in a row; all of them are blocked once it reaches the task count
+
+        while budget > 0 && skipped < self.tasks.len() {
+            let mut task = self.tasks.pop_front().expect("There are tasks left");
+            if task.blocked(&self.io) {
+                self.tasks.push_back(task);
+                skipped += 1;
+                continue;
+            }
+            skipped = 0;
+
+            let slice = self.limits.time_slice.max(1).min(budget);
+            let (executed, res) = task.run_slice(nmspc, &mut self.io, &self.limits, slice);
+            budget -= executed.min(budget);
+
+            match res {
+                Some(res) => results.push((task.id, res)),
+                None => self.tasks.push_back(task),
+            }
+        }
+
+        results
+    }
+
+    pub fn limits(&self) -> &ExecutionLimits {
+        &self.limits
+    }
+
+    pub fn set_limits(&mut self, limits: ExecutionLimits) {
+        for task in self.tasks.iter_mut() {
+            task.prog.set_limits(&limits);
+        }
+        self.limits = limits;
+    }
+
+    // the loaded programs that have not ended yet
+    pub fn tasks(&self) -> Vec<TaskID> {
+        self.tasks.iter().map(|task| task.id).collect()
+    }
+
+    // pauses the task until it is resumed; returns false if there is no such task
+    pub fn suspend_task(&mut self, id: TaskID) -> bool {
+        self.set_suspended(id, true)
+    }
+
+    pub fn resume_task(&mut self, id: TaskID) -> bool {
+        self.set_suspended(id, false)
+    }
+
+    // ends the task without executing the rest of the program
+    pub fn interrupt_task(&mut self, id: TaskID) -> Option<CommandProgRes> {
+        let ind = self.tasks.iter().position(|task| task.id == id)?;
+        self.tasks.remove(ind);
+        Some(Ok(ProgramSuccess::Interrupt))
+    }
+
+    fn set_suspended(&mut self, id: TaskID, suspended: bool) -> bool {
+        match self.tasks.iter_mut().find(|task| task.id == id) {
+            Some(task) => {
+                task.suspended = suspended;
+                true
+            },
+            None => false,
         }
     }
 
-    pub fn update(&mut self) {
+    // enables the debugging mode; the programs are paused on the breakpoints of the debugger
+    pub fn enable_debugger(&mut self) -> &mut Debugger {
+        self.debugger.get_or_insert_with(Debugger::new)
+    }
 
+    // disables the debugging mode; the paused program continues on the next update
+    pub fn disable_debugger(&mut self) {
+        self.debugger = None;
     }
 
-    // directly adds the script commands tokens to the executor tokens
-    pub fn load_commands(&mut self) {
+    pub fn debugger(&mut self) -> Option<&mut Debugger> {
+        self.debugger.as_mut()
+    }
 
+    // the stack of the current program
+    pub fn stack(&self) -> Option<&[StackType]> {
+        self.tasks.front().map(|task| task.prog.stack())
     }
 
-    // directly adds the script files tokens to the executor tokens
-    pub fn load_file(&mut self) {
+    // the index and the span of the next instruction of the current program
+    pub fn location(&self) -> Option<(usize, Span)> {
+        let prog = &self.tasks.front()?.prog;
+        prog.line(prog.pc()).map(|(_, span)| (prog.pc(), span))
+    }
 
+    // executes a single instruction of the current program
+    pub fn step(&mut self, nmspc: &mut NamespaceRegistry) -> DebugStatus {
+        self.debug_advance(nmspc, true)
     }
 
-    // directly adds the bytecode command tokens to the executor tokens
-    pub fn load_commands_bytc(&mut self, char_stream: Vec<char>) {
+    // continues the current program until it reaches a breakpoint, ends or runs out of the budget of a single update
+    pub fn resume(&mut self, nmspc: &mut NamespaceRegistry) -> DebugStatus {
+        self.debug_advance(nmspc, false)
+    }
+
+    // runs the programs until one of them is paused; a paused program is only advanced through the debugger
+    fn debug_update(&mut self, nmspc: &mut NamespaceRegistry) -> Vec<(TaskID, CommandProgRes)> {
+        let mut results = Vec::new();
+
+        loop {
+            match &self.debugger {
+                Some(dbg) if !dbg.paused => {},
+                _ => break,
+            }
+            if !self.rotate_to_runnable() {
+                break;
+            }
+
+            let (dbg, task) = match (&mut self.debugger, self.tasks.front()) {
+                (Some(dbg), Some(task)) => (dbg, task),
+                _ => break,
+            };
+            // a breakpoint on the very first instruction
+            if task.prog.pc() == 0 && dbg.hits(&task.prog) {
+                dbg.paused = true;
+                break;
+            }
+
+            let id = task.id;
+            match self.resume(nmspc) {
+                DebugStatus::Finished(res) => results.push((id, res)),
+                _ => break,
+            }
+        }
 
+        results
+    }
+
+    fn debug_advance(&mut self, nmspc: &mut NamespaceRegistry, single_step: bool) -> DebugStatus {
+        let paused = match &self.debugger {
+            Some(dbg) if !self.tasks.is_empty() => dbg.paused,
+            _ => return DebugStatus::Idle,
+        };
+        // the paused program keeps the focus of the debugger; otherwise the blocked programs give their turn away
+        let runnable = if paused {
+            !self.tasks[0].blocked(&self.io)
+        } else {
+            self.rotate_to_runnable()
+        };
+        if !runnable {
+            return DebugStatus::Yielded;
+        }
+
+        let (dbg, task) = match (&mut self.debugger, self.tasks.front_mut()) {
+            (Some(dbg), Some(task)) => (dbg, task),
+            _ => return DebugStatus::Idle,
+        };
+
+        let mut budget = self.limits.tick_budget;
+        let res = loop {
+            let index = task.prog.pc();
+            let stack_before = task.prog.stack().to_vec();
+            let (executed, res) = task.run_slice(nmspc, &mut self.io, &self.limits, 1);
+            if executed > 0 {
+                dbg.record(index, &task.prog, stack_before);
+            }
+
+            match res {
+                None => {
+                    if single_step || dbg.hits(&task.prog) {
+                        dbg.paused = true;
+                        let (_, span) = task.prog.line(task.prog.pc()).expect("The paused instruction is within the program");
+                        return DebugStatus::Paused(task.prog.pc(), span);
+                    }
+
+                    budget = budget.saturating_sub(1);
+                    if budget == 0 {
+                        return DebugStatus::Yielded;
+                    }
+                },
+                Some(res) => break res,
+            }
+        };
+
+        dbg.paused = false;
+        self.tasks.pop_front();
+        DebugStatus::Finished(res)
+    }
+
+    // moves the blocked tasks behind the others, as they are on their turns in `update`
+    // returns false if all the tasks are blocked
+    fn rotate_to_runnable(&mut self) -> bool {
+        for _ in 0..self.tasks.len() {
+            if !self.tasks[0].blocked(&self.io) {
+                return true;
+            }
+            self.tasks.rotate_left(1);
+        }
+        false
+    }
+
+    // all the console outputs from the executed programs
+    pub fn output(&self) -> &Vec<String> {
+        &self.io.output
+    }
+
+    // queues a line of input to be read by the next `CIN`
+    pub fn send_input(&mut self, line: String) {
+        self.io.input.push_back(line);
+    }
+
+    // checks if any of the running programs is waiting for an input
+    pub fn awaiting_input(&self) -> bool {
+        self.tasks.iter().any(|task| !task.suspended && task.prog.awaiting_input()) && self.io.input.is_empty()
+    }
+
+    // compiles the script commands down to the bytecode, then adds its tokens to the executor tokens
+    pub fn load_commands(&mut self, script: String) -> Result<TaskID, ProgramError> {
+        match cmd_script::compile(&script) {
+            Ok(bytc) => self.load_commands_bytc(bytc.chars().collect()),
+            Err(errs) => Err(ProgramError::ScriptErr(errs, "<command>".into(), script)),
+        }
+    }
+
+    // compiles the script file down to the bytecode, then adds its tokens to the executor tokens
+    pub fn load_file(&mut self, fname: String) -> Result<TaskID, ProgramError> {
+        let script = fs::read_to_string(&fname)
+            .map_err(|err| ProgramError::TokenErr(TokenError::FileUnreadable(Span::file(&fname), err.to_string())))?;
+
+        match cmd_script::compile(&script) {
+            Ok(bytc) => self.load_commands_bytc(bytc.chars().collect()),
+            Err(errs) => Err(ProgramError::ScriptErr(errs, fname, script)),
+        }
+    }
+
+    // directly adds the bytecode command tokens to the executor tokens (e.g. the lines typed into the console)
+    pub fn load_commands_bytc(&mut self, char_stream: Vec<char>) -> Result<TaskID, ProgramError> {
+        let tokens = linker::link_command(char_stream).map_err(ProgramError::TokenErr)?;
+        self.spawn(tokens)
     }
 
     // directly adds the bytecode file tokens to the executor tokens
-    pub fn load_file_bytc(&mut self, fname: String) {
-        match bytecode::compile_file(fname) {
-            Ok(mut tokens) => self.exec_tkn.append(&mut tokens),
-            Err(err) => println!("Loading bytecode file error: {:?}", err),
+    pub fn load_file_bytc(&mut self, fname: String) -> Result<TaskID, ProgramError> {
+        let tokens = linker::link_file(fname).map_err(ProgramError::TokenErr)?;
+        self.spawn(tokens)
+    }
+
+    // adds the pre-compiled binary bytecode file tokens to the executor tokens
+    pub fn load_file_bin(&mut self, fname: String) -> Result<TaskID, ProgramError> {
+        match binary::load_file(fname.clone()) {
+            Ok(tokens) => self.spawn(tokens),
+            Err(err) => Err(ProgramError::BinaryErr(err, fname)),
         }
     }
+
+    fn spawn(&mut self, tokens: CompiledTokens) -> Result<TaskID, ProgramError> {
+        let mut prog = Program::new(tokens).map_err(ProgramError::ExecErr)?;
+        prog.set_limits(&self.limits);
+        self.task_counter += 1;
+        let id = TaskID(self.task_counter);
+        self.tasks.push_back(Task::new(id, prog));
+        Ok(id)
+    }
 }
@@ -0,0 +1,292 @@
+/*
+The World Command Namespaces
+
+Binds the namespace paths (e.g. `:Matrixagon:world:player:main:pos:z`) of the command bytecode to the
+live game states. Each subsystem implements `Namespace` and gets registered under a path, while the
+commands (e.g. `:MTXG-CMD:Teleport`) are registered as functions with the number of values they take.
+ */
+
+use crate::world::commands::bytecode::StackType;
+
+use std::collections::HashMap;
+
+
+pub type NmspcRes<T> = Result<T, NamespaceError>;
+
+// a command callable through `CMD_COPY` and `CMD_MOVE`; the arguments are ordered from the bottom of the stack to the top
+pub type NmspcCommand = fn(&mut NamespaceRegistry<'_>, Vec<StackType>) -> NmspcRes<Vec<StackType>>;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum NamespaceError {
+    Unknown,  // nothing is registered on the path
+    ReadOnly,  // the property cannot be written
+    TypeMismatch,  // the value is of the wrong type for the property or command
+    Failed(String),  // the command could not be executed; with the reason
+}
+
+impl NamespaceError {
+    pub fn message(&self) -> String {
+        match self {
+            NamespaceError::Unknown => "unknown namespace".into(),
+            NamespaceError::ReadOnly => "namespace can only be read".into(),
+            NamespaceError::TypeMismatch => "mismatched types for the namespace".into(),
+            NamespaceError::Failed(reason) => reason.clone(),
+        }
+    }
+}
+
+// all subsystems accessible through the namespaces implements this trait
+// the paths are relative to where the subsystem was registered
+pub trait Namespace {
+    // names of all the properties and namespaces directly under the path
+    fn entries(&self, path: &[String]) -> Vec<String>;
+
+    // reads the value of the property
+    fn get(&self, path: &[String]) -> NmspcRes<StackType>;
+
+    // writes the value to the property
+    fn set(&mut self, _path: &[String], _val: StackType) -> NmspcRes<()> {
+        Err(NamespaceError::ReadOnly)
+    }
+
+    // calls a subsystem specific function; used by the commands to reach the subsystems
+    fn call(&mut self, _path: &[String], _args: Vec<StackType>) -> NmspcRes<Vec<StackType>> {
+        Err(NamespaceError::Unknown)
+    }
+}
+
+enum NmspcRef<'a> {
+    Read(&'a dyn Namespace),
+    Write(&'a mut dyn Namespace),
+}
+
+// The namespaces of the world borrowed for the duration of executing the command programs
+pub struct NamespaceRegistry<'a> {
+    namespaces: Vec<(Vec<String>, NmspcRef<'a>)>,
+    commands: HashMap<Vec<String>, (usize, NmspcCommand)>,  // command path to its number of arguments and function
+}
+
+impl<'a> Default for NamespaceRegistry<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> NamespaceRegistry<'a> {
+    pub fn new() -> Self {
+        Self {
+            namespaces: Vec::new(),
+            commands: HashMap::new(),
+        }
+    }
+
+    // registers a subsystem with readable and writable properties
+    pub fn register(&mut self, path: &str, nmspc: &'a mut dyn Namespace) {
+        self.namespaces.push((split_path(path), NmspcRef::Write(nmspc)));
+    }
+
+    // registers a subsystem with only readable properties
+    pub fn register_read(&mut self, path: &str, nmspc: &'a dyn Namespace) {
+        self.namespaces.push((split_path(path), NmspcRef::Read(nmspc)));
+    }
+
+    // registers a command taking `arity` values from the stack
+    pub fn register_command(&mut self, path: &str, arity: usize, cmd: NmspcCommand) {
+        self.commands.insert(split_path(path), (arity, cmd));
+    }
+
+    // the command registered on the path with its number of arguments
+    pub fn command(&self, path: &[String]) -> Option<(usize, NmspcCommand)> {
+        self.commands.get(path).copied()
+    }
+
+    // checks if the path is a namespace itself rather than a property (e.g. `:Matrixagon:world:player:main`)
+    pub fn is_namespace(&self, path: &[String]) -> bool {
+        let within_subsystem = match self.resolve(path) {
+            Some((ind, rel)) => {
+                match &self.namespaces[ind].1 {
+                    NmspcRef::Read(nmspc) => !nmspc.entries(rel).is_empty(),
+                    NmspcRef::Write(nmspc) => !nmspc.entries(rel).is_empty(),
+                }
+            },
+            None => false,
+        };
+
+        within_subsystem ||
+            self.namespaces.iter().any(|(nmspc, _)| nmspc.starts_with(path)) ||
+            self.commands.keys().any(|cmd| cmd.len() > path.len() && cmd.starts_with(path))
+    }
+
+    pub fn get(&self, path: &[String]) -> NmspcRes<StackType> {
+        match self.resolve(path) {
+            Some((ind, rel)) => {
+                match &self.namespaces[ind].1 {
+                    NmspcRef::Read(nmspc) => nmspc.get(rel),
+                    NmspcRef::Write(nmspc) => nmspc.get(rel),
+                }
+            },
+            None => Err(NamespaceError::Unknown),
+        }
+    }
+
+    pub fn set(&mut self, path: &[String], val: StackType) -> NmspcRes<()> {
+        match self.resolve(path) {
+            Some((ind, rel)) => {
+                match &mut self.namespaces[ind].1 {
+                    NmspcRef::Read(_) => Err(NamespaceError::ReadOnly),
+                    NmspcRef::Write(nmspc) => nmspc.set(rel, val),
+                }
+            },
+            None => Err(NamespaceError::Unknown),
+        }
+    }
+
+    pub fn call(&mut self, path: &[String], args: Vec<StackType>) -> NmspcRes<Vec<StackType>> {
+        match self.resolve(path) {
+            Some((ind, rel)) => {
+                match &mut self.namespaces[ind].1 {
+                    NmspcRef::Read(_) => Err(NamespaceError::ReadOnly),
+                    NmspcRef::Write(nmspc) => nmspc.call(rel, args),
+                }
+            },
+            None => Err(NamespaceError::Unknown),
+        }
+    }
+
+    // names of all the namespaces, properties and commands directly under the path
+    pub fn entries(&self, path: &[String]) -> Vec<String> {
+        let mut entries = Vec::new();
+
+        let registered = self.namespaces.iter().map(|(nmspc, _)| nmspc)
+            .chain(self.commands.keys());
+        for nmspc in registered {
+            if nmspc.len() > path.len() && nmspc.starts_with(path) {
+                entries.push(nmspc[path.len()].clone());
+            }
+        }
+        if let Some((ind, rel)) = self.resolve(path) {
+            entries.append(&mut match &self.namespaces[ind].1 {
+                NmspcRef::Read(nmspc) => nmspc.entries(rel),
+                NmspcRef::Write(nmspc) => nmspc.entries(rel),
+            });
+        }
+
+        entries.sort();
+        entries.dedup();
+        entries
+    }
+
+    // finds the most specific namespace containing the path, with the path relative to that namespace
+    fn resolve<'p>(&self, path: &'p [String]) -> Option<(usize, &'p [String])> {
+        self.namespaces.iter().enumerate()
+            .filter(|(_, (nmspc, _))| path.starts_with(nmspc))
+            .max_by_key(|(_, (nmspc, _))| nmspc.len())
+            .map(|(ind, (nmspc, _))| (ind, &path[nmspc.len()..]))
+    }
+}
+
+// splits the namespace path delimited by ':' into each of its names
+pub fn split_path(path: &str) -> Vec<String> {
+    path.trim_start_matches(':').split(':').map(String::from).collect()
+}
+
+// joins the names back into a namespace path
+pub fn join_path(path: &[String]) -> String {
+    format!(":{}", path.join(":"))
+}
+
+// helper for the namespaces to match on the names of the path
+pub fn path_str(path: &[String]) -> Vec<&str> {
+    path.iter().map(String::as_str).collect()
+}
+
+// helper for the namespaces to read numeric values
+pub fn number(val: &StackType) -> NmspcRes<f64> {
+    match val {
+        StackType::Int(i) => Ok(*i as f64),
+        StackType::Float(f) => Ok(*f),
+        _ => Err(NamespaceError::TypeMismatch),
+    }
+}
+
+// helper for the namespaces to list the entries of a fixed namespace tree
+pub fn names(list: &[&str]) -> Vec<String> {
+    list.iter().map(|name| String::from(*name)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // reads back which namespace the path was resolved to, along with the relative path
+    struct Echo(&'static str);
+
+    impl Namespace for Echo {
+        fn entries(&self, path: &[String]) -> Vec<String> {
+            match path_str(path).as_slice() {
+                [] => names(&["value"]),
+                _ => Vec::new(),
+            }
+        }
+
+        fn get(&self, path: &[String]) -> NmspcRes<StackType> {
+            Ok(StackType::Str(format!("{}{}", self.0, join_path(path))))
+        }
+
+        fn set(&mut self, _path: &[String], _val: StackType) -> NmspcRes<()> {
+            Ok(())
+        }
+    }
+
+    fn get(nmspc: &NamespaceRegistry, path: &str) -> NmspcRes<StackType> {
+        nmspc.get(&split_path(path))
+    }
+
+    #[test]
+    fn resolves_the_longest_prefix() {
+        let (mut world, mut player) = (Echo("world"), Echo("player"));
+        let mut nmspc = NamespaceRegistry::new();
+        nmspc.register("Matrixagon:world", &mut world);
+        nmspc.register("Matrixagon:world:player", &mut player);
+
+        assert_eq!(get(&nmspc, ":Matrixagon:world:player:main:pos"), Ok(StackType::Str("player:main:pos".into())));
+        assert_eq!(get(&nmspc, ":Matrixagon:world:chunks:loaded"), Ok(StackType::Str("world:chunks:loaded".into())));
+        // the prefix is matched by whole names
+        assert_eq!(get(&nmspc, ":Matrixagon:world:players"), Ok(StackType::Str("world:players".into())));
+        assert_eq!(get(&nmspc, ":Matrixagon:world"), Ok(StackType::Str("world:".into())));
+
+        assert_eq!(nmspc.entries(&split_path(":Matrixagon:world")), names(&["player", "value"]));
+        assert!(nmspc.is_namespace(&split_path(":Matrixagon")));
+    }
+
+    #[test]
+    fn rejects_writing_read_only_namespaces() {
+        let (mut world, player) = (Echo("world"), Echo("player"));
+        let mut nmspc = NamespaceRegistry::new();
+        nmspc.register("Matrixagon:world", &mut world);
+        nmspc.register_read("Matrixagon:world:player", &player);
+
+        let path = split_path(":Matrixagon:world:player:main:pos:x");
+        assert_eq!(nmspc.get(&path), Ok(StackType::Str("player:main:pos:x".into())));
+        assert_eq!(nmspc.set(&path, StackType::Int(1)), Err(NamespaceError::ReadOnly));
+        assert_eq!(nmspc.call(&path, Vec::new()), Err(NamespaceError::ReadOnly));
+        // the writable namespace around it is still writable
+        assert_eq!(nmspc.set(&split_path(":Matrixagon:world:seed"), StackType::Int(1)), Ok(()));
+    }
+
+    #[test]
+    fn rejects_unknown_paths() {
+        let mut world = Echo("world");
+        let mut nmspc = NamespaceRegistry::new();
+        nmspc.register("Matrixagon:world", &mut world);
+
+        for path in [":Matrixagon", ":Matrixagon:worlds", ":MTXG-CMD:Teleport"].iter() {
+            let path = split_path(path);
+            assert_eq!(nmspc.get(&path), Err(NamespaceError::Unknown));
+            assert_eq!(nmspc.set(&path, StackType::Int(1)), Err(NamespaceError::Unknown));
+            assert_eq!(nmspc.call(&path, Vec::new()), Err(NamespaceError::Unknown));
+        }
+        assert!(nmspc.command(&split_path(":MTXG-CMD:Teleport")).is_none());
+        assert!(!nmspc.is_namespace(&split_path(":Matrixagon:worlds")));
+    }
+}
@@ -0,0 +1,257 @@
+/*
+The World Command Scheduler
+
+The loaded programs run as resumable tasks, so a long running (or never ending) program cannot freeze
+the world loop. Each update only executes a limited number of instructions, handed out to the tasks
+in a round-robin of fixed time slices; the unfinished tasks continue on the next update.
+ */
+
+use crate::world::commands::interpreter::Program;
+use crate::world::commands::namespace::NamespaceRegistry;
+use crate::world::commands::{CommandProgRes, ProgramSuccess, ProgramError, ProgramIO};
+
+use std::time::{Duration, Instant};
+
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ExecutionLimits {
+    pub tick_budget: usize,  // the most instructions executed by all the tasks in a single update
+    pub time_slice: usize,  // the instructions a task executes in its turn before the next task
+    pub max_stack: usize,  // the most values a program can hold on its stack
+    pub max_call_depth: usize,  // the most calls with `CALL` a program can be in at once
+    // the most instructions a program can execute; the program then ends with `ProgramSuccess::Interrupt`
+    pub max_instructions: Option<usize>,
+    // the longest (wall clock) time a program can spend executing, over all the updates;
+    // the program then ends with `ProgramSuccess::Interrupt` as with `max_instructions`
+    pub wall_clock: Option<Duration>,
+}
+
+impl Default for ExecutionLimits {
+    fn default() -> Self {
+        Self {
+            tick_budget: 10_000,
+            time_slice: 100,
+            max_stack: 4096,
+            max_call_depth: 256,
+            max_instructions: None,
+            wall_clock: None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct TaskID(pub u32);
+
+// A loaded program along with its scheduling state
+pub(super) struct Task {
+    pub(super) id: TaskID,
+    pub(super) prog: Program,
+    pub(super) suspended: bool,  // suspended tasks are skipped until they are resumed
+    executed: usize,  // the number of instructions executed so far
+    elapsed: Duration,  // the time spent executing so far
+}
+
+impl Task {
+    pub(super) fn new(id: TaskID, prog: Program) -> Self {
+        Self {
+            id,
+            prog,
+            suspended: false,
+            executed: 0,
+            elapsed: Duration::from_secs(0),
+        }
+    }
+
+    // checks if the task cannot run on its turn; either it is suspended or it is waiting for an input
+    pub(super) fn blocked(&self, io: &ProgramIO) -> bool {
+        self.suspended || (self.prog.awaiting_input() && io.input.is_empty())
+    }
+
+    // executes up to `count` instructions of the program, ending early when it waits for an input
+    // returns the number of instructions executed, and the result once the program has ended
+    pub(super) fn run_slice(&mut self, nmspc: &mut NamespaceRegistry, io: &mut ProgramIO,
+                            limits: &ExecutionLimits, count: usize) -> (usize, Option<CommandProgRes>) {
+        let start = Instant::now();
+        let res = self.execute(nmspc, io, limits, count, start);
+        self.elapsed += start.elapsed();
+        res
+    }
+
+    fn execute(&mut self, nmspc: &mut NamespaceRegistry, io: &mut ProgramIO,
+               limits: &ExecutionLimits, count: usize, start: Instant) -> (usize, Option<CommandProgRes>) {
+        for ind in 0..count {
+            if self.prog.awaiting_input() && io.input.is_empty() {
+                return (ind, None);
+            }
+            // checked on every instruction, so a single slice cannot run past the cap
+            if let Some(cap) = limits.wall_clock {
+                if self.elapsed + start.elapsed() >= cap {
+                    return (ind, Some(Ok(ProgramSuccess::Interrupt)));
+                }
+            }
+            if let Some(max) = limits.max_instructions {
+                if self.executed >= max {
+                    return (ind, Some(Ok(ProgramSuccess::Interrupt)));
+                }
+            }
+
+            self.executed += 1;
+            match self.prog.advance(nmspc, io) {
+                Ok(None) => {},
+                Ok(Some(success)) => return (ind+1, Some(Ok(success))),
+                Err(err) => return (ind+1, Some(Err(ProgramError::ExecErr(err)))),
+            }
+        }
+
+        (count, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::commands::{WorldCommandExecutor, ProgExecutionError};
+    use crate::world::commands::bytecode::StackType;
+
+    // counts up forever
+    const FOREVER: &str = "PUSH 0\nMRK #loop\nADDI 1\nJMP #loop\n";
+
+    fn load(exec: &mut WorldCommandExecutor, src: &str) -> TaskID {
+        exec.load_commands_bytc(src.chars().collect()).expect("The test source is valid")
+    }
+
+    #[test]
+    fn endless_program_yields_each_update() {
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = WorldCommandExecutor::new();
+        exec.set_limits(ExecutionLimits { tick_budget: 50, ..Default::default() });
+        let id = load(&mut exec, FOREVER);
+
+        for _ in 0..3 {
+            assert!(exec.update(&mut nmspc).is_empty());
+        }
+        assert_eq!(exec.tasks(), vec![id]);
+        assert_eq!(exec.interrupt_task(id), Some(Ok(ProgramSuccess::Interrupt)));
+        assert!(exec.tasks().is_empty());
+    }
+
+    #[test]
+    fn interrupts_after_max_instructions() {
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = WorldCommandExecutor::new();
+        exec.set_limits(ExecutionLimits { max_instructions: Some(1000), ..Default::default() });
+        let id = load(&mut exec, FOREVER);
+
+        assert_eq!(exec.update(&mut nmspc), vec![(id, Ok(ProgramSuccess::Interrupt))]);
+    }
+
+    #[test]
+    fn interrupts_at_the_wall_clock() {
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = WorldCommandExecutor::new();
+        // a single slice would never end without the wall clock
+        exec.set_limits(ExecutionLimits {
+            tick_budget: usize::MAX,
+            time_slice: usize::MAX,
+            wall_clock: Some(Duration::from_millis(5)),
+            ..Default::default()
+        });
+        let id = load(&mut exec, FOREVER);
+
+        let start = Instant::now();
+        assert_eq!(exec.update(&mut nmspc), vec![(id, Ok(ProgramSuccess::Interrupt))]);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(exec.tasks().is_empty());
+    }
+
+    #[test]
+    fn counts_the_wall_clock_over_updates() {
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = WorldCommandExecutor::new();
+        exec.set_limits(ExecutionLimits { tick_budget: 100, wall_clock: Some(Duration::from_millis(5)), ..Default::default() });
+        let id = load(&mut exec, FOREVER);
+
+        let mut updates = 1;
+        let mut results = exec.update(&mut nmspc);
+        while results.is_empty() {
+            updates += 1;
+            results = exec.update(&mut nmspc);
+        }
+        assert!(updates > 1);
+        assert_eq!(results, vec![(id, Ok(ProgramSuccess::Interrupt))]);
+    }
+
+    #[test]
+    fn round_robin_is_fair() {
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = WorldCommandExecutor::new();
+        exec.set_limits(ExecutionLimits { tick_budget: 600, time_slice: 10, ..Default::default() });
+        let first = load(&mut exec, FOREVER);
+        let second = load(&mut exec, FOREVER);
+        exec.update(&mut nmspc);
+
+        // both programs executed the same number of instructions
+        let counts = exec.tasks.iter().map(|task| task.executed).collect::<Vec<_>>();
+        assert_eq!(counts, vec![300, 300]);
+
+        // suspended tasks give their turns to the others
+        exec.suspend_task(first);
+        exec.update(&mut nmspc);
+        let counts = exec.tasks.iter().map(|task| (task.id, task.executed)).collect::<Vec<_>>();
+        assert!(counts.contains(&(first, 300)));
+        assert!(counts.contains(&(second, 900)));
+
+        // everything is suspended
+        exec.suspend_task(second);
+        assert!(exec.update(&mut nmspc).is_empty());
+        assert!(exec.resume_task(first));
+    }
+
+    #[test]
+    fn limits_stack_depth() {
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = WorldCommandExecutor::new();
+        exec.set_limits(ExecutionLimits { max_stack: 8, ..Default::default() });
+        let id = load(&mut exec, "MRK #loop\nPUSH 1\nJMP #loop\n");
+
+        match exec.update(&mut nmspc).as_slice() {
+            [(res_id, Err(ProgramError::ExecErr(ProgExecutionError::StackOverflow(span))))] => {
+                assert_eq!(*res_id, id);
+                assert_eq!(span.line, 2);
+            },
+            res => panic!("unexpected results {:?}", res),
+        }
+    }
+
+    #[test]
+    fn limits_call_depth() {
+        // calls itself until the counter reaches zero, four calls deep
+        const COUNTDOWN: &str = "PUSH 4\nCALL #down\nRET\nMRK #down\nSUBI 1\nJMP_IF #deeper\nRET\nMRK #deeper\nCALL #down\nRET\n";
+
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = WorldCommandExecutor::new();
+        exec.set_limits(ExecutionLimits { max_call_depth: 4, ..Default::default() });
+        let id = load(&mut exec, COUNTDOWN);
+        assert_eq!(exec.update(&mut nmspc), vec![(id, Ok(ProgramSuccess::Success))]);
+
+        exec.set_limits(ExecutionLimits { max_call_depth: 3, ..Default::default() });
+        let id = load(&mut exec, COUNTDOWN);
+        match exec.update(&mut nmspc).as_slice() {
+            [(res_id, Err(ProgramError::ExecErr(ProgExecutionError::CallDepthExceeded(span))))] => {
+                assert_eq!(*res_id, id);
+                assert_eq!(span.line, 9);
+            },
+            res => panic!("unexpected results {:?}", res),
+        }
+    }
+
+    #[test]
+    fn finished_programs_report_success() {
+        let mut nmspc = NamespaceRegistry::new();
+        let mut exec = WorldCommandExecutor::new();
+        let id = load(&mut exec, "PUSH 1\nADDI 2\nCOUT\n");
+
+        assert_eq!(exec.update(&mut nmspc), vec![(id, Ok(ProgramSuccess::Success))]);
+        assert_eq!(exec.output(), &vec![StackType::Int(3).to_string()]);
+    }
+}
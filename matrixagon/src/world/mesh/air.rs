@@ -2,6 +2,12 @@
 pub struct Air {
 }
 
+impl Default for Air {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Air {
     pub fn new() -> Self {
         Self {}
@@ -1,10 +1,12 @@
 use crate::datatype::{Dimension, Position, ChunkUnit, BlockUnit};
-use crate::world::mesh::{Mesh, MeshType, MeshDataTypeFull};
+use crate::world::mesh::{Mesh, ChunkMesh, MeshType, MeshDataTypeFull};
 use crate::world::chunk::{Chunk, CHUNK_SIZE};
-use crate::world::shader::{CubeVert, cube_vs, cube_fs, IndexType};
+use crate::world::chunk_map::{ChunkMap, to_chunk_pos};
+use crate::world::shader::{CubeVert, cube_vs, cube_fs};
 use crate::world::ChunkID;
 use crate::world::block::Block;
 use crate::world::chunk_threadpool::{ChunkThreadPool, ThreadPoolOutput};
+use crate::world::mesh::MeshKind;
 use crate::world::player::camera::Camera;
 
 use vulkano::pipeline::viewport::Viewport;
@@ -12,17 +14,14 @@ use vulkano::framebuffer::{Subpass, RenderPassAbstract};
 use vulkano::device::Device;
 use vulkano::pipeline::{GraphicsPipelineAbstract, GraphicsPipeline};
 use vulkano::command_buffer::DynamicState;
-use vulkano::buffer::{CpuAccessibleBuffer, BufferUsage, CpuBufferPool};
+use vulkano::buffer::{BufferUsage, CpuBufferPool};
 use vulkano::sampler::{Sampler, SamplerAddressMode, Filter, MipmapMode};
 use vulkano::descriptor::descriptor_set::{PersistentDescriptorSet};
-use vulkano::descriptor::DescriptorSet;
-use vulkano::buffer::cpu_pool::{CpuBufferPoolSubbuffer, CpuBufferPoolChunk};
+use vulkano::buffer::cpu_pool::CpuBufferPoolSubbuffer;
 use vulkano::memory::pool::StdMemoryPool;
 use vulkano::image::ImmutableImage;
 use vulkano::format::Format;
 
-use rayon::prelude::*;
-
 use std::sync::Arc;
 use std::iter;
 
@@ -40,8 +39,8 @@ pub enum Side {
 // the cube mesh will only re-render the render data when the is update
 pub struct Cube {
     textures: Arc<ImmutableImage<Format>>,
-    // chunks: Chunk Reference, New Chunk, Chunk Culling, Chunk Vertices, Chunk Indices
-    chunks: Vec<(ChunkID, bool, bool, Vec<<Self as Mesh>::Vertex>, Vec<<Self as Mesh>::Index>)>,
+    // chunks: Chunk Reference, Dirty Chunk (new or edited), Chunk Culling, Chunk Vertices, Chunk Indices
+    chunks: Vec<ChunkMesh<<Self as Mesh>::Vertex, <Self as Mesh>::Index>>,
     grph_pipe: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
     dimensions: Dimension<u32>,  // current window dimensions
 
@@ -73,9 +72,9 @@ impl Cube {
             chunks: Vec::new(),
             grph_pipe: Cube::pipeline(
                 &vs, &fs, device.clone(),
-                render_pass.clone(), dimensions.into(),
+                render_pass.clone(), dimensions,
             ),
-            dimensions: dimensions,
+            dimensions,
 
             vert_shd: vs,
             frag_shd: fs,
@@ -101,8 +100,8 @@ impl Cube {
         };
         println!("CB-VB Resv: {:?}", s.vrtx_buf.capacity());
         println!("CB-IB Resv: {:?}", s.vrtx_buf.capacity());
-        s.vrtx_buf.reserve(4);
-        s.indx_buf.reserve(4);
+        s.vrtx_buf.reserve(4).unwrap();
+        s.indx_buf.reserve(4).unwrap();
         println!("CB-VB Resv Aft: {:?}", s.vrtx_buf.capacity());
         println!("CB-IB Resv Aft: {:?}", s.vrtx_buf.capacity());
         s.updt_world(Some(dimensions), Some(cam));
@@ -142,17 +141,9 @@ impl Cube {
         )
     }
 
-    fn mesh_data(chunk_list: Vec<(ChunkID, bool, bool, Vec<CubeVert>, Vec<u32>)>, chunks: Arc<Vec<Chunk>>, chunk: Chunk) -> ThreadPoolOutput {
-        let find_chunk = |cid: ChunkID| -> Option<&Chunk> {
-            // don't parallelize this iterator: as this closure gets executed few thousands time,
-            // the overhead of even threadpool can largely affect negatively
-            if let Some(ind) = chunks.iter().position(|x| x.id == cid) {
-                Some(&chunks[ind])
-            } else {
-                None
-            }
-        };
-
+    // the zeros are kept to line up the texture corners and the index offsets of the faces
+    #[allow(clippy::identity_op)]
+    fn mesh_data(chunks: Arc<ChunkMap>, chunk: Arc<Chunk>) -> ThreadPoolOutput {
         let start = Position::new(
             chunk.position.x.into_block(),
             chunk.position.y.into_block(),
@@ -175,51 +166,13 @@ impl Cube {
         //  ^- ELSE: add the vertices and indices to the vector
         //  ^- CASE: when there are no adjacent chunks: do not add the vertices and indices
 
-        // listing out all the theoretical adjacent chunks this Chunk has
-        let adjc_chunks: [Position<ChunkUnit>; 6] = [
-            Position::new(chunk.position.x+ChunkUnit(1.0), chunk.position.y  , chunk.position.z  ),  // LEFT
-            Position::new(chunk.position.x-ChunkUnit(1.0), chunk.position.y  , chunk.position.z  ),  // RIGHT
-            Position::new(chunk.position.x  , chunk.position.y+ChunkUnit(1.0), chunk.position.z  ),  // UP
-            Position::new(chunk.position.x  , chunk.position.y-ChunkUnit(1.0), chunk.position.z  ),  // DOWN
-            Position::new(chunk.position.x  , chunk.position.y  , chunk.position.z+ChunkUnit(1.0)),  // BACK
-            Position::new(chunk.position.x  , chunk.position.y  , chunk.position.z-ChunkUnit(1.0)),  // FRONT
-        ];
-
-        // lists all the chunks that are adjacent to this Chunk
-        let merge_chunks = chunk_list.iter()
-            .filter(|c| {
-                if let Some(c) = &find_chunk(c.0) {
-                    adjc_chunks.contains(&c.position)
-                } else {
-                    false
-                }
-            })
-            .collect::<Vec<_>>();
-
-        // println!("Merge Chunks: {:?}", merge_chunks.iter().map(|c| c.0).collect::<Vec<_>>());
-
-        let main_chunk = chunk.clone();
-
         // the coordinates of get_chunk(); coords relative to the main Chunk
-        let get_chunk = |x, y, z| {
-            for (cid, _new, _cull, _v, _i) in &merge_chunks {
-                if let Some(c_chunk) = &find_chunk(*cid) {
-                    let offset = Position::new(
-                        c_chunk.position.x - main_chunk.position.x,
-                        c_chunk.position.y - main_chunk.position.y,
-                        c_chunk.position.z - main_chunk.position.z,
-                    );
-
-                    if Position::new(x, y, z) == offset {
-                        return Some(c_chunk.clone());
-                    }
-                }
-            }
-            return None;
+        let chunk_pos = to_chunk_pos(chunk.position);
+        let get_chunk = |x: ChunkUnit, y: ChunkUnit, z: ChunkUnit| {
+            chunks.neighbour(chunk_pos, [x.0 as i64, y.0 as i64, z.0 as i64])
         };
 
         // println!("Current chunk position: {:?}", chunk.position);
-        // println!("Adjacent chunks found: {:?}", merge_chunks.iter().map(|x| &find_chunk(&x.0).unwrap().position).collect::<Vec<_>>());
 
         let mut vertices = Vec::new();
         let mut indices = Vec::new();
@@ -356,8 +309,8 @@ impl Cube {
                                 faces += 1;
                             }
 
-                            if indices.is_empty() {
-                                if faces > 0 {
+                            if indices.is_empty()
+                                && faces > 0 {
                                     indices.append(
                                         &mut vec![
                                             0, 1, 2,
@@ -366,10 +319,9 @@ impl Cube {
                                     );
                                     faces -= 1;
                                 }
-                            }
 
                             for _ in 0..faces {
-                                let ofs = *indices.last().unwrap() as u32+1;  // offset
+                                let ofs = *indices.last().unwrap()+1;  // offset
                                 indices.append(
                                     &mut vec![
                                         0+ofs, 1+ofs, 2+ofs,  // triangle 1
@@ -398,101 +350,65 @@ impl Mesh for Cube {
         self.chunks.push((chunk_id, true, false, Vec::new(), Vec::new()));
     }
 
-    fn load_chunks(&mut self, chunks: Vec<Chunk>, pool: &mut ChunkThreadPool) {
-        println!("Begin chunk loading for cube mesh");
-        let mut chunk_cloned = self.chunks.clone();
-        let chunks = Arc::new(chunks);
-        let new_chunks = self.chunks.iter().filter(|c|c.1 == true).collect::<Vec<_>>();
+    fn load_chunks(&mut self, chunks: Arc<ChunkMap>, pool: &mut ChunkThreadPool) {
+        let dirty_chunks = self.chunks.iter().filter(|c|c.1).map(|c| c.0).collect::<Vec<_>>();
 
-        for (chunk_id, new, _cull, _vert, _indx) in chunk_cloned.iter_mut() {
+        for (chunk_id, dirty, _cull, _vert, _indx) in self.chunks.iter() {
             // println!("Chunk loop");
             // println!("Chunks Arc Ref: {}", Arc::strong_count(&chunks));
 
-            let find_chunk = |cid: ChunkID| -> Option<Chunk> {
-                if let Some(ind) = chunks.par_iter().position_first(|x| x.id == cid) {
-                    Some(chunks[ind].clone())
-                } else {
-                    None
-                }
-            };
+            if let Some(chunk) = chunks.by_id_arc(*chunk_id) {
+                // checks if there are any new or edited Chunks nearby that requires to be updated again
+                // since the faces on the chunk borders are culled against them
+                let update = *dirty || chunks.neighbours(to_chunk_pos(chunk.position)).iter()
+                    .any(|c| c.is_some_and(|c| dirty_chunks.contains(&c.id)));
 
-            if let Some(chunk) = find_chunk(*chunk_id) {
-                if *new {
-                    let chunk_list = self.chunks.clone();
+                // if there are new or edited chunks, it will require an update to the mesh
+                if update {
                     let chunks = chunks.clone();
 
                     // println!("Adding a chunk thread");
                     let chunk = chunk.clone();
-                    pool.add_work( ( chunk_id.clone(), Box::new(move || {
-                        Self::mesh_data(chunk_list, chunks, chunk)
+                    pool.add_work( ( *chunk_id, MeshKind::Cube, Box::new(move || {
+                        Self::mesh_data(chunks, chunk)
                     })));  // end for adding work to the thread pool
-                } else {
-                    // listing out all the theoretical adjacent chunks this Chunk has
-                    let adjc_chunks: [Position<ChunkUnit>; 6] = [
-                        Position::new(chunk.position.x+ChunkUnit(1.0), chunk.position.y  , chunk.position.z  ),  // LEFT
-                        Position::new(chunk.position.x-ChunkUnit(1.0), chunk.position.y  , chunk.position.z  ),  // RIGHT
-                        Position::new(chunk.position.x  , chunk.position.y+ChunkUnit(1.0), chunk.position.z  ),  // UP
-                        Position::new(chunk.position.x  , chunk.position.y-ChunkUnit(1.0), chunk.position.z  ),  // DOWN
-                        Position::new(chunk.position.x  , chunk.position.y  , chunk.position.z+ChunkUnit(1.0)),  // BACK
-                        Position::new(chunk.position.x  , chunk.position.y  , chunk.position.z-ChunkUnit(1.0)),  // FRONT
-                    ];
-
-                    let mut update = false;
-
-                    // checks if there are any new Chunks nearby that requires to be updated again
-                    for (cid, _, _, _, _) in new_chunks.iter() {
-                        if let Some(c) = find_chunk(*cid) {
-                            if adjc_chunks.contains(&c.position) {
-                                update = true;
-                                break;
-                            }
-                        }
-                    }
-
-                    // if there are new chunks, it will require an update to the mesh
-                    if update {
-                        let chunk_list = self.chunks.clone();
-                        let chunks = chunks.clone();
-
-                        // println!("Adding a chunk thread");
-                        let chunk = chunk.clone();
-                        pool.add_work( ( chunk_id.clone(), Box::new(move || {
-                            Self::mesh_data(chunk_list, chunks, chunk)
-                        })));  // end for adding work to the thread pool
-                    }
                 }
             }
         }
 
-        let output = pool.join();
-
-        for (id, (mut vert, mut indx)) in output {
-            // as long the self.chunks doesn't get changed in between, it should never panic
-            let ind = self.chunks.iter().position(|c| c.0 == id).unwrap();
-
-            let mut vertices: &mut Vec<CubeVert> = (*vert).downcast_mut().unwrap();
-            let mut indices: &mut Vec<u32> = (*indx).downcast_mut().unwrap();
-
-            // .3: vertex dt of that chunk; .4 index dt of that chunk
+        // the data of the dirty chunks is on its way, so they are only regenerated along with their neighbours from now on
+        for chunk in self.chunks.iter_mut() {
+            chunk.1 = false;
+        }
+    }
 
-            // just in case if there are any vertices/indices data this chunk has previously
-            // which can cause some rendering issues
-            self.chunks[ind].3.clear();
-            self.chunks[ind].4.clear();
+    fn load_chunk_data(&mut self, id: ChunkID, data: ThreadPoolOutput) {
+        let (mut vert, mut indx) = data;
+        // the chunk might have been offloaded while its data was generated
+        let ind = match self.chunks.iter().position(|c| c.0 == id) {
+            Some(ind) => ind,
+            None => return,
+        };
 
-            self.chunks[ind].3.append(&mut vertices);
-            self.chunks[ind].4.append(&mut indices);
+        let vertices: &mut Vec<CubeVert> = (*vert).downcast_mut().unwrap();
+        let indices: &mut Vec<u32> = (*indx).downcast_mut().unwrap();
 
-            self.chunks[ind].1 = false;  // update the newly generated chunk's status to false
-        }
+        // .3: vertex dt of that chunk; .4 index dt of that chunk
 
+        // just in case if there are any vertices/indices data this chunk has previously
+        // which can cause some rendering issues
+        self.chunks[ind].3.clear();
+        self.chunks[ind].4.clear();
 
+        self.chunks[ind].3.append(vertices);
+        self.chunks[ind].4.append(indices);
     }
 
-    // TODO: Will probably be removed in future
-    // re-evaluates the vertex and index data buffer per chunk
+    // marks the chunk to be regenerated (along with its neighbours) on the next load_chunks()
     fn updt_chunks(&mut self, id: ChunkID) {
-        unimplemented!()
+        if let Some(chunk) = self.chunks.iter_mut().find(|c| c.0 == id) {
+            chunk.1 = true;
+        }
     }
 
     // removes the buffer and its reference
@@ -513,7 +429,7 @@ impl Mesh for Cube {
                 let (proj, view, world) = new_cam.gen_mvp(self.dimensions);
 
                 self.persp_buf = Some(self.persp_mat.next(
-                    cube_vs::ty::MVP {proj: proj, view: view, world: world}
+                    cube_vs::ty::MVP {proj, view, world}
                 ).unwrap());
             }
         }
@@ -531,7 +447,7 @@ impl Mesh for Cube {
 
     // renders the buffers and pipeline; only merges the vertex and index data into a one large buffer
     // called for each frame
-    fn render<'b>(&mut self,
+    fn render(&mut self,
                   device: Arc<Device>,
                   renderpass: Arc<dyn RenderPassAbstract + Send + Sync>,
                   rerender: bool,
@@ -554,8 +470,6 @@ impl Mesh for Cube {
     //        Self::PushConstants,   // push-down constants
     //    )
     {
-        println!("vvvvvvvvvvvvvvvv P");
-
         if rerender {
             self.grph_pipe = Self::pipeline(
                 &self.vert_shd, &self.frag_shd,
@@ -582,7 +496,7 @@ impl Mesh for Cube {
                     } else {
                         // last number of the index is always that largest
                         // IF using the format: 0 1 2 0 2 3
-                        let indx_max = self.indices.last().unwrap().clone();
+                        let indx_max = *self.indices.last().unwrap();
                         self.indices.extend(
                             indices.iter().map(|&x| x+indx_max+1)
                         );
@@ -606,8 +520,6 @@ impl Mesh for Cube {
             .build().unwrap()
         );
 
-        println!("^^^^^^^^^^^^^^^^ P");
-
         //TODO: using the new threadpool to generate and render the new chunks within the meshes; here
         //TODO: nevermind, maybe return a closure to be execute in future and in different scope?
         //TODO: maybue use vulkano::buffer::CpuBufferPool for better performance for handling large amount of chunk datas
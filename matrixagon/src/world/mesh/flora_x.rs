@@ -1,10 +1,12 @@
 use crate::datatype::{Dimension, Position, ChunkUnit, BlockUnit};
-use crate::world::mesh::{Mesh, MeshType, MeshDataTypeFull};
+use crate::world::mesh::{Mesh, ChunkMesh, MeshType, MeshDataTypeFull};
 use crate::world::chunk::Chunk;
+use crate::world::chunk_map::ChunkMap;
 use crate::world::shader::{FloraVert, flora_vs, flora_fs};
 use crate::world::ChunkID;
 use crate::world::block::Block;
 use crate::world::chunk_threadpool::{ChunkThreadPool, ThreadPoolOutput};
+use crate::world::mesh::MeshKind;
 use crate::world::player::camera::Camera;
 
 use vulkano::pipeline::viewport::Viewport;
@@ -12,17 +14,14 @@ use vulkano::framebuffer::{Subpass, RenderPassAbstract};
 use vulkano::device::Device;
 use vulkano::pipeline::{GraphicsPipelineAbstract, GraphicsPipeline};
 use vulkano::command_buffer::DynamicState;
-use vulkano::buffer::{CpuAccessibleBuffer, BufferUsage, CpuBufferPool};
+use vulkano::buffer::{BufferUsage, CpuBufferPool};
 use vulkano::sampler::{Sampler, SamplerAddressMode, Filter, MipmapMode};
 use vulkano::descriptor::descriptor_set::{PersistentDescriptorSet};
-use vulkano::descriptor::DescriptorSet;
-use vulkano::buffer::cpu_pool::{CpuBufferPoolSubbuffer, CpuBufferPoolChunk};
+use vulkano::buffer::cpu_pool::CpuBufferPoolSubbuffer;
 use vulkano::memory::pool::StdMemoryPool;
 use vulkano::image::ImmutableImage;
 use vulkano::format::Format;
 
-use rayon::prelude::*;
-
 use std::sync::Arc;
 use std::iter;
 
@@ -35,8 +34,8 @@ pub enum Side {
 // flora mesh is basically two diagonal textures corssing each other
 pub struct FloraX {
     textures: Arc<ImmutableImage<Format>>,
-    // chunks: Chunk Reference, Chunk Cullling, Chunk Vertices, Chunk Indices
-    chunks: Vec<(ChunkID, bool, Vec<<Self as Mesh>::Vertex>, Vec<<Self as Mesh>::Index>)>,
+    // chunks: Chunk Reference, Dirty Chunk (new or edited), Chunk Cullling, Chunk Vertices, Chunk Indices
+    chunks: Vec<ChunkMesh<<Self as Mesh>::Vertex, <Self as Mesh>::Index>>,
     grph_pipe: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
     dimensions: Dimension<u32>,
 
@@ -68,9 +67,9 @@ impl FloraX {
             chunks: Vec::new(),
             grph_pipe: FloraX::pipeline(
                 &vs, &fs, device.clone(),
-                render_pass.clone(), dimensions.into(),
+                render_pass.clone(), dimensions,
             ),
-            dimensions: dimensions,
+            dimensions,
 
             vert_shd: vs,
             frag_shd: fs,
@@ -96,8 +95,8 @@ impl FloraX {
         };
         println!("FX-VB Resv: {:?}", s.vrtx_buf.capacity());
         println!("FX-IB Resv: {:?}", s.vrtx_buf.capacity());
-        s.vrtx_buf.reserve(4);
-        s.indx_buf.reserve(4);
+        s.vrtx_buf.reserve(4).unwrap();
+        s.indx_buf.reserve(4).unwrap();
         println!("FX-VB Resv Aft: {:?}", s.vrtx_buf.capacity());
         println!("FX-IB Resv Aft: {:?}", s.vrtx_buf.capacity());
         s.updt_world(Some(dimensions), Some(cam));
@@ -132,17 +131,10 @@ impl FloraX {
         )
     }
 
-    fn mesh_data(chunk_list: Vec<(ChunkID, bool, Vec<FloraVert>, Vec<u32>)>, chunks: Arc<Vec<Chunk>>, chunk: Chunk) -> ThreadPoolOutput {
-        let find_chunk = |cid: ChunkID| -> Option<&Chunk> {
-            // don't parallelize this iterator: as this closure gets executed few thousands time,
-            // the overhead of even threadpool can largely affect negatively
-            if let Some(ind) = chunks.iter().position(|x| x.id == cid) {
-                Some(&chunks[ind])
-            } else {
-                None
-            }
-        };
-
+    // the flora is not culled against the neighbouring chunks, so only the chunk itself is needed
+    // the zeros are kept to line up the texture corners and the index offsets of the faces
+    #[allow(clippy::identity_op)]
+    fn mesh_data(chunk: Arc<Chunk>) -> ThreadPoolOutput {
         let start = Position::new(
             chunk.position.x.into_block(),
             chunk.position.y.into_block(),
@@ -158,23 +150,6 @@ impl FloraX {
         // println!("Chunk Start: {:?}", start);
         // println!("Chunk End: {:?}", end);
 
-        // Chunk Border Culling:
-        // first locate any adjacent chunks in the world.mesh
-        // then locate the world.block data to find any transparent blocks
-        //  ^- IF NOT: do not add the vertices and indices
-        //  ^- ELSE: add the vertices and indices to the vector
-        //  ^- CASE: when there are no adjacent chunks: do not add the vertices and indices
-
-        // listing out all the theoretical adjacent chunks this Chunk has
-        let adjc_chunks: [Position<ChunkUnit>; 6] = [
-            Position::new(chunk.position.x+ChunkUnit(1.0), chunk.position.y  , chunk.position.z  ),  // LEFT
-            Position::new(chunk.position.x-ChunkUnit(1.0), chunk.position.y  , chunk.position.z  ),  // RIGHT
-            Position::new(chunk.position.x  , chunk.position.y+ChunkUnit(1.0), chunk.position.z  ),  // UP
-            Position::new(chunk.position.x  , chunk.position.y-ChunkUnit(1.0), chunk.position.z  ),  // DOWN
-            Position::new(chunk.position.x  , chunk.position.y  , chunk.position.z+ChunkUnit(1.0)),  // BACK
-            Position::new(chunk.position.x  , chunk.position.y  , chunk.position.z-ChunkUnit(1.0)),  // FRONT
-        ];
-
         let mut vertices = Vec::new();
         let mut indices = Vec::new();
 
@@ -229,7 +204,7 @@ impl FloraX {
                                 ]
                             );
                         } else {
-                            let ofs = *indices.last().unwrap() as u32+1;  // offset
+                            let ofs = *indices.last().unwrap()+1;  // offset
                             indices.append(
                                 &mut vec![
                                     // first shape
@@ -258,66 +233,55 @@ impl Mesh for FloraX {
 
     fn add_chunk(&mut self, chunk_id: ChunkID) {
         // ( chunk reference, vertices vector, indices vector )
-        self.chunks.push((chunk_id, false, Vec::new(), Vec::new()));
+        self.chunks.push((chunk_id, true, false, Vec::new(), Vec::new()));
     }
 
-    fn load_chunks(&mut self, chunks: Vec<Chunk>, pool: &mut ChunkThreadPool) {
-        let mut chunk_cloned = self.chunks.clone();
-        let chunks = Arc::new(chunks);
-
-        for (chunk_id, _cull, _vert, _indx) in chunk_cloned.iter_mut() {
+    fn load_chunks(&mut self, chunks: Arc<ChunkMap>, pool: &mut ChunkThreadPool) {
+        // the flora is not culled against the neighbouring chunks, so only the dirty chunks themselves are regenerated
+        for (chunk_id, dirty, _cull, _vert, _indx) in self.chunks.iter_mut() {
             // println!("Chunk loop");
-            // println!("Chunks Arc Ref: {}", Arc::strong_count(&chunks));
-
-            let cloned_chunks = chunks.clone();
-
-            let find_chunk = |cid: ChunkID| -> Option<Chunk> {
-                if let Some(ind) = cloned_chunks.par_iter().position_first(|x| x.id == cid) {
-                    Some(cloned_chunks[ind].clone())
-                } else {
-                    None
-                }
-            };
-
-            if let Some(chunk) = find_chunk(*chunk_id) {
-                let chunk_list = self.chunks.clone();
-                let chunks = chunks.clone();
+            if !*dirty {
+                continue;
+            }
 
+            if let Some(chunk) = chunks.by_id_arc(*chunk_id) {
                 // println!("Adding a chunk thread");
                 let chunk = chunk.clone();
-                pool.add_work( ( chunk_id.clone(), Box::new(move || {
-                    Self::mesh_data(chunk_list, chunks, chunk)
+                pool.add_work( ( *chunk_id, MeshKind::FloraX, Box::new(move || {
+                    Self::mesh_data(chunk)
                 })));  // end for adding work to the thread pool
             }
+            *dirty = false;
         }
+    }
 
-        let output = pool.join();
-
-        for (id, (mut vert, mut indx)) in output {
-            // as long the self.chunks doesn't get changed in between, it should never panic
-            let ind = self.chunks.iter().position(|c| c.0 == id).unwrap();
-
-            let mut vertices: &mut Vec<FloraVert> = (*vert).downcast_mut().unwrap();
-            let mut indices: &mut Vec<u32> = (*indx).downcast_mut().unwrap();
-
-            // .2: vertex dt of that chunk; .3 index dt of that chunk
+    fn load_chunk_data(&mut self, id: ChunkID, data: ThreadPoolOutput) {
+        let (mut vert, mut indx) = data;
+        // the chunk might have been offloaded while its data was generated
+        let ind = match self.chunks.iter().position(|c| c.0 == id) {
+            Some(ind) => ind,
+            None => return,
+        };
 
-            // just in case if there are any vertices/indices data this chunk has previously
-            // which can cause some rendering issues
-            self.chunks[ind].2.clear();
-            self.chunks[ind].3.clear();
+        let vertices: &mut Vec<FloraVert> = (*vert).downcast_mut().unwrap();
+        let indices: &mut Vec<u32> = (*indx).downcast_mut().unwrap();
 
-            self.chunks[ind].2.append(&mut vertices);
-            self.chunks[ind].3.append(&mut indices);
-        }
+        // .3: vertex dt of that chunk; .4 index dt of that chunk
 
+        // just in case if there are any vertices/indices data this chunk has previously
+        // which can cause some rendering issues
+        self.chunks[ind].3.clear();
+        self.chunks[ind].4.clear();
 
+        self.chunks[ind].3.append(vertices);
+        self.chunks[ind].4.append(indices);
     }
 
-    // TODO: Will probably be removed in future
-    // re-evaluates the vertex and index data buffer
+    // marks the chunk to be regenerated on the next load_chunks()
     fn updt_chunks(&mut self, id: ChunkID) {
-        unimplemented!()
+        if let Some(chunk) = self.chunks.iter_mut().find(|c| c.0 == id) {
+            chunk.1 = true;
+        }
     }
 
     // removes the buffer and its reference
@@ -338,7 +302,7 @@ impl Mesh for FloraX {
                 let (proj, view, world) = new_cam.gen_mvp(self.dimensions);
 
                 self.persp_buf = Some(self.persp_mat.next(
-                    flora_vs::ty::MVP {proj: proj, view: view, world: world}
+                    flora_vs::ty::MVP {proj, view, world}
                 ).unwrap());
             }
         }
@@ -346,7 +310,7 @@ impl Mesh for FloraX {
 
     // renders the buffers and pipeline; only merges the vertex and index data into a one large buffer
     // called for each frame
-    fn render<'b>(&mut self,
+    fn render(&mut self,
                   device: Arc<Device>,
                   renderpass: Arc<dyn RenderPassAbstract + Send + Sync>,
                   rerender: bool,
@@ -369,8 +333,6 @@ impl Mesh for FloraX {
     //     Self::PushConstants,   // push-down constants
     // )
     {
-        println!("vvvvvvvvvvvvvvvv P");
-
         if rerender {
             self.grph_pipe = Self::pipeline(
                 &self.vert_shd, &self.frag_shd,
@@ -382,13 +344,13 @@ impl Mesh for FloraX {
             self.vertices.clear();
             self.indices.clear();
 
-            for (_chunk, cull, vertices, _indices) in self.chunks.iter() {
+            for (_chunk, _dirty, cull, vertices, _indices) in self.chunks.iter() {
                 if !*cull {  // check if the chunk is visible to be loaded (using frustum culling)
                     self.vertices.extend(vertices.iter());
                 }
             }
 
-            for (_chunk, cull, _vertices, indices) in self.chunks.iter() {
+            for (_chunk, _dirty, cull, _vertices, indices) in self.chunks.iter() {
                 if !*cull {
                     if self.indices.is_empty() {
                         self.indices.extend(
@@ -397,7 +359,7 @@ impl Mesh for FloraX {
                     } else {
                         // last number of the index is always that largest
                         // IF using the format: 0 1 2 0 2 3
-                        let indx_max = self.indices.last().unwrap().clone();
+                        let indx_max = *self.indices.last().unwrap();
                         self.indices.extend(
                             indices.iter().map(|&x| x+indx_max+1)
                         );
@@ -422,7 +384,6 @@ impl Mesh for FloraX {
             .build().unwrap()
         );
 
-        println!("^^^^^^^^^^^^^^^^ P");
         let vrtx_sb = self.vrtx_buf.chunk(self.vertices.clone()).unwrap();
         let indx_sb = self.indx_buf.chunk(self.indices.clone()).unwrap();
 
@@ -1,19 +1,19 @@
 use crate::world::mesh::cube::Cube;
 use crate::world::mesh::cube::Side;
-use crate::world::chunk::Chunk;
+use crate::world::chunk_map::ChunkMap;
 use crate::world::shader::{VertexType, IndexType, cube_vs};
 use crate::world::ChunkID;
 use crate::world::texture::TextureID;
 use crate::datatype::Dimension;
 use crate::world::player::camera::Camera;
-use crate::world::chunk_threadpool::ChunkThreadPool;
+use crate::world::chunk_threadpool::{ChunkThreadPool, ThreadPoolOutput};
 use crate::world::mesh::flora_x::FloraX;
 
 use vulkano::device::Device;
 use vulkano::framebuffer::RenderPassAbstract;
 use vulkano::command_buffer::{AutoCommandBufferBuilder, DrawIndexedError, DynamicState};
 use vulkano::pipeline::GraphicsPipelineAbstract;
-use vulkano::buffer::{BufferAccess, TypedBufferAccess, CpuAccessibleBuffer, CpuBufferPool};
+use vulkano::buffer::CpuBufferPool;
 use vulkano::pipeline::input_assembly::Index;
 use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
 use vulkano::descriptor::DescriptorSet;
@@ -21,8 +21,8 @@ use vulkano::descriptor::DescriptorSet;
 use std::sync::Arc;
 use vulkano::image::ImmutableImage;
 use vulkano::format::Format;
-use vulkano::buffer::cpu_pool::{CpuBufferPoolChunk, CpuBufferPoolSubbuffer};
-use vulkano::memory::pool::{StdMemoryPool, StdMemoryPoolAlloc};
+use vulkano::buffer::cpu_pool::CpuBufferPoolChunk;
+use vulkano::memory::pool::StdMemoryPool;
 
 
 pub mod air;
@@ -49,24 +49,24 @@ impl MeshType {
     // all sides have uniform texture
     pub fn cube_all(name: TextureID) -> Self {
         MeshType::Cube {
-            top: name.clone(),
-            bottom: name.clone(),
-            left: name.clone(),
-            right: name.clone(),
-            front: name.clone(),
-            back: name.clone(),
+            top: name,
+            bottom: name,
+            left: name,
+            right: name,
+            front: name,
+            back: name,
         }
     }
 
     //  all sides have uniform texture except the chosen side using the `single` texture
     pub fn cube_except_one(name: TextureID, single: TextureID, side: Side) -> Self {
         MeshType::Cube {
-            top: if let Side::Top = side {single.clone()} else {name.clone()},
-            bottom: if let Side::Bottom = side {single.clone()} else {name.clone()},
-            left: if let Side::Left = side {single.clone()} else {name.clone()},
-            right: if let Side::Right = side {single.clone()} else {name.clone()},
-            front: if let Side::Front = side {single.clone()} else {name.clone()},
-            back: if let Side::Back = side {single.clone()} else {name.clone()},
+            top: if let Side::Top = side {single} else {name},
+            bottom: if let Side::Bottom = side {single} else {name},
+            left: if let Side::Left = side {single} else {name},
+            right: if let Side::Right = side {single} else {name},
+            front: if let Side::Front = side {single} else {name},
+            back: if let Side::Back = side {single} else {name},
         }
     }
 
@@ -75,18 +75,18 @@ impl MeshType {
                            front: TextureID, back: TextureID,
     ) -> Self {
         MeshType::Cube {
-            top: top.clone(),
-            bottom: bottom.clone(),
-            left: left.clone(),
-            right: right.clone(),
-            front: front.clone(),
-            back: back.clone(),
+            top,
+            bottom,
+            left,
+            right,
+            front,
+            back,
         }
     }
 }
 
 // a more generalized type alias for MeshDataType below
-pub type MeshDataTypeFull<V: VertexType + Send + Sync, I: IndexType + Send + Sync, Pc> = (
+pub type MeshDataTypeFull<V, I, Pc> = (
     Arc<dyn GraphicsPipelineAbstract + Send + Sync>,  // graphic pipeline
     DynamicState,  // dynamic state for display
     // Arc<CpuAccessibleBuffer<[V]>>,   // vertex buffer
@@ -97,8 +97,12 @@ pub type MeshDataTypeFull<V: VertexType + Send + Sync, I: IndexType + Send + Syn
     Pc,   // push-down constants TODO: A Generic Return of PushDown Constants
 );
 
+// the render data of a single chunk within a mesh: Chunk Reference, Dirty Chunk (new or edited), Chunk Culling,
+// Chunk Vertices, Chunk Indices
+pub type ChunkMesh<V, I> = (ChunkID, bool, bool, Vec<V>, Vec<I>);
+
 // note: generic types here is only just for a reference, compiler does not enforce it
-pub type MeshDataType<V: VertexType + Send + Sync, I: IndexType + Send + Sync> = MeshDataTypeFull<V, I, ()>;
+pub type MeshDataType<V, I> = MeshDataTypeFull<V, I, ()>;
 
 pub type MeshesDataType = Meshes<
     MeshDataType<<Cube as Mesh>::Vertex, <Cube as Mesh>::Index>,
@@ -110,6 +114,13 @@ pub type MeshesStructType = Meshes<
     FloraX,
 >;
 
+// the mesh the work of the chunk threadpool generates the data for
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MeshKind {
+    Cube,
+    FloraX,
+}
+
 // this struct is merely used to organized each individual meshes
 pub struct Meshes<C, Fx> {
     pub cube: C,
@@ -141,45 +152,60 @@ impl MeshesStructType {
         println!("MESHES - INITIALIZED");
 
         Self {
-            cube: Cube::new(device.clone(), txtr.clone(), renderpass.clone(), dimensions, &cam),
-            flora_x: FloraX::new(device.clone(), txtr.clone(), renderpass.clone(), dimensions, &cam),
+            cube: Cube::new(device.clone(), txtr.clone(), renderpass.clone(), dimensions, cam),
+            flora_x: FloraX::new(device.clone(), txtr.clone(), renderpass.clone(), dimensions, cam),
         }
     }
 
-    pub fn add_chunk(&mut self, chunk_id: ChunkID) {
+    // re-renders the vertex and index data
+    pub fn render(
+        &mut self,
+        device: Arc<Device>,
+        renderpass: Arc<dyn RenderPassAbstract + Send + Sync>,
+        rerender: bool,
+        reload_chunk: bool,
+    ) -> MeshesDataType {
+        Meshes {
+            cube: self.cube.render(device.clone(), renderpass.clone(), rerender, reload_chunk),
+            flora_x: self.flora_x.render(device.clone(), renderpass.clone(), rerender, reload_chunk),
+        }
+    }
+}
+
+impl ChunkMeshes for MeshesStructType {
+    fn add_chunk(&mut self, chunk_id: ChunkID) {
         self.cube.add_chunk(chunk_id);
         self.flora_x.add_chunk(chunk_id);
     }
 
-    pub fn load_chunks(&mut self, chunks: Vec<Chunk>, pool: &mut ChunkThreadPool) {
+    fn load_chunks(&mut self, chunks: Arc<ChunkMap>, pool: &mut ChunkThreadPool) {
         self.cube.load_chunks(chunks.clone(), pool);
         self.flora_x.load_chunks(chunks, pool);
     }
 
-    pub fn remv_chunk(&mut self, id: ChunkID) {
+    // passes the finished data of the chunk to the mesh it was generated for
+    fn load_chunk_data(&mut self, mesh: MeshKind, id: ChunkID, data: ThreadPoolOutput) {
+        match mesh {
+            MeshKind::Cube => self.cube.load_chunk_data(id, data),
+            MeshKind::FloraX => self.flora_x.load_chunk_data(id, data),
+        }
+    }
+
+    fn mark_dirty(&mut self, id: ChunkID) {
+        self.cube.updt_chunks(id);
+        self.flora_x.updt_chunks(id);
+    }
+
+    fn remv_chunk(&mut self, id: ChunkID) {
         self.cube.remv_chunk(id);
         self.flora_x.remv_chunk(id);
     }
 
     // update meshes
-    pub fn update(&mut self, dimensions: Option<Dimension<u32>>, cam: Option<&Camera>) {
+    fn update(&mut self, dimensions: Option<Dimension<u32>>, cam: Option<&Camera>) {
         self.cube.updt_world(dimensions, cam);
         self.flora_x.updt_world(dimensions, cam);
     }
-
-    // re-renders the vertex and index data
-    pub fn render(
-        &mut self,
-        device: Arc<Device>,
-        renderpass: Arc<dyn RenderPassAbstract + Send + Sync>,
-        rerender: bool,
-        reload_chunk: bool,
-    ) -> MeshesDataType {
-        Meshes {
-            cube: self.cube.render(device.clone(), renderpass.clone(), rerender, reload_chunk),
-            flora_x: self.flora_x.render(device.clone(), renderpass.clone(), rerender, reload_chunk),
-        }
-    }
 }
 
 impl MeshesDataType {
@@ -191,7 +217,7 @@ impl MeshesDataType {
         let persp_mat = CpuBufferPool::uniform_buffer(device.clone());
 
         let persp_buf = Some(persp_mat.next(
-            cube_vs::ty::MVP {proj: proj, view: view, world: world}
+            cube_vs::ty::MVP {proj, view, world}
         ).unwrap());
 
         let sets = Meshes {
@@ -216,6 +242,17 @@ impl MeshesDataType {
     }
 }
 
+// the meshes of the chunk handler, all fed with the same chunks
+// only the rendering is left to the concrete meshes, so the chunk handler can be run without a device
+pub trait ChunkMeshes {
+    fn add_chunk(&mut self, chunk_id: ChunkID);
+    fn load_chunks(&mut self, chunks: Arc<ChunkMap>, pool: &mut ChunkThreadPool);  // submits the dirty chunks to be regenerated
+    fn load_chunk_data(&mut self, mesh: MeshKind, id: ChunkID, data: ThreadPoolOutput);
+    fn mark_dirty(&mut self, id: ChunkID);  // the chunk is regenerated in every mesh on the next load_chunks()
+    fn remv_chunk(&mut self, id: ChunkID);
+    fn update(&mut self, dimensions: Option<Dimension<u32>>, cam: Option<&Camera>);
+}
+
 // all meshes must be implemented by the world.mesh trait
 pub trait Mesh {
     type Vertex: VertexType + 'static;
@@ -225,21 +262,23 @@ pub trait Mesh {
 
     // Mesh trait functionalities description:
     // add_chunk(); when you want to add chunks
-    // load_chunk(); to load all the render data of the chunk to the world.mesh
-    // updt_chunk(); reloads all the render data of the chunk to the world.mesh TODO: whats the point?
+    // load_chunks(); to start generating the render data of the chunks on the chunk threadpool
+    // load_chunk_data(); to load the generated render data of the chunk to the world.mesh
+    // updt_chunks(); to mark the chunk to be regenerated on the next load_chunks() (e.g. once its blocks are edited)
     // remv_chunk(); to remove the chunk reference to the world.mesh
     // updt_world(); calls this when the world information needs to be updated
     // render(); to return the graphic pipeline from the world.mesh to the main renderer
 
     fn add_chunk(&mut self, chunk_id: ChunkID);  // adds the reference of the chunk to the chunk database of the world.mesh
     fn load_chunks(&mut self,
-                   chunks: Vec<Chunk>,
+                   chunks: Arc<ChunkMap>,
                    pool: &mut ChunkThreadPool,
-    );  // loads all the chunks' data to the world.mesh's main vertices and indices vector
+    );  // submits the generation of the new and edited chunks' data to the threadpool
+    fn load_chunk_data(&mut self, id: ChunkID, data: ThreadPoolOutput);  // loads the generated data of the chunk to the world.mesh's vertices and indices vector
     fn updt_chunks(&mut self, id: ChunkID);  // updates the chunk (blocks, lighting, other chunk-bound info)
     fn remv_chunk(&mut self, id: ChunkID);  // remove the chunk from the chunk database of the world.mesh
     fn updt_world(&mut self, dimensions: Option<Dimension<u32>>, player: Option<&Camera>);  // updates world-bound info
-    fn render<'b>(&mut self,
+    fn render(&mut self,
                   device: Arc<Device>,
                   renderpass: Arc<dyn RenderPassAbstract + Send + Sync>,
                   rerender: bool,
@@ -288,6 +327,7 @@ impl MeshesExt for AutoCommandBufferBuilder {
                   I: Index + Send + Sync + 'static,
                   // CpuAccessibleBuffer<[V]>: BufferAccess+TypedBufferAccess,
     {
-        self.draw_indexed(mesh_data.0, &mesh_data.1, vec![Arc::new(mesh_data.2)], mesh_data.3, mesh_data.4, mesh_data.5)
+        // the meshes have no push constants yet
+        self.draw_indexed(mesh_data.0, &mesh_data.1, vec![Arc::new(mesh_data.2)], mesh_data.3, mesh_data.4, ())
     }
 }
@@ -5,9 +5,14 @@ use crate::datatype::Dimension;
 use crate::world::texture::Texture;
 use crate::world::chunk_handler::{ChunkHandler, ChunkStatusInfo};
 use crate::world::block::registry::BlockRegistry;
-use crate::event::{EventDispatcher, EventName};
+use crate::event::EventDispatcher;
+use crate::event::types::mesh as mesh_event;
 use crate::world::commands::WorldCommandExecutor;
+use crate::world::commands::console::Console;
+use crate::world::commands::namespace::NamespaceRegistry;
+use crate::world::commands::library::{register_library, WORLD_NMSPC};
 use crate::world::player::camera::Camera;
+use crate::world::settings::WorldSettings;
 
 use vulkano::device::{Queue, Device};
 use vulkano::command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder, CommandBufferExecFuture};
@@ -16,7 +21,6 @@ use vulkano::command_buffer::pool::standard::StandardCommandPoolAlloc;
 use vulkano::sync::{GpuFuture, NowFuture};
 
 use std::sync::Arc;
-use std::mem;
 use std::rc::Rc;
 
 pub mod mesh;
@@ -30,9 +34,13 @@ pub mod chunk;
 pub mod chunk_handler;
 pub mod texture;
 pub mod chunk_threadpool;
+pub mod chunk_queue;
+pub mod chunk_map;
+pub mod chunk_edit;
+pub mod settings;
 
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub struct ChunkID(pub u32);
 
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -66,7 +74,7 @@ impl WorldStateUpd {
             cam: camera,
             dimensions: dimn,
 
-            rerender: rerender,
+            rerender,
             renderpass: renderpass.clone(),
             framebuffer: framebuffer.clone(),
 
@@ -95,11 +103,12 @@ pub struct World {
     // TODO: This can privatized once the world events has been fully added
     pub player: Player,
     command: WorldCommandExecutor,
+    console: Console,  // the command mode typing into the command executor
 
     // world structure and manager
     event: Rc<EventDispatcher>,
+    device: Arc<Device>,  // the meshes of the chunks are rendered on the update
     registry: Arc<BlockRegistry>,  // a globalized way to hold all in-game block instance
-    texture: Texture,
     texture_fut: Option<CommandBufferExecFuture<NowFuture, AutoCommandBuffer>>,
 
     // multithreading
@@ -116,7 +125,8 @@ impl World {
         queue: Arc<Queue>,
         evd: Rc<EventDispatcher>,
         renderpass: Arc<dyn RenderPassAbstract + Send + Sync>,
-        dimensions: Dimension<u32>
+        dimensions: Dimension<u32>,
+        settings: WorldSettings,
     ) -> Self {  // creates a new world
         println!("WORLD - INITIALIZED");
 
@@ -140,33 +150,44 @@ impl World {
         // chunk handler will create a new separate chunk threadpools
         // we only just need the channels
         let temp_chunkhandler = ChunkHandler::new(
-            device.clone(), queue.clone(), evd.clone(),
-            Meshes::new(device.clone(), txtr_dt.clone(), renderpass.clone(), dimensions.clone(), &player.camera),
-            Terrain::new(24, block_registry.clone()),
+            evd.clone(),
+            Meshes::new(device.clone(), txtr_dt.clone(), renderpass.clone(), dimensions, &player.camera),
+            Terrain::new(settings.seed, block_registry.clone()),
+            settings,
         );
 
+        // the errors of the programs are shown in the console
+        let mut console = Console::new();
         let mut cmd = WorldCommandExecutor::new();
-        cmd.load_file_bytc("resource/commands/test00.wcb".into());
+        if let Err(err) = cmd.load_file_bytc("resource/commands/test00.wcb".into()) {
+            console.print(&err.render());
+        }
 
         Self {
             player: player.clone(),
             command: cmd,
+            console,
 
             event: evd.clone(),
+            device: device.clone(),
             registry: block_registry.clone(),
-            texture: texture,
             texture_fut: Some(txtr_future),
 
             render_buffer: None,  // render data single buffer
             chunk_status_buffer: None,  // chunk status info single buffer
             world_state: None,
 
-            temp_chunkhandler: temp_chunkhandler,
+            temp_chunkhandler,
         }
     }
 
+    // the console of the command mode; its pressed keys are handled on the next update
+    pub fn console(&mut self) -> &mut Console {
+        &mut self.console
+    }
+
     pub fn bind_texture( &mut self, gpu_future: Box<dyn GpuFuture>, ) -> Box<dyn GpuFuture> {
-        let txtr_fut = mem::replace(&mut self.texture_fut, None);
+        let txtr_fut = self.texture_fut.take();
         Box::new(gpu_future.join(txtr_fut.expect("Texture future has already been taken"))) as Box<dyn GpuFuture>
     }
 
@@ -177,7 +198,13 @@ impl World {
                   rerender: bool,) {
         // println!("WORLD - UPDATE");
 
-
+        // executes all the world command programs loaded since the last update, along with the console
+        let mut nmspc = NamespaceRegistry::new();
+        nmspc.register(WORLD_NMSPC, &mut self.temp_chunkhandler);
+        nmspc.register("Matrixagon:world:player:main", &mut self.player.camera);
+        nmspc.register_read("Matrixagon:world:blocks", &*self.registry);
+        register_library(&mut nmspc);
+        self.console.update(&mut self.command, &mut nmspc);
 
         if let Some(stat) = &self.chunk_status_buffer {
             if stat.chunks_loaded > 0 || stat.chunks_offloaded > 0 {
@@ -185,15 +212,14 @@ impl World {
             }
         }
 
-        println!("Nooo");
         if let Some(state) = &self.world_state {
             let new_state = WorldStateUpd::from_world(self.player.camera.clone(), self.registry.clone(), dimensions, renderpass.clone(), framebuffer.clone(), rerender);
             let update_state = state.update(&new_state);
-            if update_state != ChunkUpdateState::Consistent {
-                println!("ChunkUpdateState no Consistent");
+            // the chunks keep being loaded and offloaded even while the player stands still
+            if update_state != ChunkUpdateState::Consistent || self.temp_chunkhandler.busy() {
                 // TODO: temp
-                self.event.clone().emit(EventName("MeshEvent/UpdateDimensions"), event_data![dimensions]);
-                let (rb, csb) = self.temp_chunkhandler.update(new_state.clone());
+                self.event.emit(mesh_event::UpdateDimensions(dimensions));
+                let (rb, csb) = self.temp_chunkhandler.update(self.device.clone(), new_state.clone());
 
                 self.render_buffer = Some(rb);
                 self.chunk_status_buffer = Some(csb);
@@ -201,9 +227,8 @@ impl World {
                 self.world_state = Some(new_state);
             }
         } else {
-            println!("OFC");
             let new_state = WorldStateUpd::from_world(self.player.camera.clone(), self.registry.clone(), dimensions, renderpass.clone(), framebuffer.clone(), rerender);
-            let (rb, csb) = self.temp_chunkhandler.update(new_state.clone());
+            let (rb, csb) = self.temp_chunkhandler.update(self.device.clone(), new_state.clone());
 
             self.render_buffer = Some(rb);
             self.chunk_status_buffer = Some(csb);
@@ -221,9 +246,7 @@ impl World {
     ) -> AutoCommandBuffer<StandardCommandPoolAlloc> {
         // println!("WORLD - RENDER");
 
-        println!(".__.");
         // the render buffer should always have value taken care by the code above
-        println!("DT?: {:?}", if let Some(_) = self.render_buffer {true} else {false});
         // self.render_buffer.clone();
         let mut mesh_datas = self.render_buffer.clone().expect("Render buffer missing");
         mesh_datas.update_camera(device.clone(), &self.player.camera, dimensions);
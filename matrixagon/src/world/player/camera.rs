@@ -1,8 +1,10 @@
 use crate::datatype::{Rotation, Dimension, CamDirection, Position, BlockUnit};
-use crate::world::chunk::{Chunk, CHUNK_SIZE};
-use crate::world::player::EDIT_RADIUS;
+use crate::world::chunk::CHUNK_SIZE;
+use crate::world::chunk_map::ChunkMap;
 use crate::world::block::Block;
 use crate::world::block::state::Matter;
+use crate::world::commands::bytecode::StackType;
+use crate::world::commands::namespace::{Namespace, NmspcRes, NamespaceError, names, number, path_str};
 
 use na::{
     Point3,
@@ -42,14 +44,59 @@ impl Default for Camera {
     }
 }
 
+// registered as the player namespace (e.g. `:Matrixagon:world:player:main`)
+impl Namespace for Camera {
+    fn entries(&self, path: &[String]) -> Vec<String> {
+        match path_str(path).as_slice() {
+            [] => names(&["pos", "rot", "fovy", "speed"]),
+            ["pos"] | ["rot"] => names(&["x", "y", "z"]),
+            ["speed"] => names(&["move", "rotate"]),
+            _ => Vec::new(),
+        }
+    }
+
+    fn get(&self, path: &[String]) -> NmspcRes<StackType> {
+        let val = match path_str(path).as_slice() {
+            ["pos", "x"] => self.position[0],
+            ["pos", "y"] => self.position[1],
+            ["pos", "z"] => self.position[2],
+            ["rot", "x"] => self.rotation.x,
+            ["rot", "y"] => self.rotation.y,
+            ["rot", "z"] => self.rotation.z,
+            ["fovy"] => self.fovy,
+            ["speed", "move"] => self.trans_speed,
+            ["speed", "rotate"] => self.rot_speed,
+            _ => return Err(NamespaceError::Unknown),
+        };
+        Ok(StackType::Float(val as f64))
+    }
+
+    fn set(&mut self, path: &[String], val: StackType) -> NmspcRes<()> {
+        let val = number(&val)? as f32;
+        match path_str(path).as_slice() {
+            ["pos", "x"] => self.position[0] = val,
+            ["pos", "y"] => self.position[1] = val,
+            ["pos", "z"] => self.position[2] = val,
+            ["rot", "x"] => self.rotation.x = val,
+            ["rot", "y"] => self.rotation.y = val,
+            ["rot", "z"] => self.rotation.z = val,
+            ["fovy"] => self.fovy = val,
+            ["speed", "move"] => self.trans_speed = val,
+            ["speed", "rotate"] => self.rot_speed = val,
+            _ => return Err(NamespaceError::Unknown),
+        }
+        Ok(())
+    }
+}
+
 impl Camera {
     pub fn new(rot_speed: f32, trans_speed: f32, position: Point3<f32>, rotation: Rotation<f32>) -> Self {
         Self {
-            position: position,
-            rotation: rotation,
+            position,
+            rotation,
             fovy: 1.3,
-            rot_speed: rot_speed,
-            trans_speed: trans_speed,
+            rot_speed,
+            trans_speed,
             zfar: 1000.0,
             znear: 0.1,
         }
@@ -75,16 +122,21 @@ impl Camera {
 
         for d in dir {
             match d {
-                CamDirection::Forward =>   {self.position = self.position + rot_pos( 0.0, 0.0, self.trans_speed)},
-                CamDirection::Backward =>  {self.position = self.position + rot_pos( 0.0, 0.0,-self.trans_speed)},
-                CamDirection::Leftward =>  {self.position = self.position + rot_pos(-self.trans_speed, 0.0, 0.0)},
-                CamDirection::Rightward => {self.position = self.position + rot_pos( self.trans_speed, 0.0, 0.0)},
+                CamDirection::Forward =>   {self.position += rot_pos( 0.0, 0.0, self.trans_speed)},
+                CamDirection::Backward =>  {self.position += rot_pos( 0.0, 0.0,-self.trans_speed)},
+                CamDirection::Leftward =>  {self.position += rot_pos(-self.trans_speed, 0.0, 0.0)},
+                CamDirection::Rightward => {self.position += rot_pos( self.trans_speed, 0.0, 0.0)},
                 CamDirection::Upward =>    {self.position = Translation3::new( 0.0,  self.trans_speed, 0.0).transform_point(&self.position)},
                 CamDirection::Downward =>  {self.position = Translation3::new( 0.0, -self.trans_speed, 0.0).transform_point(&self.position)},
             }
         }
     }
 
+    // the direction the camera is looking towards (the same forward as `travel()`)
+    pub fn forward(&self) -> Vector3<f32> {
+        self.rotation.matrix().transform_vector(&Vector3::new(0.0, 0.0, 1.0))
+    }
+
     // generates the mvp matrix for meshes and other pipelines
     pub fn gen_mvp(&self, dimensions: Dimension<u32>) -> (Matrix3D, Matrix3D, Matrix3D) {
         let proj = Perspective3::new(dimensions.aspect() as f32, self.fovy, self.znear, self.zfar);
@@ -119,13 +171,13 @@ impl Camera {
         (proj_dt, view_dt, model_dt)
     }
 
-    // returns a Chunk Position for breaking blocks
-    pub fn raycast_break(&self, chunks: &Vec<Chunk>) -> Option<(Position<BlockUnit>, Block)> {
+    // returns a Chunk Position for breaking blocks within the edit radius (`WorldSettings::edit_radius`)
+    pub fn raycast_break(&self, chunks: &ChunkMap, edit_radius: u32) -> Option<(Position<BlockUnit>, Block)> {
         let org_pos: Position<f32> = self.position.into();  // The original position
         let mut cur_pos: Position<f32> = self.position.into();  // Current position of the raycast
         let mut block_pos = None;  // <Block> position which the position of that world.block that was hit
 
-        'ray: while block_pos == None {
+        'ray: while block_pos.is_none() {
             println!("R: {:?}", ((org_pos.x - cur_pos.x).powi(2) +
                 (org_pos.y - cur_pos.y).powi(2) +
                 (org_pos.z - cur_pos.z).powi(2)
@@ -136,46 +188,39 @@ impl Camera {
             if ((org_pos.x - cur_pos.x).powi(2) +
                 (org_pos.y - cur_pos.y).powi(2) +
                 (org_pos.z - cur_pos.z).powi(2)
-            ).sqrt() > EDIT_RADIUS as f32 {
+            ).sqrt() > edit_radius as f32 {
                 break 'ray;
             }
 
-            // TODO: we can speed up the process by stripping chunks that'll never hit
-            for chunk in chunks.iter() {
-                let bx: f32 = chunk.position.x.into_block().into();
-                let bX: f32 = (chunk.position.x.incr()).into_block().into();
-                let by: f32 = chunk.position.y.into_block().into();
-                let bY: f32 = (chunk.position.y.incr()).into_block().into();
-                let bz: f32 = chunk.position.z.into_block().into();
-                let bZ: f32 = (chunk.position.z.incr()).into_block().into();
-
-                // check if the chunk contains the current ray position
-                if  (bx <= cur_pos.x && cur_pos.x < bX) &&
-                    (by <= cur_pos.y && cur_pos.y < bY) &&
-                    (bz <= cur_pos.z && cur_pos.z < bZ)  {
-                    // world.block raw position
-                    let brx = if (cur_pos.x % CHUNK_SIZE as f32) < 0f32 {CHUNK_SIZE as f32 + (cur_pos.x % CHUNK_SIZE as f32)} else {cur_pos.x % CHUNK_SIZE as f32};
-                    let bry = if (cur_pos.y % CHUNK_SIZE as f32) < 0f32 {CHUNK_SIZE as f32 + (cur_pos.y % CHUNK_SIZE as f32)} else {cur_pos.y % CHUNK_SIZE as f32};
-                    let brz = if (cur_pos.z % CHUNK_SIZE as f32) < 0f32 {CHUNK_SIZE as f32 + (cur_pos.z % CHUNK_SIZE as f32)} else {cur_pos.z % CHUNK_SIZE as f32};
-
-                    // world.block (cooked) position
-                    let bx = brx.floor() as usize;
-                    let by = bry.floor() as usize;
-                    let bz = brz.floor() as usize;
-
-                    // now grabbing individual blocks
-                    let block = chunk.block_data[bx*CHUNK_SIZE*CHUNK_SIZE+by*CHUNK_SIZE+bz];
-                    if block.state.breakable && block.state.matter == Matter::Solid {
-                        block_pos = Some((
-                            Position::new(
-                                BlockUnit(bx as f32) + chunk.position.x.into_block(),
-                                BlockUnit(by as f32) + chunk.position.x.into_block(),
-                                BlockUnit(bz as f32) + chunk.position.x.into_block(),
-                            ),
-                            block,
-                        ));
-                        break 'ray;
-                    }
+            // the chunk containing the current ray position
+            let chunk_pos = [
+                (cur_pos.x / CHUNK_SIZE as f32).floor() as i64,
+                (cur_pos.y / CHUNK_SIZE as f32).floor() as i64,
+                (cur_pos.z / CHUNK_SIZE as f32).floor() as i64,
+            ];
+            if let Some(chunk) = chunks.get(chunk_pos) {
+                // world.block raw position
+                let brx = if (cur_pos.x % CHUNK_SIZE as f32) < 0f32 {CHUNK_SIZE as f32 + (cur_pos.x % CHUNK_SIZE as f32)} else {cur_pos.x % CHUNK_SIZE as f32};
+                let bry = if (cur_pos.y % CHUNK_SIZE as f32) < 0f32 {CHUNK_SIZE as f32 + (cur_pos.y % CHUNK_SIZE as f32)} else {cur_pos.y % CHUNK_SIZE as f32};
+                let brz = if (cur_pos.z % CHUNK_SIZE as f32) < 0f32 {CHUNK_SIZE as f32 + (cur_pos.z % CHUNK_SIZE as f32)} else {cur_pos.z % CHUNK_SIZE as f32};
+
+                // world.block (cooked) position
+                let bx = brx.floor() as usize;
+                let by = bry.floor() as usize;
+                let bz = brz.floor() as usize;
+
+                // now grabbing individual blocks
+                let block = chunk.block_data[bx*CHUNK_SIZE*CHUNK_SIZE+by*CHUNK_SIZE+bz];
+                if block.state.breakable && block.state.matter == Matter::Solid {
+                    block_pos = Some((
+                        Position::new(
+                            BlockUnit(bx as f32) + chunk.position.x.into_block(),
+                            BlockUnit(by as f32) + chunk.position.x.into_block(),
+                            BlockUnit(bz as f32) + chunk.position.x.into_block(),
+                        ),
+                        block,
+                    ));
+                    break 'ray;
                 }
             }
 
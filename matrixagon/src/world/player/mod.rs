@@ -12,17 +12,17 @@ use std::fmt;
 pub mod camera;
 
 
-// chunk radius in chunk size
-pub const CHUNK_RADIUS: u32 = 2;
-// the radius of which the world.player can edit the world
-pub const EDIT_RADIUS: u32 = 10;
-
-
 #[derive(Clone, PartialEq)]
 pub struct Player {
     pub camera: Camera,
 }
 
+impl Default for Player {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Player {
     pub fn new() -> Self {
         Self {
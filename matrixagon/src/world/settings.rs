@@ -0,0 +1,217 @@
+use crate::world::chunk::CHUNK_SIZE;
+
+use std::io::{self, BufRead, BufReader};
+
+/*
+World Settings
+--------------
+The settings of the world that differ between the machines (e.g. the render distance and the number of
+meshing threads). They are loaded from a settings file of `name = value` lines, where `#` starts a comment
+and the settings left out keep their defaults. The file is `resource/settings.cfg`, unless another one is
+given through `MATRIXAGON_SETTINGS`. Some of the settings can also be changed while the game is running,
+through the world namespace (e.g. `:Matrixagon:world:settings:chunk_radius`).
+ */
+
+// the settings file loaded when `MATRIXAGON_SETTINGS` is not set
+pub const SETTINGS_FILE: &str = "resource/settings.cfg";
+pub const SETTINGS_ENV: &str = "MATRIXAGON_SETTINGS";
+
+// any farther and the chunks around the player would not fit into the memory anyway
+pub const MAX_CHUNK_RADIUS: u32 = 32;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct WorldSettings {
+    pub chunk_radius: u32,  // the render distance in chunks
+    pub edit_radius: u32,  // the distance in blocks within which the player can edit the world
+    pub mesh_threads: usize,  // the threads generating the chunk meshes; 0 for one per cpu
+    pub seed: u128,  // the seed of the terrain generation
+    pub chunk_size: usize,  // the chunk size in blocks; only the compiled size (`CHUNK_SIZE`) is supported for now
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum SettingsError {
+    // The settings file could not be read
+    Unreadable(String),  // the reason from the reader
+    // The line is not of the form `name = value`
+    Malformed(usize),  // the line number
+    // There is no setting with the name
+    UnknownSetting(String),  // the name of the setting
+    // The value cannot be used for the setting
+    InvalidValue(String, String),  // the name of the setting and the reason
+    // The setting on the line of the settings file is invalid
+    Line(usize, Box<SettingsError>),  // the line number and the error
+}
+
+impl SettingsError {
+    pub fn message(&self) -> String {
+        match self {
+            SettingsError::Unreadable(reason) => format!("cannot read the settings: {}", reason),
+            SettingsError::Malformed(line) => format!("line {}: expected `name = value`", line),
+            SettingsError::UnknownSetting(name) => format!("unknown setting `{}`", name),
+            SettingsError::InvalidValue(name, reason) => format!("invalid value for `{}`: {}", name, reason),
+            SettingsError::Line(line, err) => format!("line {}: {}", line, err.message()),
+        }
+    }
+}
+
+impl WorldSettings {
+    // the names of all the settings, in the order of the settings file
+    pub const NAMES: [&'static str; 5] = ["chunk_radius", "edit_radius", "mesh_threads", "seed", "chunk_size"];
+
+    // the settings of the settings file over the defaults; nothing is loaded if any line is invalid
+    pub fn load<R: io::Read>(reader: R) -> Result<Self, SettingsError> {
+        let mut settings = Self::default();
+        for (ind, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line.map_err(|err| SettingsError::Unreadable(err.to_string()))?;
+            let line = match line.find('#') {
+                Some(comment) => &line[..comment],
+                None => &line[..],
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            match (parts.next().map(str::trim), parts.next().map(str::trim)) {
+                (Some(name), Some(value)) if !name.is_empty() && !value.is_empty() => {
+                    settings.set(name, value).map_err(|err| SettingsError::Line(ind+1, Box::new(err)))?;
+                },
+                _ => return Err(SettingsError::Malformed(ind+1)),
+            }
+        }
+        Ok(settings)
+    }
+
+    pub fn load_file(fname: &str) -> Result<Self, SettingsError> {
+        let file = std::fs::File::open(fname).map_err(|err| SettingsError::Unreadable(format!("`{}`: {}", fname, err)))?;
+        Self::load(file)
+    }
+
+    // the settings of the file from `MATRIXAGON_SETTINGS`, otherwise of the default settings file if there is one
+    // falls back to the defaults (with the reason printed) if the file cannot be used
+    pub fn from_env() -> Self {
+        let fname = match std::env::var(SETTINGS_ENV) {
+            Ok(fname) => fname,
+            Err(_) if std::path::Path::new(SETTINGS_FILE).exists() => SETTINGS_FILE.into(),
+            Err(_) => return Self::default(),
+        };
+
+        match Self::load_file(&fname) {
+            Ok(settings) => settings,
+            Err(err) => {
+                println!("[SETTINGS] Using the default settings instead of `{}`: {}", fname, err.message());
+                Self::default()
+            },
+        }
+    }
+
+    // the value of the setting as written in the settings file
+    pub fn get(&self, name: &str) -> Option<String> {
+        match name {
+            "chunk_radius" => Some(self.chunk_radius.to_string()),
+            "edit_radius" => Some(self.edit_radius.to_string()),
+            "mesh_threads" => Some(self.mesh_threads.to_string()),
+            "seed" => Some(self.seed.to_string()),
+            "chunk_size" => Some(self.chunk_size.to_string()),
+            _ => None,
+        }
+    }
+
+    // sets the setting from its value as written in the settings file; the setting is unchanged if the value is invalid
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), SettingsError> {
+        let invalid = |reason: &str| SettingsError::InvalidValue(name.to_string(), reason.to_string());
+        match name {
+            "chunk_radius" => {
+                let radius = value.parse::<u32>().map_err(|_| invalid("expected a positive integer"))?;
+                if radius > MAX_CHUNK_RADIUS {
+                    return Err(invalid(&format!("the radius cannot be more than {} chunks", MAX_CHUNK_RADIUS)));
+                }
+                self.chunk_radius = radius;
+            },
+            "edit_radius" => self.edit_radius = value.parse().map_err(|_| invalid("expected a positive integer"))?,
+            "mesh_threads" => self.mesh_threads = value.parse().map_err(|_| invalid("expected a positive integer"))?,
+            "seed" => self.seed = value.parse().map_err(|_| invalid("expected a positive integer"))?,
+            "chunk_size" => {
+                let size = value.parse::<usize>().map_err(|_| invalid("expected a positive integer"))?;
+                // the chunks are fixed-size arrays, so the size can only be changed by recompiling
+                if size != CHUNK_SIZE {
+                    return Err(invalid(&format!("the chunk size is fixed to {} blocks", CHUNK_SIZE)));
+                }
+                self.chunk_size = size;
+            },
+            _ => return Err(SettingsError::UnknownSetting(name.to_string())),
+        }
+        Ok(())
+    }
+
+    // the number of the meshing threads to spawn
+    pub fn threads(&self) -> usize {
+        match self.mesh_threads {
+            0 => num_cpus::get(),
+            num => num,
+        }
+    }
+}
+
+impl Default for WorldSettings {
+    fn default() -> Self {
+        Self {
+            chunk_radius: 2,
+            edit_radius: 10,
+            // high number: faster chunk generation but laggier across the whole computer
+            // low number: slower chunk generation (maybe even stack overflow) but smoother across the whole computer
+            mesh_threads: 8,
+            seed: 24,
+            chunk_size: CHUNK_SIZE,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_over_defaults() {
+        let file = "\
+            # a slower machine\n\
+            chunk_radius = 4\n\
+            \n\
+            mesh_threads=2  # leaves room for the rest\n\
+            seed = 1234\n";
+        let settings = WorldSettings::load(file.as_bytes()).unwrap();
+
+        assert_eq!(settings, WorldSettings { chunk_radius: 4, mesh_threads: 2, seed: 1234, ..WorldSettings::default() });
+        assert_eq!(settings.get("edit_radius"), Some("10".into()));
+        assert_eq!(WorldSettings::load("".as_bytes()).unwrap(), WorldSettings::default());
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        let load = |file: &str| WorldSettings::load(file.as_bytes()).err().unwrap();
+
+        assert_eq!(load("chunk_radius = 4\nchunk_radius\n"), SettingsError::Malformed(2));
+        assert_eq!(load("= 4"), SettingsError::Malformed(1));
+        assert_eq!(load("render = 4"), SettingsError::Line(1, Box::new(SettingsError::UnknownSetting("render".into()))));
+        assert_eq!(load("chunk_radius = -1").message(), "line 1: invalid value for `chunk_radius`: expected a positive integer");
+        assert_eq!(load("chunk_radius = 1000").message(), "line 1: invalid value for `chunk_radius`: the radius cannot be more than 32 chunks");
+        assert_eq!(load("chunk_size = 16").message(), "line 1: invalid value for `chunk_size`: the chunk size is fixed to 32 blocks");
+    }
+
+    #[test]
+    fn sets_at_runtime() {
+        let mut settings = WorldSettings::default();
+        settings.set("chunk_radius", "6").unwrap();
+        assert_eq!(settings.chunk_radius, 6);
+        assert!(settings.set("chunk_radius", "six").is_err());
+        assert_eq!(settings.chunk_radius, 6);
+
+        for name in WorldSettings::NAMES.iter() {
+            let value = settings.get(name).unwrap();
+            assert_eq!(settings.set(name, &value), Ok(()));
+        }
+
+        settings.mesh_threads = 0;
+        assert!(settings.threads() >= 1);
+    }
+}
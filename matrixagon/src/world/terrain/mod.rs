@@ -3,6 +3,8 @@ use crate::world::chunk::{CHUNK_SIZE, CHUNK_BLOCKS};
 use crate::datatype::{Position, ChunkUnit};
 use crate::world::block::registry::BlockRegistry;
 use crate::world::terrain::noise::PerlinNoise2D;
+use crate::world::commands::bytecode::StackType;
+use crate::world::commands::namespace::{Namespace, NmspcRes, NamespaceError, names, path_str};
 
 use oorandom::Rand64;
 
@@ -14,6 +16,7 @@ mod noise;
 #[derive(Clone)]
 pub struct Terrain {
     registry: Arc<BlockRegistry>,
+    seed: u128,
 
     random: Rand64,
     perlin2d: PerlinNoise2D,
@@ -25,6 +28,7 @@ impl Terrain {
 
         Self {
             registry: block_reg.clone(),
+            seed,
 
             // Rand64 from oorandom is deterministic random number generator which is really REALLY useful
             // in deterministic natural terrain generation like this sandbox game. Which is why it must
@@ -35,6 +39,11 @@ impl Terrain {
         }
     }
 
+    // the block registry the terrain generates the blocks from
+    pub fn registry(&self) -> &BlockRegistry {
+        &self.registry
+    }
+
     // TODO: Make registry implement slicing
     pub fn generate_chunk(&mut self, chunk_pos: Position<ChunkUnit>) -> Box<[Block; CHUNK_BLOCKS]> {
         // println!("Terrain size allocated: {:?} Blocks", CHUNK_BLOCKS);
@@ -55,12 +64,12 @@ impl Terrain {
             let lz = n & (CHUNK_SIZE-1);
 
             // global world.block coordinates
-            let x = lx as i64+gx;
+            let _x = lx as i64+gx;
             let y = ly as i64+gy;  // y should never be below 0
-            let z = lz as i64+gz;
+            let _z = lz as i64+gz;
 
             // 2D-3D: X to X, Y to Z, Z
-            let num = hmap[lx as usize][lz as usize] as i64 - 10;  // todo: conversion error on indexes when negative form i to u?
+            let num = hmap[lx][lz] as i64 - 10;  // todo: conversion error on indexes when negative form i to u?
 
             if ground_level+num+1 > y && y >= ground_level+num {
                 if self.random.rand_range(0..10) == 0 {
@@ -99,7 +108,7 @@ impl Terrain {
     }
 
     // TERRAIN GENERATION STAGE 1: Generating the basic heightmap
-    fn generate_heightmap(&mut self, gx: i64, gy: i64, gz: i64) -> [[u32; CHUNK_SIZE]; CHUNK_SIZE] {
+    fn generate_heightmap(&mut self, gx: i64, _gy: i64, gz: i64) -> [[u32; CHUNK_SIZE]; CHUNK_SIZE] {
         // let mut height_map = (0..CHUNK_SIZE*CHUNK_SIZE).enumerate().map(|i|i.0).map(|n| {
         //     let x = (n as f32/CHUNK_SIZE as f32).floor() as isize;
         //     let z = (n%CHUNK_SIZE) as isize;
@@ -125,8 +134,8 @@ impl Terrain {
 
         let mut height_map = [[0; CHUNK_SIZE]; CHUNK_SIZE];
 
-        for x in 0..CHUNK_SIZE {
-            for z in 0..CHUNK_SIZE {
+        for (x, column) in height_map.iter_mut().enumerate() {
+            for (z, height) in column.iter_mut().enumerate() {
                 // Favorite Math Noise: ((((gx+x as isize) as f32/3 as f32).sin()+((gz+z as isize) as f32/3 as f32).cos())*32.0) as i32;
                 // height_map[x][z] = ((((gx+x as isize) as f32/3 as f32).sin()+((gz+z as isize) as f32/3 as f32).cos())*32.0) as i32;
 
@@ -136,10 +145,27 @@ impl Terrain {
 
                 // try multiplication too
                 let octaves = (pn2/15.0).round() + 16.0;
-                height_map[x][z] = if octaves >= 0.0 {octaves as u32} else {0u32};
+                *height = if octaves >= 0.0 {octaves as u32} else {0u32};
             }
         }
 
         height_map
     }
 }
+
+// registered under the world namespace (e.g. `:Matrixagon:world:terrain`)
+impl Namespace for Terrain {
+    fn entries(&self, path: &[String]) -> Vec<String> {
+        match path_str(path).as_slice() {
+            [] => names(&["seed"]),
+            _ => Vec::new(),
+        }
+    }
+
+    fn get(&self, path: &[String]) -> NmspcRes<StackType> {
+        match path_str(path).as_slice() {
+            ["seed"] => Ok(StackType::Int(self.seed as i64)),
+            _ => Err(NamespaceError::Unknown),
+        }
+    }
+}
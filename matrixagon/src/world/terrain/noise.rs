@@ -8,7 +8,6 @@ use oorandom::Rand64;
 
 #[derive(Clone)]
 pub struct PerlinNoise2D {
-    random: Rand64,  // random instance
     // permutation
     p: Vec<u32>,  // permutation to additionally randomize
     // gradient vectors
@@ -21,7 +20,6 @@ impl PerlinNoise2D {
     pub fn new(seed: u128) -> Self {
         let rand = Rand64::new(seed);
         Self {
-            random: rand,
             p: Self::permutation(rand, 256),
             // the more different gradient, the more variation the noise has
             g: vec![
@@ -49,7 +47,7 @@ impl PerlinNoise2D {
 
         // retrieves the gradient base off positon
         let grad = |x, y| {
-            self.g[self.p[(self.p[(x as u32 & 255) as usize]+(y as u32 & 255) & 255) as usize] as usize % self.g.len()]
+            self.g[self.p[((self.p[(x as u32 & 255) as usize]+(y as u32 & 255)) & 255) as usize] as usize % self.g.len()]
         };
 
         let g00 = grad(xf0, yf0);
@@ -63,7 +61,7 @@ impl PerlinNoise2D {
         let d10 = (c10.0*g10.0) + (c10.1*g10.1);
         let d11 = (c11.0*g11.0) + (c00.1*g11.1);
 
-        let (u, v) = (Self::fade(x-xf0 as f64), Self::fade(y-yf0 as f64));
+        let (u, v) = (Self::fade(x-xf0), Self::fade(y-yf0));
 
         // the ordering of the lerp is very important
         Self::lerp(
@@ -53,7 +53,7 @@ impl Texture {
     // adds the texture data
     pub fn add_texture(&mut self, file_name: &str, txtr_name: &'static str) -> TextureID {
         // retrieves the .png byte data from the file
-        let byte_stream = fs::read(Path::new(file_name)).expect(&format!("Texture file '{}' not found!", file_name)[..]);
+        let byte_stream = fs::read(Path::new(file_name)).unwrap_or_else(|_| panic!("Texture file '{}' not found!", file_name));
 
         // decodes file meta information
         let cursor = Cursor::new(byte_stream);
@@ -70,8 +70,7 @@ impl Texture {
         }
 
         // formats decoded data into a texture RGBA format data
-        let mut txtr_data = Vec::new();
-        txtr_data.resize((info.width * info.height * 4) as usize, 0);
+        let mut txtr_data = vec![0; (info.width * info.height * 4) as usize];
         reader.next_frame(&mut txtr_data).unwrap();
 
         let id = TextureID(self.txtr_cnt, txtr_name);